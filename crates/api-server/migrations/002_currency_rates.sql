@@ -0,0 +1,10 @@
+-- Exchange rates for display currency conversion
+-- Each row: units of currency_code per one unit of base_currency
+
+CREATE TABLE IF NOT EXISTS currency_rates (
+    currency_code CHAR(3) PRIMARY KEY,
+    base_currency CHAR(3) NOT NULL,
+    rate NUMERIC(20, 10) NOT NULL CHECK (rate > 0),
+    valid_on DATE NOT NULL DEFAULT CURRENT_DATE,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
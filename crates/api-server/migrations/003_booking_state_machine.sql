@@ -0,0 +1,20 @@
+-- Booking state machine
+-- flight_bookings.status: PENDING -> PRICED -> ORDERED -> TICKETED, or CANCELLED / FAILED
+
+ALTER TABLE flight_bookings ADD COLUMN IF NOT EXISTS amadeus_offer_id VARCHAR(255);
+ALTER TABLE flight_bookings ADD COLUMN IF NOT EXISTS failure_reason TEXT;
+
+ALTER TABLE flight_bookings ADD CONSTRAINT flight_bookings_status_check
+    CHECK (status IN ('PENDING', 'PRICED', 'ORDERED', 'TICKETED', 'CANCELLED', 'FAILED'));
+
+-- Every status change of a booking, oldest first
+CREATE TABLE IF NOT EXISTS booking_status_history (
+    id BIGSERIAL PRIMARY KEY,
+    booking_id UUID NOT NULL REFERENCES flight_bookings(id) ON DELETE CASCADE,
+    from_status VARCHAR(50),
+    to_status VARCHAR(50) NOT NULL,
+    reason TEXT,
+    changed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_booking_status_history_booking_id ON booking_status_history(booking_id);
@@ -0,0 +1,19 @@
+-- Ticketing deadlines of ordered bookings
+-- ticketing_deadline is the earliest of the offers' last ticketing date and the
+-- DELAY_TO_QUEUE / DELAY_TO_CANCEL date of the ticketing agreement
+
+ALTER TABLE flight_bookings ADD COLUMN IF NOT EXISTS ticketing_deadline TIMESTAMP WITH TIME ZONE;
+ALTER TABLE flight_bookings ADD COLUMN IF NOT EXISTS ticketing_option VARCHAR(20);
+
+CREATE INDEX IF NOT EXISTS idx_flight_bookings_ticketing_deadline
+    ON flight_bookings(ticketing_deadline) WHERE status = 'ORDERED';
+
+-- Events of a booking (ticketing reminders, automatic cancellation), each kind once
+CREATE TABLE IF NOT EXISTS booking_events (
+    id BIGSERIAL PRIMARY KEY,
+    booking_id UUID NOT NULL REFERENCES flight_bookings(id) ON DELETE CASCADE,
+    kind VARCHAR(50) NOT NULL,
+    detail TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (booking_id, kind)
+);
@@ -0,0 +1,16 @@
+-- Differences between stored orders and Amadeus found by the reconciliation job
+-- kind: SCHEDULE_CHANGE, SEGMENT_STATUS, SEGMENT_ADDED, SEGMENT_REMOVED, TRAVELER_CHANGE, CANCELLED
+
+CREATE TABLE IF NOT EXISTS booking_changes (
+    id BIGSERIAL PRIMARY KEY,
+    booking_id UUID NOT NULL REFERENCES flight_bookings(id) ON DELETE CASCADE,
+    kind VARCHAR(50) NOT NULL,
+    segment_id VARCHAR(50),
+    traveler_id VARCHAR(50),
+    description TEXT NOT NULL,
+    previous_value TEXT,
+    current_value TEXT,
+    detected_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_booking_changes_booking_id ON booking_changes(booking_id);
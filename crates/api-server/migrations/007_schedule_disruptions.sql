@@ -0,0 +1,19 @@
+-- Schedule changes of booked itineraries found by the schedule monitor, with
+-- alternative offers for re-accommodation
+-- status: OPEN -> ACCEPTED (accepted_offer_id is one of the alternatives)
+
+CREATE TABLE IF NOT EXISTS schedule_disruptions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    booking_id UUID NOT NULL REFERENCES flight_bookings(id) ON DELETE CASCADE,
+    itinerary_index INTEGER NOT NULL,
+    fingerprint CHAR(64) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'OPEN' CHECK (status IN ('OPEN', 'ACCEPTED')),
+    changes JSONB NOT NULL,
+    alternatives JSONB NOT NULL,
+    accepted_offer_id VARCHAR(255),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    resolved_at TIMESTAMP WITH TIME ZONE,
+    UNIQUE (booking_id, fingerprint)
+);
+
+CREATE INDEX IF NOT EXISTS idx_schedule_disruptions_booking_id ON schedule_disruptions(booking_id);
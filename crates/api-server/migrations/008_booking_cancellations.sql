@@ -0,0 +1,23 @@
+-- Two-step cancellations: a quote with the expected refund, then the confirmed
+-- cancellation with who cancelled, why, and the refund as of cancellation
+-- status: QUOTED -> CONFIRMING -> CONFIRMED or FAILED
+
+CREATE TABLE IF NOT EXISTS booking_cancellations (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    booking_id UUID NOT NULL REFERENCES flight_bookings(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'QUOTED'
+        CHECK (status IN ('QUOTED', 'CONFIRMING', 'CONFIRMED', 'FAILED')),
+    currency VARCHAR(3) NOT NULL,
+    quoted_refund DECIMAL(10,2),
+    quote JSONB NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    cancelled_by VARCHAR(255),
+    reason TEXT,
+    actual_refund DECIMAL(10,2),
+    outcome JSONB,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    confirmed_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS idx_booking_cancellations_booking_id ON booking_cancellations(booking_id);
@@ -0,0 +1,30 @@
+-- Card payments taken through a payment provider (PAYMENT_PROVIDER)
+-- flight_bookings.status gains AUTHORIZED between PRICED and ORDERED
+-- booking_payments.status: AUTHORIZED -> CAPTURED -> REFUNDED, or AUTHORIZED -> VOIDED
+
+ALTER TABLE flight_bookings DROP CONSTRAINT IF EXISTS flight_bookings_status_check;
+ALTER TABLE flight_bookings ADD CONSTRAINT flight_bookings_status_check
+    CHECK (status IN ('PENDING', 'PRICED', 'AUTHORIZED', 'ORDERED', 'TICKETED', 'CANCELLED', 'FAILED'));
+
+CREATE TABLE IF NOT EXISTS booking_payments (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    booking_id UUID NOT NULL REFERENCES flight_bookings(id) ON DELETE CASCADE,
+    provider VARCHAR(50) NOT NULL,
+    provider_payment_id VARCHAR(255) NOT NULL,
+    status VARCHAR(20) NOT NULL
+        CHECK (status IN ('AUTHORIZED', 'CAPTURED', 'VOIDED', 'REFUNDED')),
+    amount DECIMAL(10,2) NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    captured_amount DECIMAL(10,2) NOT NULL DEFAULT 0,
+    refunded_amount DECIMAL(10,2) NOT NULL DEFAULT 0,
+    -- Why the last operation on the payment failed
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (provider, provider_payment_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_booking_payments_booking_id ON booking_payments(booking_id);
+
+CREATE TRIGGER update_booking_payments_updated_at BEFORE UPDATE ON booking_payments
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,9 @@
+-- Envelope encryption of traveler PII in passenger_data and booking_data (PII_ENCRYPTION_KEYS)
+-- pii_data_key is the booking's data key, sealed by the key encryption key pii_key_id;
+-- both are NULL for bookings stored unencrypted
+
+ALTER TABLE flight_bookings
+    ADD COLUMN IF NOT EXISTS pii_key_id VARCHAR(50),
+    ADD COLUMN IF NOT EXISTS pii_data_key TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_flight_bookings_pii_key_id ON flight_bookings(pii_key_id);
@@ -0,0 +1,5 @@
+-- Lock of the request running with an idempotency key; only the request holding it
+-- can renew the lock, store its response or release the key (NULL once completed)
+
+ALTER TABLE idempotency_keys
+    ADD COLUMN IF NOT EXISTS claim_id UUID;
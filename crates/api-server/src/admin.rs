@@ -0,0 +1,78 @@
+//! Admin access
+//!
+//! Stored bookings are returned with their traveler PII masked. Only requests
+//! carrying the admin key (`ADMIN_API_KEY`) in the `x-admin-key` header get
+//! the decrypted values; without a configured key, nobody does.
+
+use axum::http::HeaderMap;
+use sha2::{Digest, Sha256};
+
+/// Request header carrying the admin key
+pub const ADMIN_KEY_HEADER: &str = "x-admin-key";
+
+/// The admin key, kept as its SHA-256 hash
+#[derive(Clone, Default)]
+pub struct AdminKey(Option<[u8; 32]>);
+
+fn digest(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+impl AdminKey {
+    pub fn new(key: &str) -> Self {
+        let key = key.trim();
+        Self((!key.is_empty()).then(|| digest(key)))
+    }
+
+    /// Key of `ADMIN_API_KEY` (no admin access if it is not set)
+    pub fn from_env() -> Self {
+        Self::new(&std::env::var("ADMIN_API_KEY").unwrap_or_default())
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Whether the request carries the admin key. Hashes are compared so the
+    /// comparison takes the same time however much of the key matches.
+    pub fn allows(&self, headers: &HeaderMap) -> bool {
+        let Some(expected) = self.0 else {
+            return false;
+        };
+        headers
+            .get(ADMIN_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|key| {
+                digest(key.trim())
+                    .iter()
+                    .zip(expected)
+                    .fold(0, |diff, (a, b)| diff | (a ^ b))
+                    == 0
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_admin_key() {
+        let headers = |key: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(ADMIN_KEY_HEADER, HeaderValue::from_str(key).unwrap());
+            headers
+        };
+
+        let admin = AdminKey::new("s3cret");
+        assert!(admin.allows(&headers("s3cret")));
+        assert!(!admin.allows(&headers("s3cre")));
+        assert!(!admin.allows(&HeaderMap::new()));
+
+        // Without a key nobody is admin
+        let none = AdminKey::new("");
+        assert!(!none.is_configured());
+        assert!(!none.allows(&headers("")));
+    }
+}
@@ -10,13 +10,16 @@ use tokio::sync::RwLock;
 
 use crate::models::{
     AirTrafficBookedResponse, AirlineDestinationsResponse, AirlinesResponse, AmadeusErrorResponse,
-    BusiestPeriodResponse, CheckinLinksResponse, DirectDestinationsResponse,
+    BusiestPeriodResponse, Cabin, CabinCoverage, CheckinLinksResponse, DirectDestinationsResponse,
     FlightAvailabilityRequest, FlightAvailabilityResponse, FlightDatesResponse,
     FlightDelayPredictionResponse, FlightDestinationsResponse, FlightOffer, FlightOffersResponse,
     FlightOrderRequest, FlightOrderResponse, FlightPriceResponse, FlightSearchRequest,
-    FlightStatusResponse, ItineraryPriceMetricsResponse, LocationScoreResponse, LocationsResponse,
-    RecommendedLocationsResponse, SeatmapResponse,
+    FlightStatusResponse, FormOfPayment, ItineraryPriceMetricsResponse, LocationScoreResponse,
+    LocationsResponse, PaymentCard, PricingInclude, RecommendedLocationsResponse, SeatmapResponse,
 };
+use crate::passengers::PassengerMix;
+use crate::redaction;
+use crate::validation::MAX_RESULTS;
 
 /// Amadeus API Base URL - configurable via AMADEUS_ENV environment variable
 /// Set AMADEUS_ENV=production for production, otherwise uses test environment
@@ -170,101 +173,47 @@ pub async fn search_flights(
         );
     }
 
-    // Build travelers array - each traveler needs a unique ID
-    let mut travelers = Vec::new();
-    let mut traveler_id = 1;
+    // Build travelers array - rejects passenger mixes Amadeus would refuse
+    let travelers = PassengerMix::from(req).travelers()?;
 
-    // Add adults
-    for _ in 0..req.adults {
-        travelers.push(serde_json::json!({
-            "id": traveler_id.to_string(),
-            "travelerType": "ADULT"
-        }));
-        traveler_id += 1;
-    }
-
-    // Add children (ages 2-11)
-    for _ in 0..req.children {
-        travelers.push(serde_json::json!({
-            "id": traveler_id.to_string(),
-            "travelerType": "CHILD"
-        }));
-        traveler_id += 1;
-    }
-
-    // Add infants (under 2, on adult lap)
-    // HELD_INFANT = infant on adult's lap (no seat), requires associatedAdultId
-    for i in 0..req.infants {
-        // Associate each infant with a different adult (if possible)
-        let adult_id = (i % req.adults) + 1;
-        travelers.push(serde_json::json!({
-            "id": traveler_id.to_string(),
-            "travelerType": "HELD_INFANT",
-            "associatedAdultId": adult_id.to_string()
-        }));
-        traveler_id += 1;
-    }
-
-    // Build origin-destinations
-    let mut origin_destinations = vec![serde_json::json!({
-        "id": "1",
-        "originLocationCode": req.origin,
-        "destinationLocationCode": req.destination,
-        "departureDateTimeRange": {
-            "date": req.departure_date
-        }
-    })];
-
-    let mut leg_id = 2;
-
-    // Add return leg if round-trip (only if no additional legs for multi-city)
-    if req.additional_legs.is_none() {
-        if let Some(ref return_date) = req.return_date {
-            origin_destinations.push(serde_json::json!({
-                "id": leg_id.to_string(),
-                "originLocationCode": req.destination,
-                "destinationLocationCode": req.origin,
-                "departureDateTimeRange": {
-                    "date": return_date
-                }
-            }));
-            leg_id += 1;
-        }
-    }
-
-    // Add additional legs for multi-city search
-    if let Some(ref additional_legs) = req.additional_legs {
-        for leg in additional_legs {
-            origin_destinations.push(serde_json::json!({
-                "id": leg_id.to_string(),
+    // Build origin-destinations, each with its own cabin and connection restrictions
+    let legs = resolve_legs(req);
+    let origin_destinations: Vec<_> = legs
+        .iter()
+        .map(|leg| {
+            serde_json::json!({
+                "id": leg.id,
                 "originLocationCode": leg.origin,
                 "destinationLocationCode": leg.destination,
                 "departureDateTimeRange": {
                     "date": leg.departure_date
                 }
-            }));
-            leg_id += 1;
-        }
-    }
+            })
+        })
+        .collect();
 
     // Build search criteria
-    // Amadeus allows up to 250 results per request
+    // Amadeus allows up to 250 results per request. Offers breaking a stricter
+    // per-leg connection limit are dropped afterwards, so ask for as many as
+    // possible then and cut the filtered results down to the requested count.
+    let max_results = req.max_results.unwrap_or(MAX_RESULTS);
     let mut search_criteria = serde_json::json!({
-        "maxFlightOffers": req.max_results.unwrap_or(250)
+        "maxFlightOffers": if leg_limits_differ(&legs) { MAX_RESULTS } else { max_results }
     });
 
     // Add flight filters if specified
     let mut flight_filters = serde_json::Map::new();
 
-    if let Some(non_stop) = req.non_stop {
-        if non_stop {
-            flight_filters.insert(
-                "connectionRestriction".to_string(),
-                serde_json::json!({
-                    "maxNumberOfConnections": 0
-                }),
-            );
-        }
+    // connectionRestriction is global in Amadeus, so send the loosest per-leg limit
+    // and enforce the stricter ones on the returned itineraries
+    let limits: Option<Vec<u32>> = legs.iter().map(|leg| leg.max_connections).collect();
+    if let Some(max_connections) = limits.and_then(|limits| limits.into_iter().max()) {
+        flight_filters.insert(
+            "connectionRestriction".to_string(),
+            serde_json::json!({
+                "maxNumberOfConnections": max_connections
+            }),
+        );
     }
 
     if let Some(ref included) = req.included_airline_codes {
@@ -276,37 +225,33 @@ pub async fn search_flights(
                 }),
             );
         }
-    } else if let Some(ref excluded) = req.excluded_airline_codes {
-        if !excluded.is_empty() {
-            flight_filters.insert(
-                "carrierRestrictions".to_string(),
-                serde_json::json!({
-                    "excludedCarrierCodes": excluded
-                }),
-            );
-        }
+    } else if let Some(ref excluded) = req.excluded_airline_codes
+        && !excluded.is_empty()
+    {
+        flight_filters.insert(
+            "carrierRestrictions".to_string(),
+            serde_json::json!({
+                "excludedCarrierCodes": excluded
+            }),
+        );
     }
 
-    if let Some(ref travel_class) = req.travel_class {
-        // Build origin destination IDs based on trip type
-        let mut od_ids = vec!["1".to_string()];
-        if req.return_date.is_some() {
-            od_ids.push("2".to_string());
-        }
-        // Add IDs for additional legs in multi-city
-        if let Some(ref legs) = req.additional_legs {
-            for i in 0..legs.len() {
-                od_ids.push((i + 2).to_string());
-            }
-        }
-
+    let cabin_restrictions: Vec<_> = legs
+        .iter()
+        .filter_map(|leg| {
+            leg.cabin.map(|cabin| {
+                serde_json::json!({
+                    "cabin": cabin,
+                    "coverage": leg.coverage,
+                    "originDestinationIds": [leg.id]
+                })
+            })
+        })
+        .collect();
+    if !cabin_restrictions.is_empty() {
         flight_filters.insert(
             "cabinRestrictions".to_string(),
-            serde_json::json!([{
-                "cabin": travel_class,
-                "coverage": "ALL_SEGMENTS",
-                "originDestinationIds": od_ids
-            }]),
+            serde_json::Value::Array(cabin_restrictions),
         );
     }
 
@@ -351,8 +296,8 @@ pub async fn search_flights(
                 &response_text[..response_text.len().min(500)]
             );
 
-            let amadeus_resp: FlightOffersResponse =
-                serde_json::from_str(&response_text).map_err(|e| {
+            let mut amadeus_resp: FlightOffersResponse = serde_json::from_str(&response_text)
+                .map_err(|e| {
                     error!(
                         "Failed to parse Amadeus response: {}. Response: {}",
                         e,
@@ -361,6 +306,11 @@ pub async fn search_flights(
                     anyhow!("Failed to parse Amadeus response: {}", e)
                 })?;
 
+            apply_leg_connection_limits(&mut amadeus_resp.data, &legs);
+            amadeus_resp
+                .data
+                .truncate(usize::try_from(max_results).unwrap_or_default());
+
             info!("Flight search returned {} offers", amadeus_resp.data.len());
             return Ok(amadeus_resp);
         } else if response.status() == 429 {
@@ -419,25 +369,155 @@ pub async fn search_flights(
     }
 }
 
+/// Coverage used when a search does not specify one
+static DEFAULT_CABIN_COVERAGE: CabinCoverage = CabinCoverage::AllSegments;
+
+/// A single origin-destination of a search with its resolved restrictions
+#[derive(Debug, Clone, PartialEq)]
+struct SearchLeg<'a> {
+    id: String,
+    origin: &'a str,
+    destination: &'a str,
+    departure_date: &'a str,
+    cabin: Option<&'a Cabin>,
+    coverage: &'a CabinCoverage,
+    max_connections: Option<u32>,
+}
+
+/// Expand a search request into its origin-destinations.
+/// Leg-level cabin, coverage and connection limits override the request-level defaults.
+fn resolve_legs(req: &FlightSearchRequest) -> Vec<SearchLeg<'_>> {
+    let default_coverage = req
+        .cabin_coverage
+        .as_ref()
+        .unwrap_or(&DEFAULT_CABIN_COVERAGE);
+    let default_max_connections = match (req.max_connections, req.non_stop) {
+        (Some(max), _) => Some(max),
+        (None, Some(true)) => Some(0),
+        _ => None,
+    };
+
+    let mut legs = vec![SearchLeg {
+        id: "1".to_string(),
+        origin: &req.origin,
+        destination: &req.destination,
+        departure_date: &req.departure_date,
+        cabin: req.travel_class.as_ref(),
+        coverage: default_coverage,
+        max_connections: default_max_connections,
+    }];
+
+    // Add return leg if round-trip (only if no additional legs for multi-city)
+    if req.additional_legs.is_none()
+        && let Some(ref return_date) = req.return_date
+    {
+        legs.push(SearchLeg {
+            id: "2".to_string(),
+            origin: &req.destination,
+            destination: &req.origin,
+            departure_date: return_date,
+            cabin: req
+                .return_travel_class
+                .as_ref()
+                .or(req.travel_class.as_ref()),
+            coverage: req
+                .return_cabin_coverage
+                .as_ref()
+                .unwrap_or(default_coverage),
+            max_connections: req.return_max_connections.or(default_max_connections),
+        });
+    }
+
+    // Add additional legs for multi-city search
+    for leg in req.additional_legs.iter().flatten() {
+        legs.push(SearchLeg {
+            id: (legs.len() + 1).to_string(),
+            origin: &leg.origin,
+            destination: &leg.destination,
+            departure_date: &leg.departure_date,
+            cabin: leg.travel_class.as_ref().or(req.travel_class.as_ref()),
+            coverage: leg.cabin_coverage.as_ref().unwrap_or(default_coverage),
+            max_connections: leg.max_connections.or(default_max_connections),
+        });
+    }
+
+    legs
+}
+
+/// Whether some legs have a stricter connection limit than Amadeus applies to
+/// all of them, so that `apply_leg_connection_limits` can drop offers
+fn leg_limits_differ(legs: &[SearchLeg<'_>]) -> bool {
+    legs.windows(2)
+        .any(|pair| pair[0].max_connections != pair[1].max_connections)
+}
+
+/// Drop offers whose itineraries exceed the connection limit of their leg.
+/// Itineraries are returned in the same order as the requested origin-destinations.
+fn apply_leg_connection_limits(offers: &mut Vec<FlightOffer>, legs: &[SearchLeg<'_>]) {
+    offers.retain(|offer| {
+        offer
+            .itineraries
+            .iter()
+            .zip(legs)
+            .all(|(itinerary, leg)| match leg.max_connections {
+                Some(max) => itinerary.segments.len().saturating_sub(1) <= max as usize,
+                None => true,
+            })
+    });
+}
+
+/// Form of payment as Amadeus expects it, with the card number and security
+/// code that serialize redacted everywhere else
+fn upstream_form_of_payment(fop: &FormOfPayment) -> serde_json::Value {
+    let mut value = serde_json::to_value(fop).unwrap_or_default();
+    if let Some(ref card) = fop.credit_card {
+        value["creditCard"]["number"] = card.number.as_ref().map(|number| number.expose()).into();
+        value["creditCard"]["securityCode"] = card
+            .security_code
+            .as_ref()
+            .map(|code| code.expose().as_str())
+            .into();
+    }
+    value
+}
+
 /// Price flight offers - confirms price and gets detailed pricing info
 /// POST /v1/shopping/flight-offers/pricing
 pub async fn price_flight_offers(
     client: &Client,
     token: &str,
     flight_offers: &[FlightOffer],
-    include_bags: bool,
+    include: &[PricingInclude],
+    payment_card: Option<&PaymentCard>,
 ) -> Result<FlightPriceResponse> {
-    let body = serde_json::json!({
+    let mut body = serde_json::json!({
         "data": {
             "type": "flight-offers-pricing",
             "flightOffers": flight_offers
         }
     });
 
+    // Card to quote credit card fees for
+    if let Some(card) = payment_card {
+        let mut payment = serde_json::json!({
+            "brand": card.brand,
+            "flightOfferIds": flight_offers.iter().map(|offer| &offer.id).collect::<Vec<_>>()
+        });
+        if let Some(bin) = card
+            .bin_number
+            .as_deref()
+            .and_then(|bin| bin.parse::<u32>().ok())
+        {
+            payment["binNumber"] = bin.into();
+        }
+        body["data"]["payments"] = serde_json::json!([payment]);
+    }
+
     // Build URL with optional include parameter
     let mut url = format!("{}/v1/shopping/flight-offers/pricing", get_base_url());
-    if include_bags {
-        url.push_str("?include=bags");
+    if !include.is_empty() {
+        let include: Vec<&str> = include.iter().map(PricingInclude::as_str).collect();
+        url.push_str(&format!("?include={}", include.join(",")));
     }
 
     let response = client
@@ -484,7 +564,7 @@ pub async fn create_flight_order(
 
     // Add formOfPayment if provided
     if let Some(ref fop) = order_request.form_of_payment {
-        data["formOfPayment"] = serde_json::to_value(fop).unwrap_or_default();
+        data["formOfPayment"] = upstream_form_of_payment(fop);
     }
 
     let body = serde_json::json!({
@@ -522,10 +602,12 @@ pub async fn create_flight_order(
             }
         }
 
+        // The error is logged and stored with the booking, so it must not
+        // carry card or document numbers Amadeus echoes back
         return Err(anyhow!(
             "Flight order creation failed with status {}: {}",
             status,
-            error_text
+            redaction::scrub(&error_text)
         ));
     }
 
@@ -548,6 +630,18 @@ pub async fn get_flight_order(
     token: &str,
     order_id: &str,
 ) -> Result<FlightOrderResponse> {
+    find_flight_order(client, token, order_id)
+        .await?
+        .ok_or_else(|| anyhow!("Flight order {} not found", order_id))
+}
+
+/// Get a flight order by ID, `None` if Amadeus no longer knows it (deleted or
+/// cancelled by the airline)
+pub async fn find_flight_order(
+    client: &Client,
+    token: &str,
+    order_id: &str,
+) -> Result<Option<FlightOrderResponse>> {
     let response = client
         .get(format!(
             "{}/v1/booking/flight-orders/{}",
@@ -558,6 +652,9 @@ pub async fn get_flight_order(
         .send()
         .await?;
 
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
     if !response.status().is_success() {
         let status = response.status();
         let error_text = response.text().await.unwrap_or_default();
@@ -573,7 +670,7 @@ pub async fn get_flight_order(
         .await
         .map_err(|e| anyhow!("Failed to parse order response: {}", e))?;
 
-    Ok(order_resp)
+    Ok(Some(order_resp))
 }
 
 /// Delete (cancel) a flight order by ID
@@ -699,7 +796,8 @@ pub async fn get_upsell_offers(
         }
     });
 
-    tracing::info!(
+    tracing::info!("Upsell request for {} offers", flight_offers.len());
+    tracing::debug!(
         "Upsell request body: {}",
         serde_json::to_string(&body).unwrap_or_default()
     );
@@ -725,6 +823,7 @@ pub async fn get_upsell_offers(
             return Ok(FlightOffersResponse {
                 data: vec![],
                 dictionaries: None,
+                brand_comparison: None,
             });
         }
 
@@ -924,6 +1023,7 @@ pub async fn get_itinerary_price_metrics(
 
 /// Predict flight delay probability
 /// GET /v1/travel/predictions/flight-delay
+#[allow(clippy::too_many_arguments)]
 pub async fn predict_flight_delay(
     client: &Client,
     token: &str,
@@ -1476,3 +1576,134 @@ pub async fn get_location_score(
 
     Ok(resp)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search_request(json: serde_json::Value) -> FlightSearchRequest {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn offer_with_segments(segments_per_itinerary: &[usize]) -> FlightOffer {
+        let segment = serde_json::json!({
+            "id": "1",
+            "departure": { "iataCode": "FRA", "at": "2025-06-15T10:00:00" },
+            "arrival": { "iataCode": "JFK", "at": "2025-06-15T13:00:00" },
+            "carrierCode": "LH",
+            "number": "400",
+            "aircraft": { "code": "744" }
+        });
+        let itineraries: Vec<_> = segments_per_itinerary
+            .iter()
+            .map(|&count| serde_json::json!({ "segments": vec![segment.clone(); count] }))
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "type": "flight-offer",
+            "source": "GDS",
+            "itineraries": itineraries,
+            "price": { "currency": "EUR", "total": "100.00", "base": "80.00" },
+            "validatingAirlineCodes": ["LH"],
+            "travelerPricings": []
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_legs_per_leg_overrides() {
+        let req = search_request(serde_json::json!({
+            "origin": "FRA",
+            "destination": "JFK",
+            "departureDate": "2025-06-15",
+            "returnDate": "2025-06-22",
+            "adults": 1,
+            "travelClass": "BUSINESS",
+            "nonStop": true,
+            "returnTravelClass": "ECONOMY",
+            "returnCabinCoverage": "MOST_SEGMENTS",
+            "returnMaxConnections": 1
+        }));
+
+        let legs = resolve_legs(&req);
+        assert_eq!(legs.len(), 2);
+        assert_eq!(legs[0].cabin, Some(&Cabin::Business));
+        assert_eq!(legs[0].coverage, &CabinCoverage::AllSegments);
+        assert_eq!(legs[0].max_connections, Some(0));
+        assert_eq!(legs[1].origin, "JFK");
+        assert_eq!(legs[1].cabin, Some(&Cabin::Economy));
+        assert_eq!(legs[1].coverage, &CabinCoverage::MostSegments);
+        assert_eq!(legs[1].max_connections, Some(1));
+
+        // One limit for all legs is left to Amadeus
+        let req = search_request(serde_json::json!({
+            "origin": "FRA",
+            "destination": "JFK",
+            "departureDate": "2025-06-15",
+            "returnDate": "2025-06-22",
+            "adults": 1,
+            "maxConnections": 1
+        }));
+        assert!(!leg_limits_differ(&resolve_legs(&req)));
+    }
+
+    #[test]
+    fn test_resolve_legs_multi_city_defaults() {
+        let req = search_request(serde_json::json!({
+            "origin": "FRA",
+            "destination": "JFK",
+            "departureDate": "2025-06-15",
+            "returnDate": "2025-06-22",
+            "adults": 1,
+            "travelClass": "PREMIUM_ECONOMY",
+            "additionalLegs": [
+                { "origin": "JFK", "destination": "LAX", "departureDate": "2025-06-18" },
+                {
+                    "origin": "LAX",
+                    "destination": "FRA",
+                    "departureDate": "2025-06-25",
+                    "travelClass": "FIRST",
+                    "maxConnections": 2
+                }
+            ]
+        }));
+
+        let legs = resolve_legs(&req);
+        // The return date is ignored for multi-city searches
+        let ids: Vec<_> = legs.iter().map(|leg| leg.id.as_str()).collect();
+        assert_eq!(ids, ["1", "2", "3"]);
+        assert_eq!(legs[1].cabin, Some(&Cabin::PremiumEconomy));
+        assert_eq!(legs[1].max_connections, None);
+        assert_eq!(legs[2].cabin, Some(&Cabin::First));
+        assert_eq!(legs[2].max_connections, Some(2));
+    }
+
+    #[test]
+    fn test_apply_leg_connection_limits() {
+        let req = search_request(serde_json::json!({
+            "origin": "FRA",
+            "destination": "JFK",
+            "departureDate": "2025-06-15",
+            "returnDate": "2025-06-22",
+            "adults": 1,
+            "maxConnections": 0,
+            "returnMaxConnections": 1
+        }));
+        let legs = resolve_legs(&req);
+
+        let mut offers = vec![
+            offer_with_segments(&[1, 1]),
+            offer_with_segments(&[1, 2]),
+            offer_with_segments(&[2, 1]),
+            offer_with_segments(&[1, 3]),
+        ];
+        assert!(leg_limits_differ(&legs));
+        apply_leg_connection_limits(&mut offers, &legs);
+
+        let kept: Vec<_> = offers
+            .iter()
+            .map(|offer| offer.itineraries[1].segments.len())
+            .collect();
+        assert_eq!(kept, [1, 2]);
+    }
+}
@@ -0,0 +1,665 @@
+//! Ancillary services added to flight offers before ordering
+//!
+//! Ancillaries are booked by writing them into
+//! `FareDetailsBySegment.additional_services` and pricing the offer again;
+//! the priced offer then carries the service prices and can be ordered.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::models::{
+    AdditionalServices, BagOption, BagSelection, ChargeableCheckedBags, FareDetailsBySegment,
+    FlightOffer, Seat, SeatAvailabilityStatus, SeatSelection, SeatTravelerPricing, SeatmapResponse,
+    TravelerPricing, TravelerType,
+};
+use crate::money::Money;
+use crate::validation::ValidationErrors;
+
+/// Seat characteristic: exit row
+const EXIT_ROW: &str = "E";
+
+/// Seat characteristic: not allowed for infants
+const NOT_FOR_INFANT: &str = "1A";
+
+/// Seat characteristic: not suitable for children
+const NOT_FOR_CHILD: &str = "IE";
+
+/// Check bag selections against the catalog returned by pricing with
+/// `include=bags` and the travelers and segments of the offer
+pub fn check_bag_selections(
+    errors: &mut ValidationErrors,
+    field: &str,
+    offer: &FlightOffer,
+    catalog: &HashMap<String, BagOption>,
+    selections: &[BagSelection],
+) {
+    let mut selected = HashSet::new();
+    for (i, selection) in selections.iter().enumerate() {
+        let field = |name: &str| format!("{}[{}].{}", field, i, name);
+
+        let Some(option) = catalog.get(&selection.bag_option_id) else {
+            errors.add(
+                field("bagOptionId"),
+                format!("Unknown bag option '{}'", selection.bag_option_id),
+            );
+            continue;
+        };
+
+        let Some(pricing) = offer
+            .traveler_pricings
+            .iter()
+            .find(|pricing| pricing.traveler_id == selection.traveler_id)
+        else {
+            errors.add(
+                field("travelerId"),
+                format!(
+                    "Traveler '{}' is not part of the offer",
+                    selection.traveler_id
+                ),
+            );
+            continue;
+        };
+        if !option.traveler_ids.is_empty() && !option.traveler_ids.contains(&selection.traveler_id)
+        {
+            errors.add(
+                field("travelerId"),
+                format!(
+                    "Bag option '{}' is not available for traveler '{}'",
+                    selection.bag_option_id, selection.traveler_id
+                ),
+            );
+        }
+
+        for segment_id in selected_segments(selection, option) {
+            if !option.segment_ids.contains(segment_id) {
+                errors.add(
+                    field("segmentIds"),
+                    format!(
+                        "Bag option '{}' is not available on segment '{}'",
+                        selection.bag_option_id, segment_id
+                    ),
+                );
+            } else if !pricing
+                .fare_details_by_segment
+                .iter()
+                .any(|details| &details.segment_id == segment_id)
+            {
+                errors.add(
+                    field("segmentIds"),
+                    format!("Segment '{}' is not part of the offer", segment_id),
+                );
+            } else if !selected.insert((selection.traveler_id.as_str(), segment_id.as_str())) {
+                errors.add(
+                    field("segmentIds"),
+                    format!(
+                        "Bags for traveler '{}' on segment '{}' are selected more than once",
+                        selection.traveler_id, segment_id
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn selected_segments<'a>(selection: &'a BagSelection, option: &'a BagOption) -> &'a [String] {
+    if selection.segment_ids.is_empty() {
+        &option.segment_ids
+    } else {
+        &selection.segment_ids
+    }
+}
+
+/// Write the chosen bags into the fare details of the offer.
+/// Selections must have been checked with `check_bag_selections`.
+pub fn apply_bag_selections(
+    offer: &mut FlightOffer,
+    catalog: &HashMap<String, BagOption>,
+    selections: &[BagSelection],
+) {
+    for selection in selections {
+        let Some(option) = catalog.get(&selection.bag_option_id) else {
+            continue;
+        };
+        let bags = ChargeableCheckedBags {
+            weight: option.weight,
+            weight_unit: option.weight_unit.clone(),
+            quantity: option.quantity,
+        };
+        let segments = selected_segments(selection, option);
+
+        let details = offer
+            .traveler_pricings
+            .iter_mut()
+            .filter(|pricing| pricing.traveler_id == selection.traveler_id)
+            .flat_map(|pricing| &mut pricing.fare_details_by_segment)
+            .filter(|details| segments.contains(&details.segment_id));
+        for details in details {
+            additional_services(details).chargeable_checked_bags = Some(bags.clone());
+        }
+    }
+}
+
+/// Catalog prices of the chosen bags, one per selection with a price
+pub fn bag_selection_prices(
+    catalog: &HashMap<String, BagOption>,
+    selections: &[BagSelection],
+) -> Vec<Money> {
+    selections
+        .iter()
+        .filter_map(|selection| catalog.get(&selection.bag_option_id)?.price.as_ref())
+        .map(|price| price.money())
+        .collect()
+}
+
+fn additional_services(details: &mut FareDetailsBySegment) -> &mut AdditionalServices {
+    details
+        .additional_services
+        .get_or_insert(AdditionalServices {
+            chargeable_checked_bags: None,
+            chargeable_seat_number: None,
+        })
+}
+
+/// Seat on the seatmap of a segment
+pub fn find_seat<'a>(
+    seatmaps: &'a SeatmapResponse,
+    segment_id: &str,
+    seat_number: &str,
+) -> Option<&'a Seat> {
+    seatmaps
+        .data
+        .iter()
+        .filter(|seatmap| seatmap.segment_id.as_deref() == Some(segment_id))
+        .flat_map(|seatmap| &seatmap.decks)
+        .flat_map(|deck| &deck.seats)
+        .find(|seat| seat.number.eq_ignore_ascii_case(seat_number))
+}
+
+/// Availability and price of a seat for one traveler
+pub fn seat_pricing<'a>(seat: &'a Seat, traveler_id: &str) -> Option<&'a SeatTravelerPricing> {
+    seat.traveler_pricing
+        .iter()
+        .flatten()
+        .find(|pricing| pricing.traveler_id.as_deref() == Some(traveler_id))
+}
+
+/// Whether the seat costs extra for the traveler
+pub fn is_paid_seat(seat: &Seat, traveler_id: &str) -> bool {
+    seat_pricing(seat, traveler_id)
+        .and_then(|pricing| pricing.price.as_ref())
+        .and_then(|price| price.total)
+        .is_some_and(|total| total.is_sign_positive() && !total.is_zero())
+}
+
+/// Whether the seat has a characteristic code (W window, A aisle, E exit row, ...)
+pub fn has_characteristic(seat: &Seat, code: &str) -> bool {
+    seat.characteristics_codes
+        .iter()
+        .flatten()
+        .any(|characteristic| characteristic == code)
+}
+
+/// Whether the seatmap offers the seat to the traveler
+pub fn is_available_for(seat: &Seat, traveler_id: &str) -> bool {
+    seat_pricing(seat, traveler_id)
+        .and_then(|pricing| pricing.seat_availability_status.as_ref())
+        .is_some_and(|status| *status == SeatAvailabilityStatus::Available)
+}
+
+/// Whether the traveler holds an infant on the lap or is a seated infant
+pub fn travels_with_infant(offer: &FlightOffer, pricing: &TravelerPricing) -> bool {
+    pricing.traveler_type == TravelerType::SeatedInfant
+        || offer.traveler_pricings.iter().any(|infant| {
+            infant.traveler_type == TravelerType::HeldInfant
+                && infant.associated_adult_id.as_deref() == Some(pricing.traveler_id.as_str())
+        })
+}
+
+/// Reasons the traveler may not sit in the seat: exit rows are for adults
+/// without infants, and some seats are not allowed with an infant or not
+/// suitable for children
+pub fn seat_restrictions(
+    seat: &Seat,
+    offer: &FlightOffer,
+    pricing: &TravelerPricing,
+) -> Vec<String> {
+    let mut restrictions = Vec::new();
+    let with_infant = travels_with_infant(offer, pricing);
+    if has_characteristic(seat, EXIT_ROW)
+        && (with_infant || !pricing.traveler_type.is_accompanying_adult())
+    {
+        restrictions.push(format!(
+            "Exit row seat '{}' is only for adults without infants",
+            seat.number
+        ));
+    }
+    if has_characteristic(seat, NOT_FOR_INFANT) && with_infant {
+        restrictions.push(format!(
+            "Seat '{}' is not allowed with an infant",
+            seat.number
+        ));
+    }
+    if has_characteristic(seat, NOT_FOR_CHILD)
+        && matches!(
+            pricing.traveler_type,
+            TravelerType::Child | TravelerType::SeatedInfant
+        )
+    {
+        restrictions.push(format!(
+            "Seat '{}' is not suitable for children",
+            seat.number
+        ));
+    }
+    restrictions
+}
+
+/// Check seat selections against the seatmaps of the offer: the seat must
+/// exist and be available for the traveler, and exit row and infant
+/// restrictions must be respected
+pub fn check_seat_selections(
+    errors: &mut ValidationErrors,
+    field: &str,
+    offer: &FlightOffer,
+    seatmaps: &SeatmapResponse,
+    selections: &[SeatSelection],
+) {
+    let mut travelers_seated = HashSet::new();
+    let mut seats_taken = HashSet::new();
+    for (i, selection) in selections.iter().enumerate() {
+        let field = |name: &str| format!("{}[{}].{}", field, i, name);
+
+        let Some(pricing) = offer
+            .traveler_pricings
+            .iter()
+            .find(|pricing| pricing.traveler_id == selection.traveler_id)
+        else {
+            errors.add(
+                field("travelerId"),
+                format!(
+                    "Traveler '{}' is not part of the offer",
+                    selection.traveler_id
+                ),
+            );
+            continue;
+        };
+        if !pricing.traveler_type.is_seated() {
+            errors.add(
+                field("travelerId"),
+                "Infants on a lap cannot be assigned a seat",
+            );
+            continue;
+        }
+        if !pricing
+            .fare_details_by_segment
+            .iter()
+            .any(|details| details.segment_id == selection.segment_id)
+        {
+            errors.add(
+                field("segmentId"),
+                format!(
+                    "Segment '{}' is not part of the offer",
+                    selection.segment_id
+                ),
+            );
+            continue;
+        }
+
+        let Some(seat) = find_seat(seatmaps, &selection.segment_id, &selection.seat_number) else {
+            errors.add(
+                field("seatNumber"),
+                format!(
+                    "Seat '{}' does not exist on segment '{}'",
+                    selection.seat_number, selection.segment_id
+                ),
+            );
+            continue;
+        };
+        if !is_available_for(seat, &selection.traveler_id) {
+            errors.add(
+                field("seatNumber"),
+                format!("Seat '{}' is not available", seat.number),
+            );
+        }
+        for restriction in seat_restrictions(seat, offer, pricing) {
+            errors.add(field("seatNumber"), restriction);
+        }
+
+        if !travelers_seated.insert((
+            selection.traveler_id.as_str(),
+            selection.segment_id.as_str(),
+        )) {
+            errors.add(
+                field("travelerId"),
+                format!(
+                    "Traveler '{}' has more than one seat on segment '{}'",
+                    selection.traveler_id, selection.segment_id
+                ),
+            );
+        }
+        if !seats_taken.insert((selection.segment_id.as_str(), seat.number.as_str())) {
+            errors.add(
+                field("seatNumber"),
+                format!("Seat '{}' is selected more than once", seat.number),
+            );
+        }
+    }
+}
+
+/// Seatmap prices of the chosen paid seats
+pub fn seat_selection_prices(
+    seatmaps: &SeatmapResponse,
+    selections: &[SeatSelection],
+) -> Vec<Money> {
+    selections
+        .iter()
+        .filter_map(|selection| {
+            let seat = find_seat(seatmaps, &selection.segment_id, &selection.seat_number)?;
+            seat_pricing(seat, &selection.traveler_id)?
+                .price
+                .as_ref()?
+                .total_money()
+        })
+        .filter(|price| !price.is_zero())
+        .collect()
+}
+
+/// Write the chosen seats into the fare details of the offer.
+/// Selections must have been checked with `check_seat_selections`.
+pub fn apply_seat_selections(offer: &mut FlightOffer, selections: &[SeatSelection]) {
+    for selection in selections {
+        let details = offer
+            .traveler_pricings
+            .iter_mut()
+            .filter(|pricing| pricing.traveler_id == selection.traveler_id)
+            .flat_map(|pricing| &mut pricing.fare_details_by_segment)
+            .filter(|details| details.segment_id == selection.segment_id);
+        for details in details {
+            additional_services(details).chargeable_seat_number =
+                Some(selection.seat_number.to_uppercase());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    fn offer() -> FlightOffer {
+        let pricing = |id: &str| {
+            serde_json::json!({
+                "travelerId": id,
+                "fareOption": "STANDARD",
+                "travelerType": "ADULT",
+                "price": { "currency": "EUR", "total": "150.00", "base": "100.00" },
+                "fareDetailsBySegment": [
+                    { "segmentId": "1", "cabin": "ECONOMY", "fareBasis": "YOWEU", "class": "Y" },
+                    { "segmentId": "2", "cabin": "ECONOMY", "fareBasis": "YOWEU", "class": "Y",
+                      "additionalServices": { "chargeableSeatNumber": "12A" } }
+                ]
+            })
+        };
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "type": "flight-offer",
+            "source": "GDS",
+            "itineraries": [],
+            "price": { "currency": "EUR", "total": "300.00", "base": "200.00" },
+            "validatingAirlineCodes": ["LH"],
+            "travelerPricings": [pricing("1"), pricing("2")]
+        }))
+        .unwrap()
+    }
+
+    fn catalog() -> HashMap<String, BagOption> {
+        serde_json::from_value(serde_json::json!({
+            "1": {
+                "quantity": 1,
+                "name": "CHECKED_BAG",
+                "price": { "amount": "30.00", "currencyCode": "EUR" },
+                "segmentIds": ["1", "2"],
+                "travelerIds": ["1", "2"]
+            },
+            "2": {
+                "quantity": 2,
+                "name": "CHECKED_BAG",
+                "price": { "amount": "70.00", "currencyCode": "EUR" },
+                "segmentIds": ["1"],
+                "travelerIds": ["1"]
+            }
+        }))
+        .unwrap()
+    }
+
+    fn selections(json: serde_json::Value) -> Vec<BagSelection> {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_bags_written_into_fare_details() {
+        let mut offer = offer();
+        let selections = selections(serde_json::json!([
+            { "bagOptionId": "1", "travelerId": "2" },
+            { "bagOptionId": "2", "travelerId": "1", "segmentIds": ["1"] }
+        ]));
+
+        let mut errors = ValidationErrors::new();
+        check_bag_selections(&mut errors, "selections", &offer, &catalog(), &selections);
+        assert!(errors.is_empty(), "{}", errors);
+
+        apply_bag_selections(&mut offer, &catalog(), &selections);
+        let bags = |traveler: usize, segment: usize| {
+            offer.traveler_pricings[traveler].fare_details_by_segment[segment]
+                .additional_services
+                .as_ref()
+                .and_then(|services| services.chargeable_checked_bags.as_ref())
+                .and_then(|bags| bags.quantity)
+        };
+        assert_eq!(bags(0, 0), Some(2));
+        assert_eq!(bags(0, 1), None);
+        assert_eq!(bags(1, 0), Some(1));
+        assert_eq!(bags(1, 1), Some(1));
+
+        // Seat selections on the segment are kept
+        let services = offer.traveler_pricings[1].fare_details_by_segment[1]
+            .additional_services
+            .as_ref()
+            .unwrap();
+        assert_eq!(services.chargeable_seat_number.as_deref(), Some("12A"));
+
+        let prices = bag_selection_prices(&catalog(), &selections);
+        let amounts: Vec<_> = prices.iter().map(|price| price.amount).collect();
+        assert_eq!(amounts, [Decimal::new(3000, 2), Decimal::new(7000, 2)]);
+    }
+
+    #[test]
+    fn test_invalid_bag_selections() {
+        let selections = selections(serde_json::json!([
+            { "bagOptionId": "9", "travelerId": "1" },
+            { "bagOptionId": "2", "travelerId": "2" },
+            { "bagOptionId": "2", "travelerId": "1", "segmentIds": ["2"] },
+            { "bagOptionId": "1", "travelerId": "3" },
+            { "bagOptionId": "1", "travelerId": "1" },
+            { "bagOptionId": "1", "travelerId": "1", "segmentIds": ["1"] }
+        ]));
+
+        let mut errors = ValidationErrors::new();
+        check_bag_selections(&mut errors, "selections", &offer(), &catalog(), &selections);
+        let fields: Vec<&str> = errors.errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            [
+                "selections[0].bagOptionId",
+                "selections[1].travelerId",
+                "selections[2].segmentIds",
+                "selections[3].travelerId",
+                "selections[5].segmentIds",
+            ]
+        );
+    }
+
+    fn family_offer() -> FlightOffer {
+        let pricing = |id: &str, traveler_type: &str, adult: Option<&str>| {
+            serde_json::json!({
+                "travelerId": id,
+                "fareOption": "STANDARD",
+                "travelerType": traveler_type,
+                "associatedAdultId": adult,
+                "price": { "currency": "EUR", "total": "100.00", "base": "80.00" },
+                "fareDetailsBySegment": [
+                    { "segmentId": "1", "cabin": "ECONOMY", "fareBasis": "YOWEU", "class": "Y" }
+                ]
+            })
+        };
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "type": "flight-offer",
+            "source": "GDS",
+            "itineraries": [],
+            "price": { "currency": "EUR", "total": "300.00", "base": "240.00" },
+            "validatingAirlineCodes": ["LH"],
+            "travelerPricings": [
+                pricing("1", "ADULT", None),
+                pricing("2", "CHILD", None),
+                pricing("3", "HELD_INFANT", Some("1")),
+                pricing("4", "ADULT", None)
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn seatmaps() -> SeatmapResponse {
+        let seat = |number: &str, codes: &[&str], status: &str, total: &str| {
+            let pricing: Vec<_> = ["1", "2", "4"]
+                .iter()
+                .map(|id| {
+                    serde_json::json!({
+                        "travelerId": id,
+                        "seatAvailabilityStatus": status,
+                        "price": { "currency": "EUR", "total": total, "base": total }
+                    })
+                })
+                .collect();
+            serde_json::json!({
+                "number": number,
+                "characteristicsCodes": codes,
+                "travelerPricing": pricing
+            })
+        };
+        serde_json::from_value(serde_json::json!({
+            "data": [{
+                "type": "seatmap",
+                "flightOfferId": "1",
+                "segmentId": "1",
+                "decks": [{
+                    "seats": [
+                        seat("10A", &["W"], "AVAILABLE", "0"),
+                        seat("10B", &["1A"], "AVAILABLE", "0"),
+                        seat("10C", &["IE"], "AVAILABLE", "0"),
+                        seat("14C", &["E", "A"], "AVAILABLE", "25.00"),
+                        seat("20A", &["W"], "OCCUPIED", "0")
+                    ]
+                }]
+            }]
+        }))
+        .unwrap()
+    }
+
+    fn seat_selections(json: serde_json::Value) -> Vec<SeatSelection> {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_seats_written_into_fare_details() {
+        let mut offer = family_offer();
+        let seatmaps = seatmaps();
+        let selections = seat_selections(serde_json::json!([
+            { "travelerId": "1", "segmentId": "1", "seatNumber": "10a" },
+            { "travelerId": "4", "segmentId": "1", "seatNumber": "14C" }
+        ]));
+
+        let mut errors = ValidationErrors::new();
+        check_seat_selections(&mut errors, "selections", &offer, &seatmaps, &selections);
+        assert!(errors.is_empty(), "{}", errors);
+
+        let seat = |number: &str| find_seat(&seatmaps, "1", number).unwrap();
+        assert!(!is_paid_seat(seat("10A"), "1"));
+        assert!(is_paid_seat(seat("14C"), "4"));
+
+        apply_seat_selections(&mut offer, &selections);
+        let seat_number = |traveler: usize| {
+            offer.traveler_pricings[traveler].fare_details_by_segment[0]
+                .additional_services
+                .as_ref()
+                .and_then(|services| services.chargeable_seat_number.as_deref())
+        };
+        assert_eq!(seat_number(0), Some("10A"));
+        assert_eq!(seat_number(3), Some("14C"));
+        assert_eq!(seat_number(1), None);
+
+        // Only the paid seat adds to the price
+        let prices = seat_selection_prices(&seatmaps, &selections);
+        let amounts: Vec<_> = prices.iter().map(|price| price.amount).collect();
+        assert_eq!(amounts, [Decimal::new(2500, 2)]);
+    }
+
+    #[test]
+    fn test_infant_and_child_restricted_seats() {
+        let offer = family_offer();
+        let seatmaps = seatmaps();
+        let restrictions = |number: &str, traveler: usize| {
+            let seat = find_seat(&seatmaps, "1", number).unwrap();
+            seat_restrictions(seat, &offer, &offer.traveler_pricings[traveler]).len()
+        };
+
+        // 1A: not with an infant, fine for children
+        assert_eq!(restrictions("10B", 0), 1);
+        assert_eq!(restrictions("10B", 1), 0);
+        // IE: not for children, fine for adults with an infant
+        assert_eq!(restrictions("10C", 1), 1);
+        assert_eq!(restrictions("10C", 0), 0);
+    }
+
+    #[test]
+    fn test_invalid_seat_selections() {
+        let selections = seat_selections(serde_json::json!([
+            { "travelerId": "1", "segmentId": "1", "seatNumber": "14C" },
+            { "travelerId": "2", "segmentId": "1", "seatNumber": "14C" },
+            { "travelerId": "1", "segmentId": "1", "seatNumber": "10B" },
+            { "travelerId": "3", "segmentId": "1", "seatNumber": "10A" },
+            { "travelerId": "4", "segmentId": "1", "seatNumber": "20A" },
+            { "travelerId": "4", "segmentId": "2", "seatNumber": "10A" },
+            { "travelerId": "2", "segmentId": "1", "seatNumber": "99Z" }
+        ]));
+
+        let mut errors = ValidationErrors::new();
+        check_seat_selections(
+            &mut errors,
+            "selections",
+            &family_offer(),
+            &seatmaps(),
+            &selections,
+        );
+        let fields: Vec<&str> = errors.errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            [
+                // Adult holding an infant in the exit row
+                "selections[0].seatNumber",
+                // Child in the exit row, seat taken twice
+                "selections[1].seatNumber",
+                "selections[1].seatNumber",
+                // Infant restricted seat, second seat for traveler 1
+                "selections[2].seatNumber",
+                "selections[2].travelerId",
+                // Lap infant
+                "selections[3].travelerId",
+                // Occupied
+                "selections[4].seatNumber",
+                // Segment not in the offer
+                "selections[5].segmentId",
+                // Unknown seat
+                "selections[6].seatNumber",
+            ]
+        );
+    }
+}
@@ -0,0 +1,1460 @@
+//! Booking records
+//!
+//! Every order attempt is stored in `flight_bookings` before Amadeus is called
+//! and then moves through an explicit state machine:
+//!
+//! ```text
+//! PENDING -> PRICED -> [AUTHORIZED ->] ORDERED -> TICKETED
+//! ```
+//!
+//! AUTHORIZED is passed when a payment provider took the payment (see
+//! `payments`); each step of that payment is kept in `booking_payments`.
+//! Bookings that are not ticketed yet may end in FAILED, and any booking that
+//! has not failed may be CANCELLED. Each transition is checked and written to
+//! `booking_status_history` with its time (see
+//! migrations/003_booking_state_machine.sql), so support staff can look up a
+//! booking without asking Amadeus. Ordered bookings also keep their ticketing
+//! deadline, and `booking_events` records what the ticketing scheduler did
+//! about it (see `ticketing`). Differences to the live Amadeus order found by
+//! the reconciliation job are kept in `booking_changes` (see `reconciliation`).
+//! Cancellations are quoted first and confirmed later; both steps are kept in
+//! `booking_cancellations` (see `cancellation`).
+//!
+//! With `PII_ENCRYPTION_KEYS` set, the traveler PII in `passenger_data` and
+//! `booking_data` is encrypted on every write (see `pii`). Records are read
+//! with the fields encrypted; `reveal` decrypts them for the code paths that
+//! need them, and `BookingRecord::mask_pii` hides them from everyone else.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::PgPool;
+use sqlx::types::Json;
+use std::fmt;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::models::{
+    FlightOffer, FlightOrderRequest, FlightOrderResponse, RefundEstimate, TravelerType,
+};
+use crate::money::Money;
+use crate::payments::{Payment, PaymentStatus};
+use crate::pii::{self, DataKey, PiiError, PiiKeys, SealedKey};
+use crate::reconciliation::OrderChange;
+use crate::ticketing;
+
+/// Status of a booking
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BookingStatus {
+    /// Order attempt received
+    Pending,
+    /// Offers priced again and booking requirements met
+    Priced,
+    /// Sale price authorized on the customer's card
+    Authorized,
+    /// Order created at Amadeus (PNR exists)
+    Ordered,
+    /// Tickets issued
+    Ticketed,
+    Cancelled,
+    /// Order creation or ticketing failed
+    Failed,
+}
+
+impl BookingStatus {
+    pub const ALL: [BookingStatus; 7] = [
+        BookingStatus::Pending,
+        BookingStatus::Priced,
+        BookingStatus::Authorized,
+        BookingStatus::Ordered,
+        BookingStatus::Ticketed,
+        BookingStatus::Cancelled,
+        BookingStatus::Failed,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BookingStatus::Pending => "PENDING",
+            BookingStatus::Priced => "PRICED",
+            BookingStatus::Authorized => "AUTHORIZED",
+            BookingStatus::Ordered => "ORDERED",
+            BookingStatus::Ticketed => "TICKETED",
+            BookingStatus::Cancelled => "CANCELLED",
+            BookingStatus::Failed => "FAILED",
+        }
+    }
+
+    /// Whether a booking in this status may move to `to`
+    pub fn can_transition_to(&self, to: BookingStatus) -> bool {
+        use BookingStatus::*;
+        matches!(
+            (self, to),
+            (Pending, Priced | Failed | Cancelled)
+                | (Priced, Authorized | Ordered | Failed | Cancelled)
+                | (Authorized, Ordered | Failed | Cancelled)
+                | (Ordered, Ticketed | Failed | Cancelled)
+                | (Ticketed, Cancelled)
+        )
+    }
+}
+
+impl fmt::Display for BookingStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TryFrom<String> for BookingStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        BookingStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == value)
+            .ok_or_else(|| format!("Unknown booking status {}", value))
+    }
+}
+
+/// Errors of the booking store
+#[derive(Debug)]
+pub enum BookingError {
+    NotFound,
+    InvalidTransition {
+        from: BookingStatus,
+        to: BookingStatus,
+    },
+    /// The request does not fit the current state of the booking
+    Conflict(String),
+    /// Traveler PII could not be encrypted or decrypted
+    Encryption(PiiError),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for BookingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookingError::NotFound => write!(f, "Booking not found"),
+            BookingError::InvalidTransition { from, to } => {
+                write!(f, "Booking cannot move from {} to {}", from, to)
+            }
+            BookingError::Conflict(message) => write!(f, "{}", message),
+            BookingError::Encryption(e) => write!(f, "{}", e),
+            BookingError::Database(e) => write!(f, "Booking database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BookingError {}
+
+impl From<PiiError> for BookingError {
+    fn from(e: PiiError) -> Self {
+        BookingError::Encryption(e)
+    }
+}
+
+impl From<sqlx::Error> for BookingError {
+    fn from(e: sqlx::Error) -> Self {
+        BookingError::Database(e)
+    }
+}
+
+/// A stored booking
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct BookingRecord {
+    pub id: Uuid,
+    #[sqlx(try_from = "String")]
+    pub status: BookingStatus,
+    /// Amadeus flight order id
+    pub order_id: Option<String>,
+    pub pnr: Option<String>,
+    pub amadeus_offer_id: Option<String>,
+    pub departure_date: Option<NaiveDate>,
+    pub return_date: Option<NaiveDate>,
+    pub adults: i32,
+    pub children: i32,
+    pub infants: i32,
+    pub total_price: Option<Decimal>,
+    pub currency_code: Option<String>,
+    pub passenger_data: Option<serde_json::Value>,
+    pub booking_data: Option<serde_json::Value>,
+    pub failure_reason: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// When Amadeus confirmed the order
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+    /// Latest time the order can be ticketed
+    pub ticketing_deadline: Option<DateTime<Utc>>,
+    /// Ticketing agreement of the order (CONFIRM, DELAY_TO_QUEUE, DELAY_TO_CANCEL)
+    pub ticketing_option: Option<String>,
+    /// Key encryption key sealing `pii_data_key`; `None` if the PII is not encrypted
+    #[serde(skip)]
+    pub pii_key_id: Option<String>,
+    /// Data key of the booking's PII fields, sealed
+    #[serde(skip)]
+    pub pii_data_key: Option<String>,
+}
+
+impl BookingRecord {
+    fn sealed_key(&self) -> Option<SealedKey> {
+        sealed_key(self.pii_key_id.clone(), self.pii_data_key.clone())
+    }
+
+    /// Mask the traveler PII, whether it is encrypted or not
+    pub fn mask_pii(&mut self) {
+        if let Some(ref mut data) = self.passenger_data {
+            pii::mask(data, &pii::PASSENGER_FIELDS);
+        }
+        if let Some(ref mut data) = self.booking_data {
+            pii::mask(data, &pii::ORDER_FIELDS);
+        }
+    }
+}
+
+fn sealed_key(key_id: Option<String>, sealed: Option<String>) -> Option<SealedKey> {
+    Some(SealedKey {
+        key_id: key_id?,
+        sealed: sealed?,
+    })
+}
+
+/// Columns of `flight_bookings` read into a `BookingRecord`
+const BOOKING_COLUMNS: &str = "id, status, order_id, pnr, amadeus_offer_id, departure_date, \
+    return_date, adults, children, infants, total_price, currency_code, passenger_data, \
+    booking_data, failure_reason, created_at, updated_at, confirmed_at, cancelled_at, \
+    ticketing_deadline, ticketing_option, pii_key_id, pii_data_key";
+
+/// Data key of a booking with the given sealed key, or a new one (returned
+/// sealed as well, to be stored); `None` without encryption keys
+fn data_key(
+    keys: Option<&PiiKeys>,
+    sealed: Option<SealedKey>,
+) -> Result<Option<(DataKey, Option<SealedKey>)>, PiiError> {
+    let Some(keys) = keys else {
+        return Ok(None);
+    };
+    Ok(Some(match sealed {
+        Some(sealed) => (keys.open(&sealed)?, None),
+        None => {
+            let (key, sealed) = keys.new_data_key();
+            (key, Some(sealed))
+        }
+    }))
+}
+
+/// Data key given to a booking stored without encryption, with its passenger
+/// data encrypted by it; both are stored together
+#[derive(Debug)]
+struct NewDataKey {
+    sealed: SealedKey,
+    passenger_data: Option<serde_json::Value>,
+}
+
+/// Encrypt the PII of an order about to be stored with a booking. A booking
+/// that has no data key yet gets one here, and its passenger data (as stored)
+/// is encrypted in the same go: the rotation job skips bookings with a data
+/// key, so it would stay unencrypted otherwise.
+fn seal_order(
+    keys: Option<&PiiKeys>,
+    sealed: Option<SealedKey>,
+    booking_data: &mut Option<serde_json::Value>,
+    mut passenger_data: Option<serde_json::Value>,
+) -> Result<Option<NewDataKey>, PiiError> {
+    let Some(data) = booking_data else {
+        return Ok(None);
+    };
+    let Some((key, new_key)) = data_key(keys, sealed)? else {
+        return Ok(None);
+    };
+    key.seal(data, &pii::ORDER_FIELDS);
+    Ok(new_key.map(|sealed| {
+        if let Some(ref mut passengers) = passenger_data {
+            key.seal(passengers, &pii::PASSENGER_FIELDS);
+        }
+        NewDataKey {
+            sealed,
+            passenger_data,
+        }
+    }))
+}
+
+/// Outcome of one batch of `BookingStore::rotate_pii_keys`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RotationBatch {
+    /// Bookings resealed or encrypted
+    pub rotated: usize,
+    /// Last booking looked at, where the next batch continues; `None` when done
+    pub last_id: Option<Uuid>,
+}
+
+/// A status change of a booking
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusChange {
+    pub from_status: Option<String>,
+    pub to_status: String,
+    pub reason: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Something that happened to a booking outside of a status change
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct BookingEvent {
+    pub kind: String,
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A difference between the stored and the live Amadeus order
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct BookingChange {
+    pub kind: String,
+    pub segment_id: Option<String>,
+    pub traveler_id: Option<String>,
+    pub description: String,
+    pub previous_value: Option<String>,
+    pub current_value: Option<String>,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Schedule disruption waiting for an alternative to be accepted
+pub const DISRUPTION_OPEN: &str = "OPEN";
+/// Schedule disruption with an accepted alternative
+pub const DISRUPTION_ACCEPTED: &str = "ACCEPTED";
+
+/// Schedule changes of a booked itinerary with alternative offers
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleDisruption {
+    pub id: Uuid,
+    pub booking_id: Uuid,
+    /// Index of the affected itinerary in the order's offers
+    pub itinerary_index: i32,
+    /// OPEN or ACCEPTED
+    pub status: String,
+    pub changes: Json<Vec<OrderChange>>,
+    /// Alternative offers (net fares, as returned by Amadeus)
+    pub alternatives: Json<Vec<FlightOffer>>,
+    pub accepted_offer_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// Columns of `schedule_disruptions` read into a `ScheduleDisruption`
+const DISRUPTION_COLUMNS: &str = "id, booking_id, itinerary_index, status, changes, alternatives, \
+    accepted_offer_id, created_at, resolved_at";
+
+/// Cancellation quote waiting for confirmation
+pub const CANCELLATION_QUOTED: &str = "QUOTED";
+/// Cancellation being performed at Amadeus
+pub const CANCELLATION_CONFIRMING: &str = "CONFIRMING";
+pub const CANCELLATION_CONFIRMED: &str = "CONFIRMED";
+pub const CANCELLATION_FAILED: &str = "FAILED";
+
+/// A cancellation quote and, once confirmed, its outcome
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct CancellationRecord {
+    pub id: Uuid,
+    pub booking_id: Uuid,
+    /// QUOTED, CONFIRMING, CONFIRMED or FAILED
+    pub status: String,
+    pub currency: String,
+    pub quoted_refund: Option<Decimal>,
+    pub quote: Json<RefundEstimate>,
+    pub expires_at: DateTime<Utc>,
+    pub cancelled_by: Option<String>,
+    pub reason: Option<String>,
+    pub actual_refund: Option<Decimal>,
+    /// Refund estimate as of the cancellation
+    pub outcome: Option<Json<RefundEstimate>>,
+    /// Why cancelling at Amadeus failed
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+}
+
+/// Columns of `booking_cancellations` read into a `CancellationRecord`
+const CANCELLATION_COLUMNS: &str = "id, booking_id, status, currency, quoted_refund, quote, \
+    expires_at, cancelled_by, reason, actual_refund, outcome, error, created_at, confirmed_at";
+
+/// A payment of a booking at a payment provider
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRecord {
+    pub id: Uuid,
+    pub booking_id: Uuid,
+    pub provider: String,
+    /// Id of the payment at the provider
+    pub provider_payment_id: String,
+    #[sqlx(try_from = "String")]
+    pub status: PaymentStatus,
+    pub amount: Decimal,
+    pub currency: String,
+    pub captured_amount: Decimal,
+    pub refunded_amount: Decimal,
+    /// Why the last operation on the payment failed
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PaymentRecord {
+    pub fn payment(&self) -> Payment {
+        Payment {
+            id: self.provider_payment_id.clone(),
+            status: self.status,
+            amount: Money::new(self.amount, self.currency.clone()),
+            captured: self.captured_amount,
+            refunded: self.refunded_amount,
+        }
+    }
+}
+
+/// Columns of `booking_payments` read into a `PaymentRecord`
+const PAYMENT_COLUMNS: &str = "id, booking_id, provider, provider_payment_id, status, amount, \
+    currency, captured_amount, refunded_amount, error, created_at, updated_at";
+
+/// A booking with its status history, events, order changes, cancellations
+/// and payments
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookingDetails {
+    #[serde(flatten)]
+    pub booking: BookingRecord,
+    pub history: Vec<StatusChange>,
+    pub events: Vec<BookingEvent>,
+    pub changes: Vec<BookingChange>,
+    pub cancellations: Vec<CancellationRecord>,
+    pub payments: Vec<PaymentRecord>,
+}
+
+/// What is known about a booking when the order attempt arrives
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewBooking {
+    pub amadeus_offer_id: Option<String>,
+    pub departure_date: Option<NaiveDate>,
+    pub return_date: Option<NaiveDate>,
+    pub adults: i32,
+    pub children: i32,
+    pub infants: i32,
+    pub passenger_data: serde_json::Value,
+}
+
+/// Date part of an Amadeus "2025-06-01T10:15:00" timestamp
+fn date_of(at: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(at.get(..10)?, "%Y-%m-%d").ok()
+}
+
+impl NewBooking {
+    pub fn from_request(request: &FlightOrderRequest) -> Self {
+        let offer = request.flight_offers.first();
+        let count = |matches: fn(&TravelerType) -> bool| {
+            offer.map_or(0, |offer| {
+                offer
+                    .traveler_pricings
+                    .iter()
+                    .filter(|pricing| matches(&pricing.traveler_type))
+                    .count() as i32
+            })
+        };
+        let itineraries = offer
+            .map(|offer| offer.itineraries.as_slice())
+            .unwrap_or_default();
+        let departure_date = itineraries
+            .first()
+            .and_then(|itinerary| itinerary.segments.first())
+            .and_then(|segment| date_of(&segment.departure.at));
+        let return_date = itineraries
+            .get(1..)
+            .and_then(<[_]>::last)
+            .and_then(|itinerary| itinerary.segments.first())
+            .and_then(|segment| date_of(&segment.departure.at));
+
+        NewBooking {
+            amadeus_offer_id: offer.map(|offer| offer.id.clone()),
+            departure_date,
+            return_date,
+            adults: count(|traveler_type| {
+                !matches!(
+                    traveler_type,
+                    TravelerType::Child | TravelerType::HeldInfant | TravelerType::SeatedInfant
+                )
+            }),
+            children: count(|traveler_type| *traveler_type == TravelerType::Child),
+            infants: count(|traveler_type| {
+                matches!(
+                    traveler_type,
+                    TravelerType::HeldInfant | TravelerType::SeatedInfant
+                )
+            }),
+            passenger_data: serde_json::to_value(&request.travelers).unwrap_or_default(),
+        }
+    }
+}
+
+/// Fields written together with a transition; `None` keeps the stored value
+#[derive(Debug, Clone, Default)]
+pub struct BookingUpdate {
+    pub order_id: Option<String>,
+    pub pnr: Option<String>,
+    pub booking_data: Option<serde_json::Value>,
+    pub ticketing_deadline: Option<DateTime<Utc>>,
+    pub ticketing_option: Option<String>,
+    /// Sale price of the order, as priced by Amadeus
+    pub total_price: Option<Decimal>,
+    pub currency_code: Option<String>,
+    /// Why the status changed (stored in the history, and as failure reason for FAILED)
+    pub reason: Option<String>,
+}
+
+impl BookingUpdate {
+    pub fn reason(reason: impl Into<String>) -> Self {
+        BookingUpdate {
+            reason: Some(reason.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Total of the offers as priced by Amadeus (with our markups), never the
+    /// prices of the order request. Orders with several offers are stored with
+    /// the total of all of them.
+    pub fn priced(offers: &[FlightOffer]) -> Self {
+        let currency_code = offers.first().map(|offer| offer.price.currency.clone());
+        let total_price = offers
+            .iter()
+            .map(|offer| {
+                (Some(&offer.price.currency) == currency_code.as_ref())
+                    .then(|| offer.price.grand_total_money().amount)
+            })
+            .sum::<Option<Decimal>>();
+        BookingUpdate {
+            total_price,
+            currency_code: total_price.and(currency_code),
+            ..Default::default()
+        }
+    }
+
+    /// Order id, PNR, order data and ticketing deadline of an Amadeus order
+    pub fn from_order(order: &FlightOrderResponse) -> Self {
+        BookingUpdate {
+            order_id: Some(order.data.id.clone()),
+            pnr: order
+                .data
+                .associated_records
+                .first()
+                .map(|record| record.reference.clone()),
+            booking_data: serde_json::to_value(&order.data).ok(),
+            ticketing_deadline: ticketing::deadline(&order.data),
+            ticketing_option: order
+                .data
+                .ticketing_agreement
+                .as_ref()
+                .map(|agreement| agreement.option.to_string()),
+            total_price: None,
+            currency_code: None,
+            reason: None,
+        }
+    }
+}
+
+/// Reads and writes `flight_bookings`
+#[derive(Debug, Clone)]
+pub struct BookingStore {
+    pool: PgPool,
+    pii: Option<Arc<PiiKeys>>,
+}
+
+impl BookingStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, pii: None }
+    }
+
+    /// Encrypt traveler PII with `keys` (see `pii`)
+    pub fn with_pii_keys(mut self, keys: PiiKeys) -> Self {
+        self.pii = Some(Arc::new(keys));
+        self
+    }
+
+    /// Decrypt the traveler PII of a record read from the store
+    pub fn reveal(&self, booking: &mut BookingRecord) -> Result<(), BookingError> {
+        let Some(sealed) = booking.sealed_key() else {
+            return Ok(());
+        };
+        let keys = self
+            .pii
+            .as_ref()
+            .ok_or_else(|| PiiError::UnknownKey(sealed.key_id.clone()))?;
+        let key = keys.open(&sealed)?;
+        if let Some(ref mut data) = booking.passenger_data {
+            key.open(data, &pii::PASSENGER_FIELDS)?;
+        }
+        if let Some(ref mut data) = booking.booking_data {
+            key.open(data, &pii::ORDER_FIELDS)?;
+        }
+        Ok(())
+    }
+
+    /// Reseal the data keys of up to `limit` bookings after `after` (by id) by
+    /// the current key, encrypting bookings stored without encryption. Bookings
+    /// that fail are logged and left for the next run.
+    pub async fn rotate_pii_keys(
+        &self,
+        after: Option<Uuid>,
+        limit: i64,
+    ) -> Result<RotationBatch, BookingError> {
+        let Some(keys) = &self.pii else {
+            return Ok(RotationBatch {
+                rotated: 0,
+                last_id: None,
+            });
+        };
+        type Row = (
+            Uuid,
+            Option<String>,
+            Option<String>,
+            Option<serde_json::Value>,
+            Option<serde_json::Value>,
+        );
+        let rows: Vec<Row> = sqlx::query_as(
+            "SELECT id, pii_key_id, pii_data_key, passenger_data, booking_data \
+             FROM flight_bookings \
+             WHERE pii_key_id IS DISTINCT FROM $1 AND ($2::uuid IS NULL OR id > $2) \
+             ORDER BY id LIMIT $3",
+        )
+        .bind(keys.current_key_id())
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut rotated = 0;
+        let last_id = rows.last().map(|row| row.0);
+        for (id, key_id, data_key, mut passenger_data, mut booking_data) in rows {
+            // Only update rows nobody re-keyed in the meantime
+            let result = match sealed_key(key_id.clone(), data_key) {
+                Some(sealed) => match keys.reseal(&sealed) {
+                    Ok(Some(resealed)) => sqlx::query(
+                        "UPDATE flight_bookings SET pii_key_id = $2, pii_data_key = $3 \
+                         WHERE id = $1 AND pii_key_id = $4",
+                    )
+                    .bind(id)
+                    .bind(&resealed.key_id)
+                    .bind(&resealed.sealed)
+                    .bind(&sealed.key_id)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(BookingError::from),
+                    Ok(None) => continue,
+                    Err(e) => Err(e.into()),
+                },
+                None => {
+                    let (key, sealed) = keys.new_data_key();
+                    if let Some(ref mut data) = passenger_data {
+                        key.seal(data, &pii::PASSENGER_FIELDS);
+                    }
+                    if let Some(ref mut data) = booking_data {
+                        key.seal(data, &pii::ORDER_FIELDS);
+                    }
+                    sqlx::query(
+                        "UPDATE flight_bookings SET pii_key_id = $2, pii_data_key = $3, \
+                         passenger_data = $4, booking_data = $5 \
+                         WHERE id = $1 AND pii_key_id IS NULL",
+                    )
+                    .bind(id)
+                    .bind(&sealed.key_id)
+                    .bind(&sealed.sealed)
+                    .bind(&passenger_data)
+                    .bind(&booking_data)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(BookingError::from)
+                }
+            };
+            match result {
+                Ok(done) => rotated += done.rows_affected() as usize,
+                Err(e) => tracing::error!("Failed to re-key the PII of booking {}: {}", id, e),
+            }
+        }
+        Ok(RotationBatch { rotated, last_id })
+    }
+
+    /// Store a new booking in PENDING
+    pub async fn create(&self, booking: &NewBooking) -> Result<BookingRecord, BookingError> {
+        let mut passenger_data = booking.passenger_data.clone();
+        let sealed = match data_key(self.pii.as_deref(), None)? {
+            Some((key, sealed)) => {
+                key.seal(&mut passenger_data, &pii::PASSENGER_FIELDS);
+                sealed
+            }
+            None => None,
+        };
+
+        let mut tx = self.pool.begin().await?;
+        let record: BookingRecord = sqlx::query_as(&format!(
+            "INSERT INTO flight_bookings (status, amadeus_offer_id, departure_date, return_date, \
+             adults, children, infants, passenger_data, pii_key_id, pii_data_key) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING {}",
+            BOOKING_COLUMNS
+        ))
+        .bind(BookingStatus::Pending.as_str())
+        .bind(&booking.amadeus_offer_id)
+        .bind(booking.departure_date)
+        .bind(booking.return_date)
+        .bind(booking.adults)
+        .bind(booking.children)
+        .bind(booking.infants)
+        .bind(&passenger_data)
+        .bind(sealed.as_ref().map(|sealed| &sealed.key_id))
+        .bind(sealed.as_ref().map(|sealed| &sealed.sealed))
+        .fetch_one(&mut *tx)
+        .await?;
+        record_change(&mut tx, record.id, None, BookingStatus::Pending, None).await?;
+        tx.commit().await?;
+        Ok(record)
+    }
+
+    /// Move a booking to `to`, rejecting transitions the state machine does not allow
+    pub async fn transition(
+        &self,
+        id: Uuid,
+        to: BookingStatus,
+        update: BookingUpdate,
+    ) -> Result<BookingRecord, BookingError> {
+        let mut tx = self.pool.begin().await?;
+        type Row = (
+            String,
+            Option<String>,
+            Option<String>,
+            Option<serde_json::Value>,
+        );
+        let (status, key_id, sealed, passenger_data): Row = sqlx::query_as(
+            "SELECT status, pii_key_id, pii_data_key, passenger_data FROM flight_bookings \
+             WHERE id = $1 FOR UPDATE",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(BookingError::NotFound)?;
+        let from = BookingStatus::try_from(status)
+            .map_err(|e| BookingError::Database(sqlx::Error::Decode(e.into())))?;
+        if !from.can_transition_to(to) {
+            return Err(BookingError::InvalidTransition { from, to });
+        }
+
+        let failure_reason = update
+            .reason
+            .clone()
+            .filter(|_| to == BookingStatus::Failed);
+        let mut booking_data = update.booking_data;
+        let new_key = seal_order(
+            self.pii.as_deref(),
+            sealed_key(key_id, sealed),
+            &mut booking_data,
+            passenger_data,
+        )?;
+        let record: BookingRecord = sqlx::query_as(&format!(
+            "UPDATE flight_bookings SET status = $2, \
+             order_id = COALESCE($3, order_id), \
+             pnr = COALESCE($4, pnr), \
+             booking_data = COALESCE($5, booking_data), \
+             failure_reason = COALESCE($6, failure_reason), \
+             ticketing_deadline = COALESCE($7, ticketing_deadline), \
+             ticketing_option = COALESCE($8, ticketing_option), \
+             pii_key_id = COALESCE($9, pii_key_id), \
+             pii_data_key = COALESCE($10, pii_data_key), \
+             passenger_data = CASE WHEN $9 IS NULL THEN passenger_data ELSE $11 END, \
+             total_price = COALESCE($12, total_price), \
+             currency_code = COALESCE($13, currency_code), \
+             confirmed_at = CASE WHEN $2 = 'ORDERED' THEN CURRENT_TIMESTAMP ELSE confirmed_at END, \
+             cancelled_at = CASE WHEN $2 = 'CANCELLED' THEN CURRENT_TIMESTAMP ELSE cancelled_at END \
+             WHERE id = $1 RETURNING {}",
+            BOOKING_COLUMNS
+        ))
+        .bind(id)
+        .bind(to.as_str())
+        .bind(update.order_id)
+        .bind(update.pnr)
+        .bind(booking_data)
+        .bind(failure_reason)
+        .bind(update.ticketing_deadline)
+        .bind(update.ticketing_option)
+        .bind(new_key.as_ref().map(|new_key| &new_key.sealed.key_id))
+        .bind(new_key.as_ref().map(|new_key| &new_key.sealed.sealed))
+        .bind(new_key.as_ref().and_then(|new_key| new_key.passenger_data.as_ref()))
+        .bind(update.total_price)
+        .bind(update.currency_code)
+        .fetch_one(&mut *tx)
+        .await?;
+        record_change(&mut tx, id, Some(from), to, update.reason.as_deref()).await?;
+        tx.commit().await?;
+        Ok(record)
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<BookingRecord, BookingError> {
+        sqlx::query_as(&format!(
+            "SELECT {} FROM flight_bookings WHERE id = $1",
+            BOOKING_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(BookingError::NotFound)
+    }
+
+    /// Booking of an Amadeus flight order
+    pub async fn find_by_order_id(&self, order_id: &str) -> Result<BookingRecord, BookingError> {
+        sqlx::query_as(&format!(
+            "SELECT {} FROM flight_bookings WHERE order_id = $1",
+            BOOKING_COLUMNS
+        ))
+        .bind(order_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(BookingError::NotFound)
+    }
+
+    /// Status changes of a booking, oldest first
+    pub async fn history(&self, id: Uuid) -> Result<Vec<StatusChange>, BookingError> {
+        Ok(sqlx::query_as(
+            "SELECT from_status, to_status, reason, changed_at FROM booking_status_history \
+             WHERE booking_id = $1 ORDER BY changed_at, id",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// Events of a booking, oldest first
+    pub async fn events(&self, id: Uuid) -> Result<Vec<BookingEvent>, BookingError> {
+        Ok(sqlx::query_as(
+            "SELECT kind, detail, created_at FROM booking_events \
+             WHERE booking_id = $1 ORDER BY created_at, id",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// Record an event of a booking. Each kind is stored once per booking;
+    /// returns false if it had been recorded before.
+    pub async fn record_event(
+        &self,
+        id: Uuid,
+        kind: &str,
+        detail: Option<&str>,
+    ) -> Result<bool, BookingError> {
+        let result = sqlx::query(
+            "INSERT INTO booking_events (booking_id, kind, detail) VALUES ($1, $2, $3) \
+             ON CONFLICT (booking_id, kind) DO NOTHING",
+        )
+        .bind(id)
+        .bind(kind)
+        .bind(detail)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Ordered bookings that are not ticketed and whose deadline is at or before `until`
+    pub async fn ticketing_due(
+        &self,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<BookingRecord>, BookingError> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {} FROM flight_bookings \
+             WHERE status = $1 AND order_id IS NOT NULL AND ticketing_deadline <= $2 \
+             ORDER BY ticketing_deadline",
+            BOOKING_COLUMNS
+        ))
+        .bind(BookingStatus::Ordered.as_str())
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// Bookings with an Amadeus order that is still active (ORDERED or TICKETED)
+    pub async fn active_orders(&self) -> Result<Vec<BookingRecord>, BookingError> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {} FROM flight_bookings \
+             WHERE status IN ($1, $2) AND order_id IS NOT NULL ORDER BY created_at",
+            BOOKING_COLUMNS
+        ))
+        .bind(BookingStatus::Ordered.as_str())
+        .bind(BookingStatus::Ticketed.as_str())
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// Order changes of a booking, oldest first
+    pub async fn changes(&self, id: Uuid) -> Result<Vec<BookingChange>, BookingError> {
+        Ok(sqlx::query_as(
+            "SELECT kind, segment_id, traveler_id, description, previous_value, current_value, \
+             detected_at FROM booking_changes WHERE booking_id = $1 ORDER BY detected_at, id",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// Store changes found in the live order together with its data, so the
+    /// next comparison starts from there
+    pub async fn record_changes(
+        &self,
+        id: Uuid,
+        changes: &[OrderChange],
+        booking_data: Option<&serde_json::Value>,
+    ) -> Result<(), BookingError> {
+        let mut tx = self.pool.begin().await?;
+        for change in changes {
+            sqlx::query(
+                "INSERT INTO booking_changes (booking_id, kind, segment_id, traveler_id, \
+                 description, previous_value, current_value) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            )
+            .bind(id)
+            .bind(change.kind.as_str())
+            .bind(&change.segment_id)
+            .bind(&change.traveler_id)
+            .bind(&change.description)
+            .bind(&change.previous)
+            .bind(&change.current)
+            .execute(&mut *tx)
+            .await?;
+        }
+        if let Some(booking_data) = booking_data {
+            let (key_id, sealed, passenger_data): (
+                Option<String>,
+                Option<String>,
+                Option<serde_json::Value>,
+            ) = sqlx::query_as(
+                "SELECT pii_key_id, pii_data_key, passenger_data FROM flight_bookings \
+                 WHERE id = $1 FOR UPDATE",
+            )
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+            let mut booking_data = Some(booking_data.clone());
+            let new_key = seal_order(
+                self.pii.as_deref(),
+                sealed_key(key_id, sealed),
+                &mut booking_data,
+                passenger_data,
+            )?;
+            sqlx::query(
+                "UPDATE flight_bookings SET booking_data = $2, \
+                 pii_key_id = COALESCE($3, pii_key_id), pii_data_key = COALESCE($4, pii_data_key), \
+                 passenger_data = CASE WHEN $3 IS NULL THEN passenger_data ELSE $5 END \
+                 WHERE id = $1",
+            )
+            .bind(id)
+            .bind(booking_data)
+            .bind(new_key.as_ref().map(|new_key| &new_key.sealed.key_id))
+            .bind(new_key.as_ref().map(|new_key| &new_key.sealed.sealed))
+            .bind(
+                new_key
+                    .as_ref()
+                    .and_then(|new_key| new_key.passenger_data.as_ref()),
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Store a schedule disruption unless the same one was found before.
+    /// `fingerprint` identifies the disruption; returns `None` for known ones.
+    pub async fn create_disruption(
+        &self,
+        booking_id: Uuid,
+        itinerary_index: i32,
+        fingerprint: &str,
+        changes: &[OrderChange],
+        alternatives: &[FlightOffer],
+    ) -> Result<Option<ScheduleDisruption>, BookingError> {
+        Ok(sqlx::query_as(&format!(
+            "INSERT INTO schedule_disruptions \
+             (booking_id, itinerary_index, fingerprint, changes, alternatives) \
+             VALUES ($1, $2, $3, $4, $5) \
+             ON CONFLICT (booking_id, fingerprint) DO NOTHING RETURNING {}",
+            DISRUPTION_COLUMNS
+        ))
+        .bind(booking_id)
+        .bind(itinerary_index)
+        .bind(fingerprint)
+        .bind(Json(changes))
+        .bind(Json(alternatives))
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    /// Schedule disruptions of a booking, newest first
+    pub async fn disruptions(
+        &self,
+        booking_id: Uuid,
+    ) -> Result<Vec<ScheduleDisruption>, BookingError> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {} FROM schedule_disruptions WHERE booking_id = $1 ORDER BY created_at DESC",
+            DISRUPTION_COLUMNS
+        ))
+        .bind(booking_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// Accept one of the alternative offers of an open disruption
+    pub async fn accept_alternative(
+        &self,
+        booking_id: Uuid,
+        disruption_id: Uuid,
+        offer_id: &str,
+    ) -> Result<ScheduleDisruption, BookingError> {
+        let mut tx = self.pool.begin().await?;
+        let disruption: ScheduleDisruption = sqlx::query_as(&format!(
+            "SELECT {} FROM schedule_disruptions WHERE id = $1 AND booking_id = $2 FOR UPDATE",
+            DISRUPTION_COLUMNS
+        ))
+        .bind(disruption_id)
+        .bind(booking_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(BookingError::NotFound)?;
+        if disruption.status != DISRUPTION_OPEN {
+            return Err(BookingError::Conflict(format!(
+                "Disruption {} is already {}",
+                disruption_id, disruption.status
+            )));
+        }
+        if !disruption
+            .alternatives
+            .iter()
+            .any(|offer| offer.id == offer_id)
+        {
+            return Err(BookingError::Conflict(format!(
+                "Offer {} is not an alternative of disruption {}",
+                offer_id, disruption_id
+            )));
+        }
+
+        let disruption = sqlx::query_as(&format!(
+            "UPDATE schedule_disruptions SET status = $2, accepted_offer_id = $3, \
+             resolved_at = CURRENT_TIMESTAMP WHERE id = $1 RETURNING {}",
+            DISRUPTION_COLUMNS
+        ))
+        .bind(disruption_id)
+        .bind(DISRUPTION_ACCEPTED)
+        .bind(offer_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(disruption)
+    }
+
+    /// Store a cancellation quote
+    pub async fn create_cancellation_quote(
+        &self,
+        booking_id: Uuid,
+        estimate: &RefundEstimate,
+        expires_at: DateTime<Utc>,
+    ) -> Result<CancellationRecord, BookingError> {
+        Ok(sqlx::query_as(&format!(
+            "INSERT INTO booking_cancellations (booking_id, currency, quoted_refund, quote, expires_at) \
+             VALUES ($1, $2, $3, $4, $5) RETURNING {}",
+            CANCELLATION_COLUMNS
+        ))
+        .bind(booking_id)
+        .bind(&estimate.currency)
+        .bind(estimate.refund)
+        .bind(Json(estimate))
+        .bind(expires_at)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    /// Take an unexpired quote for confirmation, recording who cancels and why.
+    /// A quote can be confirmed once.
+    pub async fn claim_cancellation_quote(
+        &self,
+        booking_id: Uuid,
+        quote_id: Uuid,
+        cancelled_by: &str,
+        reason: &str,
+    ) -> Result<CancellationRecord, BookingError> {
+        let claimed: Option<CancellationRecord> = sqlx::query_as(&format!(
+            "UPDATE booking_cancellations SET status = $3, cancelled_by = $4, reason = $5 \
+             WHERE id = $1 AND booking_id = $2 AND status = $6 AND expires_at > CURRENT_TIMESTAMP \
+             RETURNING {}",
+            CANCELLATION_COLUMNS
+        ))
+        .bind(quote_id)
+        .bind(booking_id)
+        .bind(CANCELLATION_CONFIRMING)
+        .bind(cancelled_by)
+        .bind(reason)
+        .bind(CANCELLATION_QUOTED)
+        .fetch_optional(&self.pool)
+        .await?;
+        if let Some(claimed) = claimed {
+            return Ok(claimed);
+        }
+
+        let quote: CancellationRecord = sqlx::query_as(&format!(
+            "SELECT {} FROM booking_cancellations WHERE id = $1 AND booking_id = $2",
+            CANCELLATION_COLUMNS
+        ))
+        .bind(quote_id)
+        .bind(booking_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(BookingError::NotFound)?;
+        Err(BookingError::Conflict(
+            if quote.status == CANCELLATION_QUOTED {
+                format!("Cancellation quote {} has expired", quote_id)
+            } else {
+                format!(
+                    "Cancellation quote {} is already {}",
+                    quote_id, quote.status
+                )
+            },
+        ))
+    }
+
+    /// Record the outcome of a claimed quote: the refund estimate as of the
+    /// cancellation, or why cancelling failed
+    pub async fn finish_cancellation(
+        &self,
+        quote_id: Uuid,
+        outcome: Result<&RefundEstimate, &str>,
+    ) -> Result<CancellationRecord, BookingError> {
+        let (status, actual, error) = match outcome {
+            Ok(actual) => (CANCELLATION_CONFIRMED, Some(actual), None),
+            Err(error) => (CANCELLATION_FAILED, None, Some(error)),
+        };
+        Ok(sqlx::query_as(&format!(
+            "UPDATE booking_cancellations SET status = $2, actual_refund = $3, outcome = $4, \
+             error = $5, confirmed_at = CASE WHEN $2 = 'CONFIRMED' THEN CURRENT_TIMESTAMP END \
+             WHERE id = $1 RETURNING {}",
+            CANCELLATION_COLUMNS
+        ))
+        .bind(quote_id)
+        .bind(status)
+        .bind(actual.and_then(|actual| actual.refund))
+        .bind(actual.map(Json))
+        .bind(error)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    /// Cancellation quotes of a booking, oldest first
+    pub async fn cancellations(
+        &self,
+        booking_id: Uuid,
+    ) -> Result<Vec<CancellationRecord>, BookingError> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {} FROM booking_cancellations WHERE booking_id = $1 ORDER BY created_at",
+            CANCELLATION_COLUMNS
+        ))
+        .bind(booking_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// Store a payment as the provider reported it after an operation, with
+    /// the error if the operation failed
+    pub async fn save_payment(
+        &self,
+        booking_id: Uuid,
+        provider: &str,
+        payment: &Payment,
+        error: Option<&str>,
+    ) -> Result<PaymentRecord, BookingError> {
+        Ok(sqlx::query_as(&format!(
+            "INSERT INTO booking_payments (booking_id, provider, provider_payment_id, status, \
+             amount, currency, captured_amount, refunded_amount, error) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) \
+             ON CONFLICT (provider, provider_payment_id) DO UPDATE SET status = EXCLUDED.status, \
+             captured_amount = EXCLUDED.captured_amount, refunded_amount = EXCLUDED.refunded_amount, \
+             error = EXCLUDED.error RETURNING {}",
+            PAYMENT_COLUMNS
+        ))
+        .bind(booking_id)
+        .bind(provider)
+        .bind(&payment.id)
+        .bind(payment.status.as_str())
+        .bind(payment.amount.amount)
+        .bind(&payment.amount.currency)
+        .bind(payment.captured)
+        .bind(payment.refunded)
+        .bind(error)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    /// Payments of a booking, oldest first
+    pub async fn payments(&self, booking_id: Uuid) -> Result<Vec<PaymentRecord>, BookingError> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {} FROM booking_payments WHERE booking_id = $1 ORDER BY created_at",
+            PAYMENT_COLUMNS
+        ))
+        .bind(booking_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    pub async fn details(&self, booking: BookingRecord) -> Result<BookingDetails, BookingError> {
+        let history = self.history(booking.id).await?;
+        let events = self.events(booking.id).await?;
+        let changes = self.changes(booking.id).await?;
+        let cancellations = self.cancellations(booking.id).await?;
+        let payments = self.payments(booking.id).await?;
+        Ok(BookingDetails {
+            booking,
+            history,
+            events,
+            changes,
+            cancellations,
+            payments,
+        })
+    }
+
+    /// Start tracking an order attempt. Database errors are logged rather than
+    /// returned, so bookings still go through while the database is down.
+    pub async fn track(&self, booking: &NewBooking) -> Option<BookingTracker<'_>> {
+        match self.create(booking).await {
+            Ok(record) => Some(BookingTracker {
+                store: self,
+                id: record.id,
+            }),
+            Err(e) => {
+                tracing::error!("Failed to store booking: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Move the booking of an Amadeus order to `to`, logging failures
+    pub async fn advance_order(&self, order_id: &str, to: BookingStatus, update: BookingUpdate) {
+        match self.find_by_order_id(order_id).await {
+            Ok(booking) if booking.status == to => {}
+            Ok(booking) => {
+                if let Err(e) = self.transition(booking.id, to, update).await {
+                    tracing::error!("Failed to update booking {}: {}", booking.id, e);
+                }
+            }
+            Err(BookingError::NotFound) => {
+                tracing::warn!("No booking stored for order {}", order_id);
+            }
+            Err(e) => tracing::error!("Failed to load booking of order {}: {}", order_id, e),
+        }
+    }
+}
+
+/// A booking being moved through the state machine by one request
+#[derive(Debug, Clone, Copy)]
+pub struct BookingTracker<'a> {
+    store: &'a BookingStore,
+    id: Uuid,
+}
+
+impl BookingTracker<'_> {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Move the booking to `to`, logging failures
+    pub async fn advance(&self, to: BookingStatus, update: BookingUpdate) {
+        if let Err(e) = self.store.transition(self.id, to, update).await {
+            tracing::error!("Failed to update booking {}: {}", self.id, e);
+        }
+    }
+
+    /// Store the payment of the booking, logging failures
+    pub async fn record_payment(&self, provider: &str, payment: &Payment, error: Option<&str>) {
+        if let Err(e) = self
+            .store
+            .save_payment(self.id, provider, payment, error)
+            .await
+        {
+            tracing::error!(
+                "Failed to store payment {} of booking {}: {}",
+                payment.id,
+                self.id,
+                e
+            );
+        }
+    }
+}
+
+async fn record_change(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    id: Uuid,
+    from: Option<BookingStatus>,
+    to: BookingStatus,
+    reason: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO booking_status_history (booking_id, from_status, to_status, reason) \
+         VALUES ($1, $2, $3, $4)",
+    )
+    .bind(id)
+    .bind(from.map(|status| status.as_str()))
+    .bind(to.as_str())
+    .bind(reason)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_state_machine() {
+        use BookingStatus::*;
+
+        let happy_path = [Pending, Priced, Ordered, Ticketed];
+        for pair in happy_path.windows(2) {
+            assert!(pair[0].can_transition_to(pair[1]), "{:?}", pair);
+            assert!(!pair[1].can_transition_to(pair[0]), "{:?}", pair);
+        }
+
+        assert!(!Pending.can_transition_to(Ordered));
+        assert!(!Priced.can_transition_to(Ticketed));
+        assert!(Priced.can_transition_to(Authorized));
+        assert!(Authorized.can_transition_to(Ordered));
+        assert!(Authorized.can_transition_to(Failed));
+        assert!(!Authorized.can_transition_to(Ticketed));
+        assert!(Ordered.can_transition_to(Failed));
+        assert!(!Ticketed.can_transition_to(Failed));
+        assert!(Ticketed.can_transition_to(Cancelled));
+        for status in BookingStatus::ALL {
+            assert!(!status.can_transition_to(status));
+            assert!(!Cancelled.can_transition_to(status));
+            assert!(!Failed.can_transition_to(status));
+            assert_eq!(
+                BookingStatus::try_from(status.as_str().to_string()),
+                Ok(status)
+            );
+        }
+        assert!(BookingStatus::try_from("BOOKED".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_new_booking_from_request() {
+        let pricing = |id: &str, traveler_type: &str| {
+            serde_json::json!({
+                "travelerId": id,
+                "fareOption": "STANDARD",
+                "travelerType": traveler_type,
+                "price": { "currency": "EUR", "total": "100.00", "base": "80.00" },
+                "fareDetailsBySegment": []
+            })
+        };
+        let segment = |id: &str, at: &str| {
+            serde_json::json!({
+                "id": id,
+                "departure": { "iataCode": "FRA", "at": at },
+                "arrival": { "iataCode": "JFK", "at": at },
+                "carrierCode": "LH",
+                "number": "400",
+                "aircraft": { "code": "388" }
+            })
+        };
+        let request: FlightOrderRequest = serde_json::from_value(serde_json::json!({
+            "flightOffers": [{
+                "id": "1",
+                "type": "flight-offer",
+                "source": "GDS",
+                "itineraries": [
+                    { "segments": [segment("1", "2025-06-01T10:15:00")] },
+                    { "segments": [segment("2", "2025-06-14T18:00:00")] }
+                ],
+                "price": { "currency": "EUR", "total": "300.00", "base": "240.00", "grandTotal": "310.00" },
+                "validatingAirlineCodes": ["LH"],
+                "travelerPricings": [
+                    pricing("1", "ADULT"),
+                    pricing("2", "CHILD"),
+                    pricing("3", "HELD_INFANT")
+                ]
+            }],
+            "travelers": [{
+                "id": "1",
+                "dateOfBirth": "1980-01-01",
+                "name": { "firstName": "JANE", "lastName": "DOE" }
+            }]
+        }))
+        .unwrap();
+
+        let booking = NewBooking::from_request(&request);
+        assert_eq!(booking.amadeus_offer_id.as_deref(), Some("1"));
+        assert_eq!(
+            (booking.adults, booking.children, booking.infants),
+            (1, 1, 1)
+        );
+        assert_eq!(booking.departure_date, NaiveDate::from_ymd_opt(2025, 6, 1));
+        assert_eq!(booking.return_date, NaiveDate::from_ymd_opt(2025, 6, 14));
+        assert_eq!(booking.passenger_data[0]["name"]["lastName"], "DOE");
+
+        // The total is only recorded from the offers as priced
+        let priced = BookingUpdate::priced(&request.flight_offers);
+        assert_eq!(
+            priced.total_price,
+            Some(Decimal::from_str("310.00").unwrap())
+        );
+        assert_eq!(priced.currency_code.as_deref(), Some("EUR"));
+    }
+
+    #[test]
+    fn test_seal_order_of_unencrypted_booking() {
+        let keys = PiiKeys::parse("k1:MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap();
+        let passengers = serde_json::json!([{
+            "id": "1",
+            "dateOfBirth": "1980-01-01",
+            "documents": [{ "documentType": "PASSPORT", "number": "C01X00T47" }]
+        }]);
+        let order =
+            serde_json::json!({ "travelers": [{ "id": "1", "dateOfBirth": "1980-01-01" }] });
+
+        // The first data key encrypts the stored passenger data as well
+        let mut booking_data = Some(order.clone());
+        let new_key = seal_order(
+            Some(&keys),
+            None,
+            &mut booking_data,
+            Some(passengers.clone()),
+        )
+        .unwrap()
+        .unwrap();
+        let mut sealed_passengers = new_key.passenger_data.unwrap();
+        let text = format!("{} {}", sealed_passengers, booking_data.as_ref().unwrap());
+        assert!(
+            !text.contains("1980-01-01") && !text.contains("C01X00T47"),
+            "{}",
+            text
+        );
+
+        let key = keys.open(&new_key.sealed).unwrap();
+        key.open(&mut sealed_passengers, &pii::PASSENGER_FIELDS)
+            .unwrap();
+        assert_eq!(sealed_passengers, passengers);
+
+        // Bookings with a data key keep it and their passenger data
+        let mut booking_data = Some(order.clone());
+        let sealed = Some(new_key.sealed);
+        assert!(
+            seal_order(Some(&keys), sealed, &mut booking_data, Some(passengers))
+                .unwrap()
+                .is_none()
+        );
+        key.open(booking_data.as_mut().unwrap(), &pii::ORDER_FIELDS)
+            .unwrap();
+        assert_eq!(booking_data, Some(order));
+    }
+}
@@ -0,0 +1,340 @@
+//! Branded fare comparison
+//!
+//! Upsell returns one offer per branded fare, each listing its amenities as
+//! free text per segment. We turn them into a matrix: one column per brand
+//! with its price above the cheapest brand, and one row per entitlement
+//! (checked bags, seat choice, changes, refund, meals, lounge).
+//!
+//! An entitlement only counts as included if every segment includes it, so a
+//! fare that serves a free meal on one leg only does not list the meal as
+//! included.
+
+use crate::models::{
+    AmenityType, BrandColumn, BrandComparison, Entitlement, EntitlementCell, EntitlementRow,
+    EntitlementStatus, FareDetailsBySegment, FlightOffer,
+};
+
+/// Rows of the comparison, in display order
+const ENTITLEMENTS: [Entitlement; 6] = [
+    Entitlement::CheckedBags,
+    Entitlement::SeatChoice,
+    Entitlement::Changes,
+    Entitlement::Refund,
+    Entitlement::Meals,
+    Entitlement::Lounge,
+];
+
+/// Distinct values of all segments, e.g. "LIGHT / CLASSIC" for a mixed itinerary
+fn joined<'a>(values: impl Iterator<Item = Option<&'a String>>) -> Option<String> {
+    let mut distinct: Vec<&str> = Vec::new();
+    for value in values.flatten() {
+        if !distinct.contains(&value.as_str()) {
+            distinct.push(value);
+        }
+    }
+    (!distinct.is_empty()).then(|| distinct.join(" / "))
+}
+
+/// Fare details of the first traveler, who stands in for the whole offer
+fn segments(offer: &FlightOffer) -> &[FareDetailsBySegment] {
+    offer
+        .traveler_pricings
+        .first()
+        .map(|pricing| pricing.fare_details_by_segment.as_slice())
+        .unwrap_or_default()
+}
+
+fn describes(
+    entitlement: Entitlement,
+    amenity_type: Option<&AmenityType>,
+    description: &str,
+) -> bool {
+    let description = description.to_uppercase();
+    if description.starts_with("NON") {
+        // "NON REFUNDABLE", "NON-CHANGEABLE"
+        return false;
+    }
+    match entitlement {
+        Entitlement::CheckedBags => {
+            amenity_type == Some(&AmenityType::Baggage)
+                && !["CABIN", "CARRY", "HAND"]
+                    .iter()
+                    .any(|word| description.contains(word))
+        }
+        Entitlement::SeatChoice => {
+            amenity_type == Some(&AmenityType::PreReservedSeat) || description.contains("SEAT")
+        }
+        Entitlement::Changes => description.contains("CHANGE"),
+        Entitlement::Refund => description.contains("REFUND"),
+        Entitlement::Meals => {
+            amenity_type == Some(&AmenityType::Meal)
+                || description.contains("MEAL")
+                || description.contains("SNACK")
+        }
+        Entitlement::Lounge => {
+            amenity_type == Some(&AmenityType::Lounge) || description.contains("LOUNGE")
+        }
+    }
+}
+
+/// "2 x 23KG", "1 PC" or "23KG"
+fn bag_allowance(details: &FareDetailsBySegment) -> Option<String> {
+    let bags = details.included_checked_bags.as_ref()?;
+    let weight = bags
+        .weight
+        .filter(|weight| *weight > 0)
+        .map(|weight| format!("{}{}", weight, bags.weight_unit.as_deref().unwrap_or("KG")));
+    match (bags.quantity.filter(|quantity| *quantity > 0), weight) {
+        (Some(quantity), Some(weight)) => Some(format!("{} x {}", quantity, weight)),
+        (Some(quantity), None) => Some(format!("{} PC", quantity)),
+        (None, weight) => weight,
+    }
+}
+
+fn segment_cell(entitlement: Entitlement, details: &FareDetailsBySegment) -> EntitlementCell {
+    if entitlement == Entitlement::CheckedBags
+        && let Some(allowance) = bag_allowance(details)
+    {
+        return EntitlementCell {
+            status: EntitlementStatus::Included,
+            detail: Some(allowance),
+        };
+    }
+
+    let amenities: Vec<_> = details
+        .amenities
+        .iter()
+        .filter(|amenity| {
+            describes(
+                entitlement,
+                amenity.amenity_type.as_ref(),
+                &amenity.description,
+            )
+        })
+        .collect();
+    let amenity = amenities
+        .iter()
+        .find(|amenity| !amenity.is_chargeable)
+        .or(amenities.first());
+    EntitlementCell {
+        status: match amenity {
+            Some(amenity) if !amenity.is_chargeable => EntitlementStatus::Included,
+            Some(_) => EntitlementStatus::Chargeable,
+            None => EntitlementStatus::NotOffered,
+        },
+        detail: amenity.map(|amenity| amenity.description.clone()),
+    }
+}
+
+/// The least generous segment decides
+fn offer_cell(entitlement: Entitlement, offer: &FlightOffer) -> EntitlementCell {
+    segments(offer)
+        .iter()
+        .map(|details| segment_cell(entitlement, details))
+        .min_by_key(|cell| cell.status)
+        .unwrap_or(EntitlementCell {
+            status: EntitlementStatus::NotOffered,
+            detail: None,
+        })
+}
+
+/// Compare the branded fares of upsell offers
+pub fn compare(offers: &[FlightOffer]) -> BrandComparison {
+    let mut offers: Vec<&FlightOffer> = offers.iter().collect();
+    offers.sort_by_key(|offer| offer.price.grand_total_money().amount);
+
+    let cheapest = offers.first().map(|offer| &offer.price);
+    let columns = offers
+        .iter()
+        .map(|offer| {
+            let price = offer.price.grand_total_money();
+            let price_delta = cheapest.and_then(|cheapest| {
+                price
+                    .checked_sub(&cheapest.grand_total_money())
+                    .ok()
+                    .map(|delta| delta.amount)
+            });
+            let display_price_delta = cheapest
+                .and_then(|cheapest| cheapest.display_total.as_ref())
+                .zip(offer.price.display_total.as_ref())
+                .filter(|(cheapest, display)| cheapest.currency == display.currency)
+                .map(|(cheapest, display)| display.amount - cheapest.amount);
+            BrandColumn {
+                offer_id: offer.id.clone(),
+                branded_fare: joined(
+                    segments(offer)
+                        .iter()
+                        .map(|details| details.branded_fare.as_ref()),
+                ),
+                branded_fare_label: joined(
+                    segments(offer)
+                        .iter()
+                        .map(|details| details.branded_fare_label.as_ref()),
+                ),
+                price,
+                price_delta,
+                display_price_delta,
+            }
+        })
+        .collect();
+
+    let rows = ENTITLEMENTS
+        .iter()
+        .map(|&entitlement| EntitlementRow {
+            entitlement,
+            cells: offers
+                .iter()
+                .map(|offer| offer_cell(entitlement, offer))
+                .collect(),
+        })
+        .collect();
+
+    BrandComparison { columns, rows }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    fn offer(
+        id: &str,
+        total: &str,
+        brand: &str,
+        bags: serde_json::Value,
+        amenities: serde_json::Value,
+    ) -> FlightOffer {
+        let segment = |segment_id: &str| {
+            serde_json::json!({
+                "segmentId": segment_id,
+                "cabin": "ECONOMY",
+                "fareBasis": "YOWEU",
+                "brandedFare": brand,
+                "brandedFareLabel": format!("ECONOMY {}", brand),
+                "class": "Y",
+                "includedCheckedBags": bags,
+                "amenities": amenities
+            })
+        };
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "type": "flight-offer",
+            "source": "GDS",
+            "itineraries": [],
+            "price": { "currency": "EUR", "total": total, "base": "100.00", "grandTotal": total },
+            "validatingAirlineCodes": ["LH"],
+            "travelerPricings": [{
+                "travelerId": "1",
+                "fareOption": "STANDARD",
+                "travelerType": "ADULT",
+                "price": { "currency": "EUR", "total": total, "base": "100.00" },
+                "fareDetailsBySegment": [segment("1"), segment("2")]
+            }]
+        }))
+        .unwrap()
+    }
+
+    fn upsell_offers() -> Vec<FlightOffer> {
+        vec![
+            offer(
+                "2",
+                "250.00",
+                "CLASSIC",
+                serde_json::json!({ "quantity": 1, "weight": 23, "weightUnit": "KG" }),
+                serde_json::json!([
+                    { "description": "PRE RESERVED SEAT ASSIGNMENT", "isChargeable": false, "amenityType": "PRE_RESERVED_SEAT" },
+                    { "description": "CHANGEABLE TICKET", "isChargeable": true, "amenityType": "BRANDED_FARES" },
+                    { "description": "NON REFUNDABLE TICKET", "isChargeable": false, "amenityType": "BRANDED_FARES" },
+                    { "description": "SNACK", "isChargeable": false, "amenityType": "MEAL" }
+                ]),
+            ),
+            offer(
+                "1",
+                "200.00",
+                "LIGHT",
+                serde_json::json!({ "quantity": 0 }),
+                serde_json::json!([
+                    { "description": "CHECKED BAG 1PC OF 23KG", "isChargeable": true, "amenityType": "BAGGAGE" },
+                    { "description": "CABIN BAG 8KG", "isChargeable": false, "amenityType": "BAGGAGE" },
+                    { "description": "PRE RESERVED SEAT ASSIGNMENT", "isChargeable": true, "amenityType": "PRE_RESERVED_SEAT" }
+                ]),
+            ),
+        ]
+    }
+
+    fn statuses(comparison: &BrandComparison, entitlement: Entitlement) -> Vec<EntitlementStatus> {
+        comparison
+            .rows
+            .iter()
+            .find(|row| row.entitlement == entitlement)
+            .unwrap()
+            .cells
+            .iter()
+            .map(|cell| cell.status)
+            .collect()
+    }
+
+    #[test]
+    fn test_columns_sorted_by_price() {
+        let comparison = compare(&upsell_offers());
+
+        assert_eq!(comparison.columns.len(), 2);
+        assert_eq!(comparison.columns[0].offer_id, "1");
+        assert_eq!(comparison.columns[0].branded_fare.as_deref(), Some("LIGHT"));
+        assert_eq!(
+            comparison.columns[1].branded_fare_label.as_deref(),
+            Some("ECONOMY CLASSIC")
+        );
+        assert_eq!(comparison.columns[0].price_delta, Some(Decimal::ZERO));
+        assert_eq!(
+            comparison.columns[1].price_delta,
+            Some(Decimal::from_str("50.00").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_entitlement_rows() {
+        use EntitlementStatus::*;
+
+        let comparison = compare(&upsell_offers());
+        assert_eq!(comparison.rows.len(), ENTITLEMENTS.len());
+
+        assert_eq!(
+            statuses(&comparison, Entitlement::CheckedBags),
+            [Chargeable, Included]
+        );
+        let bags = &comparison.rows[0].cells[1];
+        assert_eq!(bags.detail.as_deref(), Some("1 x 23KG"));
+        assert_eq!(
+            statuses(&comparison, Entitlement::SeatChoice),
+            [Chargeable, Included]
+        );
+        assert_eq!(
+            statuses(&comparison, Entitlement::Changes),
+            [NotOffered, Chargeable]
+        );
+        assert_eq!(
+            statuses(&comparison, Entitlement::Refund),
+            [NotOffered, NotOffered]
+        );
+        assert_eq!(
+            statuses(&comparison, Entitlement::Meals),
+            [NotOffered, Included]
+        );
+    }
+
+    #[test]
+    fn test_least_generous_segment_wins() {
+        let mut offers = upsell_offers();
+        offers[0].traveler_pricings[0].fare_details_by_segment[1]
+            .amenities
+            .clear();
+
+        let comparison = compare(&offers);
+        assert_eq!(
+            statuses(&comparison, Entitlement::Meals),
+            [EntitlementStatus::NotOffered, EntitlementStatus::NotOffered]
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! Refund estimates for cancellations
+//!
+//! Cancelling is done in two steps: a quote tells what the customer can expect
+//! back, and only the confirmation deletes the order at Amadeus. The estimate
+//! is built from the order itself: orders without tickets cost nothing to
+//! cancel, otherwise the cancellation rules of the fare (see `fare_rules`)
+//! decide between a refund less the penalty and the refundable taxes only.
+//! Amadeus does not report the refund of a deleted order, so the outcome of a
+//! confirmed cancellation is the estimate as of the moment it was cancelled.
+
+use chrono::{NaiveDateTime, Utc};
+use rust_decimal::Decimal;
+
+use crate::fare_rules;
+use crate::models::{FlightOffer, FlightOrderData, OfferRefund, RefundBasis, RefundEstimate};
+
+/// How long a quote can be confirmed (15 minutes)
+pub const DEFAULT_QUOTE_TTL_SECS: u64 = 900;
+
+/// Refundable taxes of an offer, from the offer price or else its travelers
+fn refundable_taxes(offer: &FlightOffer) -> Decimal {
+    offer.price.refundable_taxes.unwrap_or_else(|| {
+        offer
+            .traveler_pricings
+            .iter()
+            .filter_map(|pricing| pricing.price.refundable_taxes)
+            .sum()
+    })
+}
+
+/// Whether the first flight of the offer has departed. Departure times are
+/// local; being a few hours off does not matter for the estimate.
+fn departed(offer: &FlightOffer, now: NaiveDateTime) -> bool {
+    offer
+        .itineraries
+        .first()
+        .and_then(|itinerary| itinerary.segments.first())
+        .and_then(|segment| {
+            NaiveDateTime::parse_from_str(&segment.departure.at, "%Y-%m-%dT%H:%M:%S").ok()
+        })
+        .is_some_and(|departure| departure <= now)
+}
+
+/// Expected refund of one offer
+pub fn estimate_offer(offer: &FlightOffer, ticketed: bool, departed: bool) -> OfferRefund {
+    let paid = offer.price.grand_total_money();
+    let taxes = refundable_taxes(offer);
+    let refund = |penalty: Option<Decimal>, refund: Option<Decimal>, basis| OfferRefund {
+        offer_id: offer.id.clone(),
+        currency: paid.currency.clone(),
+        paid: paid.amount,
+        penalty,
+        refundable_taxes: taxes,
+        refund,
+        basis,
+    };
+
+    if !ticketed {
+        return refund(None, Some(paid.amount), RefundBasis::NotTicketed);
+    }
+
+    let summary = fare_rules::summarize(offer, []);
+    let condition = if departed {
+        &summary.cancellation.after_departure
+    } else {
+        &summary.cancellation.before_departure
+    };
+    match (condition.allowed, &condition.fee) {
+        // Allowed without a stated fee is only free if the rules say so
+        (Some(true), None) if condition.free => refund(
+            Some(Decimal::ZERO),
+            Some(paid.amount),
+            RefundBasis::FareRules,
+        ),
+        // Penalties are charged per ticket
+        (Some(true), Some(fee)) if fee.currency == paid.currency => {
+            let penalty = fee.amount * Decimal::from(offer.traveler_pricings.len().max(1));
+            let refund_amount = (paid.amount - penalty).max(taxes);
+            refund(Some(penalty), Some(refund_amount), RefundBasis::FareRules)
+        }
+        (Some(false), _) => refund(None, Some(taxes), RefundBasis::TaxesOnly),
+        _ => refund(None, None, RefundBasis::Unknown),
+    }
+}
+
+/// Expected refund of cancelling the order now
+pub fn estimate(order: &FlightOrderData, now: chrono::DateTime<Utc>) -> RefundEstimate {
+    let ticketed = !order.tickets.is_empty();
+    let departed = order
+        .flight_offers
+        .iter()
+        .any(|offer| departed(offer, now.naive_utc()));
+    let offers: Vec<OfferRefund> = order
+        .flight_offers
+        .iter()
+        .map(|offer| estimate_offer(offer, ticketed, departed))
+        .collect();
+
+    // All offers of an order are priced in one currency
+    RefundEstimate {
+        currency: offers
+            .first()
+            .map(|offer| offer.currency.clone())
+            .unwrap_or_default(),
+        paid: offers.iter().map(|offer| offer.paid).sum(),
+        refundable_taxes: offers.iter().map(|offer| offer.refundable_taxes).sum(),
+        refund: offers.iter().map(|offer| offer.refund).sum(),
+        ticketed,
+        departed,
+        offers,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer(fare_rules: serde_json::Value) -> FlightOffer {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "type": "flight-offer",
+            "source": "GDS",
+            "itineraries": [{ "segments": [{
+                "id": "1",
+                "departure": { "iataCode": "FRA", "at": "2025-06-01T10:15:00" },
+                "arrival": { "iataCode": "JFK", "at": "2025-06-01T13:00:00" },
+                "carrierCode": "LH",
+                "number": "400",
+                "aircraft": { "code": "388" }
+            }] }],
+            "price": {
+                "currency": "EUR",
+                "total": "600.00",
+                "base": "400.00",
+                "grandTotal": "600.00",
+                "refundableTaxes": "150.00"
+            },
+            "validatingAirlineCodes": ["LH"],
+            "travelerPricings": [
+                {
+                    "travelerId": "1",
+                    "fareOption": "STANDARD",
+                    "travelerType": "ADULT",
+                    "price": { "currency": "EUR", "total": "300.00", "base": "200.00" },
+                    "fareDetailsBySegment": []
+                },
+                {
+                    "travelerId": "2",
+                    "fareOption": "STANDARD",
+                    "travelerType": "ADULT",
+                    "price": { "currency": "EUR", "total": "300.00", "base": "200.00" },
+                    "fareDetailsBySegment": []
+                }
+            ],
+            "fareRules": fare_rules
+        }))
+        .unwrap()
+    }
+
+    fn refund_rule(rule: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({ "rules": [rule] })
+    }
+
+    #[test]
+    fn test_estimate_offer() {
+        let amount = |value: &str| value.parse::<Decimal>().unwrap();
+
+        let refundable = offer(refund_rule(
+            serde_json::json!({ "category": "REFUND", "maxPenaltyAmount": "100" }),
+        ));
+        let estimate = estimate_offer(&refundable, true, false);
+        assert_eq!(estimate.basis, RefundBasis::FareRules);
+        assert_eq!(estimate.penalty, Some(amount("200")));
+        assert_eq!(estimate.refund, Some(amount("400.00")));
+
+        // Nothing charged before ticketing
+        let estimate = estimate_offer(&refundable, false, false);
+        assert_eq!(estimate.basis, RefundBasis::NotTicketed);
+        assert_eq!(estimate.refund, Some(amount("600.00")));
+
+        // A penalty above the fare still leaves the refundable taxes
+        let expensive = offer(refund_rule(
+            serde_json::json!({ "category": "REFUND", "maxPenaltyAmount": "400" }),
+        ));
+        assert_eq!(
+            estimate_offer(&expensive, true, false).refund,
+            Some(amount("150.00"))
+        );
+
+        let non_refundable = offer(refund_rule(
+            serde_json::json!({ "category": "REFUND", "notApplicable": true }),
+        ));
+        let estimate = estimate_offer(&non_refundable, true, false);
+        assert_eq!(estimate.basis, RefundBasis::TaxesOnly);
+        assert_eq!(estimate.refund, Some(amount("150.00")));
+
+        let unknown = offer(serde_json::Value::Null);
+        let estimate = estimate_offer(&unknown, true, true);
+        assert_eq!(estimate.basis, RefundBasis::Unknown);
+        assert_eq!(estimate.refund, None);
+        assert_eq!(estimate.refundable_taxes, amount("150.00"));
+    }
+
+    #[test]
+    fn test_estimate_order() {
+        let order: FlightOrderData = serde_json::from_value(serde_json::json!({
+            "type": "flight-order",
+            "id": "eJzTd9f3NjIJdzUGAAp%2fAiY=",
+            "associatedRecords": [],
+            "travelers": [],
+            "flightOffers": [offer(refund_rule(
+                serde_json::json!({ "category": "REFUND", "maxPenaltyAmount": "0" }),
+            ))],
+            "tickets": [{ "documentType": "ETICKET", "documentNumber": "220-1234567890" }]
+        }))
+        .unwrap();
+
+        let before = estimate(&order, "2025-05-01T00:00:00Z".parse().unwrap());
+        assert!(before.ticketed && !before.departed);
+        assert_eq!(before.currency, "EUR");
+        assert_eq!(before.refund, Some(before.paid));
+
+        let after = estimate(&order, "2025-06-02T00:00:00Z".parse().unwrap());
+        assert!(after.departed);
+    }
+}
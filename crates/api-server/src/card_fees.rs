@@ -0,0 +1,91 @@
+//! Credit card surcharges
+//!
+//! With `include=credit-card-fees` and a card brand/BIN in the pricing
+//! request, Amadeus returns the surcharge the card triggers per offer. We turn
+//! it into the total the customer pays, so the booking flow can show it before
+//! the order is created.
+
+use crate::models::{CardFeeBreakdown, FlightPriceResponse};
+use crate::money::Money;
+
+/// Add the fee of the given card brand to every priced offer Amadeus returned
+/// a fee for
+pub fn attach_card_fees(resp: &mut FlightPriceResponse, brand: &str) {
+    let Some(ref included) = resp.included else {
+        return;
+    };
+
+    let mut breakdowns = Vec::new();
+    for offer in &resp.data.flight_offers {
+        let Some(card_fee) = included
+            .credit_card_fees
+            .values()
+            .find(|fee| fee.flight_offer_id == offer.id && fee.brand.eq_ignore_ascii_case(brand))
+        else {
+            continue;
+        };
+
+        let price = offer.price.grand_total_money();
+        let fee = Money::new(card_fee.amount, card_fee.currency.clone());
+        match price.checked_add(&fee) {
+            Ok(total) => breakdowns.push(CardFeeBreakdown {
+                offer_id: offer.id.clone(),
+                brand: card_fee.brand.clone(),
+                price,
+                fee,
+                total,
+            }),
+            Err(e) => tracing::warn!("Ignoring card fee for offer {}: {}", offer.id, e),
+        }
+    }
+    resp.card_fees = breakdowns;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    fn response(fee_currency: &str) -> FlightPriceResponse {
+        serde_json::from_value(serde_json::json!({
+            "data": {
+                "type": "flight-offers-pricing",
+                "flightOffers": [{
+                    "id": "1",
+                    "type": "flight-offer",
+                    "source": "GDS",
+                    "itineraries": [],
+                    "price": { "currency": "EUR", "total": "300.00", "base": "200.00", "grandTotal": "300.00" },
+                    "validatingAirlineCodes": ["LH"],
+                    "travelerPricings": []
+                }]
+            },
+            "included": {
+                "credit-card-fees": {
+                    "1": { "brand": "VISA", "amount": "4.50", "currency": fee_currency, "flightOfferId": "1" },
+                    "2": { "brand": "MASTERCARD", "amount": "3.00", "currency": fee_currency, "flightOfferId": "1" }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_card_fee_added_to_total() {
+        let mut resp = response("EUR");
+        attach_card_fees(&mut resp, "visa");
+
+        assert_eq!(resp.card_fees.len(), 1);
+        let breakdown = &resp.card_fees[0];
+        assert_eq!(breakdown.brand, "VISA");
+        assert_eq!(breakdown.fee, Money::new(Decimal::new(450, 2), "EUR"));
+        assert_eq!(breakdown.total, Money::new(Decimal::new(30450, 2), "EUR"));
+    }
+
+    #[test]
+    fn test_card_fee_in_other_currency_is_ignored() {
+        let mut resp = response("USD");
+        attach_card_fees(&mut resp, "VISA");
+        assert!(resp.card_fees.is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+//! Card tokenization
+//!
+//! Clients exchange the card number, expiry date and security code for a
+//! short-lived token (`POST /payment-cards`) and order with the token. The
+//! card is kept in this process' memory only and read back right before the
+//! payment is taken (see `detokenize`), so order requests as logged or stored
+//! never carry the card number. Tokens expire after `CARD_TOKEN_TTL_SECS`.
+
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+use crate::models::{CardToken, CardTokenRequest, FlightOrderRequest};
+use crate::redaction::{CardNumber, Secret};
+use crate::validation::ValidationErrors;
+
+/// How long a card token can be ordered with (15 minutes)
+pub const DEFAULT_TOKEN_TTL_SECS: u64 = 900;
+
+/// Card data behind a token
+struct VaultedCard {
+    number: CardNumber,
+    expiry_date: String,
+    security_code: Option<Secret<String>>,
+    expires_at: DateTime<Utc>,
+}
+
+/// In-memory store of tokenized cards
+#[derive(Clone)]
+pub struct CardVault {
+    cards: Arc<Mutex<HashMap<String, VaultedCard>>>,
+    ttl: Duration,
+}
+
+impl CardVault {
+    pub fn new(ttl: Duration) -> Self {
+        CardVault {
+            cards: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+        }
+    }
+
+    /// Keep a validated card and return its token
+    pub fn tokenize(&self, card: CardTokenRequest, now: DateTime<Utc>) -> CardToken {
+        let token = format!("tok_{}", Uuid::new_v4().simple());
+        let expires_at = now + self.ttl;
+        let response = CardToken {
+            token: token.clone(),
+            bin_number: card.number.expose().chars().take(6).collect(),
+            last4: card.number.last4().to_string(),
+            expiry_date: card.expiry_date.clone(),
+            expires_at,
+        };
+
+        let mut cards = self.cards.lock().expect("card vault lock");
+        cards.retain(|_, card| card.expires_at > now);
+        cards.insert(
+            token,
+            VaultedCard {
+                number: card.number,
+                expiry_date: card.expiry_date,
+                security_code: card.security_code,
+                expires_at,
+            },
+        );
+        response
+    }
+
+    /// Put the card data of a tokenized card back into the order request.
+    /// Requests without a token are left as they are.
+    pub fn detokenize(
+        &self,
+        request: &mut FlightOrderRequest,
+        now: DateTime<Utc>,
+    ) -> Result<(), ValidationErrors> {
+        let Some(card) = request
+            .form_of_payment
+            .as_mut()
+            .and_then(|payment| payment.credit_card.as_mut())
+        else {
+            return Ok(());
+        };
+        let Some(token) = card.token.take() else {
+            return Ok(());
+        };
+
+        let cards = self.cards.lock().expect("card vault lock");
+        match cards.get(&token).filter(|vaulted| vaulted.expires_at > now) {
+            Some(vaulted) => {
+                card.number = Some(vaulted.number.clone());
+                card.expiry_date = Some(vaulted.expiry_date.clone());
+                card.security_code = vaulted.security_code.clone();
+                Ok(())
+            }
+            None => {
+                let mut errors = ValidationErrors::new();
+                errors.add(
+                    "formOfPayment.creditCard.token",
+                    "Unknown or expired card token",
+                );
+                Err(errors)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CreditCard, FormOfPayment};
+
+    fn order(token: &str) -> FlightOrderRequest {
+        FlightOrderRequest {
+            flight_offers: Vec::new(),
+            travelers: Vec::new(),
+            remarks: None,
+            ticketing_agreement: None,
+            contacts: None,
+            form_of_payment: Some(FormOfPayment {
+                other: None,
+                credit_card: Some(CreditCard {
+                    brand: "VI".to_string(),
+                    bin_number: "411111".to_string(),
+                    holder: "JORGE GONZALES".to_string(),
+                    number: None,
+                    expiry_date: None,
+                    security_code: None,
+                    flight_offer_ids: None,
+                    token: Some(token.to_string()),
+                }),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_tokenize_and_detokenize() {
+        let vault = CardVault::new(Duration::minutes(15));
+        let now: DateTime<Utc> = "2025-06-01T10:00:00Z".parse().unwrap();
+        let token = vault.tokenize(
+            CardTokenRequest {
+                number: CardNumber::new("4111111111111111"),
+                expiry_date: "2028-08".to_string(),
+                security_code: Some(Secret::new("737".to_string())),
+            },
+            now,
+        );
+        assert!(token.token.starts_with("tok_"));
+        assert_eq!(
+            (token.bin_number.as_str(), token.last4.as_str()),
+            ("411111", "1111")
+        );
+
+        let mut request = order(&token.token);
+        vault.detokenize(&mut request, now).unwrap();
+        let card = request.form_of_payment.unwrap().credit_card.unwrap();
+        assert_eq!(card.number.unwrap().expose(), "4111111111111111");
+        assert_eq!(card.expiry_date.as_deref(), Some("2028-08"));
+        assert_eq!(card.security_code.unwrap().expose(), "737");
+        assert!(card.token.is_none());
+
+        let expired = now + Duration::minutes(16);
+        assert!(vault.detokenize(&mut order(&token.token), expired).is_err());
+        assert!(vault.detokenize(&mut order("tok_unknown"), now).is_err());
+    }
+}
@@ -0,0 +1,378 @@
+//! Currency conversion for display prices
+//!
+//! Amadeus prices offers in the currency of the search (or the point of sale),
+//! while bags and seats may come back in other currencies. Responses keep the
+//! Amadeus amounts untouched and gain `display*` amounts converted with a
+//! locally loaded rate table, so every converted total can be traced back to
+//! the original amount and rate.
+//!
+//! Rates are loaded from a JSON file (`CURRENCY_RATES_FILE`) or from the
+//! `currency_rates` table (`DATABASE_URL`).
+
+use anyhow::{Context, Result, bail};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use crate::models::{
+    BagPrice, DisplayAmount, FlightOffer, FlightOffersResponse, FlightPriceResponse, Price,
+    PriceMatrixEntry, SeatPrice, SeatmapResponse, TravelerPrice,
+};
+use crate::money::Money;
+use crate::validation::{self, Validate, ValidationErrors};
+
+/// Decimal places kept for the rate reported with a converted amount
+const RATE_DECIMALS: u32 = 6;
+
+/// Exchange rates relative to a base currency
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateTable {
+    /// Currency all rates are quoted against
+    pub base: String,
+    /// Date the rates were published
+    #[serde(default)]
+    pub as_of: Option<String>,
+    /// Units of each currency per unit of `base`
+    pub rates: HashMap<String, Decimal>,
+}
+
+impl RateTable {
+    /// Units of `to` per unit of `from`, if both currencies are known
+    pub fn rate(&self, from: &str, to: &str) -> Option<Decimal> {
+        if from == to {
+            return Some(Decimal::ONE);
+        }
+        let per_base = |currency: &str| {
+            if currency == self.base {
+                Some(Decimal::ONE)
+            } else {
+                self.rates.get(currency).copied()
+            }
+        };
+        per_base(to)?.checked_div(per_base(from)?)
+    }
+
+    /// Convert an amount into `currency`, rounded to the currency's minor units
+    pub fn convert(&self, money: &Money, currency: &str) -> Option<DisplayAmount> {
+        let rate = self.rate(&money.currency, currency)?;
+        let converted = Money::new(money.amount.checked_mul(rate)?, currency).round();
+        Some(DisplayAmount {
+            amount: converted.amount,
+            currency: converted.currency,
+            original_amount: money.amount,
+            original_currency: money.currency.clone(),
+            rate: rate.round_dp(RATE_DECIMALS).normalize(),
+            rates_as_of: self.as_of.clone(),
+        })
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.base.is_empty() {
+            bail!("Rate table has no base currency");
+        }
+        if let Some((currency, rate)) = self.rates.iter().find(|(_, rate)| **rate <= Decimal::ZERO)
+        {
+            bail!("Rate for {} must be positive, got {}", currency, rate);
+        }
+        Ok(())
+    }
+}
+
+/// Where the rate table is loaded from
+#[derive(Debug, Clone)]
+pub enum RateSource {
+    /// JSON file: `{"base": "EUR", "asOf": "2025-01-10", "rates": {"USD": "1.0842"}}`
+    File(PathBuf),
+    /// `currency_rates` table (see migrations/002_currency_rates.sql)
+    Postgres(PgPool),
+}
+
+impl RateSource {
+    pub async fn load(&self) -> Result<RateTable> {
+        let table = match self {
+            RateSource::File(path) => {
+                let content = tokio::fs::read_to_string(path)
+                    .await
+                    .with_context(|| format!("Failed to read rate file {}", path.display()))?;
+                serde_json::from_str::<RateTable>(&content)
+                    .with_context(|| format!("Invalid rate file {}", path.display()))?
+            }
+            RateSource::Postgres(pool) => {
+                let rows: Vec<(String, String, Decimal, NaiveDate)> = sqlx::query_as(
+                    "SELECT currency_code, base_currency, rate, valid_on FROM currency_rates",
+                )
+                .fetch_all(pool)
+                .await
+                .context("Failed to load currency rates")?;
+
+                let mut table = RateTable::default();
+                for (currency, base, rate, valid_on) in rows {
+                    if table.base.is_empty() {
+                        table.base = base.clone();
+                    } else if table.base != base {
+                        bail!(
+                            "Currency rates use mixed base currencies: {} and {}",
+                            table.base,
+                            base
+                        );
+                    }
+                    // The table is only as current as its oldest rate
+                    let valid_on = valid_on.to_string();
+                    if table.as_of.as_ref().is_none_or(|as_of| *as_of > valid_on) {
+                        table.as_of = Some(valid_on);
+                    }
+                    table.rates.insert(currency, rate);
+                }
+                table
+            }
+        };
+
+        table.check()?;
+        Ok(table)
+    }
+}
+
+/// Holds the current rate table; the table can be swapped while requests are served
+#[derive(Debug, Default)]
+pub struct CurrencyService {
+    rates: RwLock<Arc<RateTable>>,
+}
+
+impl CurrencyService {
+    /// Snapshot of the current rate table
+    pub fn rates(&self) -> Arc<RateTable> {
+        self.rates
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Reload rates from the source, keeping the current table on failure
+    pub async fn reload(&self, source: &RateSource) -> Result<()> {
+        let table = source.load().await?;
+        tracing::info!(
+            "Loaded {} currency rates (base {}, as of {})",
+            table.rates.len(),
+            table.base,
+            table.as_of.as_deref().unwrap_or("unknown")
+        );
+        *self
+            .rates
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(table);
+        Ok(())
+    }
+
+    /// Add display amounts in `display_currency`, if the client asked for one
+    pub fn apply<T: ConvertForDisplay>(&self, display_currency: Option<&str>, value: &mut T) {
+        if let Some(currency) = display_currency {
+            value.convert_for_display(&self.rates(), currency);
+        }
+    }
+}
+
+/// Query parameter selecting the currency responses are displayed in
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisplayCurrencyQuery {
+    pub display_currency: Option<String>,
+}
+
+impl Validate for DisplayCurrencyQuery {
+    fn validate_on(&self, _today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        if let Some(ref currency) = self.display_currency {
+            validation::check_currency(&mut errors, "displayCurrency", currency);
+        }
+        errors.into_result()
+    }
+}
+
+/// Responses that carry amounts which can be shown in a display currency.
+/// Amounts without a known rate are left without a display amount.
+pub trait ConvertForDisplay {
+    fn convert_for_display(&mut self, rates: &RateTable, currency: &str);
+}
+
+impl ConvertForDisplay for Price {
+    fn convert_for_display(&mut self, rates: &RateTable, currency: &str) {
+        self.display_total = rates.convert(&self.grand_total_money(), currency);
+    }
+}
+
+impl ConvertForDisplay for TravelerPrice {
+    fn convert_for_display(&mut self, rates: &RateTable, currency: &str) {
+        self.display_total = rates.convert(&self.total_money(), currency);
+    }
+}
+
+impl ConvertForDisplay for BagPrice {
+    fn convert_for_display(&mut self, rates: &RateTable, currency: &str) {
+        self.display_amount = rates.convert(&self.money(), currency);
+    }
+}
+
+impl ConvertForDisplay for SeatPrice {
+    fn convert_for_display(&mut self, rates: &RateTable, currency: &str) {
+        self.display_total = self
+            .total_money()
+            .and_then(|money| rates.convert(&money, currency));
+    }
+}
+
+impl ConvertForDisplay for FlightOffer {
+    fn convert_for_display(&mut self, rates: &RateTable, currency: &str) {
+        self.price.convert_for_display(rates, currency);
+        for pricing in &mut self.traveler_pricings {
+            pricing.price.convert_for_display(rates, currency);
+        }
+    }
+}
+
+impl ConvertForDisplay for FlightOffersResponse {
+    fn convert_for_display(&mut self, rates: &RateTable, currency: &str) {
+        for offer in &mut self.data {
+            offer.convert_for_display(rates, currency);
+        }
+    }
+}
+
+impl ConvertForDisplay for FlightPriceResponse {
+    fn convert_for_display(&mut self, rates: &RateTable, currency: &str) {
+        for offer in &mut self.data.flight_offers {
+            offer.convert_for_display(rates, currency);
+        }
+        if let Some(ref mut included) = self.included {
+            for price in included
+                .bags
+                .values_mut()
+                .filter_map(|bag| bag.price.as_mut())
+            {
+                price.convert_for_display(rates, currency);
+            }
+        }
+    }
+}
+
+impl ConvertForDisplay for SeatmapResponse {
+    fn convert_for_display(&mut self, rates: &RateTable, currency: &str) {
+        let prices = self
+            .data
+            .iter_mut()
+            .flat_map(|seatmap| &mut seatmap.decks)
+            .flat_map(|deck| &mut deck.seats)
+            .flat_map(|seat| seat.traveler_pricing.iter_mut().flatten())
+            .filter_map(|pricing| pricing.price.as_mut());
+        for price in prices {
+            price.convert_for_display(rates, currency);
+        }
+    }
+}
+
+impl ConvertForDisplay for PriceMatrixEntry {
+    fn convert_for_display(&mut self, rates: &RateTable, currency: &str) {
+        self.display_price = self
+            .price
+            .and_then(|price| rates.convert(&Money::new(price, self.currency.clone()), currency));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn rates() -> RateTable {
+        serde_json::from_value(serde_json::json!({
+            "base": "EUR",
+            "asOf": "2025-01-10",
+            "rates": { "USD": "1.08", "GBP": "0.84", "JPY": "162.5" }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rates_cross_through_base() {
+        let rates = rates();
+        assert_eq!(rates.rate("EUR", "EUR"), Some(Decimal::ONE));
+        assert_eq!(rates.rate("EUR", "USD"), Some(dec("1.08")));
+        assert_eq!(
+            rates.rate("USD", "EUR").unwrap().round_dp(6),
+            dec("0.925926")
+        );
+        assert_eq!(
+            rates.rate("GBP", "JPY").unwrap().round_dp(4),
+            dec("193.4524")
+        );
+        assert_eq!(rates.rate("EUR", "CHF"), None);
+    }
+
+    #[test]
+    fn test_convert_keeps_original_amount() {
+        let converted = rates()
+            .convert(&Money::new(dec("299.00"), "EUR"), "USD")
+            .unwrap();
+        assert_eq!(converted.amount, dec("322.92"));
+        assert_eq!(converted.currency, "USD");
+        assert_eq!(converted.original_amount, dec("299.00"));
+        assert_eq!(converted.original_currency, "EUR");
+        assert_eq!(converted.rate, dec("1.08"));
+        assert_eq!(converted.rates_as_of.as_deref(), Some("2025-01-10"));
+
+        // Rounded to the minor units of the target currency
+        let yen = rates()
+            .convert(&Money::new(dec("10.01"), "EUR"), "JPY")
+            .unwrap();
+        assert_eq!(yen.amount, dec("1627"));
+    }
+
+    #[test]
+    fn test_offer_conversion_is_not_sent_upstream() {
+        let mut offer: FlightOffer = serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "type": "flight-offer",
+            "source": "GDS",
+            "itineraries": [],
+            "price": { "currency": "EUR", "total": "100.00", "base": "80.00", "grandTotal": "100.00" },
+            "validatingAirlineCodes": ["LH"],
+            "travelerPricings": [{
+                "travelerId": "1",
+                "fareOption": "STANDARD",
+                "travelerType": "ADULT",
+                "price": { "currency": "EUR", "total": "100.00", "base": "80.00" },
+                "fareDetailsBySegment": []
+            }]
+        }))
+        .unwrap();
+
+        offer.convert_for_display(&rates(), "GBP");
+        let display = offer.price.display_total.as_ref().unwrap();
+        assert_eq!(display.amount, dec("84.00"));
+        assert!(offer.traveler_pricings[0].price.display_total.is_some());
+
+        let json = serde_json::to_value(&offer).unwrap();
+        assert_eq!(json["price"]["displayTotal"]["originalCurrency"], "EUR");
+
+        // Display amounts sent back by clients are not read
+        let returned: FlightOffer = serde_json::from_value(json).unwrap();
+        assert!(returned.price.display_total.is_none());
+        assert!(returned.traveler_pricings[0].price.display_total.is_none());
+    }
+
+    #[test]
+    fn test_rate_table_rejects_non_positive_rates() {
+        let mut table = rates();
+        assert!(table.check().is_ok());
+        table.rates.insert("XXX".to_string(), Decimal::ZERO);
+        assert!(table.check().is_err());
+    }
+}
@@ -0,0 +1,427 @@
+//! Penalty summaries from fare rules
+//!
+//! Flight Offers Price returns fare rules in two shapes: structured
+//! `fareRules` on each offer (refund/exchange with a maximum penalty, no
+//! timing) and, with `include=detailed-fare-rules`, the ATPCO rule texts per
+//! fare component. We reduce both to one `PenaltySummary` per offer so clients
+//! can answer "is this refundable?" without reading rule texts.
+//!
+//! Rule texts are free text, so parsing is best effort: whatever the text does
+//! not state is taken from the structured rules, and anything still unknown
+//! stays `None`.
+
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+use crate::models::{
+    DetailedFareRules, FareRuleCategory, FlightOffer, FlightPriceResponse, PenaltyCondition,
+    PenaltyRule, PenaltySummary,
+};
+use crate::money::Money;
+
+/// Description type of the penalty rules (ATPCO category 16)
+const PENALTIES: &str = "PENALTIES";
+
+const NOT_ALLOWED: &[&str] = &[
+    "NON-REFUNDABLE",
+    "NON REFUNDABLE",
+    "NONREFUNDABLE",
+    "NON-CHANGEABLE",
+    "NOT PERMITTED",
+    "NOT ALLOWED",
+];
+
+const FREE: &[&str] = &["FREE OF CHARGE", "WITHOUT CHARGE", "NO CHARGE"];
+
+/// Allowed, but the fee (if any) is not stated in a form we parse, e.g.
+/// "PERMITTED SUBJECT TO A FEE OF USD 150"
+const ALLOWED: &[&str] = &["PERMITTED"];
+
+impl PenaltyCondition {
+    fn is_known(&self) -> bool {
+        self.allowed.is_some() || self.fee.is_some()
+    }
+
+    /// Combine two statements about the same action, keeping the more
+    /// restrictive one: "not allowed" wins and the higher fee wins. It is only
+    /// free if every statement says so.
+    fn restrict(&mut self, other: &PenaltyCondition) {
+        let free = match (self.is_known(), other.is_known()) {
+            (true, true) => self.free && other.free,
+            (true, false) => self.free,
+            (false, true) => other.free,
+            (false, false) => false,
+        };
+        self.allowed = match (self.allowed, other.allowed) {
+            (Some(false), _) | (_, Some(false)) => Some(false),
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (None, None) => None,
+        };
+        self.fee = match (self.fee.take(), &other.fee) {
+            (Some(fee), Some(other)) if fee.currency == other.currency => {
+                Some(if other.amount > fee.amount {
+                    other.clone()
+                } else {
+                    fee
+                })
+            }
+            (Some(fee), _) => Some(fee),
+            (None, other) => other.clone(),
+        };
+        self.free = free && self.allowed == Some(true) && self.fee.is_none();
+    }
+}
+
+impl PenaltyRule {
+    fn restrict(&mut self, other: &PenaltyRule) {
+        self.before_departure.restrict(&other.before_departure);
+        self.after_departure.restrict(&other.after_departure);
+    }
+
+    /// Use `other` wherever this rule says nothing
+    fn or(mut self, other: &PenaltyRule) -> PenaltyRule {
+        if !self.before_departure.is_known() {
+            self.before_departure = other.before_departure.clone();
+        }
+        if !self.after_departure.is_known() {
+            self.after_departure = other.after_departure.clone();
+        }
+        self
+    }
+}
+
+/// Penalties stated by one or more fare components
+#[derive(Debug, Default)]
+struct Penalties {
+    change: PenaltyRule,
+    cancellation: PenaltyRule,
+    no_show: PenaltyCondition,
+}
+
+impl Penalties {
+    fn restrict(&mut self, other: &Penalties) {
+        self.change.restrict(&other.change);
+        self.cancellation.restrict(&other.cancellation);
+        self.no_show.restrict(&other.no_show);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Change,
+    Cancellation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Timing {
+    Before,
+    After,
+    AnyTime,
+}
+
+/// Fee after "CHARGE", e.g. "CHARGE EUR 70.00 FOR REISSUE"
+fn parse_charge(line: &str) -> Option<Money> {
+    let mut words = line
+        .split_whitespace()
+        .skip_while(|word| !word.starts_with("CHARGE"))
+        .skip(1);
+    let currency = words.next()?;
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let amount = words.next()?.trim_end_matches(['.', ',', ';']);
+    Decimal::from_str(amount)
+        .ok()
+        .map(|amount| Money::new(amount, currency))
+}
+
+/// What a single line of rule text says, if anything
+fn parse_statement(line: &str) -> Option<PenaltyCondition> {
+    if NOT_ALLOWED.iter().any(|phrase| line.contains(phrase)) {
+        return Some(PenaltyCondition {
+            allowed: Some(false),
+            fee: None,
+            free: false,
+        });
+    }
+    if let Some(fee) = parse_charge(line) {
+        return Some(PenaltyCondition {
+            allowed: Some(true),
+            fee: Some(fee),
+            free: false,
+        });
+    }
+    if FREE.iter().any(|phrase| line.contains(phrase)) {
+        return Some(PenaltyCondition {
+            allowed: Some(true),
+            fee: None,
+            free: true,
+        });
+    }
+    if ALLOWED.iter().any(|phrase| line.contains(phrase)) {
+        return Some(PenaltyCondition {
+            allowed: Some(true),
+            fee: None,
+            free: false,
+        });
+    }
+    None
+}
+
+/// Parse the penalty text of one fare component
+fn parse_penalty_text(text: &str) -> Penalties {
+    let mut penalties = Penalties::default();
+    let mut section = None;
+    let mut timing = Timing::AnyTime;
+
+    for line in text.lines().map(|line| line.trim().to_uppercase()) {
+        if line.starts_with("CANCEL") {
+            section = Some(Section::Cancellation);
+            timing = Timing::AnyTime;
+        } else if line.starts_with("CHANGE") {
+            section = Some(Section::Change);
+            timing = Timing::AnyTime;
+        }
+        if line.contains("BEFORE DEPARTURE") {
+            timing = Timing::Before;
+        } else if line.contains("AFTER DEPARTURE") {
+            timing = Timing::After;
+        } else if line.contains("ANY TIME") || line.contains("ANYTIME") {
+            timing = Timing::AnyTime;
+        }
+
+        let Some(statement) = parse_statement(&line) else {
+            continue;
+        };
+        if line.contains("NO-SHOW") || line.contains("NO SHOW") {
+            penalties.no_show.restrict(&statement);
+            continue;
+        }
+        let rule = match section {
+            Some(Section::Change) => &mut penalties.change,
+            Some(Section::Cancellation) => &mut penalties.cancellation,
+            None => continue,
+        };
+        if timing != Timing::After {
+            rule.before_departure.restrict(&statement);
+        }
+        if timing != Timing::Before {
+            rule.after_departure.restrict(&statement);
+        }
+    }
+    penalties
+}
+
+/// Penalties from the structured `fareRules` of an offer (no timing, so they
+/// apply before and after departure)
+fn structured_penalties(offer: &FlightOffer) -> Penalties {
+    let mut penalties = Penalties::default();
+    let Some(ref fare_rules) = offer.fare_rules else {
+        return penalties;
+    };
+    let currency = fare_rules
+        .currency
+        .clone()
+        .unwrap_or_else(|| offer.price.currency.clone());
+
+    for rule in &fare_rules.rules {
+        let target = match rule.category {
+            FareRuleCategory::Refund => &mut penalties.cancellation,
+            FareRuleCategory::Exchange => &mut penalties.change,
+            _ => continue,
+        };
+        let condition = if rule.not_applicable == Some(true) {
+            PenaltyCondition {
+                allowed: Some(false),
+                fee: None,
+                free: false,
+            }
+        } else if let Some(amount) = rule.max_penalty_amount {
+            PenaltyCondition {
+                allowed: Some(true),
+                fee: (!amount.is_zero()).then(|| Money::new(amount, currency.clone())),
+                free: amount.is_zero(),
+            }
+        } else {
+            continue;
+        };
+        target.before_departure.restrict(&condition);
+        target.after_departure.restrict(&condition);
+    }
+    penalties
+}
+
+/// Summarize the penalties of an offer from its structured fare rules and the
+/// rule texts of its fare components
+pub fn summarize<'a>(
+    offer: &FlightOffer,
+    detailed: impl IntoIterator<Item = &'a DetailedFareRules>,
+) -> PenaltySummary {
+    let mut text = None;
+    for rules in detailed {
+        let descriptions = rules
+            .fare_notes
+            .iter()
+            .flat_map(|notes| &notes.descriptions)
+            .filter(|description| description.description_type.as_deref() == Some(PENALTIES));
+        for description in descriptions {
+            let Some(ref body) = description.text else {
+                continue;
+            };
+            let parsed = parse_penalty_text(body);
+            text.get_or_insert_with(Penalties::default)
+                .restrict(&parsed);
+        }
+    }
+
+    let structured = structured_penalties(offer);
+    let text = text.unwrap_or_default();
+    let change = text.change.or(&structured.change);
+    let cancellation = text.cancellation.or(&structured.cancellation);
+    let no_show = if text.no_show.is_known() {
+        text.no_show
+    } else {
+        structured.no_show
+    };
+
+    PenaltySummary {
+        offer_id: offer.id.clone(),
+        refundable: cancellation.before_departure.allowed,
+        changeable: change.before_departure.allowed,
+        change,
+        cancellation,
+        no_show,
+    }
+}
+
+/// Add a penalty summary for every priced offer. Rule texts are matched to
+/// offers by segment; texts without a segment apply to every offer.
+pub fn attach_penalty_summaries(resp: &mut FlightPriceResponse) {
+    let detailed: Vec<&DetailedFareRules> = resp
+        .included
+        .iter()
+        .flat_map(|included| included.detailed_fare_rules.values())
+        .collect();
+
+    resp.penalty_summaries = resp
+        .data
+        .flight_offers
+        .iter()
+        .map(|offer| {
+            let has_segment = |segment_id: &str| {
+                offer
+                    .itineraries
+                    .iter()
+                    .flat_map(|itinerary| &itinerary.segments)
+                    .any(|segment| segment.id == segment_id)
+            };
+            let rules = detailed
+                .iter()
+                .copied()
+                .filter(|rules| rules.segment_id.as_deref().is_none_or(&has_segment));
+            summarize(offer, rules)
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PENALTY_TEXT: &str = "PE.PENALTIES
+UNLESS OTHERWISE SPECIFIED
+CANCELLATIONS
+  BEFORE DEPARTURE
+    CHARGE EUR 100.00 FOR CANCEL/REFUND.
+  AFTER DEPARTURE
+    TICKET IS NON-REFUNDABLE.
+  NOTE - IN CASE OF NO-SHOW TICKET IS NON-REFUNDABLE.
+CHANGES
+  ANY TIME
+    CHARGE EUR 50.00 FOR REISSUE/REVALIDATION.";
+
+    fn offer(fare_rules: serde_json::Value) -> FlightOffer {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "type": "flight-offer",
+            "source": "GDS",
+            "itineraries": [],
+            "price": { "currency": "EUR", "total": "300.00", "base": "200.00" },
+            "validatingAirlineCodes": ["LH"],
+            "travelerPricings": [],
+            "fareRules": fare_rules
+        }))
+        .unwrap()
+    }
+
+    fn detailed(text: &str) -> DetailedFareRules {
+        serde_json::from_value(serde_json::json!({
+            "fareBasis": "YOWEU",
+            "segmentId": "1",
+            "fareNotes": { "descriptions": [{ "descriptionType": "PENALTIES", "text": text }] }
+        }))
+        .unwrap()
+    }
+
+    fn eur(amount: i64) -> Option<Money> {
+        Some(Money::new(Decimal::from(amount), "EUR"))
+    }
+
+    #[test]
+    fn test_summary_from_rule_text() {
+        let rules = detailed(PENALTY_TEXT);
+        let summary = summarize(&offer(serde_json::Value::Null), [&rules]);
+
+        assert_eq!(summary.refundable, Some(true));
+        assert_eq!(summary.cancellation.before_departure.fee, eur(100));
+        assert_eq!(summary.cancellation.after_departure.allowed, Some(false));
+        assert_eq!(summary.changeable, Some(true));
+        assert_eq!(summary.change.before_departure.fee, eur(50));
+        assert_eq!(summary.change.after_departure.fee, eur(50));
+        assert_eq!(summary.no_show.allowed, Some(false));
+    }
+
+    #[test]
+    fn test_summary_from_structured_rules() {
+        let offer = offer(serde_json::json!({
+            "rules": [
+                { "category": "EXCHANGE", "maxPenaltyAmount": "75.00" },
+                { "category": "REFUND", "notApplicable": true },
+                { "category": "REVALIDATION", "notApplicable": true }
+            ]
+        }));
+        let summary = summarize(&offer, []);
+
+        assert_eq!(summary.refundable, Some(false));
+        assert_eq!(summary.cancellation.after_departure.allowed, Some(false));
+        assert_eq!(summary.changeable, Some(true));
+        assert_eq!(summary.change.before_departure.fee, eur(75));
+        assert_eq!(summary.no_show, PenaltyCondition::default());
+    }
+
+    #[test]
+    fn test_most_restrictive_fare_component_wins() {
+        let first = detailed(PENALTY_TEXT);
+        let second = detailed(
+            "CANCELLATIONS\n  ANY TIME\n    TICKET IS NON-REFUNDABLE.\nCHANGES\n  ANY TIME\n    CHARGE EUR 80.00 FOR REISSUE.",
+        );
+        let summary = summarize(&offer(serde_json::Value::Null), [&first, &second]);
+
+        assert_eq!(summary.refundable, Some(false));
+        assert_eq!(summary.change.before_departure.fee, eur(80));
+    }
+
+    #[test]
+    fn test_fee_without_charge_is_not_free() {
+        let rules = detailed(
+            "CANCELLATIONS\n  ANY TIME\n    CANCELLATION PERMITTED SUBJECT TO A FEE OF USD 150.\nCHANGES\n  ANY TIME\n    CHANGES FREE OF CHARGE.",
+        );
+        let summary = summarize(&offer(serde_json::Value::Null), [&rules]);
+
+        assert_eq!(summary.refundable, Some(true));
+        assert_eq!(summary.cancellation.before_departure.fee, None);
+        assert!(!summary.cancellation.before_departure.free);
+        assert_eq!(summary.change.before_departure.allowed, Some(true));
+        assert!(summary.change.before_departure.free);
+    }
+}
@@ -0,0 +1,687 @@
+//! Idempotency keys for order creation and cancellation
+//!
+//! Clients may send an `Idempotency-Key` header with `POST /flight-order` and
+//! `DELETE /flight-order/{id}`. The first request with a key runs normally and
+//! its response is stored. Retries with the same key and payload get the stored
+//! response back instead of creating a second PNR. A retry with a different
+//! payload is rejected with 422, and a retry while the first request is still
+//! running is rejected with 409. The running request keeps renewing its lock,
+//! however long the upstream calls take, and only the request holding the lock
+//! can store its response or release the key.
+//!
+//! Keys are kept in Redis (`REDIS_URL`) or, without Redis, in the
+//! `idempotency_keys` table (`DATABASE_URL`, see
+//! migrations/004_idempotency_keys.sql). Server errors are not stored, so the
+//! client can retry them with the same key.
+//!
+//! Stored order responses carry the travelers' birth dates, documents and
+//! contacts, so with `PII_ENCRYPTION_KEYS` set the stored bodies are encrypted
+//! (see `pii`). Keys must be kept for `IDEMPOTENCY_KEY_TTL_SECS` after a
+//! rotation for their responses to be replayed.
+
+use anyhow::{Context, Result};
+use axum::{
+    Json,
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::pii::{self, PiiKeys};
+
+/// Request header carrying the key
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Response header set on replayed responses
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// How long keys and their responses are kept by default (24 hours)
+pub const DEFAULT_TTL_SECS: u64 = 86_400;
+
+/// How long a key stays locked once its request stops renewing the lock
+/// (e.g. a crash)
+const IN_PROGRESS_TTL_SECS: u64 = 120;
+
+/// How often a running request renews its lock
+const LOCK_RENEWAL_INTERVAL: Duration = Duration::from_secs(IN_PROGRESS_TTL_SECS / 4);
+
+/// Set KEYS[1] to ARGV[2] for ARGV[3] seconds if it still holds ARGV[1]
+const REDIS_COMPLETE: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    redis.call('SET', KEYS[1], ARGV[2], 'EX', ARGV[3])
+    return 1
+end
+return 0
+"#;
+
+/// Let KEYS[1] expire in ARGV[2] seconds if it still holds ARGV[1]
+const REDIS_RENEW: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('EXPIRE', KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
+/// Delete KEYS[1] if it still holds ARGV[1]
+const REDIS_RELEASE: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Largest request and response bodies buffered for fingerprinting and replay
+const MAX_BODY_BYTES: usize = 8 * 1024 * 1024;
+
+/// Where keys are stored
+#[derive(Debug, Clone)]
+pub enum IdempotencyStore {
+    Redis(redis::Client),
+    /// `idempotency_keys` table
+    Postgres(PgPool),
+}
+
+/// A key and what is known about its request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredRequest {
+    /// Hash of method, path with query and body of the first request
+    fingerprint: String,
+    /// Lock of the running request; missing once its response is stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    claim_id: Option<Uuid>,
+    /// Missing while the first request is still running
+    response: Option<StoredResponse>,
+}
+
+/// A key locked by the running request
+#[derive(Debug, Clone)]
+struct Claim {
+    key: String,
+    id: Uuid,
+    /// What the lock holds in Redis, compared before every change
+    pending: String,
+}
+
+/// Outcome of claiming a key
+enum Claimed {
+    /// The key is locked for this request
+    New(Claim),
+    /// Another request used the key (or still runs with it)
+    Existing(StoredRequest),
+}
+
+/// Stops renewing a lock when the request is done or dropped
+struct Renewal(JoinHandle<()>);
+
+impl Drop for Renewal {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredResponse {
+    status: u16,
+    content_type: Option<String>,
+    /// Base64 encoded body
+    body: String,
+}
+
+impl StoredResponse {
+    fn into_response(self, keys: Option<&PiiKeys>) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        let mut body = BASE64.decode(self.body).unwrap_or_default();
+        if pii::is_sealed_document(&body) {
+            let opened = match keys {
+                Some(keys) => keys.open_document(&body),
+                None => Err(pii::PiiError::InvalidKeys("Not set".to_string())),
+            };
+            body = match opened {
+                Ok(body) => body,
+                Err(e) => {
+                    tracing::error!("Cannot replay stored response: {}", e);
+                    return error_response(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "IDEMPOTENCY_REPLAY_FAILED",
+                        "The stored response of this Idempotency-Key cannot be read",
+                    );
+                }
+            };
+        }
+        let mut response = (status, body).into_response();
+        let headers = response.headers_mut();
+        if let Some(content_type) = self
+            .content_type
+            .and_then(|value| HeaderValue::from_str(&value).ok())
+        {
+            headers.insert(header::CONTENT_TYPE, content_type);
+        }
+        headers.insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+/// A row of `idempotency_keys`
+#[derive(sqlx::FromRow)]
+struct KeyRow {
+    fingerprint: String,
+    status_code: Option<i32>,
+    content_type: Option<String>,
+    body: Option<Vec<u8>>,
+}
+
+/// Idempotency key storage with the configured retention
+#[derive(Debug, Clone)]
+pub struct Idempotency {
+    store: IdempotencyStore,
+    ttl: Duration,
+    pii: Option<Arc<PiiKeys>>,
+}
+
+impl Idempotency {
+    pub fn new(store: IdempotencyStore, ttl: Duration) -> Self {
+        Self {
+            store,
+            ttl,
+            pii: None,
+        }
+    }
+
+    /// Encrypt stored responses with `keys`
+    pub fn with_pii_keys(mut self, keys: PiiKeys) -> Self {
+        self.pii = Some(Arc::new(keys));
+        self
+    }
+
+    /// Lock `key` for a new request, or return what is stored for it
+    async fn claim(&self, key: &str, fingerprint: &str) -> Result<Claimed> {
+        let pending = StoredRequest {
+            fingerprint: fingerprint.to_string(),
+            claim_id: Some(Uuid::new_v4()),
+            response: None,
+        };
+        let claim = Claim {
+            key: key.to_string(),
+            id: pending.claim_id.unwrap_or_default(),
+            pending: serde_json::to_string(&pending)?,
+        };
+        match &self.store {
+            IdempotencyStore::Redis(client) => {
+                let mut conn = client.get_multiplexed_async_connection().await?;
+                let claimed: Option<String> = redis::cmd("SET")
+                    .arg(redis_key(key))
+                    .arg(&claim.pending)
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(IN_PROGRESS_TTL_SECS)
+                    .query_async(&mut conn)
+                    .await?;
+                if claimed.is_some() {
+                    return Ok(Claimed::New(claim));
+                }
+                let stored: Option<String> = conn.get(redis_key(key)).await?;
+                match stored {
+                    Some(stored) => Ok(Claimed::Existing(
+                        serde_json::from_str(&stored).context("Invalid stored idempotency key")?,
+                    )),
+                    // Expired in between, the next retry claims it
+                    None => Ok(Claimed::Existing(pending)),
+                }
+            }
+            IdempotencyStore::Postgres(pool) => {
+                sqlx::query("DELETE FROM idempotency_keys WHERE key = $1 AND expires_at < $2")
+                    .bind(key)
+                    .bind(Utc::now())
+                    .execute(pool)
+                    .await?;
+                let claimed = sqlx::query(
+                    "INSERT INTO idempotency_keys (key, fingerprint, claim_id, expires_at) \
+                     VALUES ($1, $2, $3, $4) ON CONFLICT (key) DO NOTHING",
+                )
+                .bind(key)
+                .bind(fingerprint)
+                .bind(claim.id)
+                .bind(expires_in(IN_PROGRESS_TTL_SECS))
+                .execute(pool)
+                .await?
+                .rows_affected()
+                    == 1;
+                if claimed {
+                    return Ok(Claimed::New(claim));
+                }
+                let row: Option<KeyRow> = sqlx::query_as(
+                    "SELECT fingerprint, status_code, content_type, body FROM idempotency_keys \
+                         WHERE key = $1",
+                )
+                .bind(key)
+                .fetch_optional(pool)
+                .await?;
+                Ok(Claimed::Existing(match row {
+                    Some(row) => StoredRequest {
+                        fingerprint: row.fingerprint,
+                        claim_id: None,
+                        response: row.status_code.map(|status| StoredResponse {
+                            status: status as u16,
+                            content_type: row.content_type,
+                            body: BASE64.encode(row.body.unwrap_or_default()),
+                        }),
+                    },
+                    None => pending,
+                }))
+            }
+        }
+    }
+
+    /// Extend the lock of a running request, returning whether it still holds it
+    async fn renew(&self, claim: &Claim) -> Result<bool> {
+        match &self.store {
+            IdempotencyStore::Redis(client) => {
+                let mut conn = client.get_multiplexed_async_connection().await?;
+                let renewed: i64 = redis::Script::new(REDIS_RENEW)
+                    .key(redis_key(&claim.key))
+                    .arg(&claim.pending)
+                    .arg(IN_PROGRESS_TTL_SECS)
+                    .invoke_async(&mut conn)
+                    .await?;
+                Ok(renewed == 1)
+            }
+            IdempotencyStore::Postgres(pool) => Ok(sqlx::query(
+                "UPDATE idempotency_keys SET expires_at = $3 \
+                 WHERE key = $1 AND claim_id = $2 AND status_code IS NULL",
+            )
+            .bind(&claim.key)
+            .bind(claim.id)
+            .bind(expires_in(IN_PROGRESS_TTL_SECS))
+            .execute(pool)
+            .await?
+            .rows_affected()
+                == 1),
+        }
+    }
+
+    /// Renew the lock in the background until the returned handle is dropped
+    fn keep_locked(&self, claim: &Claim) -> Renewal {
+        let idempotency = self.clone();
+        let claim = claim.clone();
+        Renewal(tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(LOCK_RENEWAL_INTERVAL).await;
+                match idempotency.renew(&claim).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        tracing::warn!("Lost the lock of Idempotency-Key {}", claim.key);
+                        return;
+                    }
+                    Err(e) => tracing::error!(
+                        "Failed to renew the lock of Idempotency-Key {}: {:#}",
+                        claim.key,
+                        e
+                    ),
+                }
+            }
+        }))
+    }
+
+    /// Store the response of the request holding `claim`, returning whether it
+    /// still held the lock (otherwise nothing is stored)
+    async fn complete(
+        &self,
+        claim: &Claim,
+        fingerprint: &str,
+        response: &StoredResponse,
+    ) -> Result<bool> {
+        match &self.store {
+            IdempotencyStore::Redis(client) => {
+                let mut conn = client.get_multiplexed_async_connection().await?;
+                let stored = StoredRequest {
+                    fingerprint: fingerprint.to_string(),
+                    claim_id: None,
+                    response: Some(response.clone()),
+                };
+                let completed: i64 = redis::Script::new(REDIS_COMPLETE)
+                    .key(redis_key(&claim.key))
+                    .arg(&claim.pending)
+                    .arg(serde_json::to_string(&stored)?)
+                    .arg(self.ttl.as_secs())
+                    .invoke_async(&mut conn)
+                    .await?;
+                Ok(completed == 1)
+            }
+            IdempotencyStore::Postgres(pool) => Ok(sqlx::query(
+                "UPDATE idempotency_keys SET status_code = $3, content_type = $4, body = $5, \
+                 expires_at = $6, claim_id = NULL \
+                 WHERE key = $1 AND claim_id = $2 AND status_code IS NULL",
+            )
+            .bind(&claim.key)
+            .bind(claim.id)
+            .bind(i32::from(response.status))
+            .bind(&response.content_type)
+            .bind(BASE64.decode(&response.body)?)
+            .bind(expires_in(self.ttl.as_secs()))
+            .execute(pool)
+            .await?
+            .rows_affected()
+                == 1),
+        }
+    }
+
+    /// Unlock the key of `claim` so the request can be retried, unless another
+    /// request holds it by now
+    async fn release(&self, claim: &Claim) -> Result<()> {
+        match &self.store {
+            IdempotencyStore::Redis(client) => {
+                let mut conn = client.get_multiplexed_async_connection().await?;
+                let _: i64 = redis::Script::new(REDIS_RELEASE)
+                    .key(redis_key(&claim.key))
+                    .arg(&claim.pending)
+                    .invoke_async(&mut conn)
+                    .await?;
+            }
+            IdempotencyStore::Postgres(pool) => {
+                sqlx::query(
+                    "DELETE FROM idempotency_keys \
+                     WHERE key = $1 AND claim_id = $2 AND status_code IS NULL",
+                )
+                .bind(&claim.key)
+                .bind(claim.id)
+                .execute(pool)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn redis_key(key: &str) -> String {
+    format!("idempotency:{}", key)
+}
+
+fn expires_in(secs: u64) -> DateTime<Utc> {
+    Utc::now() + chrono::Duration::seconds(secs as i64)
+}
+
+/// The key of a request; `Ok(None)` if the client sent none
+fn idempotency_key(headers: &HeaderMap) -> Result<Option<String>, &'static str> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    let key = value
+        .to_str()
+        .map_err(|_| "Idempotency-Key must be visible ASCII")?
+        .trim();
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        return Err("Idempotency-Key must have 1 to 255 characters");
+    }
+    Ok(Some(key.to_string()))
+}
+
+/// Hash of what makes two requests the same: method, path with query and body
+fn fingerprint(method: &str, target: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_bytes());
+    hasher.update(b" ");
+    hasher.update(target.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+fn error_response(status: StatusCode, title: &str, detail: &str) -> Response {
+    (
+        status,
+        Json(serde_json::json!({
+            "errors": [{
+                "status": status.as_u16(),
+                "title": title,
+                "detail": detail,
+                "source": { "parameter": "Idempotency-Key" }
+            }]
+        })),
+    )
+        .into_response()
+}
+
+/// Middleware deduplicating requests by their `Idempotency-Key`
+pub async fn guard(
+    State(idempotency): State<Option<Idempotency>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let key = match idempotency_key(request.headers()) {
+        Ok(Some(key)) => key,
+        Ok(None) => return next.run(request).await,
+        Err(message) => {
+            return error_response(StatusCode::BAD_REQUEST, "INVALID_IDEMPOTENCY_KEY", message);
+        }
+    };
+    let Some(idempotency) = idempotency else {
+        tracing::warn!("Idempotency-Key received but neither Redis nor Postgres is configured");
+        return next.run(request).await;
+    };
+
+    let (parts, body) = request.into_parts();
+    let body: Bytes = match axum::body::to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(_) => return StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+    };
+    let target = parts
+        .uri
+        .path_and_query()
+        .map_or(parts.uri.path(), |target| target.as_str());
+    let fingerprint = fingerprint(parts.method.as_str(), target, &body);
+
+    let claim = match idempotency.claim(&key, &fingerprint).await {
+        Ok(Claimed::New(claim)) => claim,
+        Ok(Claimed::Existing(stored)) if stored.fingerprint != fingerprint => {
+            return error_response(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "IDEMPOTENCY_KEY_REUSED",
+                "Idempotency-Key was already used for a different request",
+            );
+        }
+        Ok(Claimed::Existing(StoredRequest {
+            response: Some(response),
+            ..
+        })) => {
+            tracing::info!("Replaying response for Idempotency-Key {}", key);
+            return response.into_response(idempotency.pii.as_deref());
+        }
+        Ok(Claimed::Existing(_)) => {
+            return error_response(
+                StatusCode::CONFLICT,
+                "IDEMPOTENCY_KEY_IN_USE",
+                "A request with this Idempotency-Key is still being processed",
+            );
+        }
+        Err(e) => {
+            // Better a possible duplicate than no booking at all
+            tracing::error!("Idempotency store error: {:#}", e);
+            return next.run(Request::from_parts(parts, Body::from(body))).await;
+        }
+    };
+
+    let renewal = idempotency.keep_locked(&claim);
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    drop(renewal);
+    if response.status().is_server_error() {
+        if let Err(e) = idempotency.release(&claim).await {
+            tracing::error!("Failed to release Idempotency-Key {}: {:#}", key, e);
+        }
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!(
+                "Failed to buffer response for Idempotency-Key {}: {}",
+                key,
+                e
+            );
+            let _ = idempotency.release(&claim).await;
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let stored = StoredResponse {
+        status: parts.status.as_u16(),
+        content_type: parts
+            .headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        body: match &idempotency.pii {
+            Some(keys) => BASE64.encode(keys.seal_document(&body)),
+            None => BASE64.encode(&body),
+        },
+    };
+    match idempotency.complete(&claim, &fingerprint, &stored).await {
+        Ok(true) => {}
+        Ok(false) => tracing::warn!(
+            "Response for Idempotency-Key {} not stored: the key is no longer locked by this request",
+            key
+        ),
+        Err(e) => tracing::error!(
+            "Failed to store response for Idempotency-Key {}: {:#}",
+            key,
+            e
+        ),
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idempotency_key_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(idempotency_key(&headers), Ok(None));
+
+        headers.insert(
+            IDEMPOTENCY_KEY_HEADER,
+            HeaderValue::from_static(" abc-123 "),
+        );
+        assert_eq!(idempotency_key(&headers), Ok(Some("abc-123".to_string())));
+
+        headers.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_static(""));
+        assert!(idempotency_key(&headers).is_err());
+
+        let long = "k".repeat(MAX_KEY_LENGTH + 1);
+        headers.insert(
+            IDEMPOTENCY_KEY_HEADER,
+            HeaderValue::from_str(&long).unwrap(),
+        );
+        assert!(idempotency_key(&headers).is_err());
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let order = fingerprint("POST", "/flight-order", br#"{"travelers":[]}"#);
+        assert_eq!(order.len(), 64);
+        assert_eq!(
+            order,
+            fingerprint("POST", "/flight-order", br#"{"travelers":[]}"#)
+        );
+        assert_ne!(
+            order,
+            fingerprint("POST", "/flight-order", br#"{"travelers":[1]}"#)
+        );
+        assert_ne!(
+            order,
+            fingerprint("DELETE", "/flight-order", br#"{"travelers":[]}"#)
+        );
+
+        // Cancellations by someone else or for another reason are other requests
+        let delete = |target: &str| fingerprint("DELETE", target, b"");
+        assert_ne!(
+            delete("/flight-order/1?cancelledBy=agent&reason=duplicate"),
+            delete("/flight-order/1?cancelledBy=agent&reason=schedule")
+        );
+        assert_ne!(
+            delete("/flight-order/1"),
+            delete("/flight-order/1?cancelledBy=agent")
+        );
+    }
+
+    #[test]
+    fn test_stored_request_claim() {
+        let pending = |id| StoredRequest {
+            fingerprint: "abc".to_string(),
+            claim_id: Some(id),
+            response: None,
+        };
+        // Every claim locks the key with its own value
+        let first = serde_json::to_string(&pending(Uuid::new_v4())).unwrap();
+        let second = serde_json::to_string(&pending(Uuid::new_v4())).unwrap();
+        assert_ne!(first, second);
+
+        // Entries stored before claims existed still load
+        let stored: StoredRequest =
+            serde_json::from_str(r#"{"fingerprint":"abc","response":null}"#).unwrap();
+        assert!(stored.claim_id.is_none());
+
+        // Completed entries drop the claim
+        let completed = StoredRequest {
+            claim_id: None,
+            ..pending(Uuid::new_v4())
+        };
+        assert!(!serde_json::to_string(&completed).unwrap().contains("claim"));
+    }
+
+    #[tokio::test]
+    async fn test_replayed_response() {
+        let stored = StoredResponse {
+            status: 201,
+            content_type: Some("application/json".to_string()),
+            body: BASE64.encode(br#"{"data":{"id":"ORDER1"}}"#),
+        };
+        let response = stored.into_response(None);
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[REPLAYED_HEADER], "true");
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        let body = axum::body::to_bytes(response.into_body(), MAX_BODY_BYTES)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], br#"{"data":{"id":"ORDER1"}}"#);
+    }
+
+    #[tokio::test]
+    async fn test_replayed_encrypted_response() {
+        let keys = PiiKeys::parse("k1:MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap();
+        let stored = StoredResponse {
+            status: 201,
+            content_type: Some("application/json".to_string()),
+            body: BASE64.encode(keys.seal_document(br#"{"data":{"id":"ORDER1"}}"#)),
+        };
+
+        let response = stored.clone().into_response(Some(&keys));
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = axum::body::to_bytes(response.into_body(), MAX_BODY_BYTES)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], br#"{"data":{"id":"ORDER1"}}"#);
+
+        // Never replayed as ciphertext
+        let response = stored.into_response(None);
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
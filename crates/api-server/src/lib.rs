@@ -4,6 +4,31 @@
 //! integrating with Amadeus Self-Service APIs for flight search, pricing, and booking.
 
 pub mod models;
+pub mod money;
+pub mod admin;
 pub mod amadeus;
+pub mod ancillaries;
+pub mod bookings;
+pub mod brand_comparison;
+pub mod cancellation;
+pub mod card_fees;
+pub mod card_vault;
+pub mod currency;
+pub mod fare_rules;
+pub mod idempotency;
+pub mod markup;
+pub mod notifications;
+pub mod passengers;
+pub mod payments;
+pub mod pii;
+pub mod price_change;
+pub mod reconciliation;
+pub mod redaction;
+pub mod schedule_changes;
+pub mod seat_recommendation;
+pub mod seatmap_grid;
+pub mod ticketing;
+pub mod travelers;
+pub mod validation;
 
 pub use models::*;
@@ -1,7 +1,8 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
+    http::{HeaderMap, StatusCode},
+    middleware,
+    response::{IntoResponse, Json, Response},
     routing::{delete, get, post},
     Router,
 };
@@ -11,21 +12,60 @@ use tokio::net::TcpListener;
 use tower_http::cors::CorsLayer;
 use redis::AsyncCommands;
 use chrono::Datelike;
+use currency::DisplayCurrencyQuery;
+use markup::{ApplyMarkup, SalesContext};
+use validation::Validate;
 
+mod admin;
 mod amadeus;
+mod ancillaries;
+mod bookings;
+mod brand_comparison;
+mod cancellation;
+mod card_fees;
+mod card_vault;
+mod currency;
+mod fare_rules;
+mod idempotency;
+mod markup;
+mod notifications;
 pub mod models;
+pub mod money;
+mod passengers;
+mod payments;
+mod pii;
+mod price_change;
 mod rate_limiter;
+mod reconciliation;
+mod redaction;
+mod schedule_changes;
+mod seat_recommendation;
+mod seatmap_grid;
 mod sse;
+mod ticketing;
+mod travelers;
+mod validation;
 
 pub use models::*;
 
 /// Cache TTL for flight search results (5 minutes)
 const SEARCH_CACHE_TTL_SECS: u64 = 300;
 
+/// Default interval for reloading currency rates (1 hour)
+const CURRENCY_RATES_REFRESH_SECS: u64 = 3600;
+
 #[derive(Clone)]
 struct AppState {
     amadeus_client: reqwest::Client,
     redis_client: Option<redis::Client>,
+    currency: Arc<currency::CurrencyService>,
+    markup: Arc<markup::MarkupEngine>,
+    price_tolerance: price_change::PriceChangeTolerance,
+    bookings: Option<bookings::BookingStore>,
+    cancellation_quote_ttl: chrono::Duration,
+    payments: Option<Arc<dyn payments::PaymentProvider>>,
+    card_vault: card_vault::CardVault,
+    admin_key: admin::AdminKey,
 }
 
 
@@ -33,8 +73,10 @@ struct AppState {
 async fn main() {
     dotenv::dotenv().ok();
 
-    // Initialize logging with explicit level
+    // Initialize logging with explicit level; card and document numbers are
+    // scrubbed from everything logged
     tracing_subscriber::fmt()
+        .with_writer(redaction::Scrubbing::new(std::io::stdout))
         .with_max_level(tracing::Level::INFO)
         .with_target(false)
         .with_thread_ids(false)
@@ -63,25 +105,207 @@ async fn main() {
         }
     };
 
+    // Initialize Postgres pool (optional - connects on first use)
+    let db_pool = match std::env::var("DATABASE_URL") {
+        Ok(url) => {
+            match sqlx::postgres::PgPoolOptions::new().max_connections(5).connect_lazy(&url) {
+                Ok(pool) => Some(pool),
+                Err(e) => {
+                    tracing::warn!("Invalid DATABASE_URL: {}. Database features disabled.", e);
+                    None
+                }
+            }
+        }
+        Err(_) => {
+            tracing::info!("DATABASE_URL not set. Database features disabled.");
+            None
+        }
+    };
+
+    // Load currency rates for display currency conversion (optional)
+    // CURRENCY_RATES_FILE takes precedence over the currency_rates table
+    let currency_service = Arc::new(currency::CurrencyService::default());
+    let rate_source = match std::env::var("CURRENCY_RATES_FILE").ok().filter(|path| !path.is_empty()) {
+        Some(path) => Some(currency::RateSource::File(path.into())),
+        None => db_pool.clone().map(currency::RateSource::Postgres),
+    };
+    match rate_source {
+        Some(source) => {
+            if let Err(e) = currency_service.reload(&source).await {
+                tracing::warn!("Failed to load currency rates: {:#}", e);
+            }
+
+            let refresh_secs = std::env::var("CURRENCY_RATES_REFRESH_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(CURRENCY_RATES_REFRESH_SECS);
+            let service = currency_service.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(refresh_secs));
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = service.reload(&source).await {
+                        tracing::warn!("Failed to reload currency rates: {:#}", e);
+                    }
+                }
+            });
+        }
+        None => tracing::info!("No currency rates configured. Display currency conversion disabled."),
+    }
+
+    // Load markup and service fee rules (optional - offers are sold at net fares without)
+    let markup_engine = match std::env::var("MARKUP_RULES_FILE").ok().filter(|path| !path.is_empty()) {
+        Some(path) => match markup::MarkupEngine::load(std::path::Path::new(&path)).await {
+            Ok(engine) => {
+                tracing::info!("Loaded {} markup rules", engine.rules().len());
+                engine
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load markup rules: {:#}. Markups disabled.", e);
+                markup::MarkupEngine::default()
+            }
+        },
+        None => {
+            tracing::info!("MARKUP_RULES_FILE not set. Markups disabled.");
+            markup::MarkupEngine::default()
+        }
+    };
+
+    // Sales contexts (tenant, channel) of the API keys markup rules match on
+    let sales_keys = match markup::SalesKeys::from_env() {
+        Ok(keys) if keys.is_empty() => {
+            tracing::info!("SALES_API_KEYS not set. All requests use the public sales context.");
+            keys
+        }
+        Ok(keys) => {
+            tracing::info!("Loaded {} sales API keys", keys.len());
+            keys
+        }
+        Err(e) => {
+            tracing::warn!("Invalid SALES_API_KEYS: {:#}. All requests use the public sales context.", e);
+            markup::SalesKeys::default()
+        }
+    };
+
+    // Encryption of traveler PII in stored bookings and idempotency responses
+    // (required to store either; with invalid keys, neither is stored)
+    let pii_keys = match pii::PiiKeys::from_env() {
+        Ok(None) if db_pool.is_some() || redis_client.is_some() => {
+            if !pii::PiiKeys::unencrypted_allowed() {
+                tracing::error!("PII_ENCRYPTION_KEYS not set. Set it to store bookings and idempotency keys (or PII_ALLOW_UNENCRYPTED=true in development).");
+                std::process::exit(1);
+            }
+            tracing::warn!("PII_ENCRYPTION_KEYS not set and PII_ALLOW_UNENCRYPTED=true. Traveler PII of bookings and idempotency responses is stored unencrypted.");
+            Ok(None)
+        }
+        Ok(keys) => Ok(keys),
+        Err(e) => {
+            tracing::error!("{}. Bookings and idempotency keys are not stored.", e);
+            Err(e)
+        }
+    };
+
+    // Idempotency keys of order creation and cancellation (Redis, otherwise Postgres)
+    let idempotency_ttl = std::env::var("IDEMPOTENCY_KEY_TTL_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(idempotency::DEFAULT_TTL_SECS);
+    let idempotency = match (&redis_client, &db_pool) {
+        _ if pii_keys.is_err() => None,
+        (Some(client), _) => Some(idempotency::IdempotencyStore::Redis(client.clone())),
+        (None, Some(pool)) => Some(idempotency::IdempotencyStore::Postgres(pool.clone())),
+        (None, None) => {
+            tracing::info!("Neither Redis nor Postgres configured. Idempotency keys disabled.");
+            None
+        }
+    }
+    .map(|store| idempotency::Idempotency::new(store, std::time::Duration::from_secs(idempotency_ttl)))
+    .map(|idempotency| match &pii_keys {
+        Ok(Some(keys)) => idempotency.with_pii_keys(keys.clone()),
+        _ => idempotency,
+    });
+
+    let bookings = match pii_keys {
+        Ok(Some(keys)) => db_pool.clone().map(|pool| bookings::BookingStore::new(pool).with_pii_keys(keys)),
+        Ok(None) => db_pool.clone().map(bookings::BookingStore::new),
+        Err(_) => None,
+    };
+
     let state = AppState {
         amadeus_client: reqwest::Client::new(),
         redis_client,
+        currency: currency_service,
+        markup: Arc::new(markup_engine),
+        price_tolerance: price_change::PriceChangeTolerance::from_env(),
+        bookings,
+        cancellation_quote_ttl: chrono::Duration::seconds(
+            std::env::var("CANCELLATION_QUOTE_TTL_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(cancellation::DEFAULT_QUOTE_TTL_SECS as i64),
+        ),
+        payments: payments::from_env(),
+        card_vault: card_vault::CardVault::new(chrono::Duration::seconds(
+            std::env::var("CARD_TOKEN_TTL_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .filter(|secs| *secs > 0)
+                .unwrap_or(card_vault::DEFAULT_TOKEN_TTL_SECS as i64),
+        )),
+        admin_key: admin::AdminKey::from_env(),
     };
+    if !state.admin_key.is_configured() {
+        tracing::info!("ADMIN_API_KEY not set. Stored bookings are only returned with masked traveler data.");
+    }
+
+    // Ticketing deadline reminders and automatic cancellation of unticketed orders,
+    // reconciliation of stored bookings with their live Amadeus orders, and the
+    // schedule change monitor, and resealing of PII by the current encryption key
+    match &state.bookings {
+        Some(store) => {
+            pii::spawn_rotation(store.clone());
+            let notifier = notifications::Notifier::from_env(state.amadeus_client.clone());
+            ticketing::spawn(store.clone(), state.amadeus_client.clone(), state.payments.clone(), notifier.clone(), ticketing::TicketingPolicy::from_env());
+
+            let reconciliation_secs = std::env::var("RECONCILIATION_INTERVAL_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .filter(|secs| *secs > 0)
+                .unwrap_or(reconciliation::DEFAULT_INTERVAL_SECS);
+            reconciliation::spawn(store.clone(), state.amadeus_client.clone(), notifier.clone(), std::time::Duration::from_secs(reconciliation_secs));
+            schedule_changes::spawn(store.clone(), state.amadeus_client.clone(), notifier, schedule_changes::MonitorConfig::from_env());
+        }
+        None => tracing::info!("No database configured. Ticketing deadlines, order and schedule changes are not tracked."),
+    }
 
 
     let app = Router::new()
         .route("/health", get(health))
         .route("/flight-search", post(flight_search))
         .route("/flight-price", post(flight_price))
+        .route("/flight-price/bags", post(select_bags))
+        .route("/flight-price/seats", post(select_seats))
         .route("/flight-price-stream", post(sse::flight_price_stream))
         .route("/upsell-stream", post(sse::upsell_stream))
         .route("/price-matrix", post(price_matrix))
         .route("/price-matrix-stream", post(sse::price_matrix_stream))
-        .route("/flight-order", post(flight_order))
+        .route("/payment-cards", post(tokenize_card))
+        .route("/flight-order", post(flight_order)
+            .layer(middleware::from_fn_with_state(idempotency.clone(), idempotency::guard)))
         .route("/flight-order/{id}", get(get_flight_order))
-        .route("/flight-order/{id}", delete(delete_flight_order))
+        .route("/flight-order/{id}", delete(delete_flight_order)
+            .layer(middleware::from_fn_with_state(idempotency.clone(), idempotency::guard)))
+        .route("/flight-order/{id}/cancellation/quote", post(quote_cancellation))
+        .route("/flight-order/{id}/cancellation/confirm", post(confirm_cancellation)
+            .layer(middleware::from_fn_with_state(idempotency, idempotency::guard)))
+        .route("/bookings/{id}", get(get_booking))
+        .route("/bookings/order/{id}", get(get_booking_by_order))
+        .route("/bookings/{id}/disruptions", get(get_booking_disruptions))
+        .route("/bookings/{id}/disruptions/{disruption_id}/accept", post(accept_disruption_alternative))
         .route("/seatmaps", post(get_seatmaps))
         .route("/seatmaps/order/{id}", get(get_seatmaps_by_order))
+        .route("/seatmaps/recommendations", post(recommend_seats))
         .route("/upsell", post(get_upsell_offers))
         .route("/flight-availabilities", post(get_flight_availabilities))
         .route("/flight-destinations", get(get_flight_destinations))
@@ -100,6 +324,7 @@ async fn main() {
         .route("/air-traffic-booked", get(get_air_traffic_booked))
         .route("/recommended-locations", get(get_recommended_locations))
         .route("/location-score", get(get_location_score))
+        .layer(middleware::from_fn_with_state(Arc::new(sales_keys), markup::resolve_sales_context))
         .layer(CorsLayer::permissive())
         .with_state(Arc::new(state));
 
@@ -120,8 +345,10 @@ async fn health() -> StatusCode {
 
 async fn flight_search(
     State(state): State<Arc<AppState>>,
+    sales: SalesContext,
+    Query(display): Query<DisplayCurrencyQuery>,
     Json(payload): Json<FlightSearchRequest>,
-) -> Result<Json<models::FlightOffersResponse>, StatusCode> {
+) -> Result<Json<models::FlightOffersResponse>, Response> {
     if let Some(ref return_date) = payload.return_date {
         tracing::info!("🔍 Flight search request: {} -> {}, departure: {}, return: {}",
             payload.origin, payload.destination, payload.departure_date, return_date);
@@ -130,31 +357,24 @@ async fn flight_search(
             payload.origin, payload.destination, payload.departure_date);
     }
 
-    // Generate cache key from search parameters (including travel_class, non_stop, and maxResults)
-    let cache_key = format!(
-        "flight_search:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}",
-        payload.origin,
-        payload.destination,
-        payload.departure_date,
-        payload.return_date.as_deref().unwrap_or(""),
-        payload.adults,
-        payload.children,
-        payload.infants,
-        payload.travel_class.as_deref().unwrap_or("ECONOMY"),
-        payload.non_stop.unwrap_or(false),
-        payload.max_results.unwrap_or(50)
-    );
+    // Reject invalid requests before calling Amadeus
+    payload.validate().map_err(IntoResponse::into_response)?;
+    display.validate().map_err(IntoResponse::into_response)?;
+    let display_currency = display.display_currency.as_deref();
+
+    // Generate cache key from all search parameters
+    let cache_key = payload.cache_key();
 
     // Try to get from cache first
-    if let Some(ref redis_client) = state.redis_client {
-        if let Ok(mut conn) = redis_client.get_multiplexed_async_connection().await {
-            if let Ok(cached) = conn.get::<_, String>(&cache_key).await {
-                if let Ok(resp) = serde_json::from_str::<models::FlightOffersResponse>(&cached) {
-                    tracing::debug!("Cache hit for flight search: {}", cache_key);
-                    return Ok(Json(resp));
-                }
-            }
-        }
+    if let Some(ref redis_client) = state.redis_client
+        && let Ok(mut conn) = redis_client.get_multiplexed_async_connection().await
+        && let Ok(cached) = conn.get::<_, String>(&cache_key).await
+        && let Ok(mut resp) = serde_json::from_str::<models::FlightOffersResponse>(&cached)
+    {
+        tracing::debug!("Cache hit for flight search: {}", cache_key);
+        resp.apply_markup(&state.markup, &sales, &state.currency.rates());
+        state.currency.apply(display_currency, &mut resp);
+        return Ok(Json(resp));
     }
 
     // Get token (cached)
@@ -162,38 +382,42 @@ async fn flight_search(
         Ok(t) => t,
         Err(e) => {
             tracing::error!("Amadeus token error: {:?}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
         }
     };
 
     // Search flights
     match amadeus::search_flights(&state.amadeus_client, &token, &payload).await {
-        Ok(resp) => {
-            // Cache the result
-            if let Some(ref redis_client) = state.redis_client {
-                if let Ok(mut conn) = redis_client.get_multiplexed_async_connection().await {
-                    if let Ok(json) = serde_json::to_string(&resp) {
-                        let _: Result<(), _> = conn.set_ex(&cache_key, json, SEARCH_CACHE_TTL_SECS).await;
-                        tracing::debug!("Cached flight search result: {}", cache_key);
-                    }
-                }
+        Ok(mut resp) => {
+            // Cache the result (before markups and display conversion, which depend on the client)
+            if let Some(ref redis_client) = state.redis_client
+                && let Ok(mut conn) = redis_client.get_multiplexed_async_connection().await
+                && let Ok(json) = serde_json::to_string(&resp)
+            {
+                let _: Result<(), _> = conn.set_ex(&cache_key, json, SEARCH_CACHE_TTL_SECS).await;
+                tracing::debug!("Cached flight search result: {}", cache_key);
             }
+            resp.apply_markup(&state.markup, &sales, &state.currency.rates());
+            state.currency.apply(display_currency, &mut resp);
             Ok(Json(resp))
         }
         Err(e) => {
-            // Log auch ohne Tracing-Filter sichtbar machen
-            println!("Amadeus search error: {:?}", e);
             tracing::error!("Amadeus search error: {:?}", e);
-            Err(StatusCode::BAD_GATEWAY)
+            Err(StatusCode::BAD_GATEWAY.into_response())
         }
     }
 }
 
 async fn flight_price(
     State(state): State<Arc<AppState>>,
+    sales: SalesContext,
+    Query(display): Query<DisplayCurrencyQuery>,
     Json(payload): Json<models::FlightPriceRequest>,
 ) -> Result<Json<models::FlightPriceResponse>, (StatusCode, Json<serde_json::Value>)> {
-    tracing::info!("Flight price request received, include_bags: {}", payload.include_bags);
+    tracing::info!("Flight price request received, include: {:?}", payload.includes());
+
+    payload.validate().map_err(<(StatusCode, Json<serde_json::Value>)>::from)?;
+    display.validate().map_err(<(StatusCode, Json<serde_json::Value>)>::from)?;
 
     // Get token (cached)
     let token = match amadeus::get_token(&state.amadeus_client).await {
@@ -217,10 +441,11 @@ async fn flight_price(
     match amadeus::price_flight_offers(
         &state.amadeus_client,
         &token,
-        &[payload.flight_offer],
-        payload.include_bags,
+        std::slice::from_ref(&payload.flight_offer),
+        &payload.includes(),
+        payload.payment_card.as_ref(),
     ).await {
-        Ok(resp) => {
+        Ok(mut resp) => {
             // Log included bags info
             if let Some(ref included) = resp.included {
                 tracing::info!("Pricing response includes {} bag options", included.bags.len());
@@ -230,6 +455,13 @@ async fn flight_price(
             } else {
                 tracing::info!("Pricing response has no included bag options");
             }
+            resp.apply_markup(&state.markup, &sales, &state.currency.rates());
+            fare_rules::attach_penalty_summaries(&mut resp);
+            price_change::attach_reports(&mut resp, std::slice::from_ref(&payload.flight_offer), &state.price_tolerance);
+            if let Some(ref card) = payload.payment_card {
+                card_fees::attach_card_fees(&mut resp, &card.brand);
+            }
+            state.currency.apply(display.display_currency.as_deref(), &mut resp);
             Ok(Json(resp))
         },
         Err(e) => {
@@ -265,19 +497,195 @@ async fn flight_price(
     }
 }
 
+/// Add checked bags to an offer and price it again
+///
+/// The offer is first priced with the bag catalog, so the choices are checked
+/// against what the airline offers right now.
+async fn select_bags(
+    State(state): State<Arc<AppState>>,
+    sales: SalesContext,
+    Query(display): Query<DisplayCurrencyQuery>,
+    Json(payload): Json<models::BagSelectionRequest>,
+) -> Result<Json<models::FlightPriceResponse>, Response> {
+    tracing::info!("Bag selection request received with {} selections", payload.selections.len());
+
+    payload.validate().map_err(IntoResponse::into_response)?;
+    display.validate().map_err(IntoResponse::into_response)?;
+
+    // Get token (cached)
+    let token = match amadeus::get_token(&state.amadeus_client).await {
+        Ok(t) => t,
+        Err(e) => {
+            tracing::error!("Amadeus token error: {:?}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        }
+    };
+
+    // Get the current bag catalog
+    let include = [models::PricingInclude::Bags];
+    let catalog_resp = match amadeus::price_flight_offers(
+        &state.amadeus_client,
+        &token,
+        std::slice::from_ref(&payload.flight_offer),
+        &include,
+        None,
+    ).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            tracing::error!("Amadeus pricing error: {:?}", e);
+            return Err(StatusCode::BAD_GATEWAY.into_response());
+        }
+    };
+    let Some(mut offer) = catalog_resp.data.flight_offers.into_iter().next() else {
+        tracing::error!("Amadeus pricing returned no offer");
+        return Err(StatusCode::BAD_GATEWAY.into_response());
+    };
+    let catalog = catalog_resp.included.map(|included| included.bags).unwrap_or_default();
+
+    let mut errors = validation::ValidationErrors::new();
+    ancillaries::check_bag_selections(&mut errors, "selections", &offer, &catalog, &payload.selections);
+    errors.into_result().map_err(IntoResponse::into_response)?;
+    ancillaries::apply_bag_selections(&mut offer, &catalog, &payload.selections);
+    // What the client expects to pay: the offer it sent plus the bags
+    let expected = price_change::with_services(&payload.flight_offer, &ancillaries::bag_selection_prices(&catalog, &payload.selections));
+
+    // Price again with the bags
+    match amadeus::price_flight_offers(
+        &state.amadeus_client,
+        &token,
+        std::slice::from_ref(&offer),
+        &include,
+        None,
+    ).await {
+        Ok(mut resp) => {
+            resp.apply_markup(&state.markup, &sales, &state.currency.rates());
+            fare_rules::attach_penalty_summaries(&mut resp);
+            price_change::attach_reports(&mut resp, std::slice::from_ref(&expected), &state.price_tolerance);
+            state.currency.apply(display.display_currency.as_deref(), &mut resp);
+            Ok(Json(resp))
+        }
+        Err(e) => {
+            tracing::error!("Amadeus pricing with bags error: {:?}", e);
+            Err(StatusCode::BAD_GATEWAY.into_response())
+        }
+    }
+}
+
+/// Assign seats from the seatmap to an offer
+///
+/// Seats are checked against the current seatmap, written into the offer, and
+/// the offer is priced again with them: the price always comes from Amadeus,
+/// even if all seats are free.
+async fn select_seats(
+    State(state): State<Arc<AppState>>,
+    sales: SalesContext,
+    Query(display): Query<DisplayCurrencyQuery>,
+    Json(payload): Json<models::SeatSelectionRequest>,
+) -> Result<Json<models::FlightPriceResponse>, Response> {
+    tracing::info!("Seat selection request received with {} selections", payload.selections.len());
+
+    payload.validate().map_err(IntoResponse::into_response)?;
+    display.validate().map_err(IntoResponse::into_response)?;
+
+    // Get token (cached)
+    let token = match amadeus::get_token(&state.amadeus_client).await {
+        Ok(t) => t,
+        Err(e) => {
+            tracing::error!("Amadeus token error: {:?}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        }
+    };
+
+    let seatmaps = match amadeus::get_seatmaps(&state.amadeus_client, &token, std::slice::from_ref(&payload.flight_offer)).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            tracing::error!("Amadeus seatmap error: {:?}", e);
+            return Err(StatusCode::BAD_GATEWAY.into_response());
+        }
+    };
+
+    let mut errors = validation::ValidationErrors::new();
+    ancillaries::check_seat_selections(&mut errors, "selections", &payload.flight_offer, &seatmaps, &payload.selections);
+    errors.into_result().map_err(IntoResponse::into_response)?;
+
+    let mut offer = payload.flight_offer.clone();
+    ancillaries::apply_seat_selections(&mut offer, &payload.selections);
+
+    let paid_seats = payload.selections.iter().filter(|selection| {
+        ancillaries::find_seat(&seatmaps, &selection.segment_id, &selection.seat_number)
+            .is_some_and(|seat| ancillaries::is_paid_seat(seat, &selection.traveler_id))
+    }).count();
+    tracing::info!("Pricing offer with {} seats, {} of them paid", payload.selections.len(), paid_seats);
+    let mut resp = match amadeus::price_flight_offers(&state.amadeus_client, &token, std::slice::from_ref(&offer), &[], None).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            tracing::error!("Amadeus pricing with seats error: {:?}", e);
+            return Err(StatusCode::BAD_GATEWAY.into_response());
+        }
+    };
+
+    // What the client expects to pay: the offer it sent plus the paid seats
+    let expected = price_change::with_services(&payload.flight_offer, &ancillaries::seat_selection_prices(&seatmaps, &payload.selections));
+    resp.apply_markup(&state.markup, &sales, &state.currency.rates());
+    fare_rules::attach_penalty_summaries(&mut resp);
+    price_change::attach_reports(&mut resp, std::slice::from_ref(&expected), &state.price_tolerance);
+    state.currency.apply(display.display_currency.as_deref(), &mut resp);
+    Ok(Json(resp))
+}
+
+/// Suggest seats for all travelers of an offer
+///
+/// Families and groups are seated side by side where the seatmap allows it,
+/// following the window/aisle preferences and preferring free seats.
+async fn recommend_seats(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<models::SeatRecommendationRequest>,
+) -> Result<Json<models::SeatRecommendationResponse>, Response> {
+    tracing::info!("Seat recommendation request received for {} travelers", payload.flight_offer.traveler_pricings.len());
+
+    payload.validate().map_err(IntoResponse::into_response)?;
+
+    // Get token (cached)
+    let token = match amadeus::get_token(&state.amadeus_client).await {
+        Ok(t) => t,
+        Err(e) => {
+            tracing::error!("Amadeus token error: {:?}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        }
+    };
+
+    let seatmaps = match amadeus::get_seatmaps(&state.amadeus_client, &token, std::slice::from_ref(&payload.flight_offer)).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            tracing::error!("Amadeus seatmap error: {:?}", e);
+            return Err(StatusCode::BAD_GATEWAY.into_response());
+        }
+    };
+
+    Ok(Json(models::SeatRecommendationResponse {
+        data: seat_recommendation::recommend_seats(&payload.flight_offer, &seatmaps, &payload.preferences),
+    }))
+}
+
 async fn price_matrix(
     State(state): State<Arc<AppState>>,
+    sales: SalesContext,
+    Query(display): Query<DisplayCurrencyQuery>,
     Json(payload): Json<models::PriceMatrixRequest>,
-) -> Result<Json<models::PriceMatrixResponse>, StatusCode> {
+) -> Result<Json<models::PriceMatrixResponse>, Response> {
     tracing::info!("Price matrix request: {} -> {}, {} outbound dates x {} inbound dates",
         payload.origin, payload.destination, payload.outbound_dates.len(), payload.inbound_dates.len());
 
+    // Reject invalid requests before calling Amadeus
+    payload.validate().map_err(IntoResponse::into_response)?;
+    display.validate().map_err(IntoResponse::into_response)?;
+
     // Get token (cached)
     let token = match amadeus::get_token(&state.amadeus_client).await {
         Ok(t) => t,
         Err(e) => {
             tracing::error!("Amadeus token error: {:?}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
         }
     };
 
@@ -317,7 +725,15 @@ async fn price_matrix(
             let adults = payload.adults;
             let children = payload.children;
             let infants = payload.infants;
+            let seated_infants = payload.seated_infants;
+            let seniors = payload.seniors;
+            let youths = payload.youths;
+            let students = payload.students;
+            let child_ages = payload.child_ages.clone();
             let currency = currency.clone();
+            let markup = state.markup.clone();
+            let rates = state.currency.rates();
+            let sales = sales.clone();
 
             async move {
                 let req = models::FlightSearchRequest {
@@ -328,9 +744,19 @@ async fn price_matrix(
                     adults,
                     children,
                     infants,
+                    seated_infants,
+                    seniors,
+                    youths,
+                    students,
+                    child_ages,
                     currency: Some(currency.clone()),
                     travel_class: None,
+                    cabin_coverage: None,
                     non_stop: None,
+                    max_connections: None,
+                    return_travel_class: None,
+                    return_cabin_coverage: None,
+                    return_max_connections: None,
                     max_price: None,
                     max_results: Some(250), // Get up to 250 offers to find cheapest
                     included_airline_codes: None,
@@ -339,8 +765,9 @@ async fn price_matrix(
                 };
 
                 match amadeus::search_flights(&client, &token, &req).await {
-                    Ok(resp) => {
-                        let price = resp.data.first().map(|offer| offer.price.total.clone());
+                    Ok(mut resp) => {
+                        resp.apply_markup(&markup, &sales, &rates);
+                        let price = resp.data.iter().map(|offer| offer.price.total).min();
                         (outbound, inbound, price, currency)
                     }
                     Err(e) => {
@@ -365,12 +792,15 @@ async fn price_matrix(
 
     // Build response
     let prices: Vec<models::PriceMatrixEntry> = results.into_iter().map(|(outbound, inbound, price, currency)| {
-        models::PriceMatrixEntry {
+        let mut entry = models::PriceMatrixEntry {
             outbound_date: outbound,
             inbound_date: inbound,
             price,
             currency,
-        }
+            display_price: None,
+        };
+        state.currency.apply(display.display_currency.as_deref(), &mut entry);
+        entry
     }).collect();
 
     tracing::info!("Price matrix completed: {} prices returned", prices.len());
@@ -378,31 +808,154 @@ async fn price_matrix(
     Ok(Json(models::PriceMatrixResponse { prices }))
 }
 
+/// Exchange card data for a token to order with (see `card_vault`)
+async fn tokenize_card(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<models::CardTokenRequest>,
+) -> Result<(StatusCode, Json<models::CardToken>), Response> {
+    payload.validate().map_err(IntoResponse::into_response)?;
+    Ok((StatusCode::CREATED, Json(state.card_vault.tokenize(payload, chrono::Utc::now()))))
+}
+
 async fn flight_order(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<models::FlightOrderRequest>,
-) -> Result<Json<models::FlightOrderResponse>, StatusCode> {
+    sales: SalesContext,
+    Json(mut payload): Json<models::FlightOrderRequest>,
+) -> Result<Json<models::FlightOrderResponse>, Response> {
+    payload.validate().map_err(IntoResponse::into_response)?;
+    state.card_vault.detokenize(&mut payload, chrono::Utc::now()).map_err(IntoResponse::into_response)?;
+
+    // Record the attempt before anything can fail
+    let booking = match &state.bookings {
+        Some(store) => store.track(&bookings::NewBooking::from_request(&payload)).await,
+        None => None,
+    };
+
     // Get token (cached)
     let token = match amadeus::get_token(&state.amadeus_client).await {
         Ok(t) => t,
         Err(e) => {
             tracing::error!("Amadeus token error: {:?}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            if let Some(booking) = &booking {
+                booking.advance(bookings::BookingStatus::Failed, bookings::BookingUpdate::reason(format!("Amadeus token error: {}", e))).await;
+            }
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
         }
     };
 
+    // Price again for the airline's current booking requirements
+    let priced = match amadeus::price_flight_offers(&state.amadeus_client, &token, &payload.flight_offers, &[], None).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            tracing::error!("Amadeus pricing before order error: {:?}", e);
+            if let Some(booking) = &booking {
+                booking.advance(bookings::BookingStatus::Failed, bookings::BookingUpdate::reason(format!("{:#}", e))).await;
+            }
+            return Err(StatusCode::BAD_GATEWAY.into_response());
+        }
+    };
+    if let Some(ref requirements) = priced.data.booking_requirements {
+        let mut errors = validation::ValidationErrors::new();
+        travelers::check_booking_requirements(&mut errors, &payload, requirements);
+        if !errors.is_empty() {
+            if let Some(booking) = &booking {
+                booking.advance(bookings::BookingStatus::Failed, bookings::BookingUpdate::reason(errors.to_string())).await;
+            }
+            return Err(errors.into_response());
+        }
+    }
+    // The sale price: Amadeus prices with our markups
+    let mut sale_offers = priced.data.flight_offers.clone();
+    for offer in &mut sale_offers {
+        offer.apply_markup(&state.markup, &sales, &state.currency.rates());
+    }
+
+    // Never charge more than the client confirmed without asking again
+    if let Some(reports) = price_change::reconfirmations(&payload.flight_offers, &sale_offers, &state.price_tolerance) {
+        tracing::info!("Order not placed: the price of the offers changed since they were confirmed");
+        if let Some(booking) = &booking {
+            booking.advance(bookings::BookingStatus::Failed, bookings::BookingUpdate::reason("Price changed since it was confirmed")).await;
+        }
+        return Err(price_changed_response(reports));
+    }
+
+    if let Some(booking) = &booking {
+        booking.advance(bookings::BookingStatus::Priced, bookings::BookingUpdate::priced(&sale_offers)).await;
+    }
+
+    // Order the offers as Amadeus priced them: offers of the request may carry
+    // our marked-up prices, and the net prices are only known from Amadeus
+    payload.flight_offers = priced.data.flight_offers.clone();
+
+    // Authorize the sale price before the order exists, so a declined card
+    // never leaves an order behind
+    let payment = match (&state.payments, payments::order_card(&payload)) {
+        (Some(provider), Some(card)) => {
+            let authorized = match payments::sale_price(&sale_offers) {
+                Ok(amount) => {
+                    let request = payments::PaymentRequest { amount, card, booking_id: booking.map(|booking| booking.id()) };
+                    provider.authorize(&request).await
+                }
+                Err(e) => Err(e),
+            };
+            match authorized {
+                Ok(payment) => {
+                    if let Some(booking) = &booking {
+                        booking.advance(bookings::BookingStatus::Authorized, bookings::BookingUpdate::default()).await;
+                        booking.record_payment(provider.name(), &payment, None).await;
+                    }
+                    Some((provider.as_ref(), payment))
+                }
+                Err(e) => {
+                    tracing::warn!("Payment authorization failed: {}", e);
+                    if let Some(booking) = &booking {
+                        booking.advance(bookings::BookingStatus::Failed, bookings::BookingUpdate::reason(e.to_string())).await;
+                    }
+                    return Err(payment_error_response(&e));
+                }
+            }
+        }
+        _ => None,
+    };
+
+    // Orders we took the payment for are paid from the agency account
+    let order_request = match payment {
+        Some(_) => std::borrow::Cow::Owned(payments::agency_order(&payload)),
+        None => std::borrow::Cow::Borrowed(&payload),
+    };
+
     // Create the flight order
-    match amadeus::create_flight_order(&state.amadeus_client, &token, &payload).await {
-        Ok(resp) => Ok(Json(resp)),
+    match amadeus::create_flight_order(&state.amadeus_client, &token, &order_request).await {
+        Ok(mut resp) => {
+            if let Some(booking) = &booking {
+                booking.advance(bookings::BookingStatus::Ordered, bookings::BookingUpdate::from_order(&resp)).await;
+                if !resp.data.tickets.is_empty() {
+                    booking.advance(bookings::BookingStatus::Ticketed, bookings::BookingUpdate::default()).await;
+                }
+                resp.booking_id = Some(booking.id());
+            }
+            if let Some((provider, payment)) = &payment {
+                payments::capture_order(*provider, booking.as_ref(), payment).await;
+            }
+            resp.apply_markup(&state.markup, &sales, &state.currency.rates());
+            Ok(Json(resp))
+        }
         Err(e) => {
             tracing::error!("Amadeus order creation error: {:?}", e);
-            Err(StatusCode::BAD_GATEWAY)
+            if let Some((provider, payment)) = &payment {
+                payments::void_order(*provider, booking.as_ref(), payment).await;
+            }
+            if let Some(booking) = &booking {
+                booking.advance(bookings::BookingStatus::Failed, bookings::BookingUpdate::reason(format!("{:#}", e))).await;
+            }
+            Err(StatusCode::BAD_GATEWAY.into_response())
         }
     }
 }
 
 async fn get_flight_order(
     State(state): State<Arc<AppState>>,
+    sales: SalesContext,
     Path(id): Path<String>,
 ) -> Result<Json<models::FlightOrderResponse>, StatusCode> {
     // Get token (cached)
@@ -416,7 +969,15 @@ async fn get_flight_order(
 
     // Get the flight order
     match amadeus::get_flight_order(&state.amadeus_client, &token, &id).await {
-        Ok(resp) => Ok(Json(resp)),
+        Ok(mut resp) => {
+            if let Some(store) = &state.bookings
+                && !resp.data.tickets.is_empty()
+            {
+                store.advance_order(&id, bookings::BookingStatus::Ticketed, bookings::BookingUpdate::from_order(&resp)).await;
+            }
+            resp.apply_markup(&state.markup, &sales, &state.currency.rates());
+            Ok(Json(resp))
+        }
         Err(e) => {
             tracing::error!("Amadeus get order error: {:?}", e);
             Err(StatusCode::BAD_GATEWAY)
@@ -424,10 +985,78 @@ async fn get_flight_order(
     }
 }
 
+/// Cancel an order right away, without a refund quote (see `quote_cancellation`).
+/// Who cancels and why are required; stored bookings get a cancellation quoted
+/// and confirmed on the spot, so the cancellation is recorded like a confirmed one.
 async fn delete_flight_order(
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
-) -> Result<StatusCode, StatusCode> {
+    Query(params): Query<models::OrderDeletionQuery>,
+) -> Result<StatusCode, Response> {
+    params.validate().map_err(IntoResponse::into_response)?;
+    let cancelled_by = params.cancelled_by();
+    let reason = params.reason();
+    let booking = match &state.bookings {
+        Some(store) => match store.find_by_order_id(&id).await {
+            Ok(booking) => Some((store, booking)),
+            Err(bookings::BookingError::NotFound) => None,
+            Err(e) => return Err(booking_error_status(e).into_response()),
+        },
+        None => None,
+    };
+
+    // Get token (cached)
+    let token = match amadeus::get_token(&state.amadeus_client).await {
+        Ok(t) => t,
+        Err(e) => {
+            tracing::error!("Amadeus token error: {:?}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        }
+    };
+
+    let Some((store, booking)) = booking else {
+        // Nothing to record the cancellation with
+        if let Err(e) = amadeus::delete_flight_order(&state.amadeus_client, &token, &id).await {
+            tracing::error!("Amadeus delete order error: {:?}", e);
+            return Err(StatusCode::BAD_GATEWAY.into_response());
+        }
+        tracing::info!("Order {} cancelled by {}: {}", id, cancelled_by, reason);
+        return Ok(StatusCode::NO_CONTENT);
+    };
+
+    let order = match amadeus::get_flight_order(&state.amadeus_client, &token, &id).await {
+        Ok(order) => order,
+        Err(e) => {
+            tracing::error!("Amadeus get order error: {:?}", e);
+            return Err(StatusCode::BAD_GATEWAY.into_response());
+        }
+    };
+    let now = chrono::Utc::now();
+    let actual = cancellation::estimate(&order.data, now);
+    let quote = store
+        .create_cancellation_quote(booking.id, &actual, now + state.cancellation_quote_ttl)
+        .await
+        .map_err(|e| booking_error_status(e).into_response())?;
+    let quote = store
+        .claim_cancellation_quote(booking.id, quote.id, cancelled_by, reason)
+        .await
+        .map_err(|e| booking_error_status(e).into_response())?;
+    cancel_quoted_order(&state, store, &quote, &id, &token, &actual).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// First step of a cancellation: the refund to expect if the order is cancelled
+/// now. The quote is stored with the booking and can be confirmed until it expires.
+async fn quote_cancellation(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<models::CancellationQuote>, StatusCode> {
+    let store = state.bookings.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let booking = store.find_by_order_id(&id).await.map_err(booking_error_status)?;
+    if !matches!(booking.status, bookings::BookingStatus::Ordered | bookings::BookingStatus::Ticketed) {
+        return Err(StatusCode::CONFLICT);
+    }
+
     // Get token (cached)
     let token = match amadeus::get_token(&state.amadeus_client).await {
         Ok(t) => t,
@@ -437,35 +1066,275 @@ async fn delete_flight_order(
         }
     };
 
-    // Delete the flight order
-    match amadeus::delete_flight_order(&state.amadeus_client, &token, &id).await {
-        Ok(()) => Ok(StatusCode::NO_CONTENT),
+    let order = match amadeus::get_flight_order(&state.amadeus_client, &token, &id).await {
+        Ok(order) => order,
         Err(e) => {
-            tracing::error!("Amadeus delete order error: {:?}", e);
-            Err(StatusCode::BAD_GATEWAY)
+            tracing::error!("Amadeus get order error: {:?}", e);
+            return Err(StatusCode::BAD_GATEWAY);
+        }
+    };
+    let now = chrono::Utc::now();
+    let estimate = cancellation::estimate(&order.data, now);
+    let expires_at = now + state.cancellation_quote_ttl;
+    let quote = store.create_cancellation_quote(booking.id, &estimate, expires_at).await.map_err(booking_error_status)?;
+
+    Ok(Json(models::CancellationQuote {
+        quote_id: quote.id,
+        booking_id: booking.id,
+        order_id: id,
+        estimate,
+        expires_at,
+    }))
+}
+
+/// Second step of a cancellation: cancel the order at Amadeus as quoted and
+/// record who cancelled, why, and the quoted vs. actual refund
+async fn confirm_cancellation(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(payload): Json<models::CancellationConfirmRequest>,
+) -> Result<Json<models::CancellationOutcome>, Response> {
+    payload.validate().map_err(IntoResponse::into_response)?;
+    let store = state.bookings.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE.into_response())?;
+    let booking = store.find_by_order_id(&id).await.map_err(|e| booking_error_status(e).into_response())?;
+    let cancelled_by = payload.cancelled_by.trim();
+    let reason = payload.reason.trim();
+    let quote = store
+        .claim_cancellation_quote(booking.id, payload.quote_id, cancelled_by, reason)
+        .await
+        .map_err(|e| booking_error_status(e).into_response())?;
+
+    // Get token (cached)
+    let token = match amadeus::get_token(&state.amadeus_client).await {
+        Ok(t) => t,
+        Err(e) => {
+            tracing::error!("Amadeus token error: {:?}", e);
+            fail_cancellation(store, quote.id, &format!("Amadeus token error: {}", e)).await;
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        }
+    };
+
+    // The order right before it is deleted gives the actual refund
+    let order = match amadeus::get_flight_order(&state.amadeus_client, &token, &id).await {
+        Ok(order) => order,
+        Err(e) => {
+            tracing::error!("Amadeus get order error: {:?}", e);
+            fail_cancellation(store, quote.id, &format!("{:#}", e)).await;
+            return Err(StatusCode::BAD_GATEWAY.into_response());
+        }
+    };
+    let actual = cancellation::estimate(&order.data, chrono::Utc::now());
+    cancel_quoted_order(&state, store, &quote, &id, &token, &actual).await?;
+
+    let quoted = quote.quote.0;
+    Ok(Json(models::CancellationOutcome {
+        quote_id: quote.id,
+        booking_id: booking.id,
+        order_id: id,
+        cancelled_by: cancelled_by.to_string(),
+        reason: reason.to_string(),
+        refund_difference: actual.refund.zip(quoted.refund).map(|(actual, quoted)| actual - quoted),
+        quoted,
+        actual,
+    }))
+}
+
+/// Delete a claimed order at Amadeus, record the cancellation and settle the
+/// payment with the refund as of the cancellation
+async fn cancel_quoted_order(
+    state: &AppState,
+    store: &bookings::BookingStore,
+    quote: &bookings::CancellationRecord,
+    order_id: &str,
+    token: &str,
+    actual: &models::RefundEstimate,
+) -> Result<(), Response> {
+    let quote_id = quote.id;
+    if let Err(e) = amadeus::delete_flight_order(&state.amadeus_client, token, order_id).await {
+        tracing::error!("Amadeus delete order error: {:?}", e);
+        fail_cancellation(store, quote_id, &format!("{:#}", e)).await;
+        return Err(StatusCode::BAD_GATEWAY.into_response());
+    }
+
+    // The order is gone at Amadeus now, so storage errors are only logged
+    if let Err(e) = store.finish_cancellation(quote_id, Ok(actual)).await {
+        tracing::error!("Failed to record cancellation {}: {}", quote_id, e);
+    }
+    store.advance_order(order_id, bookings::BookingStatus::Cancelled, bookings::BookingUpdate::reason(format!(
+        "Cancelled by {}: {}",
+        quote.cancelled_by.as_deref().unwrap_or_default(),
+        quote.reason.as_deref().unwrap_or_default()
+    ))).await;
+    if let Some(provider) = &state.payments {
+        payments::settle_cancellation(provider.as_ref(), store, quote.booking_id, payments::Refund::from(actual)).await;
+    }
+    Ok(())
+}
+
+async fn fail_cancellation(store: &bookings::BookingStore, quote_id: uuid::Uuid, error: &str) {
+    if let Err(e) = store.finish_cancellation(quote_id, Err(error)).await {
+        tracing::error!("Failed to record cancellation {}: {}", quote_id, e);
+    }
+}
+
+/// Stored booking with its status history, served from our database only
+async fn get_booking(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<bookings::BookingDetails>, StatusCode> {
+    let store = state.bookings.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let mut booking = store.get(id).await.map_err(booking_error_status)?;
+    show_travelers(&state, store, &headers, &mut booking)?;
+    store.details(booking).await.map(Json).map_err(booking_error_status)
+}
+
+/// Stored booking of an Amadeus flight order
+async fn get_booking_by_order(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<bookings::BookingDetails>, StatusCode> {
+    let store = state.bookings.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let mut booking = store.find_by_order_id(&id).await.map_err(booking_error_status)?;
+    show_travelers(&state, store, &headers, &mut booking)?;
+    store.details(booking).await.map(Json).map_err(booking_error_status)
+}
+
+/// Decrypt the traveler PII of a booking for admins and mask it for everyone else
+fn show_travelers(state: &AppState, store: &bookings::BookingStore, headers: &HeaderMap, booking: &mut bookings::BookingRecord) -> Result<(), StatusCode> {
+    if state.admin_key.allows(headers) {
+        store.reveal(booking).map_err(booking_error_status)
+    } else {
+        booking.mask_pii();
+        Ok(())
+    }
+}
+
+/// Schedule disruptions of a booking with their alternative offers
+async fn get_booking_disruptions(
+    State(state): State<Arc<AppState>>,
+    sales: SalesContext,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<Vec<bookings::ScheduleDisruption>>, StatusCode> {
+    let store = state.bookings.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    store.get(id).await.map_err(booking_error_status)?;
+    let mut disruptions = store.disruptions(id).await.map_err(booking_error_status)?;
+    for disruption in &mut disruptions {
+        apply_disruption_markup(&state, &sales, disruption);
+    }
+    Ok(Json(disruptions))
+}
+
+/// Accept an alternative offer of a schedule disruption. The offer is then
+/// priced and ordered like any other through /flight-price and /flight-order.
+async fn accept_disruption_alternative(
+    State(state): State<Arc<AppState>>,
+    sales: SalesContext,
+    Path((id, disruption_id)): Path<(uuid::Uuid, uuid::Uuid)>,
+    Json(payload): Json<models::AcceptAlternativeRequest>,
+) -> Result<Json<bookings::ScheduleDisruption>, Response> {
+    payload.validate().map_err(IntoResponse::into_response)?;
+    let store = state.bookings.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE.into_response())?;
+    let mut disruption = store
+        .accept_alternative(id, disruption_id, &payload.offer_id)
+        .await
+        .map_err(|e| booking_error_status(e).into_response())?;
+    apply_disruption_markup(&state, &sales, &mut disruption);
+    Ok(Json(disruption))
+}
+
+/// Alternatives are stored at net fares, like the offers coming from Amadeus
+fn apply_disruption_markup(state: &AppState, sales: &SalesContext, disruption: &mut bookings::ScheduleDisruption) {
+    let rates = state.currency.rates();
+    for offer in disruption.alternatives.iter_mut() {
+        offer.apply_markup(&state.markup, sales, &rates);
+    }
+}
+
+/// Error body of a payment that could not be authorized: 402 for cards the
+/// customer can replace, 502 if the provider failed
+fn payment_error_response(e: &payments::PaymentError) -> Response {
+    let status = match e {
+        payments::PaymentError::Declined(_) | payments::PaymentError::Invalid(_) => StatusCode::PAYMENT_REQUIRED,
+        _ => StatusCode::BAD_GATEWAY,
+    };
+    (
+        status,
+        Json(serde_json::json!({
+            "errors": [{
+                "status": status.as_u16(),
+                "title": "PAYMENT_NOT_AUTHORIZED",
+                "detail": e.to_string(),
+                "source": { "pointer": "/formOfPayment/creditCard" }
+            }]
+        })),
+    )
+        .into_response()
+}
+
+/// The order was not placed because the offers must be re-confirmed first
+fn price_changed_response(reports: Vec<models::PriceChangeReport>) -> Response {
+    (
+        StatusCode::CONFLICT,
+        Json(serde_json::json!({
+            "errors": [{
+                "status": 409,
+                "title": "PRICE_CHANGED",
+                "detail": "The price of the offers changed since they were confirmed. Confirm the new price and order again.",
+                "source": { "pointer": "/flightOffers" }
+            }],
+            "priceChanges": reports
+        })),
+    )
+        .into_response()
+}
+
+fn booking_error_status(e: bookings::BookingError) -> StatusCode {
+    match e {
+        bookings::BookingError::NotFound => StatusCode::NOT_FOUND,
+        bookings::BookingError::InvalidTransition { .. } | bookings::BookingError::Conflict(_) => StatusCode::CONFLICT,
+        bookings::BookingError::Encryption(e) => {
+            tracing::error!("Booking encryption error: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+        bookings::BookingError::Database(e) => {
+            tracing::error!("Booking database error: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
         }
     }
 }
 
 async fn get_seatmaps(
     State(state): State<Arc<AppState>>,
+    Query(display): Query<DisplayCurrencyQuery>,
+    Query(view): Query<seatmap_grid::SeatmapViewQuery>,
     Json(payload): Json<models::SeatmapRequest>,
-) -> Result<Json<models::SeatmapResponse>, StatusCode> {
+) -> Result<Json<models::SeatmapResponse>, Response> {
+    payload.validate().map_err(IntoResponse::into_response)?;
+    display.validate().map_err(IntoResponse::into_response)?;
+
     // Get token (cached)
     let token = match amadeus::get_token(&state.amadeus_client).await {
         Ok(t) => t,
         Err(e) => {
             tracing::error!("Amadeus token error: {:?}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
         }
     };
 
     // Get seatmaps
     match amadeus::get_seatmaps(&state.amadeus_client, &token, &payload.flight_offers).await {
-        Ok(resp) => Ok(Json(resp)),
+        Ok(mut resp) => {
+            if view.grid {
+                seatmap_grid::add_grids(&mut resp);
+            }
+            state.currency.apply(display.display_currency.as_deref(), &mut resp);
+            Ok(Json(resp))
+        }
         Err(e) => {
             tracing::error!("Amadeus seatmap error: {:?}", e);
-            Err(StatusCode::BAD_GATEWAY)
+            Err(StatusCode::BAD_GATEWAY.into_response())
         }
     }
 }
@@ -473,50 +1342,68 @@ async fn get_seatmaps(
 async fn get_seatmaps_by_order(
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
-) -> Result<Json<models::SeatmapResponse>, StatusCode> {
+    Query(display): Query<DisplayCurrencyQuery>,
+    Query(view): Query<seatmap_grid::SeatmapViewQuery>,
+) -> Result<Json<models::SeatmapResponse>, Response> {
+    display.validate().map_err(IntoResponse::into_response)?;
+
     // Get token (cached)
     let token = match amadeus::get_token(&state.amadeus_client).await {
         Ok(t) => t,
         Err(e) => {
             tracing::error!("Amadeus token error: {:?}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
         }
     };
 
     // Get seatmaps by order ID
     match amadeus::get_seatmaps_by_order(&state.amadeus_client, &token, &id).await {
-        Ok(resp) => Ok(Json(resp)),
+        Ok(mut resp) => {
+            if view.grid {
+                seatmap_grid::add_grids(&mut resp);
+            }
+            state.currency.apply(display.display_currency.as_deref(), &mut resp);
+            Ok(Json(resp))
+        }
         Err(e) => {
             tracing::error!("Amadeus seatmap by order error: {:?}", e);
-            Err(StatusCode::BAD_GATEWAY)
+            Err(StatusCode::BAD_GATEWAY.into_response())
         }
     }
 }
 
 async fn get_upsell_offers(
     State(state): State<Arc<AppState>>,
+    sales: SalesContext,
+    Query(display): Query<DisplayCurrencyQuery>,
     Json(payload): Json<models::UpsellRequest>,
-) -> Result<Json<models::FlightOffersResponse>, StatusCode> {
+) -> Result<Json<models::FlightOffersResponse>, Response> {
     tracing::info!("Upsell request received with {} offers", payload.flight_offers.len());
 
+    payload.validate().map_err(IntoResponse::into_response)?;
+    display.validate().map_err(IntoResponse::into_response)?;
+
     // Get token (cached)
     let token = match amadeus::get_token(&state.amadeus_client).await {
         Ok(t) => t,
         Err(e) => {
             tracing::error!("Amadeus token error: {:?}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
         }
     };
 
     // Get upsell offers
     match amadeus::get_upsell_offers(&state.amadeus_client, &token, &payload.flight_offers).await {
-        Ok(resp) => {
+        Ok(mut resp) => {
             tracing::info!("Upsell response received with {} offers", resp.data.len());
+            resp.apply_markup(&state.markup, &sales, &state.currency.rates());
+            state.currency.apply(display.display_currency.as_deref(), &mut resp);
+            resp.brand_comparison = Some(brand_comparison::compare(&resp.data));
             Ok(Json(resp))
         },
         Err(e) => {
             tracing::error!("Amadeus upsell error: {:?}", e);
-            Err(StatusCode::BAD_GATEWAY)
+            Err(StatusCode::BAD_GATEWAY.into_response())
         }
     }
 }
@@ -524,13 +1411,15 @@ async fn get_upsell_offers(
 async fn get_flight_availabilities(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<models::FlightAvailabilityRequest>,
-) -> Result<Json<models::FlightAvailabilityResponse>, StatusCode> {
+) -> Result<Json<models::FlightAvailabilityResponse>, Response> {
+    payload.validate().map_err(IntoResponse::into_response)?;
+
     // Get token (cached)
     let token = match amadeus::get_token(&state.amadeus_client).await {
         Ok(t) => t,
         Err(e) => {
             tracing::error!("Amadeus token error: {:?}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
         }
     };
 
@@ -539,7 +1428,7 @@ async fn get_flight_availabilities(
         Ok(resp) => Ok(Json(resp)),
         Err(e) => {
             tracing::error!("Amadeus availability error: {:?}", e);
-            Err(StatusCode::BAD_GATEWAY)
+            Err(StatusCode::BAD_GATEWAY.into_response())
         }
     }
 }
@@ -610,9 +1499,10 @@ async fn get_flight_dates(
 /// Generate mock flight dates for testing
 fn generate_mock_flight_dates(origin: &str, destination: &str) -> models::FlightDatesResponse {
     use chrono::{Utc, Duration};
+    use rust_decimal::Decimal;
 
     let mut dates = Vec::new();
-    let base_price = 500.0;
+    let base_price = Decimal::new(500, 0);
 
     // Generate dates for the next 60 days
     for i in 0..60 {
@@ -621,10 +1511,10 @@ fn generate_mock_flight_dates(origin: &str, destination: &str) -> models::Flight
 
         // Vary prices based on day of week (weekends more expensive)
         let day_of_week = date.weekday().num_days_from_monday();
-        let weekend_multiplier = if day_of_week >= 5 { 1.3 } else { 1.0 };
+        let weekend_multiplier = if day_of_week >= 5 { Decimal::new(13, 1) } else { Decimal::new(10, 1) };
 
         // Add some randomness
-        let random_factor = 0.8 + (i % 7) as f64 * 0.1;
+        let random_factor = Decimal::new(8 + i % 7, 1);
         let price = base_price * weekend_multiplier * random_factor;
 
         dates.push(models::FlightDate {
@@ -634,7 +1524,7 @@ fn generate_mock_flight_dates(origin: &str, destination: &str) -> models::Flight
             departure_date: date_str,
             return_date: None,
             price: models::FlightDestinationPrice {
-                total: format!("{:.2}", price),
+                total: price.round_dp(2),
             },
         });
     }
@@ -742,13 +1632,15 @@ async fn predict_flight_delay(
 async fn predict_flight_choice(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<models::FlightChoicePredictionRequest>,
-) -> Result<Json<models::FlightOffersResponse>, StatusCode> {
+) -> Result<Json<models::FlightOffersResponse>, Response> {
+    payload.validate().map_err(IntoResponse::into_response)?;
+
     // Get token (cached)
     let token = match amadeus::get_token(&state.amadeus_client).await {
         Ok(t) => t,
         Err(e) => {
             tracing::error!("Amadeus token error: {:?}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
         }
     };
 
@@ -757,7 +1649,7 @@ async fn predict_flight_choice(
         Ok(resp) => Ok(Json(resp)),
         Err(e) => {
             tracing::error!("Amadeus choice prediction error: {:?}", e);
-            Err(StatusCode::BAD_GATEWAY)
+            Err(StatusCode::BAD_GATEWAY.into_response())
         }
     }
 }
@@ -0,0 +1,594 @@
+//! Markup and service fee rules
+//!
+//! Amadeus returns net fares. Before offers reach the client we add our margin
+//! and service fees according to configurable rules. The same engine runs on
+//! every response that carries offers (search, pricing, upsell, orders), and
+//! the net Amadeus prices are kept in `PriceMarkup` so the engine can start
+//! from them again. `PriceMarkup` is never read from requests, so offers that
+//! clients send back reach Amadeus with the marked-up prices they were shown.
+//! Net prices only come from Amadeus, which re-prices those offers, and
+//! orders are placed with the re-priced offers.
+//!
+//! Rules are loaded from a JSON file (`MARKUP_RULES_FILE`). For each kind
+//! (markup, service fee) the first matching rule in file order applies, per
+//! traveler.
+//!
+//! The tenant and channel rules match on are configured per API key
+//! (`SALES_API_KEYS`) and never taken from client headers, so callers cannot
+//! pick a cheaper rule set. Requests without a known key get the public
+//! context (no tenant, no channel).
+
+use anyhow::{Context, Result, bail};
+use axum::extract::{FromRequestParts, Request, State};
+use axum::http::HeaderMap;
+use axum::http::request::Parts;
+use axum::middleware::Next;
+use axum::response::Response;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::currency::RateTable;
+use crate::models::{
+    Cabin, FareType, FlightOffer, FlightOffersResponse, FlightOrderResponse, FlightPriceResponse,
+    PriceMarkup, TravelerPricing, TravelerType,
+};
+use crate::money::Money;
+
+/// Header carrying the API key that selects the sales context
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// What a rule adds to the fare
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MarkupKind {
+    /// Margin on the fare
+    #[default]
+    Markup,
+    /// Service fee charged on top of the fare
+    ServiceFee,
+}
+
+/// A markup or service fee rule. Empty condition lists match everything.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkupRule {
+    pub id: String,
+    #[serde(default)]
+    pub kind: MarkupKind,
+    /// Validating carriers
+    #[serde(default)]
+    pub carriers: Vec<String>,
+    /// Origin of the first itinerary
+    #[serde(default)]
+    pub origins: Vec<String>,
+    /// Destination of the first itinerary
+    #[serde(default)]
+    pub destinations: Vec<String>,
+    /// Cabins of the traveler's segments (any segment matches)
+    #[serde(default)]
+    pub cabins: Vec<Cabin>,
+    #[serde(default)]
+    pub channels: Vec<String>,
+    #[serde(default)]
+    pub tenants: Vec<String>,
+    #[serde(default)]
+    pub traveler_types: Vec<TravelerType>,
+    #[serde(default)]
+    pub fare_types: Vec<FareType>,
+    /// Percentage of the traveler's net total
+    #[serde(default)]
+    pub percent: Decimal,
+    /// Fixed amount per traveler, converted into the offer currency if needed
+    #[serde(default)]
+    pub fixed: Option<Money>,
+}
+
+/// Sales channel and tenant of a request, as configured for its API key
+/// (see [`resolve_sales_context`])
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SalesContext {
+    pub channel: Option<String>,
+    pub tenant: Option<String>,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for SalesContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<SalesContext>()
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+/// Sales contexts by API key
+#[derive(Debug, Clone, Default)]
+pub struct SalesKeys {
+    contexts: HashMap<String, SalesContext>,
+}
+
+impl SalesKeys {
+    /// Parse "key:tenant:channel,..." (tenant and channel may be empty)
+    pub fn parse(config: &str) -> Result<Self> {
+        let mut contexts = HashMap::new();
+        for entry in config.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let mut fields = entry.split(':').map(str::trim);
+            let (Some(key), tenant, channel, None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                bail!("Invalid sales API key entry (expected key:tenant:channel)");
+            };
+            if key.is_empty() {
+                bail!("Empty sales API key");
+            }
+            let field =
+                |value: Option<&str>| value.filter(|value| !value.is_empty()).map(str::to_string);
+            contexts.insert(
+                key.to_string(),
+                SalesContext {
+                    tenant: field(tenant),
+                    channel: field(channel),
+                },
+            );
+        }
+        Ok(Self { contexts })
+    }
+
+    /// Keys of `SALES_API_KEYS` (none if it is not set)
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("SALES_API_KEYS") {
+            Ok(config) => Self::parse(&config),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.contexts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contexts.is_empty()
+    }
+
+    /// Context of the request's API key; the public context without a known key
+    pub fn context(&self, headers: &HeaderMap) -> SalesContext {
+        headers
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|key| self.contexts.get(key.trim()))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Middleware attaching the [`SalesContext`] of the request's API key
+pub async fn resolve_sales_context(
+    State(keys): State<Arc<SalesKeys>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let context = keys.context(request.headers());
+    request.extensions_mut().insert(context);
+    next.run(request).await
+}
+
+/// Offer-level facts the rules match on
+struct OfferFacts<'a> {
+    carriers: &'a [String],
+    origin: Option<&'a str>,
+    destination: Option<&'a str>,
+    fare_types: &'a [FareType],
+}
+
+impl<'a> OfferFacts<'a> {
+    fn of(offer: &'a FlightOffer) -> Self {
+        let first_itinerary = offer.itineraries.first();
+        Self {
+            carriers: &offer.validating_airline_codes,
+            origin: first_itinerary
+                .and_then(|itinerary| itinerary.segments.first())
+                .map(|segment| segment.departure.iata_code.as_str()),
+            destination: first_itinerary
+                .and_then(|itinerary| itinerary.segments.last())
+                .map(|segment| segment.arrival.iata_code.as_str()),
+            fare_types: offer
+                .pricing_options
+                .as_ref()
+                .map(|options| options.fare_type.as_slice())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Empty list means "any"; otherwise one of the values must be listed
+fn matches_any<T: PartialEq>(allowed: &[T], values: &[T]) -> bool {
+    allowed.is_empty() || values.iter().any(|value| allowed.contains(value))
+}
+
+fn matches_optional(allowed: &[String], value: Option<&str>) -> bool {
+    allowed.is_empty() || value.is_some_and(|value| allowed.iter().any(|a| a == value))
+}
+
+impl MarkupRule {
+    fn matches(
+        &self,
+        offer: &OfferFacts<'_>,
+        pricing: &TravelerPricing,
+        ctx: &SalesContext,
+    ) -> bool {
+        let cabins: Vec<Cabin> = pricing
+            .fare_details_by_segment
+            .iter()
+            .map(|details| details.cabin.clone())
+            .collect();
+
+        matches_any(&self.carriers, offer.carriers)
+            && matches_optional(&self.origins, offer.origin)
+            && matches_optional(&self.destinations, offer.destination)
+            && matches_any(&self.cabins, &cabins)
+            && matches_optional(&self.channels, ctx.channel.as_deref())
+            && matches_optional(&self.tenants, ctx.tenant.as_deref())
+            && matches_any(
+                &self.traveler_types,
+                std::slice::from_ref(&pricing.traveler_type),
+            )
+            && matches_any(&self.fare_types, offer.fare_types)
+    }
+
+    /// Amount this rule adds to a traveler's net total, or None if the fixed
+    /// amount cannot be converted into the offer currency
+    fn amount(&self, net: &Money, rates: &RateTable) -> Option<Decimal> {
+        let percent = net.amount * self.percent / Decimal::ONE_HUNDRED;
+        let fixed = match self.fixed {
+            Some(ref fixed) => rates.convert(fixed, &net.currency)?.amount,
+            None => Decimal::ZERO,
+        };
+        Some(
+            Money::new(percent + fixed, net.currency.clone())
+                .round()
+                .amount,
+        )
+    }
+}
+
+/// Applies markup rules to offers
+#[derive(Debug, Clone, Default)]
+pub struct MarkupEngine {
+    rules: Vec<MarkupRule>,
+}
+
+impl MarkupEngine {
+    pub fn new(rules: Vec<MarkupRule>) -> Self {
+        Self { rules }
+    }
+
+    /// Load rules from a JSON file containing an array of rules
+    pub async fn load(path: &Path) -> Result<Self> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read markup rules {}", path.display()))?;
+        let rules: Vec<MarkupRule> = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid markup rules {}", path.display()))?;
+        if let Some(rule) = rules
+            .iter()
+            .find(|rule| rule.percent.is_sign_negative() && !rule.percent.is_zero())
+        {
+            bail!("Markup rule {} has a negative percentage", rule.id);
+        }
+        Ok(Self::new(rules))
+    }
+
+    pub fn rules(&self) -> &[MarkupRule] {
+        &self.rules
+    }
+
+    fn first_match(
+        &self,
+        kind: MarkupKind,
+        offer: &OfferFacts<'_>,
+        pricing: &TravelerPricing,
+        ctx: &SalesContext,
+    ) -> Option<&MarkupRule> {
+        self.rules
+            .iter()
+            .find(|rule| rule.kind == kind && rule.matches(offer, pricing, ctx))
+    }
+
+    /// Add markups and service fees to an offer, starting from its net prices.
+    /// Applying the engine twice gives the same result.
+    pub fn apply(&self, offer: &mut FlightOffer, ctx: &SalesContext, rates: &RateTable) {
+        offer.remove_markup();
+        if self.rules.is_empty() {
+            return;
+        }
+
+        let facts = OfferFacts::of(offer);
+        let mut traveler_markups = Vec::with_capacity(offer.traveler_pricings.len());
+        for pricing in &offer.traveler_pricings {
+            let net = pricing.price.total_money();
+            let mut markup = PriceMarkup {
+                net_total: net.amount,
+                net_grand_total: None,
+                markup: Decimal::ZERO,
+                service_fee: Decimal::ZERO,
+                rule_ids: Vec::new(),
+            };
+
+            for kind in [MarkupKind::Markup, MarkupKind::ServiceFee] {
+                let Some(rule) = self.first_match(kind, &facts, pricing, ctx) else {
+                    continue;
+                };
+                let Some(amount) = rule.amount(&net, rates) else {
+                    tracing::warn!(
+                        "Skipping markup rule {}: no rate from {} to {}",
+                        rule.id,
+                        rule.fixed
+                            .as_ref()
+                            .map_or("", |fixed| fixed.currency.as_str()),
+                        net.currency
+                    );
+                    continue;
+                };
+                match kind {
+                    MarkupKind::Markup => markup.markup = amount,
+                    MarkupKind::ServiceFee => markup.service_fee = amount,
+                }
+                markup.rule_ids.push(rule.id.clone());
+            }
+            traveler_markups.push(markup);
+        }
+
+        let mut offer_markup = PriceMarkup {
+            net_total: offer.price.total,
+            net_grand_total: offer.price.grand_total,
+            markup: Decimal::ZERO,
+            service_fee: Decimal::ZERO,
+            rule_ids: Vec::new(),
+        };
+        for (pricing, markup) in offer.traveler_pricings.iter_mut().zip(traveler_markups) {
+            offer_markup.markup += markup.markup;
+            offer_markup.service_fee += markup.service_fee;
+            for rule_id in &markup.rule_ids {
+                if !offer_markup.rule_ids.contains(rule_id) {
+                    offer_markup.rule_ids.push(rule_id.clone());
+                }
+            }
+            pricing.price.total = markup.net_total + markup.markup + markup.service_fee;
+            pricing.price.markup = Some(markup);
+        }
+
+        let added = offer_markup.markup + offer_markup.service_fee;
+        offer.price.total = offer_markup.net_total + added;
+        offer.price.grand_total = offer_markup.net_grand_total.map(|total| total + added);
+        offer.price.markup = Some(offer_markup);
+    }
+}
+
+/// Responses carrying offers that get markups applied
+pub trait ApplyMarkup {
+    fn apply_markup(&mut self, engine: &MarkupEngine, ctx: &SalesContext, rates: &RateTable);
+}
+
+impl ApplyMarkup for FlightOffer {
+    fn apply_markup(&mut self, engine: &MarkupEngine, ctx: &SalesContext, rates: &RateTable) {
+        engine.apply(self, ctx, rates);
+    }
+}
+
+impl ApplyMarkup for FlightOffersResponse {
+    fn apply_markup(&mut self, engine: &MarkupEngine, ctx: &SalesContext, rates: &RateTable) {
+        for offer in &mut self.data {
+            engine.apply(offer, ctx, rates);
+        }
+    }
+}
+
+impl ApplyMarkup for FlightPriceResponse {
+    fn apply_markup(&mut self, engine: &MarkupEngine, ctx: &SalesContext, rates: &RateTable) {
+        for offer in &mut self.data.flight_offers {
+            engine.apply(offer, ctx, rates);
+        }
+    }
+}
+
+impl ApplyMarkup for FlightOrderResponse {
+    fn apply_markup(&mut self, engine: &MarkupEngine, ctx: &SalesContext, rates: &RateTable) {
+        for offer in &mut self.data.flight_offers {
+            engine.apply(offer, ctx, rates);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn offer() -> FlightOffer {
+        let segment = |from: &str, to: &str| {
+            serde_json::json!({
+                "id": "1",
+                "departure": { "iataCode": from, "at": "2025-06-15T10:00:00" },
+                "arrival": { "iataCode": to, "at": "2025-06-15T12:00:00" },
+                "carrierCode": "LH",
+                "number": "400",
+                "aircraft": { "code": "744" }
+            })
+        };
+        let pricing = |id: &str, traveler_type: &str, total: &str| {
+            serde_json::json!({
+                "travelerId": id,
+                "fareOption": "STANDARD",
+                "travelerType": traveler_type,
+                "price": { "currency": "EUR", "total": total, "base": total },
+                "fareDetailsBySegment": [{
+                    "segmentId": "1",
+                    "cabin": "ECONOMY",
+                    "fareBasis": "YOWEU",
+                    "class": "Y"
+                }]
+            })
+        };
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "type": "flight-offer",
+            "source": "GDS",
+            "itineraries": [{ "segments": [segment("FRA", "MUC"), segment("MUC", "JFK")] }],
+            "price": { "currency": "EUR", "total": "500.00", "base": "400.00", "grandTotal": "500.00" },
+            "pricingOptions": { "fareType": ["PUBLISHED"] },
+            "validatingAirlineCodes": ["LH"],
+            "travelerPricings": [pricing("1", "ADULT", "300.00"), pricing("2", "CHILD", "200.00")]
+        }))
+        .unwrap()
+    }
+
+    fn rules(json: serde_json::Value) -> MarkupEngine {
+        MarkupEngine::new(serde_json::from_value(json).unwrap())
+    }
+
+    fn rates() -> RateTable {
+        serde_json::from_value(serde_json::json!({
+            "base": "EUR",
+            "rates": { "USD": "1.25" }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_markup_and_service_fee_per_traveler() {
+        let engine = rules(serde_json::json!([
+            { "id": "lh-jfk", "carriers": ["LH"], "destinations": ["JFK"], "percent": "5" },
+            { "id": "default", "percent": "10" },
+            { "id": "fee", "kind": "SERVICE_FEE", "fixed": { "amount": "12.50", "currency": "USD" } },
+            { "id": "child-fee", "kind": "SERVICE_FEE", "travelerTypes": ["CHILD"], "percent": "50" }
+        ]));
+
+        let mut offer = offer();
+        engine.apply(&mut offer, &SalesContext::default(), &rates());
+
+        // First matching rule per kind wins
+        let adult = offer.traveler_pricings[0].price.markup.as_ref().unwrap();
+        assert_eq!(adult.net_total, dec("300.00"));
+        assert_eq!(adult.markup, dec("15.00"));
+        assert_eq!(adult.service_fee, dec("10.00"));
+        assert_eq!(adult.rule_ids, ["lh-jfk", "fee"]);
+        assert_eq!(offer.traveler_pricings[0].price.total, dec("325.00"));
+        assert_eq!(offer.traveler_pricings[1].price.total, dec("220.00"));
+
+        let markup = offer.price.markup.as_ref().unwrap();
+        assert_eq!(markup.markup, dec("25.00"));
+        assert_eq!(markup.service_fee, dec("20.00"));
+        assert_eq!(offer.price.total, dec("545.00"));
+        assert_eq!(offer.price.grand_total, Some(dec("545.00")));
+
+        // Re-applying starts from the net prices again
+        engine.apply(&mut offer, &SalesContext::default(), &rates());
+        assert_eq!(offer.price.total, dec("545.00"));
+
+        // Removing the markups restores the net prices
+        let mut net = offer.clone();
+        net.remove_markup();
+        assert_eq!(net.price.total, dec("500.00"));
+        assert_eq!(net.traveler_pricings[1].price.total, dec("200.00"));
+        assert!(net.price.markup.is_none());
+
+        // Offers sent back by clients keep their prices: markups are not read
+        let mut returned: FlightOffer =
+            serde_json::from_value(serde_json::to_value(&offer).unwrap()).unwrap();
+        assert!(returned.price.markup.is_none());
+        returned.remove_markup();
+        assert_eq!(returned.price.total, dec("545.00"));
+    }
+
+    #[test]
+    fn test_rules_match_channel_tenant_cabin_and_fare_type() {
+        let engine = rules(serde_json::json!([
+            { "id": "agent", "channels": ["AGENT"], "tenants": ["acme"], "percent": "2" },
+            { "id": "business", "cabins": ["BUSINESS"], "percent": "8" },
+            { "id": "negotiated", "fareTypes": ["NEGOTIATED"], "percent": "1" }
+        ]));
+
+        let mut offer = offer();
+        engine.apply(&mut offer, &SalesContext::default(), &rates());
+        assert_eq!(offer.price.markup.as_ref().unwrap().markup, Decimal::ZERO);
+        assert!(offer.price.markup.as_ref().unwrap().rule_ids.is_empty());
+
+        let agent = SalesContext {
+            channel: Some("AGENT".to_string()),
+            tenant: Some("acme".to_string()),
+        };
+        engine.apply(&mut offer, &agent, &rates());
+        assert_eq!(offer.price.markup.as_ref().unwrap().markup, dec("10.00"));
+    }
+
+    #[test]
+    fn test_fixed_amount_without_rate_is_skipped() {
+        let engine = rules(serde_json::json!([
+            { "id": "fee", "kind": "SERVICE_FEE", "fixed": { "amount": "5", "currency": "CHF" } }
+        ]));
+
+        let mut offer = offer();
+        engine.apply(&mut offer, &SalesContext::default(), &rates());
+        assert_eq!(offer.price.total, dec("500.00"));
+    }
+
+    #[tokio::test]
+    async fn test_sales_context_comes_from_api_key() {
+        use axum::{Router, body::Body, http::Request, middleware, routing::get};
+        use tower::ServiceExt;
+
+        let engine = Arc::new(rules(serde_json::json!([
+            { "id": "acme-agent", "channels": ["AGENT"], "tenants": ["acme"], "percent": "0" },
+            { "id": "default", "percent": "10" }
+        ])));
+        let keys = Arc::new(SalesKeys::parse("web-key::WEB, agent-key:acme:AGENT").unwrap());
+        let app = Router::new()
+            .route(
+                "/",
+                get(move |sales: SalesContext| async move {
+                    let mut offer = offer();
+                    engine.apply(&mut offer, &sales, &rates());
+                    offer.price.markup.unwrap().rule_ids.join(",")
+                }),
+            )
+            .layer(middleware::from_fn_with_state(keys, resolve_sales_context));
+        let rule = |headers: &[(&str, &str)]| {
+            let mut request = Request::builder().uri("/");
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(request.body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                String::from_utf8(body.to_vec()).unwrap()
+            }
+        };
+
+        assert_eq!(rule(&[("x-api-key", "agent-key")]).await, "acme-agent");
+        // Spoofed tenant and channel headers do not select another rule
+        let spoofed = [("x-tenant-id", "acme"), ("x-sales-channel", "AGENT")];
+        assert_eq!(rule(&spoofed).await, "default");
+        let spoofed = [spoofed[0], spoofed[1], ("x-api-key", "web-key")];
+        assert_eq!(rule(&spoofed).await, "default");
+        assert_eq!(rule(&[("x-api-key", "unknown")]).await, "default");
+
+        assert!(SalesKeys::parse("key:acme:WEB:extra").is_err());
+        assert!(SalesKeys::parse(":acme:WEB").is_err());
+    }
+}
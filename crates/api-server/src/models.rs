@@ -1,10 +1,322 @@
 //! Amadeus API Response Models
 //! Based on Amadeus Flight Offers Search API v2
 
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::money::Money;
+use crate::redaction::{CardNumber, Secret};
+
+/// Declares a string enum of Amadeus codes.
+/// Values unknown to this crate deserialize into `Unknown` and serialize back unchanged,
+/// so new Amadeus codes never break parsing.
+macro_rules! amadeus_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident => $value:literal,)+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(from = "String", into = "String")]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)+
+            /// Code not known to this crate
+            Unknown(String),
+        }
+
+        impl $name {
+            /// All codes known to this crate
+            pub const VALUES: &'static [&'static str] = &[$($value),+];
+
+            /// Amadeus code of the value
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $value,)+
+                    $name::Unknown(value) => value,
+                }
+            }
+
+            pub fn is_unknown(&self) -> bool {
+                matches!(self, $name::Unknown(_))
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                match value {
+                    $($value => $name::$variant,)+
+                    other => $name::Unknown(other.to_string()),
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(value: String) -> Self {
+                match $name::from(value.as_str()) {
+                    $name::Unknown(_) => $name::Unknown(value),
+                    known => known,
+                }
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                match value {
+                    $name::Unknown(value) => value,
+                    known => known.as_str().to_string(),
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+// ============================================================================
+// Amadeus Enumerations
+// ============================================================================
+
+amadeus_enum! {
+    /// Amadeus traveler type
+    pub enum TravelerType {
+        Adult => "ADULT",
+        Child => "CHILD",
+        Senior => "SENIOR",
+        Young => "YOUNG",
+        HeldInfant => "HELD_INFANT",
+        SeatedInfant => "SEATED_INFANT",
+        Student => "STUDENT",
+    }
+}
+
+amadeus_enum! {
+    /// Cabin (travel class)
+    pub enum Cabin {
+        Economy => "ECONOMY",
+        PremiumEconomy => "PREMIUM_ECONOMY",
+        Business => "BUSINESS",
+        First => "FIRST",
+    }
+}
+
+amadeus_enum! {
+    /// How many segments of an origin-destination must be in the requested cabin
+    pub enum CabinCoverage {
+        MostSegments => "MOST_SEGMENTS",
+        AtLeastOneSegment => "AT_LEAST_ONE_SEGMENT",
+        AllSegments => "ALL_SEGMENTS",
+    }
+}
+
+amadeus_enum! {
+    /// Fee type of a price
+    pub enum FeeType {
+        Ticketing => "TICKETING",
+        FormOfPayment => "FORM_OF_PAYMENT",
+        Supplier => "SUPPLIER",
+    }
+}
+
+amadeus_enum! {
+    /// Fare type of a flight offer
+    pub enum FareType {
+        Published => "PUBLISHED",
+        Negotiated => "NEGOTIATED",
+        Corporate => "CORPORATE",
+    }
+}
+
+amadeus_enum! {
+    /// Fare option of a traveler pricing
+    pub enum FareOption {
+        Standard => "STANDARD",
+        InclusiveTour => "INCLUSIVE_TOUR",
+        SpanishMelillaResident => "SPANISH_MELILLA_RESIDENT",
+        SpanishCeutaResident => "SPANISH_CEUTA_RESIDENT",
+        SpanishCanaryResident => "SPANISH_CANARY_RESIDENT",
+        SpanishBalearicResident => "SPANISH_BALEARIC_RESIDENT",
+        AirFranceMetropolitanDiscountPass => "AIR_FRANCE_METROPOLITAN_DISCOUNT_PASS",
+        AirFranceDomDiscountPass => "AIR_FRANCE_DOM_DISCOUNT_PASS",
+        AirFranceCombinedDiscountPass => "AIR_FRANCE_COMBINED_DISCOUNT_PASS",
+        AirFranceFamily => "AIR_FRANCE_FAMILY",
+        AdultWithCompanion => "ADULT_WITH_COMPANION",
+        Companion => "COMPANION",
+    }
+}
+
+amadeus_enum! {
+    /// Amenity type of a fare
+    pub enum AmenityType {
+        Baggage => "BAGGAGE",
+        CarbonOffset => "CARBON_OFFSET",
+        Entertainment => "ENTERTAINMENT",
+        Meal => "MEAL",
+        PreReservedSeat => "PRE_RESERVED_SEAT",
+        TravelServices => "TRAVEL_SERVICES",
+        Upgrades => "UPGRADES",
+        BrandedFares => "BRANDED_FARES",
+        Lounge => "LOUNGE",
+    }
+}
+
+amadeus_enum! {
+    /// Phone device type
+    pub enum DeviceType {
+        Mobile => "MOBILE",
+        Landline => "LANDLINE",
+        Fax => "FAX",
+    }
+}
+
+amadeus_enum! {
+    /// Traveler document type
+    pub enum DocumentType {
+        Passport => "PASSPORT",
+        IdentityCard => "IDENTITY_CARD",
+        Visa => "VISA",
+        KnownTraveler => "KNOWN_TRAVELER",
+        Redress => "REDRESS",
+    }
+}
+
+amadeus_enum! {
+    /// What happens to an order when its ticketing time limit is reached
+    pub enum TicketingOption {
+        Confirm => "CONFIRM",
+        DelayToQueue => "DELAY_TO_QUEUE",
+        DelayToCancel => "DELAY_TO_CANCEL",
+    }
+}
+
+amadeus_enum! {
+    /// Booking status of a segment in a flight order
+    pub enum SegmentBookingStatus {
+        Confirmed => "CONFIRMED",
+        Waitlisted => "WAITLISTED",
+        Unconfirmed => "UNCONFIRMED",
+        Cancelled => "CANCELLED",
+    }
+}
+
+amadeus_enum! {
+    /// Availability of a seat for a traveler
+    pub enum SeatAvailabilityStatus {
+        Available => "AVAILABLE",
+        Blocked => "BLOCKED",
+        Occupied => "OCCUPIED",
+    }
+}
+
+amadeus_enum! {
+    /// Power outlet type of a cabin
+    pub enum PowerType {
+        Plug => "PLUG",
+        UsbPort => "USB_PORT",
+        Adaptor => "ADAPTOR",
+        PlugOrUsbPort => "PLUG_OR_USB_PORT",
+    }
+}
+
+amadeus_enum! {
+    /// USB port type of a cabin
+    pub enum UsbType {
+        UsbA => "USB_A",
+        UsbC => "USB_C",
+        UsbAAndUsbC => "USB_A_AND_USB_C",
+    }
+}
+
+amadeus_enum! {
+    /// WiFi coverage of a flight
+    pub enum WifiCoverage {
+        Full => "FULL",
+        Partial => "PARTIAL",
+        None => "NONE",
+    }
+}
+
+amadeus_enum! {
+    /// In-flight entertainment type
+    pub enum EntertainmentType {
+        LiveTv => "LIVE_TV",
+        Movies => "MOVIES",
+        AudioVideoOnDemand => "AUDIO_VIDEO_ON_DEMAND",
+        TvShows => "TV_SHOWS",
+        IpTv => "IP_TV",
+    }
+}
+
+amadeus_enum! {
+    /// In-flight food type
+    pub enum FoodType {
+        Meal => "MEAL",
+        FreshMeal => "FRESH_MEAL",
+        Snack => "SNACK",
+        FreshSnack => "FRESH_SNACK",
+    }
+}
+
+amadeus_enum! {
+    /// In-flight beverage type
+    pub enum BeverageType {
+        Alcoholic => "ALCOHOLIC",
+        NonAlcoholic => "NON_ALCOHOLIC",
+        AlcoholicAndNonAlcoholic => "ALCOHOLIC_AND_NON_ALCOHOLIC",
+    }
+}
+
+amadeus_enum! {
+    /// Seat recline of a cabin
+    pub enum SeatTilt {
+        FullFlat => "FULL_FLAT",
+        AngleFlat => "ANGLE_FLAT",
+        Normal => "NORMAL",
+    }
+}
+
+amadeus_enum! {
+    /// Quartile of a price within the historical price distribution
+    pub enum QuartileRanking {
+        Minimum => "MINIMUM",
+        First => "FIRST",
+        Medium => "MEDIUM",
+        Third => "THIRD",
+        Maximum => "MAXIMUM",
+    }
+}
+
+amadeus_enum! {
+    /// Additional data requested from Flight Offers Price
+    pub enum PricingInclude {
+        Bags => "bags",
+        CreditCardFees => "credit-card-fees",
+        DetailedFareRules => "detailed-fare-rules",
+        OtherServices => "other-services",
+    }
+}
+
+amadeus_enum! {
+    /// Category of a fare rule (term and condition)
+    pub enum FareRuleCategory {
+        Refund => "REFUND",
+        Exchange => "EXCHANGE",
+        Revalidation => "REVALIDATION",
+        Reissue => "REISSUE",
+        Bank => "BANK",
+        Local => "LOCAL",
+        Others => "OTHERS",
+    }
+}
 
 /// Request for flight search
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FlightSearchRequest {
     pub origin: String,
@@ -14,11 +326,35 @@ pub struct FlightSearchRequest {
     pub adults: u32,
     #[serde(default)]
     pub children: u32,
+    /// Infants on an adult's lap (HELD_INFANT)
     #[serde(default)]
     pub infants: u32,
+    /// Infants occupying their own seat (SEATED_INFANT)
+    #[serde(default)]
+    pub seated_infants: u32,
+    #[serde(default)]
+    pub seniors: u32,
+    /// Young travelers (YOUNG, typically 12-24)
+    #[serde(default)]
+    pub youths: u32,
+    #[serde(default)]
+    pub students: u32,
+    /// Age of each child at travel, one entry per child
+    pub child_ages: Option<Vec<u32>>,
     pub currency: Option<String>,
-    pub travel_class: Option<String>,
+    /// Cabin for the outbound leg, and the default for every other leg
+    pub travel_class: Option<Cabin>,
+    /// Cabin coverage (ALL_SEGMENTS, MOST_SEGMENTS, AT_LEAST_ONE_SEGMENT), defaults to ALL_SEGMENTS
+    pub cabin_coverage: Option<CabinCoverage>,
     pub non_stop: Option<bool>,
+    /// Maximum connections for the outbound leg, and the default for every other leg
+    pub max_connections: Option<u32>,
+    /// Cabin for the return leg (overrides `travel_class`)
+    pub return_travel_class: Option<Cabin>,
+    /// Cabin coverage for the return leg (overrides `cabin_coverage`)
+    pub return_cabin_coverage: Option<CabinCoverage>,
+    /// Maximum connections for the return leg (overrides `max_connections`/`non_stop`)
+    pub return_max_connections: Option<u32>,
     pub max_price: Option<i32>,
     pub max_results: Option<i32>,
     pub included_airline_codes: Option<Vec<String>>,
@@ -27,6 +363,16 @@ pub struct FlightSearchRequest {
     pub additional_legs: Option<Vec<FlightLegRequest>>,
 }
 
+impl FlightSearchRequest {
+    /// Redis cache key covering every parameter that changes the search result
+    pub fn cache_key(&self) -> String {
+        format!(
+            "flight_search:{}",
+            serde_json::to_string(self).unwrap_or_default()
+        )
+    }
+}
+
 /// A single leg for multi-city search
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -34,6 +380,24 @@ pub struct FlightLegRequest {
     pub origin: String,
     pub destination: String,
     pub departure_date: String,
+    /// Cabin for this leg (overrides the request's `travel_class`)
+    pub travel_class: Option<Cabin>,
+    /// Cabin coverage for this leg (overrides the request's `cabin_coverage`)
+    pub cabin_coverage: Option<CabinCoverage>,
+    /// Maximum connections for this leg (overrides the request's `max_connections`/`non_stop`)
+    pub max_connections: Option<u32>,
+}
+
+impl TravelerType {
+    /// Whether the traveler occupies a seat (everyone except infants on a lap)
+    pub fn is_seated(&self) -> bool {
+        *self != TravelerType::HeldInfant
+    }
+
+    /// Whether the traveler may accompany children and hold an infant
+    pub fn is_accompanying_adult(&self) -> bool {
+        matches!(self, TravelerType::Adult | TravelerType::Senior)
+    }
 }
 
 /// Request for batch price matrix search
@@ -49,6 +413,15 @@ pub struct PriceMatrixRequest {
     pub children: u32,
     #[serde(default)]
     pub infants: u32,
+    #[serde(default)]
+    pub seated_infants: u32,
+    #[serde(default)]
+    pub seniors: u32,
+    #[serde(default)]
+    pub youths: u32,
+    #[serde(default)]
+    pub students: u32,
+    pub child_ages: Option<Vec<u32>>,
     pub currency: Option<String>,
 }
 
@@ -65,8 +438,11 @@ pub struct PriceMatrixResponse {
 pub struct PriceMatrixEntry {
     pub outbound_date: String,
     pub inbound_date: String,
-    pub price: Option<String>,
+    pub price: Option<Decimal>,
     pub currency: String,
+    /// Price in the client's display currency
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_price: Option<DisplayAmount>,
 }
 
 /// Root response from Flight Offers Search API
@@ -75,6 +451,74 @@ pub struct FlightOffersResponse {
     pub data: Vec<FlightOffer>,
     #[serde(default)]
     pub dictionaries: Option<Dictionaries>,
+    /// Side-by-side view of the branded fares (upsell only, not sent by Amadeus)
+    #[serde(default, rename = "brandComparison", skip_serializing_if = "Option::is_none")]
+    pub brand_comparison: Option<BrandComparison>,
+}
+
+/// Branded fares as columns and what they include as rows
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrandComparison {
+    /// One column per offer, cheapest first
+    pub columns: Vec<BrandColumn>,
+    /// One row per entitlement, with a cell per column
+    pub rows: Vec<EntitlementRow>,
+}
+
+/// A branded fare in the comparison
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrandColumn {
+    pub offer_id: String,
+    pub branded_fare: Option<String>,
+    pub branded_fare_label: Option<String>,
+    /// Offer grand total
+    pub price: Money,
+    /// Price above the cheapest column, missing if the currencies differ
+    pub price_delta: Option<Decimal>,
+    /// Price above the cheapest column in the display currency
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_price_delta: Option<Decimal>,
+}
+
+/// Normalized fare entitlement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Entitlement {
+    CheckedBags,
+    SeatChoice,
+    Changes,
+    Refund,
+    Meals,
+    Lounge,
+}
+
+/// Whether a fare includes an entitlement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EntitlementStatus {
+    NotOffered,
+    Chargeable,
+    Included,
+}
+
+/// An entitlement across all compared fares
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntitlementRow {
+    pub entitlement: Entitlement,
+    /// In the order of the columns
+    pub cells: Vec<EntitlementCell>,
+}
+
+/// An entitlement of one fare
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntitlementCell {
+    pub status: EntitlementStatus,
+    /// Amenity description or bag allowance, e.g. "2 x 23KG"
+    pub detail: Option<String>,
 }
 
 /// A single flight offer
@@ -101,6 +545,56 @@ pub struct FlightOffer {
     pub pricing_options: Option<PricingOptions>,
     pub validating_airline_codes: Vec<String>,
     pub traveler_pricings: Vec<TravelerPricing>,
+    /// Fare rules, returned by Flight Offers Price
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fare_rules: Option<FareRules>,
+}
+
+impl FlightOffer {
+    /// Restore the net Amadeus prices if markups were applied to this offer.
+    /// Markups are never read from requests, so offers sent by clients keep
+    /// the marked-up prices they were shown.
+    pub fn remove_markup(&mut self) {
+        if let Some(markup) = self.price.markup.take() {
+            self.price.total = markup.net_total;
+            self.price.grand_total = markup.net_grand_total;
+        }
+        for pricing in &mut self.traveler_pricings {
+            if let Some(markup) = pricing.price.markup.take() {
+                pricing.price.total = markup.net_total;
+            }
+        }
+    }
+}
+
+/// Fare rules of an offer
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FareRules {
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub rules: Vec<TermAndCondition>,
+}
+
+/// A single fare rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TermAndCondition {
+    pub category: FareRuleCategory,
+    pub circumstances: Option<String>,
+    /// The action (refund, exchange) is not possible
+    pub not_applicable: Option<bool>,
+    pub max_penalty_amount: Option<Decimal>,
+    #[serde(default)]
+    pub descriptions: Vec<FareRuleDescription>,
+}
+
+/// Free text of a fare rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FareRuleDescription {
+    pub description_type: Option<String>,
+    pub text: Option<String>,
 }
 
 /// An itinerary (outbound or return leg)
@@ -130,6 +624,9 @@ pub struct Segment {
     pub co2_emissions: Vec<Co2Emission>,
     #[serde(default)]
     pub stops: Vec<FlightStop>,
+    /// Booking status, only present on segments of a flight order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub booking_status: Option<SegmentBookingStatus>,
 }
 
 /// Departure or arrival endpoint
@@ -162,7 +659,7 @@ pub struct OperatingFlight {
 pub struct Co2Emission {
     pub weight: f64,
     pub weight_unit: String,
-    pub cabin: String,
+    pub cabin: Cabin,
 }
 
 /// Flight stop information (per Amadeus API FlightStop model)
@@ -181,30 +678,87 @@ pub struct FlightStop {
 #[serde(rename_all = "camelCase")]
 pub struct Price {
     pub currency: String,
-    pub total: String,
-    pub base: String,
+    pub total: Decimal,
+    pub base: Decimal,
     #[serde(default)]
     pub fees: Vec<Fee>,
-    pub grand_total: Option<String>,
+    pub grand_total: Option<Decimal>,
     #[serde(default)]
     pub taxes: Vec<Tax>,
-    pub refundable_taxes: Option<String>,
+    pub refundable_taxes: Option<Decimal>,
     pub billing_currency: Option<String>,
+    /// Grand total in the client's display currency (only in responses: ignored
+    /// in requests, so never sent to Amadeus)
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub display_total: Option<DisplayAmount>,
+    /// Net fare vs. our markup, present once markups were applied (only in
+    /// responses: ignored in requests, net prices never come from clients)
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub markup: Option<PriceMarkup>,
+}
+
+impl Price {
+    pub fn total_money(&self) -> Money {
+        Money::new(self.total, self.currency.clone())
+    }
+
+    pub fn base_money(&self) -> Money {
+        Money::new(self.base, self.currency.clone())
+    }
+
+    /// Grand total including all fees, falling back to the total
+    pub fn grand_total_money(&self) -> Money {
+        Money::new(self.grand_total.unwrap_or(self.total), self.currency.clone())
+    }
+}
+
+/// An amount converted into the display currency requested by the client.
+/// The original amount and the applied rate are kept so totals stay auditable.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisplayAmount {
+    pub amount: Decimal,
+    pub currency: String,
+    pub original_amount: Decimal,
+    pub original_currency: String,
+    /// Units of `currency` per unit of `original_currency`
+    pub rate: Decimal,
+    /// Date of the rate table the conversion used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rates_as_of: Option<String>,
+}
+
+/// Breakdown of a price into the Amadeus net fare and what we add on top
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceMarkup {
+    /// Total as priced by Amadeus
+    pub net_total: Decimal,
+    /// Grand total as priced by Amadeus (offer prices only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub net_grand_total: Option<Decimal>,
+    /// Margin added to the fare
+    pub markup: Decimal,
+    /// Service fees added to the fare
+    pub service_fee: Decimal,
+    /// Rules that produced the markup and fees
+    #[serde(default)]
+    pub rule_ids: Vec<String>,
 }
 
 /// Fee information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Fee {
-    pub amount: String,
+    pub amount: Decimal,
     #[serde(rename = "type")]
-    pub fee_type: String,
+    pub fee_type: FeeType,
 }
 
 /// Tax information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tax {
-    pub amount: String,
+    pub amount: Decimal,
     pub code: String,
 }
 
@@ -213,7 +767,7 @@ pub struct Tax {
 #[serde(rename_all = "camelCase")]
 pub struct PricingOptions {
     #[serde(default)]
-    pub fare_type: Vec<String>,
+    pub fare_type: Vec<FareType>,
     #[serde(default)]
     pub included_checked_bags_only: bool,
 }
@@ -223,8 +777,11 @@ pub struct PricingOptions {
 #[serde(rename_all = "camelCase")]
 pub struct TravelerPricing {
     pub traveler_id: String,
-    pub fare_option: String,
-    pub traveler_type: String,
+    pub fare_option: FareOption,
+    pub traveler_type: TravelerType,
+    /// Adult holding the infant (held infants only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub associated_adult_id: Option<String>,
     pub price: TravelerPrice,
     pub fare_details_by_segment: Vec<FareDetailsBySegment>,
 }
@@ -234,11 +791,25 @@ pub struct TravelerPricing {
 #[serde(rename_all = "camelCase")]
 pub struct TravelerPrice {
     pub currency: String,
-    pub total: String,
-    pub base: String,
+    pub total: Decimal,
+    pub base: Decimal,
     #[serde(default)]
     pub taxes: Vec<Tax>,
-    pub refundable_taxes: Option<String>,
+    pub refundable_taxes: Option<Decimal>,
+    /// Total in the client's display currency (only in responses: ignored in
+    /// requests, so never sent to Amadeus)
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub display_total: Option<DisplayAmount>,
+    /// Net fare vs. our markup, present once markups were applied (only in
+    /// responses: ignored in requests, net prices never come from clients)
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub markup: Option<PriceMarkup>,
+}
+
+impl TravelerPrice {
+    pub fn total_money(&self) -> Money {
+        Money::new(self.total, self.currency.clone())
+    }
 }
 
 /// Fare details for a specific segment
@@ -246,7 +817,7 @@ pub struct TravelerPrice {
 #[serde(rename_all = "camelCase")]
 pub struct FareDetailsBySegment {
     pub segment_id: String,
-    pub cabin: String,
+    pub cabin: Cabin,
     pub fare_basis: String,
     pub branded_fare: Option<String>,
     pub branded_fare_label: Option<String>,
@@ -296,7 +867,7 @@ pub struct Amenity {
     pub description: String,
     #[serde(default)]
     pub is_chargeable: bool,
-    pub amenity_type: Option<String>,
+    pub amenity_type: Option<AmenityType>,
     pub amenity_provider: Option<AmenityProvider>,
 }
 
@@ -351,6 +922,137 @@ pub struct FlightPriceRequest {
     pub flight_offer: FlightOffer,
     #[serde(default)]
     pub include_bags: bool,
+    /// Additional data to request (bags, credit-card-fees, detailed-fare-rules, other-services)
+    #[serde(default)]
+    pub include: Vec<PricingInclude>,
+    /// Card the customer intends to pay with, to quote its surcharge
+    #[serde(default)]
+    pub payment_card: Option<PaymentCard>,
+}
+
+impl FlightPriceRequest {
+    /// Requested includes, with `includeBags` folded in.
+    /// Credit card fees are always requested when a payment card is given.
+    pub fn includes(&self) -> Vec<PricingInclude> {
+        let mut includes = pricing_includes(self.include_bags, &self.include);
+        if self.payment_card.is_some() && !includes.contains(&PricingInclude::CreditCardFees) {
+            includes.push(PricingInclude::CreditCardFees);
+        }
+        includes
+    }
+}
+
+/// Request to add checked bags to a flight offer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BagSelectionRequest {
+    pub flight_offer: FlightOffer,
+    pub selections: Vec<BagSelection>,
+}
+
+/// A bag option from the pricing catalog chosen for one traveler
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BagSelection {
+    /// Key of the option in `included.bags` of the pricing response
+    pub bag_option_id: String,
+    pub traveler_id: String,
+    /// Segments to add the bags to (defaults to all segments of the option)
+    #[serde(default)]
+    pub segment_ids: Vec<String>,
+}
+
+/// Request to assign seats from the seatmap to a flight offer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeatSelectionRequest {
+    pub flight_offer: FlightOffer,
+    pub selections: Vec<SeatSelection>,
+}
+
+/// A seat chosen for one traveler on one segment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeatSelection {
+    pub traveler_id: String,
+    pub segment_id: String,
+    /// Seat number as shown on the seatmap (e.g. 12A)
+    pub seat_number: String,
+}
+
+/// Request for seat suggestions for the travelers of an offer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeatRecommendationRequest {
+    pub flight_offer: FlightOffer,
+    #[serde(default)]
+    pub preferences: Vec<SeatPreference>,
+}
+
+/// Seat position a traveler prefers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeatPreference {
+    pub traveler_id: String,
+    pub position: SeatPosition,
+}
+
+/// Preferred seat position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SeatPosition {
+    Window,
+    Aisle,
+}
+
+/// Suggested seats per segment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeatRecommendationResponse {
+    pub data: Vec<SegmentSeatRecommendation>,
+}
+
+/// Suggested seats for one segment, ready for `/flight-price/seats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentSeatRecommendation {
+    pub segment_id: String,
+    pub seats: Vec<SeatSelection>,
+    /// All travelers sit side by side in one row
+    pub seated_together: bool,
+    /// Preferences that could not be met
+    pub unmet_preferences: usize,
+    /// Total seat price, missing if all seats are free
+    pub total: Option<Money>,
+    /// Travelers no suitable seat was found for
+    #[serde(default)]
+    pub unassigned_traveler_ids: Vec<String>,
+}
+
+/// Card brand and BIN used to quote credit card fees
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentCard {
+    /// Amadeus card brand (VISA, MASTERCARD, AMERICAN_EXPRESS, ...)
+    pub brand: String,
+    /// First 6 to 8 digits of the card number
+    pub bin_number: Option<String>,
+}
+
+/// Merge the legacy `includeBags` flag into a list of includes
+pub fn pricing_includes(include_bags: bool, include: &[PricingInclude]) -> Vec<PricingInclude> {
+    let mut includes = include.to_vec();
+    if include_bags {
+        includes.push(PricingInclude::Bags);
+    }
+    let mut seen = Vec::with_capacity(includes.len());
+    includes.retain(|include| {
+        let new = !seen.contains(include);
+        if new {
+            seen.push(include.clone());
+        }
+        new
+    });
+    includes
 }
 
 /// Response from Flight Offers Price API
@@ -362,6 +1064,127 @@ pub struct FlightPriceResponse {
     /// Included additional services (bags, seats, etc.) when include=bags is used
     #[serde(default)]
     pub included: Option<IncludedServices>,
+    /// Differences between the offers as searched and as priced (not sent by Amadeus)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub price_changes: Vec<PriceChangeReport>,
+    /// Normalized change, refund and no-show conditions per offer (not sent by Amadeus)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub penalty_summaries: Vec<PenaltySummary>,
+    /// Credit card surcharge and total to pay per offer (not sent by Amadeus)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub card_fees: Vec<CardFeeBreakdown>,
+}
+
+/// What paying an offer with a given card costs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardFeeBreakdown {
+    pub offer_id: String,
+    pub brand: String,
+    /// Offer grand total without the card fee
+    pub price: Money,
+    pub fee: Money,
+    /// Grand total including the card fee
+    pub total: Money,
+}
+
+/// Change, cancellation and no-show conditions of an offer.
+/// `None` values mean the fare rules did not say.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PenaltySummary {
+    pub offer_id: String,
+    /// Cancellation with refund is possible before departure (possibly for a fee)
+    pub refundable: Option<bool>,
+    /// Changes are possible before departure (possibly for a fee)
+    pub changeable: Option<bool>,
+    pub change: PenaltyRule,
+    pub cancellation: PenaltyRule,
+    /// What remains of the ticket after a no-show
+    pub no_show: PenaltyCondition,
+}
+
+/// A penalty before and after departure
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PenaltyRule {
+    pub before_departure: PenaltyCondition,
+    pub after_departure: PenaltyCondition,
+}
+
+/// Whether an action is allowed and what it costs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PenaltyCondition {
+    pub allowed: Option<bool>,
+    /// Highest fee charged, if any
+    pub fee: Option<Money>,
+    /// Whether the rules state that it costs nothing; allowed without a fee
+    /// and not free means the fee is unknown
+    #[serde(default)]
+    pub free: bool,
+}
+
+/// Comparison of an offer as searched with the offer as confirmed by pricing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceChangeReport {
+    pub offer_id: String,
+    pub old_currency: String,
+    pub currency: String,
+    /// Grand total the client saw before pricing
+    pub old_total: Decimal,
+    /// Grand total confirmed by pricing
+    pub new_total: Decimal,
+    /// New minus old total, missing if the currency changed
+    pub difference: Option<Decimal>,
+    /// Difference relative to the old total, in percent
+    pub difference_percent: Option<Decimal>,
+    pub price_changed: bool,
+    /// Fare basis or booking class changed on any segment
+    pub fare_changed: bool,
+    /// The change is outside the configured tolerance and the client must re-confirm
+    pub requires_reconfirmation: bool,
+    #[serde(default)]
+    pub travelers: Vec<TravelerPriceChange>,
+    /// Taxes whose amount changed, summed over all travelers
+    #[serde(default)]
+    pub taxes: Vec<TaxChange>,
+    /// Segments whose fare basis or booking class changed
+    #[serde(default)]
+    pub fares: Vec<SegmentFareChange>,
+}
+
+/// Price difference for one traveler
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TravelerPriceChange {
+    pub traveler_id: String,
+    pub old_total: Option<Decimal>,
+    pub new_total: Option<Decimal>,
+    pub difference: Option<Decimal>,
+}
+
+/// Change of one tax code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaxChange {
+    pub code: String,
+    pub old_amount: Decimal,
+    pub new_amount: Decimal,
+    pub difference: Decimal,
+}
+
+/// Fare basis or booking class change on a segment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentFareChange {
+    pub traveler_id: String,
+    pub segment_id: String,
+    pub old_fare_basis: Option<String>,
+    pub new_fare_basis: String,
+    pub old_booking_class: Option<String>,
+    pub new_booking_class: String,
 }
 
 /// Included additional services from pricing API
@@ -370,6 +1193,43 @@ pub struct IncludedServices {
     /// Baggage options catalog - key is the bag option ID
     #[serde(default)]
     pub bags: std::collections::HashMap<String, BagOption>,
+    /// Fare rule texts - key is the rule ID
+    #[serde(default, rename = "detailed-fare-rules", skip_serializing_if = "std::collections::HashMap::is_empty")]
+    pub detailed_fare_rules: std::collections::HashMap<String, DetailedFareRules>,
+    /// Credit card surcharges - key is the fee ID
+    #[serde(default, rename = "credit-card-fees", skip_serializing_if = "std::collections::HashMap::is_empty")]
+    pub credit_card_fees: std::collections::HashMap<String, CreditCardFee>,
+    /// Other services, passed through as returned by Amadeus
+    #[serde(default, rename = "other-services", skip_serializing_if = "std::collections::HashMap::is_empty")]
+    pub other_services: std::collections::HashMap<String, serde_json::Value>,
+}
+
+/// Fare rule texts of a fare component
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetailedFareRules {
+    pub fare_basis: Option<String>,
+    pub name: Option<String>,
+    pub segment_id: Option<String>,
+    pub fare_notes: Option<FareNotes>,
+}
+
+/// Notes of a fare component, by rule category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FareNotes {
+    #[serde(default)]
+    pub descriptions: Vec<FareRuleDescription>,
+}
+
+/// Surcharge for paying with a credit card brand
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreditCardFee {
+    pub brand: String,
+    pub amount: Decimal,
+    pub currency: String,
+    pub flight_offer_id: String,
 }
 
 /// Baggage option from pricing API
@@ -401,8 +1261,17 @@ pub struct BagOption {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BagPrice {
-    pub amount: String,
+    pub amount: Decimal,
     pub currency_code: String,
+    /// Amount in the client's display currency
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_amount: Option<DisplayAmount>,
+}
+
+impl BagPrice {
+    pub fn money(&self) -> Money {
+        Money::new(self.amount, self.currency_code.clone())
+    }
 }
 
 /// Flight price data
@@ -479,17 +1348,42 @@ pub struct OtherPayment {
     pub flight_offer_ids: Option<Vec<String>>,
 }
 
-/// Credit card payment
+/// Credit card payment. Clients should send a `token` from `POST /payment-cards`
+/// instead of the card number and security code.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreditCard {
     pub brand: String,
     pub bin_number: String,
     pub holder: String,
-    pub number: Option<String>,
+    pub number: Option<CardNumber>,
     pub expiry_date: Option<String>,
-    pub security_code: Option<String>,
+    pub security_code: Option<Secret<String>>,
     pub flight_offer_ids: Option<Vec<String>>,
+    /// Card token standing for number, expiry date and security code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+/// Card to exchange for a token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardTokenRequest {
+    pub number: CardNumber,
+    /// YYYY-MM
+    pub expiry_date: String,
+    pub security_code: Option<Secret<String>>,
+}
+
+/// Token to order with instead of the card data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardToken {
+    pub token: String,
+    pub bin_number: String,
+    pub last4: String,
+    pub expiry_date: String,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
 }
 
 /// Traveler information for booking
@@ -556,7 +1450,7 @@ pub struct TravelerContact {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Phone {
-    pub device_type: String,
+    pub device_type: DeviceType,
     pub country_calling_code: String,
     pub number: String,
 }
@@ -565,7 +1459,7 @@ pub struct Phone {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TravelerDocument {
-    pub document_type: String,
+    pub document_type: DocumentType,
     pub birth_place: Option<String>,
     pub issuance_location: Option<String>,
     pub issuance_date: Option<String>,
@@ -595,7 +1489,7 @@ pub struct GeneralRemark {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TicketingAgreement {
-    pub option: String,
+    pub option: TicketingOption,
     pub date_time: Option<String>,
 }
 
@@ -627,6 +1521,9 @@ pub struct FlightOrderResponse {
     pub data: FlightOrderData,
     #[serde(default)]
     pub dictionaries: Option<Dictionaries>,
+    /// Our booking record of the order (not sent by Amadeus)
+    #[serde(default, rename = "bookingId", skip_serializing_if = "Option::is_none")]
+    pub booking_id: Option<uuid::Uuid>,
 }
 
 /// Flight order data
@@ -642,6 +1539,139 @@ pub struct FlightOrderData {
     pub flight_offers: Vec<FlightOffer>,
     pub ticketing_agreement: Option<TicketingAgreement>,
     pub contacts: Option<Vec<Contact>>,
+    /// Issued tickets, present once the order is ticketed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tickets: Vec<IssuedTicket>,
+}
+
+/// Alternative offer of a schedule disruption chosen by an agent or the customer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptAlternativeRequest {
+    pub offer_id: String,
+}
+
+/// What an expected refund is based on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RefundBasis {
+    /// No ticket issued yet, so nothing is charged
+    NotTicketed,
+    /// Refundable according to the fare rules, less the penalty
+    FareRules,
+    /// Not refundable, only the refundable taxes are returned
+    TaxesOnly,
+    /// The fare rules do not say; at least the refundable taxes are returned
+    Unknown,
+}
+
+/// Expected refund of one offer of an order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfferRefund {
+    pub offer_id: String,
+    pub currency: String,
+    /// Grand total paid for the offer
+    pub paid: Decimal,
+    /// Cancellation penalty of all tickets of the offer
+    pub penalty: Option<Decimal>,
+    pub refundable_taxes: Decimal,
+    /// `None` if the fare rules do not tell
+    pub refund: Option<Decimal>,
+    pub basis: RefundBasis,
+}
+
+/// Expected refund of cancelling an order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefundEstimate {
+    pub currency: String,
+    pub paid: Decimal,
+    pub refundable_taxes: Decimal,
+    /// `None` if the refund of any offer is unknown
+    pub refund: Option<Decimal>,
+    pub ticketed: bool,
+    /// The first flight has departed, so penalties after departure apply
+    pub departed: bool,
+    pub offers: Vec<OfferRefund>,
+}
+
+/// First step of a cancellation: the expected refund, valid until `expires_at`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancellationQuote {
+    pub quote_id: uuid::Uuid,
+    pub booking_id: uuid::Uuid,
+    pub order_id: String,
+    #[serde(flatten)]
+    pub estimate: RefundEstimate,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Second step of a cancellation: cancel the order as quoted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancellationConfirmRequest {
+    pub quote_id: uuid::Uuid,
+    /// Agent or customer who cancels
+    pub cancelled_by: String,
+    pub reason: String,
+}
+
+/// Who cancels an order without a quote (`DELETE /flight-order/{id}`) and why.
+/// Both are optional so existing clients keep working.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderDeletionQuery {
+    pub cancelled_by: Option<String>,
+    pub reason: Option<String>,
+}
+
+impl OrderDeletionQuery {
+    /// Recorded when the client does not say who cancels
+    pub const DEFAULT_CANCELLED_BY: &str = "api";
+
+    /// Recorded when the client does not say why
+    pub const DEFAULT_REASON: &str = "Cancelled without a quote";
+
+    pub fn cancelled_by(&self) -> &str {
+        self.cancelled_by
+            .as_deref()
+            .unwrap_or(Self::DEFAULT_CANCELLED_BY)
+            .trim()
+    }
+
+    pub fn reason(&self) -> &str {
+        self.reason.as_deref().unwrap_or(Self::DEFAULT_REASON).trim()
+    }
+}
+
+/// Result of a confirmed cancellation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancellationOutcome {
+    pub quote_id: uuid::Uuid,
+    pub booking_id: uuid::Uuid,
+    pub order_id: String,
+    pub cancelled_by: String,
+    pub reason: String,
+    pub quoted: RefundEstimate,
+    /// Estimate from the order as it was when it was cancelled
+    pub actual: RefundEstimate,
+    /// Actual minus quoted refund, if both are known
+    pub refund_difference: Option<Decimal>,
+}
+
+/// Ticket or EMD issued for an order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuedTicket {
+    pub document_type: Option<String>,
+    pub document_number: Option<String>,
+    pub document_status: Option<String>,
+    pub traveler_id: Option<String>,
+    #[serde(default)]
+    pub segment_ids: Vec<String>,
 }
 
 /// Associated record (PNR reference)
@@ -683,8 +1713,9 @@ pub struct SeatmapData {
     pub segment_id: Option<String>,
     pub carrier_code: Option<String>,
     pub number: Option<String>,
+    /// One-letter booking class of the segment (e.g. "Y", "M"), not a cabin
     #[serde(rename = "class")]
-    pub cabin_class: Option<String>,  // Cabin class (per Amadeus API)
+    pub booking_class: Option<String>,
     pub aircraft: Option<SeatmapAircraft>,
     pub departure: Option<SeatmapDeparture>,
     pub arrival: Option<SeatmapArrival>,
@@ -724,6 +1755,59 @@ pub struct Deck {
     pub seats: Vec<Seat>,
     #[serde(default)]
     pub facilities: Option<Vec<Facility>>,
+    /// Row-by-column layout for rendering, on request (not sent by Amadeus)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grid: Option<DeckGrid>,
+}
+
+/// Deck layout as a grid of rows (along the aircraft) and columns (across it)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeckGrid {
+    pub columns: Vec<GridColumn>,
+    pub rows: Vec<GridRow>,
+}
+
+/// A column of the deck grid
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GridColumn {
+    /// Seat letter, missing for aisles
+    pub letter: Option<String>,
+    pub is_aisle: bool,
+}
+
+/// A row of the deck grid, with one cell per column
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GridRow {
+    /// Seat row number, missing for rows without seats
+    pub number: Option<String>,
+    pub is_exit_row: bool,
+    pub is_over_wing: bool,
+    pub cells: Vec<GridCell>,
+}
+
+/// Content of a grid cell
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GridCell {
+    #[serde(rename_all = "camelCase")]
+    Seat {
+        number: String,
+        /// Best availability over all travelers
+        status: Option<SeatAvailabilityStatus>,
+        #[serde(default)]
+        characteristics_codes: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Facility {
+        code: String,
+        /// LAVATORY, GALLEY, CLOSET, ... if the code is known
+        name: Option<String>,
+    },
+    Aisle,
+    Empty,
 }
 
 /// Facility in the aircraft (per Amadeus Seatmap API)
@@ -771,7 +1855,7 @@ pub struct DeckConfiguration {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Seat {
-    pub cabin: Option<String>,
+    pub cabin: Option<Cabin>,
     pub number: String,
     pub characteristics_codes: Option<Vec<String>>,
     pub coordinates: Option<SeatCoordinates>,
@@ -792,7 +1876,7 @@ pub struct SeatCoordinates {
 #[serde(rename_all = "camelCase")]
 pub struct SeatTravelerPricing {
     pub traveler_id: Option<String>,
-    pub seat_availability_status: Option<String>,
+    pub seat_availability_status: Option<SeatAvailabilityStatus>,
     pub price: Option<SeatPrice>,
 }
 
@@ -800,9 +1884,19 @@ pub struct SeatTravelerPricing {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SeatPrice {
     pub currency: Option<String>,
-    pub total: Option<String>,
-    pub base: Option<String>,
+    pub total: Option<Decimal>,
+    pub base: Option<Decimal>,
     pub taxes: Option<Vec<Tax>>,
+    /// Total in the client's display currency
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_total: Option<DisplayAmount>,
+}
+
+impl SeatPrice {
+    /// Seat total, if both amount and currency are given
+    pub fn total_money(&self) -> Option<Money> {
+        Some(Money::new(self.total?, self.currency.clone()?))
+    }
 }
 
 /// Available seats counter
@@ -864,8 +1958,8 @@ pub struct AircraftCabinAmenities {
 #[serde(rename_all = "camelCase")]
 pub struct CabinAmenity {
     pub is_chargeable: Option<bool>,
-    pub power_type: Option<String>,  // PLUG, USB_PORT, ADAPTOR, PLUG_OR_USB_PORT
-    pub usb_type: Option<String>,    // USB_A, USB_C, USB_A_AND_USB_C
+    pub power_type: Option<PowerType>,
+    pub usb_type: Option<UsbType>,
 }
 
 /// WiFi amenity
@@ -873,7 +1967,7 @@ pub struct CabinAmenity {
 #[serde(rename_all = "camelCase")]
 pub struct WifiAmenity {
     pub is_chargeable: Option<bool>,
-    pub wifi_coverage: Option<String>,  // FULL, PARTIAL, NONE
+    pub wifi_coverage: Option<WifiCoverage>,
 }
 
 /// Entertainment amenity
@@ -881,7 +1975,7 @@ pub struct WifiAmenity {
 #[serde(rename_all = "camelCase")]
 pub struct EntertainmentAmenity {
     pub is_chargeable: Option<bool>,
-    pub entertainment_type: Option<String>,  // LIVE_TV, MOVIES, AUDIO_VIDEO_ON_DEMAND, TV_SHOWS, IP_TV
+    pub entertainment_type: Option<EntertainmentType>,
 }
 
 /// Food amenity
@@ -889,7 +1983,7 @@ pub struct EntertainmentAmenity {
 #[serde(rename_all = "camelCase")]
 pub struct FoodAmenity {
     pub is_chargeable: Option<bool>,
-    pub food_type: Option<String>,  // MEAL, FRESH_MEAL, SNACK, FRESH_SNACK
+    pub food_type: Option<FoodType>,
 }
 
 /// Beverage amenity
@@ -897,7 +1991,7 @@ pub struct FoodAmenity {
 #[serde(rename_all = "camelCase")]
 pub struct BeverageAmenity {
     pub is_chargeable: Option<bool>,
-    pub beverage_type: Option<String>,  // ALCOHOLIC, NON_ALCOHOLIC, ALCOHOLIC_AND_NON_ALCOHOLIC
+    pub beverage_type: Option<BeverageType>,
 }
 
 /// Seat amenity info
@@ -905,7 +1999,7 @@ pub struct BeverageAmenity {
 #[serde(rename_all = "camelCase")]
 pub struct SeatAmenityInfo {
     pub is_chargeable: Option<bool>,
-    pub seat_tilt: Option<String>,  // FULL_FLAT, ANGLE_FLAT, NORMAL
+    pub seat_tilt: Option<SeatTilt>,
     pub leg_space: Option<i32>,     // Leg space in inches
     pub space_unit: Option<String>, // Unit for leg space
 }
@@ -958,7 +2052,7 @@ pub struct DateTimeRange {
 #[serde(rename_all = "camelCase")]
 pub struct TravelerInfo {
     pub id: String,
-    pub traveler_type: String,
+    pub traveler_type: TravelerType,
 }
 
 /// Response from Flight Availabilities API
@@ -1038,7 +2132,7 @@ pub struct FlightDestination {
 /// Price for flight destination
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FlightDestinationPrice {
-    pub total: String,
+    pub total: Decimal,
 }
 
 /// Response from Flight Dates (Cheapest Date) API
@@ -1098,8 +2192,8 @@ pub struct PriceMetricLocation {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PriceMetrics {
-    pub amount: String,
-    pub quartile_ranking: String,
+    pub amount: Decimal,
+    pub quartile_ranking: QuartileRanking,
 }
 
 // ============================================================================
@@ -1570,7 +2664,7 @@ mod tests {
 
         let request: FlightSearchRequest = serde_json::from_str(json).unwrap();
         assert_eq!(request.return_date, Some("2024-06-22".to_string()));
-        assert_eq!(request.travel_class, Some("BUSINESS".to_string()));
+        assert_eq!(request.travel_class, Some(Cabin::Business));
         assert_eq!(request.non_stop, Some(true));
         assert_eq!(request.max_price, Some(1000));
     }
@@ -1591,56 +2685,60 @@ mod tests {
             itineraries: vec![],
             price: Price {
                 currency: "EUR".to_string(),
-                total: "299.00".to_string(),
-                base: "250.00".to_string(),
+                total: Decimal::new(29900, 2),
+                base: Decimal::new(25000, 2),
                 fees: vec![],
                 taxes: vec![],
-                grand_total: Some("299.00".to_string()),
+                grand_total: Some(Decimal::new(29900, 2)),
                 refundable_taxes: None,
                 billing_currency: None,
+                display_total: None,
+                markup: None,
             },
             pricing_options: Some(PricingOptions {
-                fare_type: vec!["PUBLISHED".to_string()],
+                fare_type: vec![FareType::Published],
                 included_checked_bags_only: true,
             }),
             validating_airline_codes: vec!["LH".to_string()],
             traveler_pricings: vec![],
+            fare_rules: None,
         };
 
         let json = serde_json::to_string(&offer).unwrap();
         assert!(json.contains("\"id\":\"1\""));
         assert!(json.contains("\"source\":\"GDS\""));
+        // Amounts keep the Amadeus string format
+        assert!(json.contains("\"total\":\"299.00\""));
     }
 
     #[test]
     fn test_price_structure() {
-        let price = Price {
-            currency: "EUR".to_string(),
-            total: "299.00".to_string(),
-            base: "250.00".to_string(),
-            fees: vec![Fee {
-                amount: "10.00".to_string(),
-                fee_type: "SUPPLIER".to_string(),
-            }],
-            taxes: vec![Tax {
-                amount: "39.00".to_string(),
-                code: "MX".to_string(),
-            }],
-            grand_total: Some("299.00".to_string()),
-            refundable_taxes: None,
-            billing_currency: None,
-        };
-
-        // Parse amounts
-        let base: f64 = price.base.parse().unwrap();
-        let total: f64 = price.total.parse().unwrap();
-        let grand_total: f64 = price.grand_total.unwrap().parse().unwrap();
+        let json = r#"{
+            "currency": "EUR",
+            "total": "299.00",
+            "base": "250.00",
+            "fees": [{ "amount": "10.00", "type": "SUPPLIER" }],
+            "grandTotal": "299.00",
+            "taxes": [{ "amount": "39.00", "code": "MX" }]
+        }"#;
+        let price: Price = serde_json::from_str(json).unwrap();
 
-        assert_eq!(base, 250.0);
-        assert_eq!(total, 299.0);
-        assert_eq!(grand_total, 299.0);
+        assert_eq!(price.base, Decimal::new(250, 0));
+        assert_eq!(price.total, Decimal::new(299, 0));
+        assert_eq!(price.grand_total_money(), price.total_money());
         assert_eq!(price.fees.len(), 1);
+        assert_eq!(price.fees[0].fee_type, FeeType::Supplier);
         assert_eq!(price.taxes.len(), 1);
+
+        // Base plus taxes and fees adds up exactly
+        let taxes_and_fees: Decimal = price
+            .taxes
+            .iter()
+            .map(|t| t.amount)
+            .chain(price.fees.iter().map(|f| f.amount))
+            .sum();
+        assert_eq!(price.base + taxes_and_fees, price.total);
+        assert_eq!(price.total.to_string(), "299.00");
     }
 
     #[test]
@@ -1696,10 +2794,24 @@ mod tests {
 
         let pricing: TravelerPricing = serde_json::from_str(json).unwrap();
         assert_eq!(pricing.traveler_id, "1");
-        assert_eq!(pricing.traveler_type, "ADULT");
-        assert_eq!(pricing.price.total, "299.00");
+        assert_eq!(pricing.traveler_type, TravelerType::Adult);
+        assert_eq!(pricing.price.total_money().to_string(), "299.00 EUR");
         assert_eq!(pricing.fare_details_by_segment.len(), 1);
-        assert_eq!(pricing.fare_details_by_segment[0].cabin, "ECONOMY");
+        assert_eq!(pricing.fare_details_by_segment[0].cabin, Cabin::Economy);
+    }
+
+    #[test]
+    fn test_amadeus_enum_round_trip() {
+        let cabin: Cabin = serde_json::from_str("\"PREMIUM_ECONOMY\"").unwrap();
+        assert_eq!(cabin, Cabin::PremiumEconomy);
+        assert_eq!(serde_json::to_string(&cabin).unwrap(), "\"PREMIUM_ECONOMY\"");
+
+        // Codes added by Amadeus later are kept verbatim
+        let status: SeatAvailabilityStatus = serde_json::from_str("\"RESERVED\"").unwrap();
+        assert_eq!(status, SeatAvailabilityStatus::Unknown("RESERVED".to_string()));
+        assert!(status.is_unknown());
+        assert_eq!(serde_json::to_string(&status).unwrap(), "\"RESERVED\"");
+        assert_eq!(status.to_string(), "RESERVED");
     }
 
     #[test]
@@ -0,0 +1,211 @@
+//! Exact money amounts
+//!
+//! Amadeus sends amounts as decimal strings ("299.00"). They are parsed into
+//! `Decimal` so totals, markups and bag sums never pick up float rounding errors.
+
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Currencies without minor units (ISO 4217 exponent 0)
+const ZERO_DECIMAL_CURRENCIES: [&str; 16] = [
+    "BIF", "CLP", "DJF", "GNF", "ISK", "JPY", "KMF", "KRW", "PYG", "RWF", "UGX", "UYI", "VND",
+    "VUV", "XAF", "XOF",
+];
+
+/// Currencies with three minor units (ISO 4217 exponent 3)
+const THREE_DECIMAL_CURRENCIES: [&str; 7] = ["BHD", "IQD", "JOD", "KWD", "LYD", "OMR", "TND"];
+
+/// Number of minor units of an ISO currency
+pub fn minor_units(currency: &str) -> u32 {
+    if ZERO_DECIMAL_CURRENCIES.contains(&currency) {
+        0
+    } else if THREE_DECIMAL_CURRENCIES.contains(&currency) {
+        3
+    } else {
+        2
+    }
+}
+
+/// Errors of money arithmetic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoneyError {
+    /// Amounts in different currencies cannot be combined without conversion
+    CurrencyMismatch { expected: String, found: String },
+    /// The result does not fit into a `Decimal`
+    Overflow,
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoneyError::CurrencyMismatch { expected, found } => {
+                write!(
+                    f,
+                    "Currency mismatch: expected {}, found {}",
+                    expected, found
+                )
+            }
+            MoneyError::Overflow => write!(f, "Money amount overflow"),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+/// An exact amount in an ISO currency
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Money {
+    pub amount: Decimal,
+    pub currency: String,
+}
+
+impl Money {
+    pub fn new(amount: Decimal, currency: impl Into<String>) -> Self {
+        Self {
+            amount,
+            currency: currency.into(),
+        }
+    }
+
+    pub fn zero(currency: impl Into<String>) -> Self {
+        Self::new(Decimal::ZERO, currency)
+    }
+
+    fn same_currency(&self, other: &Money) -> Result<(), MoneyError> {
+        if self.currency == other.currency {
+            Ok(())
+        } else {
+            Err(MoneyError::CurrencyMismatch {
+                expected: self.currency.clone(),
+                found: other.currency.clone(),
+            })
+        }
+    }
+
+    /// Sum of two amounts in the same currency
+    pub fn checked_add(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.same_currency(other)?;
+        let amount = self
+            .amount
+            .checked_add(other.amount)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Money::new(amount, self.currency.clone()))
+    }
+
+    /// Difference of two amounts in the same currency
+    pub fn checked_sub(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.same_currency(other)?;
+        let amount = self
+            .amount
+            .checked_sub(other.amount)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Money::new(amount, self.currency.clone()))
+    }
+
+    /// Amount multiplied by a factor (quantities, percentages, exchange rates)
+    pub fn checked_mul(&self, factor: Decimal) -> Result<Money, MoneyError> {
+        let amount = self
+            .amount
+            .checked_mul(factor)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Money::new(amount, self.currency.clone()))
+    }
+
+    /// Sum of amounts in `currency`; fails on the first amount in another currency
+    pub fn sum<'a>(
+        currency: &str,
+        amounts: impl IntoIterator<Item = &'a Money>,
+    ) -> Result<Money, MoneyError> {
+        amounts
+            .into_iter()
+            .try_fold(Money::zero(currency), |total, amount| {
+                total.checked_add(amount)
+            })
+    }
+
+    /// Round half away from zero to the minor units of the currency
+    pub fn round(&self) -> Money {
+        Money::new(
+            self.amount.round_dp_with_strategy(
+                minor_units(&self.currency),
+                RoundingStrategy::MidpointAwayFromZero,
+            ),
+            self.currency.clone(),
+        )
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.amount.is_zero()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.amount.is_sign_negative() && !self.amount.is_zero()
+    }
+}
+
+/// Amounts are only comparable within the same currency
+impl PartialOrd for Money {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.currency == other.currency {
+            Some(self.amount.cmp(&other.amount))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.currency)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn eur(amount: &str) -> Money {
+        Money::new(Decimal::from_str(amount).unwrap(), "EUR")
+    }
+
+    #[test]
+    fn test_arithmetic_is_exact() {
+        let total = Money::sum("EUR", &[eur("0.10"), eur("0.20")]).unwrap();
+        assert_eq!(total, eur("0.30"));
+        assert_eq!(
+            eur("299.00").checked_sub(&eur("49.99")).unwrap(),
+            eur("249.01")
+        );
+        assert_eq!(
+            eur("35.00").checked_mul(Decimal::from(3)).unwrap(),
+            eur("105.00")
+        );
+    }
+
+    #[test]
+    fn test_currency_mismatch() {
+        let usd = Money::new(Decimal::ONE, "USD");
+        assert_eq!(
+            eur("1.00").checked_add(&usd),
+            Err(MoneyError::CurrencyMismatch {
+                expected: "EUR".to_string(),
+                found: "USD".to_string()
+            })
+        );
+        assert_eq!(eur("1.00").partial_cmp(&usd), None);
+        assert!(eur("1.00") < eur("1.01"));
+    }
+
+    #[test]
+    fn test_round_to_minor_units() {
+        assert_eq!(eur("10.005").round(), eur("10.01"));
+        assert_eq!(eur("-10.005").round(), eur("-10.01"));
+        let yen = Money::new(Decimal::from_str("1234.5").unwrap(), "JPY");
+        assert_eq!(yen.round().amount, Decimal::from(1235));
+        let dinar = Money::new(Decimal::from_str("1.23456").unwrap(), "KWD");
+        assert_eq!(dinar.round().amount.to_string(), "1.235");
+    }
+}
@@ -0,0 +1,85 @@
+//! Booking notifications
+//!
+//! Background jobs report changes and ticketing deadlines of stored bookings
+//! here. Every notification is logged and, if NOTIFICATION_WEBHOOK_URL is set,
+//! posted there as JSON so support tooling can pick it up.
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::bookings::BookingRecord;
+use crate::reconciliation::OrderChange;
+
+/// A change of a booking that someone should look at
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub booking_id: Uuid,
+    pub order_id: Option<String>,
+    pub pnr: Option<String>,
+    pub subject: String,
+    pub changes: Vec<OrderChange>,
+}
+
+impl Notification {
+    pub fn new(
+        booking: &BookingRecord,
+        subject: impl Into<String>,
+        changes: Vec<OrderChange>,
+    ) -> Self {
+        Self {
+            booking_id: booking.id,
+            order_id: booking.order_id.clone(),
+            pnr: booking.pnr.clone(),
+            subject: subject.into(),
+            changes,
+        }
+    }
+}
+
+/// Sends notifications to the log and the optional webhook
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    client: reqwest::Client,
+    webhook_url: Option<String>,
+}
+
+impl Notifier {
+    /// Read NOTIFICATION_WEBHOOK_URL (notifications are only logged without it)
+    pub fn from_env(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            webhook_url: std::env::var("NOTIFICATION_WEBHOOK_URL")
+                .ok()
+                .filter(|url| !url.trim().is_empty()),
+        }
+    }
+
+    /// Log the notification and post it to the webhook. Failures are logged
+    /// only; the change itself is already stored with the booking.
+    pub async fn send(&self, notification: &Notification) {
+        tracing::warn!(
+            "Booking {} (PNR {}): {}",
+            notification.booking_id,
+            notification.pnr.as_deref().unwrap_or("-"),
+            notification.subject
+        );
+        let Some(url) = &self.webhook_url else {
+            return;
+        };
+        let result = self
+            .client
+            .post(url)
+            .json(notification)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = result {
+            tracing::error!(
+                "Failed to post notification of booking {}: {}",
+                notification.booking_id,
+                e
+            );
+        }
+    }
+}
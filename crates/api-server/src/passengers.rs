@@ -0,0 +1,290 @@
+//! Passenger mix validation for flight searches
+//!
+//! Turns the traveler counts of a search request into the Amadeus `travelers`
+//! array and rejects combinations Amadeus would refuse.
+
+use serde::Serialize;
+use std::ops::RangeInclusive;
+
+use crate::models::{FlightSearchRequest, PriceMatrixRequest, TravelerType};
+use crate::validation::ValidationErrors;
+
+/// Amadeus accepts at most 9 seated travelers per search
+pub const MAX_SEATED_TRAVELERS: u64 = 9;
+
+/// Children are travelers aged 2-11 at the time of travel
+pub const CHILD_AGES: RangeInclusive<u32> = 2..=11;
+
+/// Traveler counts of a search request
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PassengerMix {
+    pub adults: u32,
+    pub children: u32,
+    pub held_infants: u32,
+    pub seated_infants: u32,
+    pub seniors: u32,
+    pub youths: u32,
+    pub students: u32,
+    pub child_ages: Option<Vec<u32>>,
+}
+
+/// A traveler of the Amadeus flight offers search request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchTraveler {
+    pub id: String,
+    pub traveler_type: TravelerType,
+    /// Adult holding the infant (HELD_INFANT only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub associated_adult_id: Option<String>,
+}
+
+impl PassengerMix {
+    /// Travelers occupying a seat (everyone except held infants)
+    pub fn seated(&self) -> u64 {
+        [
+            self.adults,
+            self.children,
+            self.seated_infants,
+            self.seniors,
+            self.youths,
+            self.students,
+        ]
+        .iter()
+        .map(|&n| u64::from(n))
+        .sum()
+    }
+
+    /// Travelers who may accompany children and hold infants
+    fn accompanying_adults(&self) -> u64 {
+        u64::from(self.adults) + u64::from(self.seniors)
+    }
+
+    /// Check the mix against the Amadeus traveler rules
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        let seated = self.seated();
+        if seated == 0 {
+            errors.add("adults", "At least one seated traveler is required");
+        } else if seated > MAX_SEATED_TRAVELERS {
+            errors.add(
+                "adults",
+                format!(
+                    "At most {} seated travelers are allowed, got {}",
+                    MAX_SEATED_TRAVELERS, seated
+                ),
+            );
+        }
+
+        let dependents = u64::from(self.children)
+            + u64::from(self.seated_infants)
+            + u64::from(self.held_infants);
+        if dependents > 0 && self.accompanying_adults() == 0 {
+            errors.add(
+                "adults",
+                "Children and infants must travel with at least one adult or senior",
+            );
+        }
+
+        if u64::from(self.held_infants) > self.accompanying_adults() {
+            errors.add(
+                "infants",
+                format!(
+                    "Each infant on a lap needs its own adult or senior, got {} infants for {}",
+                    self.held_infants,
+                    self.accompanying_adults()
+                ),
+            );
+        }
+
+        if let Some(ref ages) = self.child_ages {
+            if ages.len() != self.children as usize {
+                errors.add(
+                    "childAges",
+                    format!("Expected {} child ages, got {}", self.children, ages.len()),
+                );
+            }
+            for (i, age) in ages.iter().enumerate() {
+                if !CHILD_AGES.contains(age) {
+                    errors.add(
+                        format!("childAges[{}]", i),
+                        format!(
+                            "Child age must be between {} and {}, got {} (travelers under 2 are infants)",
+                            CHILD_AGES.start(),
+                            CHILD_AGES.end(),
+                            age
+                        ),
+                    );
+                }
+            }
+        }
+
+        errors.into_result()
+    }
+
+    /// Build the Amadeus travelers array, assigning IDs and infant holders
+    pub fn travelers(&self) -> Result<Vec<SearchTraveler>, ValidationErrors> {
+        self.validate()?;
+
+        let groups = [
+            (TravelerType::Adult, self.adults),
+            (TravelerType::Senior, self.seniors),
+            (TravelerType::Young, self.youths),
+            (TravelerType::Student, self.students),
+            (TravelerType::Child, self.children),
+            (TravelerType::SeatedInfant, self.seated_infants),
+        ];
+
+        let mut travelers = Vec::new();
+        for (traveler_type, count) in groups {
+            for _ in 0..count {
+                travelers.push(SearchTraveler {
+                    id: (travelers.len() + 1).to_string(),
+                    traveler_type: traveler_type.clone(),
+                    associated_adult_id: None,
+                });
+            }
+        }
+
+        // Associate each infant on a lap with a different adult or senior
+        let holder_ids: Vec<String> = travelers
+            .iter()
+            .filter(|t| t.traveler_type.is_accompanying_adult())
+            .map(|t| t.id.clone())
+            .collect();
+        for holder_id in holder_ids.into_iter().take(self.held_infants as usize) {
+            travelers.push(SearchTraveler {
+                id: (travelers.len() + 1).to_string(),
+                traveler_type: TravelerType::HeldInfant,
+                associated_adult_id: Some(holder_id),
+            });
+        }
+
+        Ok(travelers)
+    }
+}
+
+impl From<&FlightSearchRequest> for PassengerMix {
+    fn from(req: &FlightSearchRequest) -> Self {
+        Self {
+            adults: req.adults,
+            children: req.children,
+            held_infants: req.infants,
+            seated_infants: req.seated_infants,
+            seniors: req.seniors,
+            youths: req.youths,
+            students: req.students,
+            child_ages: req.child_ages.clone(),
+        }
+    }
+}
+
+impl From<&PriceMatrixRequest> for PassengerMix {
+    fn from(req: &PriceMatrixRequest) -> Self {
+        Self {
+            adults: req.adults,
+            children: req.children,
+            held_infants: req.infants,
+            seated_infants: req.seated_infants,
+            seniors: req.seniors,
+            youths: req.youths,
+            students: req.students,
+            child_ages: req.child_ages.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_travelers_assign_infants_to_adults_and_seniors() {
+        let mix = PassengerMix {
+            adults: 1,
+            seniors: 1,
+            children: 1,
+            held_infants: 2,
+            child_ages: Some(vec![7]),
+            ..Default::default()
+        };
+
+        let travelers = mix.travelers().unwrap();
+        let types: Vec<_> = travelers.iter().map(|t| t.traveler_type.clone()).collect();
+        assert_eq!(
+            types,
+            [
+                TravelerType::Adult,
+                TravelerType::Senior,
+                TravelerType::Child,
+                TravelerType::HeldInfant,
+                TravelerType::HeldInfant,
+            ]
+        );
+        assert_eq!(travelers[3].associated_adult_id.as_deref(), Some("1"));
+        assert_eq!(travelers[4].associated_adult_id.as_deref(), Some("2"));
+
+        let json = serde_json::to_value(&travelers[3]).unwrap();
+        assert_eq!(json["travelerType"], "HELD_INFANT");
+        assert_eq!(json["associatedAdultId"], "1");
+        assert!(serde_json::to_value(&travelers[0]).unwrap()["associatedAdultId"].is_null());
+    }
+
+    #[test]
+    fn test_infants_without_adults_are_rejected() {
+        let mix = PassengerMix {
+            youths: 1,
+            held_infants: 1,
+            ..Default::default()
+        };
+
+        let errors = mix.travelers().unwrap_err();
+        let fields: Vec<_> = errors.errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["adults", "infants"]);
+    }
+
+    #[test]
+    fn test_seated_traveler_limits() {
+        let empty = PassengerMix::default();
+        assert!(empty.validate().is_err());
+
+        let full = PassengerMix {
+            adults: 4,
+            students: 3,
+            seated_infants: 2,
+            held_infants: 4,
+            ..Default::default()
+        };
+        assert!(full.validate().is_ok());
+
+        let too_many = PassengerMix {
+            adults: 5,
+            students: 5,
+            ..Default::default()
+        };
+        assert!(too_many.validate().is_err());
+    }
+
+    #[test]
+    fn test_child_ages() {
+        let mix = PassengerMix {
+            adults: 1,
+            children: 2,
+            child_ages: Some(vec![1, 12]),
+            ..Default::default()
+        };
+
+        let errors = mix.validate().unwrap_err();
+        let fields: Vec<_> = errors.errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["childAges[0]", "childAges[1]"]);
+
+        let missing = PassengerMix {
+            adults: 1,
+            children: 2,
+            child_ages: Some(vec![5]),
+            ..Default::default()
+        };
+        assert_eq!(missing.validate().unwrap_err().errors[0].field, "childAges");
+    }
+}
@@ -0,0 +1,646 @@
+//! Card payments
+//!
+//! Without a payment provider the card of an order is passed to Amadeus and
+//! charged by the airline. With one (`PAYMENT_PROVIDER`), we charge the
+//! customer ourselves: the sale price is authorized before the order is
+//! created, captured once Amadeus confirmed the order and voided if ordering
+//! fails. The order itself is then paid from the agency account, so the card
+//! never reaches Amadeus. Cancelled bookings get their authorization voided or
+//! their refund paid back. Every step is kept in `booking_payments` (see
+//! migrations/009_booking_payments.sql).
+//!
+//! `SandboxPaymentProvider` keeps payments in memory for development and
+//! tests; it approves any valid card except the test cards below.
+
+use futures::future::BoxFuture;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+use crate::bookings::{BookingStore, BookingTracker};
+use crate::models::{
+    CreditCard, FlightOffer, FlightOrderRequest, FormOfPayment, OtherPayment, RefundEstimate,
+};
+use crate::money::Money;
+use crate::redaction::{CardNumber, luhn_valid};
+use crate::validation::card_expired;
+
+/// Form of payment sent to Amadeus for orders we took the payment for
+pub const AGENCY_PAYMENT_METHOD: &str = "ACCOUNT";
+
+/// Sandbox card that is always declined
+pub const SANDBOX_DECLINED_CARD: &str = "4000000000000002";
+/// Sandbox card declined for insufficient funds
+pub const SANDBOX_INSUFFICIENT_FUNDS_CARD: &str = "4000000000009995";
+/// Sandbox card failing as if the provider had an outage
+pub const SANDBOX_PROVIDER_ERROR_CARD: &str = "4000000000000119";
+
+/// Event recorded when a captured payment needs a manual refund
+pub const REFUND_PENDING_EVENT: &str = "PAYMENT_REFUND_PENDING";
+
+/// Status of a payment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PaymentStatus {
+    /// Amount reserved on the card
+    Authorized,
+    /// Amount charged
+    Captured,
+    /// Authorization released without charging
+    Voided,
+    /// Charged amount paid back, in full or in part
+    Refunded,
+}
+
+impl PaymentStatus {
+    pub const ALL: [PaymentStatus; 4] = [
+        PaymentStatus::Authorized,
+        PaymentStatus::Captured,
+        PaymentStatus::Voided,
+        PaymentStatus::Refunded,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentStatus::Authorized => "AUTHORIZED",
+            PaymentStatus::Captured => "CAPTURED",
+            PaymentStatus::Voided => "VOIDED",
+            PaymentStatus::Refunded => "REFUNDED",
+        }
+    }
+}
+
+impl fmt::Display for PaymentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TryFrom<String> for PaymentStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        PaymentStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == value)
+            .ok_or_else(|| format!("Unknown payment status {}", value))
+    }
+}
+
+/// A payment as known to the provider
+#[derive(Debug, Clone, PartialEq)]
+pub struct Payment {
+    /// Id of the payment at the provider
+    pub id: String,
+    pub status: PaymentStatus,
+    /// Authorized amount
+    pub amount: Money,
+    pub captured: Decimal,
+    pub refunded: Decimal,
+}
+
+impl Payment {
+    /// Captured amount not refunded yet
+    pub fn refundable(&self) -> Decimal {
+        self.captured - self.refunded
+    }
+}
+
+/// Errors of payment providers
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaymentError {
+    /// The issuer declined the card
+    Declined(String),
+    /// Card data or amount not usable
+    Invalid(String),
+    /// The operation does not fit the status or amounts of the payment
+    InvalidState(String),
+    NotFound(String),
+    /// The provider could not be reached or failed
+    Provider(String),
+}
+
+impl fmt::Display for PaymentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaymentError::Declined(reason) => write!(f, "Card declined: {}", reason),
+            PaymentError::Invalid(reason) => write!(f, "Invalid payment: {}", reason),
+            PaymentError::InvalidState(reason) => f.write_str(reason),
+            PaymentError::NotFound(id) => write!(f, "Payment {} not found", id),
+            PaymentError::Provider(reason) => write!(f, "Payment provider error: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for PaymentError {}
+
+/// Amount to authorize on a card
+#[derive(Debug, Clone)]
+pub struct PaymentRequest<'a> {
+    pub amount: Money,
+    pub card: &'a CreditCard,
+    /// Booking the payment is for, if it is stored
+    pub booking_id: Option<Uuid>,
+}
+
+/// A payment service provider. Operations return the payment as it is after
+/// the operation.
+pub trait PaymentProvider: Send + Sync {
+    /// Name stored with each payment
+    fn name(&self) -> &'static str;
+
+    fn authorize<'a>(
+        &'a self,
+        request: &'a PaymentRequest<'a>,
+    ) -> BoxFuture<'a, Result<Payment, PaymentError>>;
+
+    /// Charge `amount` of an authorization
+    fn capture<'a>(
+        &'a self,
+        payment: &'a Payment,
+        amount: Decimal,
+    ) -> BoxFuture<'a, Result<Payment, PaymentError>>;
+
+    /// Release an authorization that was not captured
+    fn void<'a>(&'a self, payment: &'a Payment) -> BoxFuture<'a, Result<Payment, PaymentError>>;
+
+    /// Pay back `amount` of a captured payment
+    fn refund<'a>(
+        &'a self,
+        payment: &'a Payment,
+        amount: Decimal,
+    ) -> BoxFuture<'a, Result<Payment, PaymentError>>;
+}
+
+/// Provider named by `PAYMENT_PROVIDER`, or `None` to leave cards to Amadeus
+pub fn from_env() -> Option<Arc<dyn PaymentProvider>> {
+    match std::env::var("PAYMENT_PROVIDER").unwrap_or_default().trim() {
+        "" => None,
+        "sandbox" => {
+            tracing::warn!("Sandbox payment provider: cards are not charged");
+            Some(Arc::new(SandboxPaymentProvider::default()))
+        }
+        other => {
+            tracing::error!("Unknown PAYMENT_PROVIDER {}, cards go to Amadeus", other);
+            None
+        }
+    }
+}
+
+/// In-memory provider for development and tests
+#[derive(Debug, Default)]
+pub struct SandboxPaymentProvider {
+    payments: Mutex<HashMap<String, Payment>>,
+}
+
+impl SandboxPaymentProvider {
+    fn check_card(card: &CreditCard, today: chrono::NaiveDate) -> Result<(), PaymentError> {
+        let number = card
+            .number
+            .as_ref()
+            .map(CardNumber::expose)
+            .ok_or_else(|| PaymentError::Invalid("Card number is missing".to_string()))?;
+        if !luhn_valid(number) {
+            return Err(PaymentError::Invalid(
+                "Card number failed the check digit".to_string(),
+            ));
+        }
+        match card
+            .expiry_date
+            .as_deref()
+            .map(|date| card_expired(date, today))
+        {
+            None | Some(None) => Err(PaymentError::Invalid(
+                "Expiry date must be YYYY-MM".to_string(),
+            )),
+            Some(Some(true)) => Err(PaymentError::Declined("Card expired".to_string())),
+            Some(Some(false)) => match number {
+                SANDBOX_DECLINED_CARD => Err(PaymentError::Declined("Do not honor".to_string())),
+                SANDBOX_INSUFFICIENT_FUNDS_CARD => {
+                    Err(PaymentError::Declined("Insufficient funds".to_string()))
+                }
+                SANDBOX_PROVIDER_ERROR_CARD => {
+                    Err(PaymentError::Provider("Processing error".to_string()))
+                }
+                _ => Ok(()),
+            },
+        }
+    }
+
+    fn authorize_now(&self, request: &PaymentRequest<'_>) -> Result<Payment, PaymentError> {
+        if request.amount.amount <= Decimal::ZERO {
+            return Err(PaymentError::Invalid(format!(
+                "Cannot authorize {}",
+                request.amount
+            )));
+        }
+        Self::check_card(request.card, chrono::Utc::now().date_naive())?;
+
+        let payment = Payment {
+            id: format!("sbx_{}", Uuid::new_v4().simple()),
+            status: PaymentStatus::Authorized,
+            amount: request.amount.clone(),
+            captured: Decimal::ZERO,
+            refunded: Decimal::ZERO,
+        };
+        self.payments
+            .lock()
+            .expect("sandbox payments lock")
+            .insert(payment.id.clone(), payment.clone());
+        tracing::info!(
+            "Sandbox authorized {} as {} (booking {})",
+            payment.amount,
+            payment.id,
+            request
+                .booking_id
+                .map_or_else(|| "-".to_string(), |id| id.to_string())
+        );
+        Ok(payment)
+    }
+
+    /// Apply `change` to the stored payment and return it
+    fn update(
+        &self,
+        id: &str,
+        change: impl FnOnce(&mut Payment) -> Result<(), PaymentError>,
+    ) -> Result<Payment, PaymentError> {
+        let mut payments = self.payments.lock().expect("sandbox payments lock");
+        let payment = payments
+            .get_mut(id)
+            .ok_or_else(|| PaymentError::NotFound(id.to_string()))?;
+        change(payment)?;
+        Ok(payment.clone())
+    }
+}
+
+/// Reject an operation on a payment in the wrong status
+fn require_status(
+    payment: &Payment,
+    allowed: &[PaymentStatus],
+    operation: &str,
+) -> Result<(), PaymentError> {
+    if allowed.contains(&payment.status) {
+        Ok(())
+    } else {
+        Err(PaymentError::InvalidState(format!(
+            "Cannot {} payment {}: it is {}",
+            operation, payment.id, payment.status
+        )))
+    }
+}
+
+impl PaymentProvider for SandboxPaymentProvider {
+    fn name(&self) -> &'static str {
+        "sandbox"
+    }
+
+    fn authorize<'a>(
+        &'a self,
+        request: &'a PaymentRequest<'a>,
+    ) -> BoxFuture<'a, Result<Payment, PaymentError>> {
+        Box::pin(std::future::ready(self.authorize_now(request)))
+    }
+
+    fn capture<'a>(
+        &'a self,
+        payment: &'a Payment,
+        amount: Decimal,
+    ) -> BoxFuture<'a, Result<Payment, PaymentError>> {
+        let result = self.update(&payment.id, |payment| {
+            require_status(payment, &[PaymentStatus::Authorized], "capture")?;
+            if amount <= Decimal::ZERO || amount > payment.amount.amount {
+                return Err(PaymentError::Invalid(format!(
+                    "Cannot capture {} of {}",
+                    amount, payment.amount
+                )));
+            }
+            payment.status = PaymentStatus::Captured;
+            payment.captured = amount;
+            Ok(())
+        });
+        Box::pin(std::future::ready(result))
+    }
+
+    fn void<'a>(&'a self, payment: &'a Payment) -> BoxFuture<'a, Result<Payment, PaymentError>> {
+        let result = self.update(&payment.id, |payment| {
+            require_status(payment, &[PaymentStatus::Authorized], "void")?;
+            payment.status = PaymentStatus::Voided;
+            Ok(())
+        });
+        Box::pin(std::future::ready(result))
+    }
+
+    fn refund<'a>(
+        &'a self,
+        payment: &'a Payment,
+        amount: Decimal,
+    ) -> BoxFuture<'a, Result<Payment, PaymentError>> {
+        let result = self.update(&payment.id, |payment| {
+            require_status(
+                payment,
+                &[PaymentStatus::Captured, PaymentStatus::Refunded],
+                "refund",
+            )?;
+            if amount <= Decimal::ZERO || amount > payment.refundable() {
+                return Err(PaymentError::Invalid(format!(
+                    "Cannot refund {} of {} {} captured and {} refunded",
+                    amount, payment.captured, payment.amount.currency, payment.refunded
+                )));
+            }
+            payment.status = PaymentStatus::Refunded;
+            payment.refunded += amount;
+            Ok(())
+        });
+        Box::pin(std::future::ready(result))
+    }
+}
+
+/// Credit card of an order request
+pub fn order_card(request: &FlightOrderRequest) -> Option<&CreditCard> {
+    request
+        .form_of_payment
+        .as_ref()
+        .and_then(|payment| payment.credit_card.as_ref())
+}
+
+/// Sale price of the offers (markup applied) to authorize
+pub fn sale_price(offers: &[FlightOffer]) -> Result<Money, PaymentError> {
+    let totals: Vec<Money> = offers
+        .iter()
+        .map(|offer| offer.price.grand_total_money())
+        .collect();
+    let currency = totals
+        .first()
+        .map(|total| total.currency.clone())
+        .ok_or_else(|| PaymentError::Invalid("No offers to pay for".to_string()))?;
+    Money::sum(&currency, &totals).map_err(|e| PaymentError::Invalid(e.to_string()))
+}
+
+/// The order request with the card replaced by the agency account
+pub fn agency_order(request: &FlightOrderRequest) -> FlightOrderRequest {
+    let flight_offer_ids = order_card(request).and_then(|card| card.flight_offer_ids.clone());
+    FlightOrderRequest {
+        form_of_payment: Some(FormOfPayment {
+            other: Some(OtherPayment {
+                method: AGENCY_PAYMENT_METHOD.to_string(),
+                flight_offer_ids,
+            }),
+            credit_card: None,
+        }),
+        ..request.clone()
+    }
+}
+
+/// Keep the outcome of a payment operation with the booking; failures are
+/// logged and recorded with the payment as it was
+async fn record_outcome(
+    provider: &dyn PaymentProvider,
+    booking: Option<&BookingTracker<'_>>,
+    payment: &Payment,
+    operation: &str,
+    result: Result<Payment, PaymentError>,
+) {
+    let (payment, error) = match result {
+        Ok(payment) => (payment, None),
+        Err(e) => {
+            tracing::error!("Failed to {} payment {}: {}", operation, payment.id, e);
+            (payment.clone(), Some(e.to_string()))
+        }
+    };
+    if let Some(booking) = booking {
+        booking
+            .record_payment(provider.name(), &payment, error.as_deref())
+            .await;
+    }
+}
+
+/// Charge the authorization of a booking whose order Amadeus confirmed
+pub async fn capture_order(
+    provider: &dyn PaymentProvider,
+    booking: Option<&BookingTracker<'_>>,
+    payment: &Payment,
+) {
+    let result = provider.capture(payment, payment.amount.amount).await;
+    record_outcome(provider, booking, payment, "capture", result).await;
+}
+
+/// Release the authorization of a booking whose order failed
+pub async fn void_order(
+    provider: &dyn PaymentProvider,
+    booking: Option<&BookingTracker<'_>>,
+    payment: &Payment,
+) {
+    let result = provider.void(payment).await;
+    record_outcome(provider, booking, payment, "void", result).await;
+}
+
+/// What the customer gets back for a cancelled booking
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Refund {
+    /// Everything captured, for orders cancelled before ticketing
+    Full,
+    Amount(Decimal),
+    /// Not known; left to support staff
+    Unknown,
+}
+
+impl From<&RefundEstimate> for Refund {
+    /// Unticketed orders are refunded in full, markup included; for tickets the
+    /// fare rules decide and our markup is kept
+    fn from(estimate: &RefundEstimate) -> Self {
+        match (estimate.ticketed, estimate.refund) {
+            (false, _) => Refund::Full,
+            (true, Some(amount)) => Refund::Amount(amount),
+            (true, None) => Refund::Unknown,
+        }
+    }
+}
+
+/// Void the authorization of a cancelled booking or pay its refund back.
+/// Failures are logged and recorded with the payment; the cancellation stands.
+pub async fn settle_cancellation(
+    provider: &dyn PaymentProvider,
+    store: &BookingStore,
+    booking_id: Uuid,
+    refund: Refund,
+) {
+    let record = match store.payments(booking_id).await {
+        Ok(payments) => payments
+            .into_iter()
+            .rev()
+            .find(|payment| payment.provider == provider.name()),
+        Err(e) => {
+            tracing::error!("Failed to load payments of booking {}: {}", booking_id, e);
+            return;
+        }
+    };
+    let Some(payment) = record.map(|record| record.payment()) else {
+        return;
+    };
+
+    let (operation, result) = match (payment.status, refund) {
+        (PaymentStatus::Voided, _) => return,
+        (PaymentStatus::Authorized, _) => ("void", provider.void(&payment).await),
+        (_, Refund::Unknown) => {
+            tracing::warn!(
+                "Refund of payment {} of booking {} needs a manual review",
+                payment.id,
+                booking_id
+            );
+            if let Err(e) = store
+                .record_event(booking_id, REFUND_PENDING_EVENT, Some(&payment.id))
+                .await
+            {
+                tracing::error!("Failed to record event of booking {}: {}", booking_id, e);
+            }
+            return;
+        }
+        (_, refund) => {
+            let amount = match refund {
+                Refund::Amount(amount) => amount.min(payment.refundable()),
+                _ => payment.refundable(),
+            };
+            if amount <= Decimal::ZERO {
+                return;
+            }
+            ("refund", provider.refund(&payment, amount).await)
+        }
+    };
+
+    let (updated, error) = match result {
+        Ok(updated) => (updated, None),
+        Err(e) => {
+            tracing::error!("Failed to {} payment {}: {}", operation, payment.id, e);
+            (payment, Some(e.to_string()))
+        }
+    };
+    if let Err(e) = store
+        .save_payment(booking_id, provider.name(), &updated, error.as_deref())
+        .await
+    {
+        tracing::error!("Failed to store payment {}: {}", updated.id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(number: &str) -> CreditCard {
+        CreditCard {
+            brand: "VISA".to_string(),
+            bin_number: number[..6].to_string(),
+            holder: "JORGE GONZALES".to_string(),
+            number: Some(CardNumber::new(number)),
+            expiry_date: Some("2099-12".to_string()),
+            security_code: Some("123".to_string().into()),
+            flight_offer_ids: None,
+            token: None,
+        }
+    }
+
+    fn eur(amount: &str) -> Money {
+        Money::new(amount.parse().unwrap(), "EUR")
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_payment_lifecycle() {
+        let provider = SandboxPaymentProvider::default();
+        let card = card("4111111111111111");
+        let request = PaymentRequest {
+            amount: eur("450.00"),
+            card: &card,
+            booking_id: None,
+        };
+
+        let authorized = provider.authorize(&request).await.unwrap();
+        assert_eq!(authorized.status, PaymentStatus::Authorized);
+        assert!(provider.refund(&authorized, Decimal::ONE).await.is_err());
+
+        let captured = provider
+            .capture(&authorized, authorized.amount.amount)
+            .await
+            .unwrap();
+        assert_eq!(captured.status, PaymentStatus::Captured);
+        assert!(matches!(
+            provider.void(&captured).await,
+            Err(PaymentError::InvalidState(_))
+        ));
+
+        let refunded = provider
+            .refund(&captured, "150.00".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(refunded.status, PaymentStatus::Refunded);
+        assert_eq!(refunded.refundable(), "300.00".parse::<Decimal>().unwrap());
+        assert!(
+            provider
+                .refund(&refunded, "300.01".parse().unwrap())
+                .await
+                .is_err()
+        );
+
+        // Authorizations that are not captured can be released
+        let other = provider.authorize(&request).await.unwrap();
+        assert_eq!(
+            provider.void(&other).await.unwrap().status,
+            PaymentStatus::Voided
+        );
+        assert!(provider.capture(&other, Decimal::ONE).await.is_err());
+    }
+
+    #[test]
+    fn test_sandbox_cards() {
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 6, 15).unwrap();
+        let check = |card: &CreditCard| SandboxPaymentProvider::check_card(card, today);
+
+        assert!(luhn_valid(SANDBOX_DECLINED_CARD));
+        assert!(luhn_valid(SANDBOX_INSUFFICIENT_FUNDS_CARD));
+        assert!(luhn_valid(SANDBOX_PROVIDER_ERROR_CARD));
+        assert_eq!(check(&card("4111111111111111")), Ok(()));
+        assert!(matches!(
+            check(&card(SANDBOX_DECLINED_CARD)),
+            Err(PaymentError::Declined(_))
+        ));
+        assert!(matches!(
+            check(&card(SANDBOX_PROVIDER_ERROR_CARD)),
+            Err(PaymentError::Provider(_))
+        ));
+        assert!(matches!(
+            check(&card("4111111111111112")),
+            Err(PaymentError::Invalid(_))
+        ));
+
+        let mut expiring = card("4111111111111111");
+        expiring.expiry_date = Some("2025-06".to_string());
+        assert_eq!(check(&expiring), Ok(()));
+        expiring.expiry_date = Some("2025-05".to_string());
+        assert_eq!(
+            check(&expiring),
+            Err(PaymentError::Declined("Card expired".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_refund_from_estimate() {
+        let estimate = |ticketed: bool, refund: Option<&str>| RefundEstimate {
+            currency: "EUR".to_string(),
+            paid: "600.00".parse().unwrap(),
+            refundable_taxes: "150.00".parse().unwrap(),
+            refund: refund.map(|refund| refund.parse().unwrap()),
+            ticketed,
+            departed: false,
+            offers: Vec::new(),
+        };
+
+        assert_eq!(Refund::from(&estimate(false, Some("600.00"))), Refund::Full);
+        assert_eq!(
+            Refund::from(&estimate(true, Some("400.00"))),
+            Refund::Amount("400.00".parse().unwrap())
+        );
+        assert_eq!(Refund::from(&estimate(true, None)), Refund::Unknown);
+    }
+}
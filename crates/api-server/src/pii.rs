@@ -0,0 +1,503 @@
+//! Encryption of traveler PII at rest
+//!
+//! Stored bookings keep the travelers of the order request (`passenger_data`)
+//! and the Amadeus order (`booking_data`). Their birth dates, document
+//! numbers, phone numbers and e-mail addresses are encrypted field by field
+//! with envelope encryption: every booking has its own data key, the fields
+//! are encrypted with it (AES-256-GCM), and the data key is stored sealed by
+//! one of the key encryption keys of `PII_ENCRYPTION_KEYS`. Encrypted fields
+//! stay strings ("pii:v1:..."), so the documents keep their shape for code
+//! that only reads flights; code that needs the fields decrypts explicitly
+//! (see `BookingStore::reveal`).
+//!
+//! Keys are rotated by putting a new key first in `PII_ENCRYPTION_KEYS` and
+//! keeping the old ones behind it: new bookings use the first key, and a
+//! background job reseals the data keys of existing bookings with it (the
+//! fields themselves are not touched). Once it is done, old keys can go.
+//!
+//! Other documents holding traveler data for a limited time (responses stored
+//! for idempotency keys) are encrypted as a whole with their own data key (see
+//! `PiiKeys::seal_document`); they are not resealed, so old keys must be kept
+//! until those have expired.
+//!
+//! The server does not store bookings or idempotency responses without keys,
+//! unless `PII_ALLOW_UNENCRYPTED` is set for development.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use crate::bookings::BookingStore;
+use crate::redaction::Secret;
+
+/// Prefix of encrypted field values
+const SEALED_PREFIX: &str = "pii:v1:";
+
+/// AES-GCM nonce length
+const NONCE_LEN: usize = 12;
+
+/// Bookings resealed per batch by the rotation job
+pub const ROTATION_BATCH_SIZE: i64 = 100;
+
+/// Pause between rotation batches, to keep the database responsive
+const ROTATION_PAUSE: Duration = Duration::from_millis(200);
+
+/// PII fields of `passenger_data` (the travelers of the order request), as
+/// keys from the document root; `*` stands for every array element
+pub const PASSENGER_FIELDS: [&[&str]; 5] = [
+    &["*", "dateOfBirth"],
+    &["*", "documents", "*", "number"],
+    &["*", "documents", "*", "birthPlace"],
+    &["*", "contact", "phones", "*", "number"],
+    &["*", "contact", "emailAddress"],
+];
+
+/// PII fields of `booking_data` (the Amadeus order)
+pub const ORDER_FIELDS: [&[&str]; 8] = [
+    &["travelers", "*", "dateOfBirth"],
+    &["travelers", "*", "documents", "*", "number"],
+    &["travelers", "*", "documents", "*", "birthPlace"],
+    &["travelers", "*", "contact", "phones", "*", "number"],
+    &["travelers", "*", "contact", "emailAddress"],
+    &["contacts", "*", "phones", "*", "number"],
+    &["contacts", "*", "emailAddress"],
+    &["contacts", "*", "address", "lines", "*"],
+];
+
+/// Errors of PII encryption
+#[derive(Debug, Clone, PartialEq)]
+pub enum PiiError {
+    /// `PII_ENCRYPTION_KEYS` is malformed
+    InvalidKeys(String),
+    /// The data key is sealed by a key that is not configured
+    UnknownKey(String),
+    /// A sealed value could not be decrypted
+    Corrupt(String),
+}
+
+impl fmt::Display for PiiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PiiError::InvalidKeys(reason) => write!(f, "Invalid PII_ENCRYPTION_KEYS: {}", reason),
+            PiiError::UnknownKey(id) => write!(f, "PII encryption key {} is not configured", id),
+            PiiError::Corrupt(reason) => write!(f, "Cannot decrypt PII: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for PiiError {}
+
+fn cipher(key: &Secret<[u8; 32]>) -> Aes256Gcm {
+    Aes256Gcm::new(aes_gcm::Key::<Aes256Gcm>::from_slice(key.expose()))
+}
+
+/// Encrypt `plaintext` under `key` as base64 of nonce and ciphertext
+fn encrypt(key: &Secret<[u8; 32]>, plaintext: &[u8], aad: &[u8]) -> String {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher(key)
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .expect("AES-GCM encryption");
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    BASE64.encode(sealed)
+}
+
+fn decrypt(key: &Secret<[u8; 32]>, sealed: &str, aad: &[u8]) -> Result<Vec<u8>, PiiError> {
+    let sealed = BASE64
+        .decode(sealed)
+        .map_err(|e| PiiError::Corrupt(e.to_string()))?;
+    if sealed.len() <= NONCE_LEN {
+        return Err(PiiError::Corrupt("Sealed value too short".to_string()));
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    cipher(key)
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| PiiError::Corrupt("Authentication failed".to_string()))
+}
+
+/// A booking's data key, sealed by the key encryption key `key_id`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SealedKey {
+    pub key_id: String,
+    pub sealed: String,
+}
+
+/// The key encrypting the PII fields of one booking
+#[derive(Debug, Clone)]
+pub struct DataKey(Secret<[u8; 32]>);
+
+impl DataKey {
+    /// Encrypt the string values at `fields` that are not encrypted yet
+    pub fn seal(&self, document: &mut Value, fields: &[&[&str]]) {
+        for path in fields {
+            visit(document, path, &mut |value| {
+                if let Value::String(plain) = value
+                    && !plain.starts_with(SEALED_PREFIX)
+                {
+                    *plain = format!(
+                        "{}{}",
+                        SEALED_PREFIX,
+                        encrypt(&self.0, plain.as_bytes(), &[])
+                    );
+                }
+                Ok(())
+            })
+            .expect("sealing does not fail");
+        }
+    }
+
+    /// Decrypt the encrypted string values at `fields`
+    pub fn open(&self, document: &mut Value, fields: &[&[&str]]) -> Result<(), PiiError> {
+        for path in fields {
+            visit(document, path, &mut |value| {
+                if let Value::String(sealed) = value
+                    && let Some(ciphertext) = sealed.strip_prefix(SEALED_PREFIX)
+                {
+                    let plain = decrypt(&self.0, ciphertext, &[])?;
+                    *sealed = String::from_utf8(plain)
+                        .map_err(|_| PiiError::Corrupt("Not UTF-8".to_string()))?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Value replacing masked PII fields
+pub const MASKED: &str = "***";
+
+/// Replace the PII fields of a document, encrypted or not, by [`MASKED`]
+pub fn mask(document: &mut Value, fields: &[&[&str]]) {
+    for path in fields {
+        let _ = visit(document, path, &mut |value| {
+            if value.is_string() {
+                *value = Value::String(MASKED.to_string());
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Call `f` with every value at `path` (`*` for every array element)
+fn visit(
+    value: &mut Value,
+    path: &[&str],
+    f: &mut dyn FnMut(&mut Value) -> Result<(), PiiError>,
+) -> Result<(), PiiError> {
+    let Some((first, rest)) = path.split_first() else {
+        return f(value);
+    };
+    match (value, *first) {
+        (Value::Array(items), "*") => {
+            for item in items {
+                visit(item, rest, f)?;
+            }
+            Ok(())
+        }
+        (Value::Object(map), key) => match map.get_mut(key) {
+            Some(child) => visit(child, rest, f),
+            None => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Key encryption keys; the first one seals new data keys
+#[derive(Debug, Clone)]
+pub struct PiiKeys {
+    current: String,
+    keys: HashMap<String, Secret<[u8; 32]>>,
+}
+
+impl PiiKeys {
+    /// Parse "id:base64key,id:base64key", newest first. Keys are 32 random
+    /// bytes (e.g. `openssl rand -base64 32`).
+    pub fn parse(config: &str) -> Result<Self, PiiError> {
+        let mut current = None;
+        let mut keys = HashMap::new();
+        for entry in config
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let (id, key) = entry
+                .split_once(':')
+                .ok_or_else(|| PiiError::InvalidKeys("Expected id:base64key".to_string()))?;
+            let id = id.trim();
+            if id.is_empty() {
+                return Err(PiiError::InvalidKeys("Empty key id".to_string()));
+            }
+            let key: [u8; 32] = BASE64
+                .decode(key.trim())
+                .ok()
+                .and_then(|key| key.try_into().ok())
+                .ok_or_else(|| {
+                    PiiError::InvalidKeys(format!("Key {} is not 32 bytes of base64", id))
+                })?;
+            if keys.insert(id.to_string(), Secret::new(key)).is_some() {
+                return Err(PiiError::InvalidKeys(format!("Key {} given twice", id)));
+            }
+            current.get_or_insert_with(|| id.to_string());
+        }
+        match current {
+            Some(current) => Ok(PiiKeys { current, keys }),
+            None => Err(PiiError::InvalidKeys("No keys".to_string())),
+        }
+    }
+
+    /// Whether PII may be stored unencrypted when no keys are set
+    /// (`PII_ALLOW_UNENCRYPTED`, for development only)
+    pub fn unencrypted_allowed() -> bool {
+        std::env::var("PII_ALLOW_UNENCRYPTED")
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(false)
+    }
+
+    /// Keys of `PII_ENCRYPTION_KEYS`, `None` if it is not set
+    pub fn from_env() -> Result<Option<Self>, PiiError> {
+        match std::env::var("PII_ENCRYPTION_KEYS") {
+            Ok(config) if !config.trim().is_empty() => PiiKeys::parse(&config).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Id of the key sealing new data keys
+    pub fn current_key_id(&self) -> &str {
+        &self.current
+    }
+
+    /// A new data key and the same key sealed by the current key
+    pub fn new_data_key(&self) -> (DataKey, SealedKey) {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&Aes256Gcm::generate_key(&mut OsRng));
+        let sealed = encrypt(&self.keys[&self.current], &key, self.current.as_bytes());
+        (
+            DataKey(Secret::new(key)),
+            SealedKey {
+                key_id: self.current.clone(),
+                sealed,
+            },
+        )
+    }
+
+    /// Unseal a booking's data key
+    pub fn open(&self, sealed: &SealedKey) -> Result<DataKey, PiiError> {
+        let key = self
+            .keys
+            .get(&sealed.key_id)
+            .ok_or_else(|| PiiError::UnknownKey(sealed.key_id.clone()))?;
+        let key = decrypt(key, &sealed.sealed, sealed.key_id.as_bytes())?;
+        let key: [u8; 32] = key
+            .try_into()
+            .map_err(|_| PiiError::Corrupt("Data key is not 32 bytes".to_string()))?;
+        Ok(DataKey(Secret::new(key)))
+    }
+
+    /// Seal a data key by the current key, if another key sealed it
+    pub fn reseal(&self, sealed: &SealedKey) -> Result<Option<SealedKey>, PiiError> {
+        if sealed.key_id == self.current {
+            return Ok(None);
+        }
+        let key = self.open(sealed)?;
+        Ok(Some(SealedKey {
+            key_id: self.current.clone(),
+            sealed: encrypt(
+                &self.keys[&self.current],
+                key.0.expose(),
+                self.current.as_bytes(),
+            ),
+        }))
+    }
+
+    /// Encrypt a whole document with a new data key, as
+    /// "pii:v1:<key id>:<sealed data key>:<ciphertext>"
+    pub fn seal_document(&self, plaintext: &[u8]) -> Vec<u8> {
+        let (key, sealed) = self.new_data_key();
+        format!(
+            "{}{}:{}:{}",
+            SEALED_PREFIX,
+            sealed.key_id,
+            sealed.sealed,
+            encrypt(&key.0, plaintext, &[])
+        )
+        .into_bytes()
+    }
+
+    /// Decrypt a document of `seal_document`
+    pub fn open_document(&self, document: &[u8]) -> Result<Vec<u8>, PiiError> {
+        let corrupt = || PiiError::Corrupt("Not a sealed document".to_string());
+        let document = std::str::from_utf8(document)
+            .ok()
+            .and_then(|document| document.strip_prefix(SEALED_PREFIX))
+            .ok_or_else(corrupt)?;
+        let mut parts = document.splitn(3, ':');
+        let (Some(key_id), Some(sealed), Some(ciphertext)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(corrupt());
+        };
+        let key = self.open(&SealedKey {
+            key_id: key_id.to_string(),
+            sealed: sealed.to_string(),
+        })?;
+        decrypt(&key.0, ciphertext, &[])
+    }
+}
+
+/// Whether a document was encrypted by `PiiKeys::seal_document`
+pub fn is_sealed_document(document: &[u8]) -> bool {
+    document.starts_with(SEALED_PREFIX.as_bytes())
+}
+
+/// Reseal the data keys of all bookings by the current key and encrypt the
+/// PII of bookings stored before encryption was enabled, in batches
+pub fn spawn_rotation(store: BookingStore) {
+    tokio::spawn(async move {
+        let mut after = None;
+        let mut total = 0;
+        loop {
+            match store.rotate_pii_keys(after, ROTATION_BATCH_SIZE).await {
+                Ok(batch) => {
+                    total += batch.rotated;
+                    match batch.last_id {
+                        Some(last_id) => after = Some(last_id),
+                        None => break,
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("PII key rotation stopped: {}", e);
+                    return;
+                }
+            }
+            tokio::time::sleep(ROTATION_PAUSE).await;
+        }
+        if total > 0 {
+            tracing::info!("Resealed the PII of {} bookings", total);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_1: &str = "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=";
+    const KEY_2: &str = "ZmVkY2JhOTg3NjU0MzIxMGZlZGNiYTk4NzY1NDMyMTA=";
+
+    #[test]
+    fn test_seal_and_open_fields() {
+        let keys = PiiKeys::parse(&format!("k1:{}", KEY_1)).unwrap();
+        let (key, sealed) = keys.new_data_key();
+        let travelers = serde_json::json!([{
+            "id": "1",
+            "dateOfBirth": "1982-01-16",
+            "name": { "firstName": "JORGE", "lastName": "GONZALES" },
+            "documents": [{ "documentType": "PASSPORT", "number": "00000000" }],
+            "contact": { "phones": [{ "countryCallingCode": "34", "number": "480080076" }] }
+        }]);
+
+        let mut document = travelers.clone();
+        key.seal(&mut document, &PASSENGER_FIELDS);
+        let text = document.to_string();
+        for secret in ["1982-01-16", "00000000", "480080076"] {
+            assert!(!text.contains(secret), "{}", secret);
+        }
+        assert_eq!(document[0]["name"]["lastName"], "GONZALES");
+        assert!(
+            document[0]["dateOfBirth"]
+                .as_str()
+                .unwrap()
+                .starts_with(SEALED_PREFIX)
+        );
+
+        // Sealing twice leaves sealed values alone
+        let once = document.clone();
+        key.seal(&mut document, &PASSENGER_FIELDS);
+        assert_eq!(document, once);
+
+        let mut masked = document.clone();
+        mask(&mut masked, &PASSENGER_FIELDS);
+        assert_eq!(masked[0]["dateOfBirth"], MASKED);
+        assert_eq!(masked[0]["name"]["lastName"], "GONZALES");
+
+        let key = keys.open(&sealed).unwrap();
+        key.open(&mut document, &PASSENGER_FIELDS).unwrap();
+        assert_eq!(document, travelers);
+
+        // Another booking's key cannot open them
+        let (other, _) = keys.new_data_key();
+        assert!(matches!(
+            other.open(&mut once.clone(), &PASSENGER_FIELDS),
+            Err(PiiError::Corrupt(_))
+        ));
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let old = PiiKeys::parse(&format!("k1:{}", KEY_1)).unwrap();
+        let (key, sealed) = old.new_data_key();
+        let mut document = serde_json::json!({ "travelers": [{ "dateOfBirth": "1982-01-16" }] });
+        key.seal(&mut document, &ORDER_FIELDS);
+
+        let rotated = PiiKeys::parse(&format!("k2:{}, k1:{}", KEY_2, KEY_1)).unwrap();
+        assert_eq!(rotated.current_key_id(), "k2");
+        let resealed = rotated.reseal(&sealed).unwrap().unwrap();
+        assert_eq!(resealed.key_id, "k2");
+        assert_eq!(rotated.reseal(&resealed).unwrap(), None);
+
+        // Once resealed, the old key is no longer needed
+        let new = PiiKeys::parse(&format!("k2:{}", KEY_2)).unwrap();
+        assert_eq!(
+            new.open(&sealed).err(),
+            Some(PiiError::UnknownKey("k1".to_string()))
+        );
+        new.open(&resealed)
+            .unwrap()
+            .open(&mut document, &ORDER_FIELDS)
+            .unwrap();
+        assert_eq!(document["travelers"][0]["dateOfBirth"], "1982-01-16");
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert!(PiiKeys::parse("").is_err());
+        assert!(PiiKeys::parse("k1").is_err());
+        assert!(PiiKeys::parse("k1:c2hvcnQ=").is_err());
+        assert!(PiiKeys::parse(&format!("k1:{},k1:{}", KEY_1, KEY_2)).is_err());
+    }
+
+    #[test]
+    fn test_seal_and_open_document() {
+        let keys = PiiKeys::parse(&format!("k1:{}", KEY_1)).unwrap();
+        let body = br#"{"travelers":[{"dateOfBirth":"1982-01-16"}]}"#;
+        let sealed = keys.seal_document(body);
+        assert!(is_sealed_document(&sealed));
+        assert!(!is_sealed_document(body));
+        assert!(!String::from_utf8_lossy(&sealed).contains("1982-01-16"));
+        assert_eq!(keys.open_document(&sealed).unwrap(), body);
+
+        // Still readable after rotating as long as the old key is kept
+        let rotated = PiiKeys::parse(&format!("k2:{},k1:{}", KEY_2, KEY_1)).unwrap();
+        assert_eq!(rotated.open_document(&sealed).unwrap(), body);
+        assert!(rotated.open_document(body).is_err());
+    }
+}
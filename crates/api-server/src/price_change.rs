@@ -0,0 +1,396 @@
+//! Price change reports
+//!
+//! The price confirmed by Flight Offers Price often differs from the price
+//! shown in search. We compare the offer the client sent for pricing with the
+//! priced offer (both including our markups) and report what changed, so the
+//! client does not have to diff the offers itself. Offers priced with new bags
+//! or seats are compared with the requested offer plus the prices the catalog
+//! and seatmap gave for them, so the services alone are no price change.
+//! Orders are only placed
+//! without re-confirmation if the re-priced offers stay within the tolerance of
+//! the offers the client confirmed.
+
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+use crate::models::{
+    FlightOffer, FlightPriceResponse, PriceChangeReport, SegmentFareChange, TaxChange,
+    TravelerPriceChange,
+};
+use crate::money::Money;
+
+/// How much a price may rise before the client has to re-confirm it.
+/// An increase is accepted if it stays within either limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PriceChangeTolerance {
+    /// Absolute amount in the offer currency
+    pub amount: Decimal,
+    /// Percentage of the old total
+    pub percent: Decimal,
+}
+
+impl PriceChangeTolerance {
+    /// Read PRICE_CHANGE_TOLERANCE_AMOUNT and PRICE_CHANGE_TOLERANCE_PERCENT
+    /// (both default to 0, i.e. every increase needs re-confirmation)
+    pub fn from_env() -> Self {
+        let read = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.trim().parse::<Decimal>().ok())
+                .filter(|value| !value.is_sign_negative())
+                .unwrap_or_default()
+        };
+        Self {
+            amount: read("PRICE_CHANGE_TOLERANCE_AMOUNT"),
+            percent: read("PRICE_CHANGE_TOLERANCE_PERCENT"),
+        }
+    }
+
+    fn accepts(&self, old_total: Decimal, difference: Decimal) -> bool {
+        difference <= self.amount || difference <= old_total * self.percent / Decimal::ONE_HUNDRED
+    }
+}
+
+/// Sum of each tax code over all travelers
+fn taxes_by_code(offer: &FlightOffer) -> BTreeMap<&str, Decimal> {
+    let mut taxes = BTreeMap::new();
+    for tax in offer
+        .traveler_pricings
+        .iter()
+        .flat_map(|pricing| &pricing.price.taxes)
+    {
+        *taxes.entry(tax.code.as_str()).or_default() += tax.amount;
+    }
+    taxes
+}
+
+fn traveler_changes(old: &FlightOffer, new: &FlightOffer) -> Vec<TravelerPriceChange> {
+    let mut changes: Vec<TravelerPriceChange> = new
+        .traveler_pricings
+        .iter()
+        .map(|pricing| {
+            let old_total = old
+                .traveler_pricings
+                .iter()
+                .find(|old| old.traveler_id == pricing.traveler_id)
+                .filter(|old| old.price.currency == pricing.price.currency)
+                .map(|old| old.price.total);
+            TravelerPriceChange {
+                traveler_id: pricing.traveler_id.clone(),
+                old_total,
+                new_total: Some(pricing.price.total),
+                difference: old_total.map(|old_total| pricing.price.total - old_total),
+            }
+        })
+        .collect();
+
+    // Travelers that pricing dropped
+    changes.extend(
+        old.traveler_pricings
+            .iter()
+            .filter(|old| {
+                !new.traveler_pricings
+                    .iter()
+                    .any(|new| new.traveler_id == old.traveler_id)
+            })
+            .map(|old| TravelerPriceChange {
+                traveler_id: old.traveler_id.clone(),
+                old_total: Some(old.price.total),
+                new_total: None,
+                difference: None,
+            }),
+    );
+    changes
+}
+
+fn tax_changes(old: &FlightOffer, new: &FlightOffer) -> Vec<TaxChange> {
+    let old_taxes = taxes_by_code(old);
+    let new_taxes = taxes_by_code(new);
+    let mut codes: Vec<&str> = old_taxes.keys().chain(new_taxes.keys()).copied().collect();
+    codes.sort_unstable();
+    codes.dedup();
+
+    codes
+        .into_iter()
+        .filter_map(|code| {
+            let old_amount = old_taxes.get(code).copied().unwrap_or_default();
+            let new_amount = new_taxes.get(code).copied().unwrap_or_default();
+            let difference = new_amount - old_amount;
+            (!difference.is_zero()).then(|| TaxChange {
+                code: code.to_string(),
+                old_amount,
+                new_amount,
+                difference,
+            })
+        })
+        .collect()
+}
+
+fn fare_changes(old: &FlightOffer, new: &FlightOffer) -> Vec<SegmentFareChange> {
+    let mut changes = Vec::new();
+    for pricing in &new.traveler_pricings {
+        let old_pricing = old
+            .traveler_pricings
+            .iter()
+            .find(|old| old.traveler_id == pricing.traveler_id);
+        for details in &pricing.fare_details_by_segment {
+            let old_details = old_pricing.and_then(|old| {
+                old.fare_details_by_segment
+                    .iter()
+                    .find(|old| old.segment_id == details.segment_id)
+            });
+            let unchanged = old_details.is_some_and(|old| {
+                old.fare_basis == details.fare_basis && old.booking_class == details.booking_class
+            });
+            if !unchanged {
+                changes.push(SegmentFareChange {
+                    traveler_id: pricing.traveler_id.clone(),
+                    segment_id: details.segment_id.clone(),
+                    old_fare_basis: old_details.map(|old| old.fare_basis.clone()),
+                    new_fare_basis: details.fare_basis.clone(),
+                    old_booking_class: old_details.map(|old| old.booking_class.clone()),
+                    new_booking_class: details.booking_class.clone(),
+                });
+            }
+        }
+    }
+    changes
+}
+
+/// Compare an offer as searched with the same offer as priced
+pub fn compare(
+    old: &FlightOffer,
+    new: &FlightOffer,
+    tolerance: &PriceChangeTolerance,
+) -> PriceChangeReport {
+    let old_total = old.price.grand_total_money();
+    let new_total = new.price.grand_total_money();
+    let difference = new_total
+        .checked_sub(&old_total)
+        .ok()
+        .map(|difference| difference.amount);
+    let difference_percent = difference
+        .filter(|_| !old_total.is_zero())
+        .map(|difference| (difference * Decimal::ONE_HUNDRED / old_total.amount).round_dp(2));
+
+    let fares = fare_changes(old, new);
+    let fare_changed = !fares.is_empty();
+    let requires_reconfirmation = match difference {
+        Some(difference) => fare_changed || !tolerance.accepts(old_total.amount, difference),
+        // Currency changed, the totals cannot be compared
+        None => true,
+    };
+
+    PriceChangeReport {
+        offer_id: new.id.clone(),
+        old_currency: old_total.currency,
+        currency: new_total.currency,
+        old_total: old_total.amount,
+        new_total: new_total.amount,
+        difference,
+        difference_percent,
+        price_changed: difference.is_none_or(|difference| !difference.is_zero()),
+        fare_changed,
+        requires_reconfirmation,
+        travelers: traveler_changes(old, new),
+        taxes: tax_changes(old, new),
+        fares,
+    }
+}
+
+/// The offer with the prices of added services (bags, seats) on its grand
+/// total, to compare it with the offer priced with the services. Services in
+/// another currency cannot be added and show up as a change.
+pub fn with_services(offer: &FlightOffer, services: &[Money]) -> FlightOffer {
+    let mut offer = offer.clone();
+    let mut total = offer.price.grand_total_money();
+    for service in services {
+        match total.checked_add(service) {
+            Ok(sum) => total = sum,
+            Err(e) => tracing::warn!("Service price not added to offer {}: {}", offer.id, e),
+        }
+    }
+    offer.price.grand_total = Some(total.amount);
+    offer
+}
+
+/// A report for every priced offer that matches a requested offer by id
+pub fn reports(
+    requested: &[FlightOffer],
+    priced: &[FlightOffer],
+    tolerance: &PriceChangeTolerance,
+) -> Vec<PriceChangeReport> {
+    priced
+        .iter()
+        .filter_map(|priced| {
+            requested
+                .iter()
+                .find(|offer| offer.id == priced.id)
+                .map(|offer| compare(offer, priced, tolerance))
+        })
+        .collect()
+}
+
+/// Add a report for every priced offer that matches a requested offer by id
+pub fn attach_reports(
+    resp: &mut FlightPriceResponse,
+    requested: &[FlightOffer],
+    tolerance: &PriceChangeTolerance,
+) {
+    resp.price_changes = reports(requested, &resp.data.flight_offers, tolerance);
+}
+
+/// Reports of the priced offers the client has to re-confirm before ordering.
+/// Requested offers that pricing did not return count as changed.
+pub fn reconfirmations(
+    requested: &[FlightOffer],
+    priced: &[FlightOffer],
+    tolerance: &PriceChangeTolerance,
+) -> Option<Vec<PriceChangeReport>> {
+    let reports = reports(requested, priced, tolerance);
+    let missing = requested
+        .iter()
+        .any(|offer| !priced.iter().any(|priced| priced.id == offer.id));
+    (missing || reports.iter().any(|report| report.requires_reconfirmation)).then_some(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn offer(total: &str, yq: &str, fare_basis: &str) -> FlightOffer {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "type": "flight-offer",
+            "source": "GDS",
+            "itineraries": [],
+            "price": { "currency": "EUR", "total": total, "base": "200.00", "grandTotal": total },
+            "validatingAirlineCodes": ["LH"],
+            "travelerPricings": [{
+                "travelerId": "1",
+                "fareOption": "STANDARD",
+                "travelerType": "ADULT",
+                "price": {
+                    "currency": "EUR",
+                    "total": total,
+                    "base": "200.00",
+                    "taxes": [{ "amount": yq, "code": "YQ" }, { "amount": "20.00", "code": "DE" }]
+                },
+                "fareDetailsBySegment": [{
+                    "segmentId": "1",
+                    "cabin": "ECONOMY",
+                    "fareBasis": fare_basis,
+                    "class": "Y"
+                }]
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_unchanged_offer() {
+        let report = compare(
+            &offer("300.00", "80.00", "YOWEU"),
+            &offer("300.00", "80.00", "YOWEU"),
+            &PriceChangeTolerance::default(),
+        );
+        assert!(!report.price_changed);
+        assert!(!report.fare_changed);
+        assert!(!report.requires_reconfirmation);
+        assert_eq!(report.difference, Some(Decimal::ZERO));
+        assert!(report.taxes.is_empty());
+    }
+
+    #[test]
+    fn test_price_increase_and_tolerance() {
+        let old = offer("300.00", "80.00", "YOWEU");
+        let new = offer("306.00", "86.00", "YOWEU");
+
+        let report = compare(&old, &new, &PriceChangeTolerance::default());
+        assert_eq!(report.difference, Some(dec("6.00")));
+        assert_eq!(report.difference_percent, Some(dec("2.00")));
+        assert!(report.requires_reconfirmation);
+        assert_eq!(report.travelers[0].difference, Some(dec("6.00")));
+        assert_eq!(report.taxes.len(), 1);
+        assert_eq!(report.taxes[0].code, "YQ");
+        assert_eq!(report.taxes[0].difference, dec("6.00"));
+
+        let by_amount = PriceChangeTolerance {
+            amount: dec("10"),
+            percent: Decimal::ZERO,
+        };
+        assert!(!compare(&old, &new, &by_amount).requires_reconfirmation);
+
+        let by_percent = PriceChangeTolerance {
+            amount: Decimal::ZERO,
+            percent: dec("1.5"),
+        };
+        assert!(compare(&old, &new, &by_percent).requires_reconfirmation);
+
+        // Price drops never need re-confirmation
+        assert!(!compare(&new, &old, &PriceChangeTolerance::default()).requires_reconfirmation);
+    }
+
+    #[test]
+    fn test_fare_basis_change_requires_reconfirmation() {
+        let report = compare(
+            &offer("300.00", "80.00", "YOWEU"),
+            &offer("300.00", "80.00", "MOWEU"),
+            &PriceChangeTolerance::default(),
+        );
+        assert!(!report.price_changed);
+        assert!(report.fare_changed);
+        assert!(report.requires_reconfirmation);
+        assert_eq!(report.fares[0].old_fare_basis.as_deref(), Some("YOWEU"));
+        assert_eq!(report.fares[0].new_fare_basis, "MOWEU");
+    }
+
+    #[test]
+    fn test_added_services_are_no_price_change() {
+        let requested = offer("300.00", "80.00", "YOWEU");
+        let mut priced = offer("300.00", "80.00", "YOWEU");
+        priced.price.grand_total = Some(dec("355.00"));
+        let services = [
+            Money::new(dec("30.00"), "EUR".to_string()),
+            Money::new(dec("25.00"), "EUR".to_string()),
+        ];
+
+        let tolerance = PriceChangeTolerance::default();
+        assert!(compare(&requested, &priced, &tolerance).requires_reconfirmation);
+        let report = compare(&with_services(&requested, &services), &priced, &tolerance);
+        assert!(!report.price_changed);
+        assert!(!report.requires_reconfirmation);
+
+        // A fare increase on top of the services still counts
+        priced.price.grand_total = Some(dec("365.00"));
+        let report = compare(&with_services(&requested, &services), &priced, &tolerance);
+        assert_eq!(report.difference, Some(dec("10.00")));
+        assert!(report.requires_reconfirmation);
+    }
+
+    #[test]
+    fn test_reconfirmations() {
+        let tolerance = PriceChangeTolerance {
+            amount: dec("5"),
+            percent: Decimal::ZERO,
+        };
+        let confirmed = [offer("300.00", "80.00", "YOWEU")];
+
+        let within = [offer("304.00", "84.00", "YOWEU")];
+        assert!(reconfirmations(&confirmed, &within, &tolerance).is_none());
+
+        let increased = [offer("306.00", "86.00", "YOWEU")];
+        let reports = reconfirmations(&confirmed, &increased, &tolerance).unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].difference, Some(dec("6.00")));
+
+        // An offer pricing dropped cannot be ordered as confirmed
+        let reports = reconfirmations(&confirmed, &[], &tolerance).unwrap();
+        assert!(reports.is_empty());
+    }
+}
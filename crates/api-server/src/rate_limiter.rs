@@ -73,8 +73,9 @@ mod tests {
 
         let elapsed = start.elapsed();
 
-        // Should take approximately 1000ms (10 requests at 100ms each)
-        assert!(elapsed.as_millis() >= 950);
+        // Should take approximately 900ms (the first request passes immediately,
+        // the remaining 9 are spaced 100ms apart)
+        assert!(elapsed.as_millis() >= 900);
         assert!(elapsed.as_millis() <= 1100); // Allow some tolerance
     }
 }
@@ -0,0 +1,559 @@
+//! Order reconciliation
+//!
+//! Airlines change orders after they were created: flights are retimed or
+//! replaced, segments are waitlisted or cancelled, and travelers are edited
+//! through other channels. A background job fetches the live order of every
+//! active booking, compares it with the order data stored with the booking
+//! and records each difference in `booking_changes`. Changed bookings produce
+//! a notification, and orders Amadeus no longer knows are marked CANCELLED.
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::amadeus;
+use crate::bookings::{BookingRecord, BookingStatus, BookingStore, BookingUpdate};
+use crate::models::{FlightOrderData, Segment, SegmentBookingStatus, Traveler};
+use crate::notifications::{Notification, Notifier};
+
+/// How often active bookings are reconciled (1 hour)
+pub const DEFAULT_INTERVAL_SECS: u64 = 3600;
+
+/// Kind of a difference between the stored and the live order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderChangeKind {
+    /// Flight number, airports or times of a segment changed
+    ScheduleChange,
+    /// Booking status of a segment changed (e.g. waitlisted or cancelled)
+    SegmentStatus,
+    SegmentAdded,
+    SegmentRemoved,
+    /// Traveler added, removed or edited
+    TravelerChange,
+    /// A retimed flight leaves less than the minimum connection time (found
+    /// by the schedule monitor, see `schedule_changes`)
+    MissedConnection,
+    /// The whole order was cancelled
+    Cancelled,
+}
+
+impl OrderChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderChangeKind::ScheduleChange => "SCHEDULE_CHANGE",
+            OrderChangeKind::SegmentStatus => "SEGMENT_STATUS",
+            OrderChangeKind::SegmentAdded => "SEGMENT_ADDED",
+            OrderChangeKind::SegmentRemoved => "SEGMENT_REMOVED",
+            OrderChangeKind::TravelerChange => "TRAVELER_CHANGE",
+            OrderChangeKind::MissedConnection => "MISSED_CONNECTION",
+            OrderChangeKind::Cancelled => "CANCELLED",
+        }
+    }
+}
+
+/// A difference between the stored and the live order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderChange {
+    pub kind: OrderChangeKind,
+    pub segment_id: Option<String>,
+    pub traveler_id: Option<String>,
+    pub description: String,
+    pub previous: Option<String>,
+    pub current: Option<String>,
+}
+
+impl OrderChange {
+    fn segment(
+        kind: OrderChangeKind,
+        segment: &Segment,
+        description: String,
+        previous: Option<String>,
+        current: Option<String>,
+    ) -> Self {
+        Self {
+            kind,
+            segment_id: Some(segment.id.clone()),
+            traveler_id: None,
+            description,
+            previous,
+            current,
+        }
+    }
+
+    fn traveler(
+        id: &str,
+        description: String,
+        previous: Option<String>,
+        current: Option<String>,
+    ) -> Self {
+        Self {
+            kind: OrderChangeKind::TravelerChange,
+            segment_id: None,
+            traveler_id: Some(id.to_string()),
+            description,
+            previous,
+            current,
+        }
+    }
+
+    fn cancelled(description: impl Into<String>) -> Self {
+        Self {
+            kind: OrderChangeKind::Cancelled,
+            segment_id: None,
+            traveler_id: None,
+            description: description.into(),
+            previous: None,
+            current: None,
+        }
+    }
+}
+
+/// Segments of all offers of an order by id
+fn segments(order: &FlightOrderData) -> BTreeMap<&str, &Segment> {
+    order
+        .flight_offers
+        .iter()
+        .flat_map(|offer| &offer.itineraries)
+        .flat_map(|itinerary| &itinerary.segments)
+        .map(|segment| (segment.id.as_str(), segment))
+        .collect()
+}
+
+/// "LH400 FRA-JFK"
+pub fn flight(segment: &Segment) -> String {
+    format!(
+        "{}{} {}-{}",
+        segment.carrier_code,
+        segment.number,
+        segment.departure.iata_code,
+        segment.arrival.iata_code
+    )
+}
+
+/// "LH400 FRA 2025-06-01T10:15:00 JFK 2025-06-01T13:00:00"
+fn schedule(segment: &Segment) -> String {
+    format!(
+        "{}{} {} {} {} {}",
+        segment.carrier_code,
+        segment.number,
+        segment.departure.iata_code,
+        segment.departure.at,
+        segment.arrival.iata_code,
+        segment.arrival.at
+    )
+}
+
+/// Travelers of an order by id
+fn travelers(order: &FlightOrderData) -> BTreeMap<&str, &Traveler> {
+    order
+        .travelers
+        .iter()
+        .map(|traveler| (traveler.id.as_str(), traveler))
+        .collect()
+}
+
+fn segment_status(segment: &Segment) -> Option<String> {
+    segment.booking_status.as_ref().map(ToString::to_string)
+}
+
+fn traveler_name(traveler: &Traveler) -> String {
+    format!("{} {}", traveler.name.first_name, traveler.name.last_name)
+}
+
+/// Documents as "PASSPORT 00000000 2030-01-01", sorted
+fn traveler_documents(traveler: &Traveler) -> String {
+    let mut documents: Vec<String> = traveler
+        .documents
+        .iter()
+        .flatten()
+        .map(|document| {
+            format!(
+                "{} {} {}",
+                document.document_type, document.number, document.expiry_date
+            )
+        })
+        .collect();
+    documents.sort();
+    documents.join(", ")
+}
+
+fn diff_segments(stored: &FlightOrderData, live: &FlightOrderData, changes: &mut Vec<OrderChange>) {
+    let stored_segments = segments(stored);
+    let live_segments = segments(live);
+
+    for (id, before) in &stored_segments {
+        let Some(after) = live_segments.get(id) else {
+            changes.push(OrderChange::segment(
+                OrderChangeKind::SegmentRemoved,
+                before,
+                format!("Segment {} was removed", flight(before)),
+                Some(schedule(before)),
+                None,
+            ));
+            continue;
+        };
+        if schedule(before) != schedule(after) {
+            changes.push(OrderChange::segment(
+                OrderChangeKind::ScheduleChange,
+                after,
+                format!("Schedule of {} changed", flight(before)),
+                Some(schedule(before)),
+                Some(schedule(after)),
+            ));
+        }
+        if segment_status(before) != segment_status(after) {
+            changes.push(OrderChange::segment(
+                OrderChangeKind::SegmentStatus,
+                after,
+                format!(
+                    "{} is now {}",
+                    flight(after),
+                    segment_status(after).as_deref().unwrap_or("without status")
+                ),
+                segment_status(before),
+                segment_status(after),
+            ));
+        }
+    }
+    for (id, after) in &live_segments {
+        if !stored_segments.contains_key(id) {
+            changes.push(OrderChange::segment(
+                OrderChangeKind::SegmentAdded,
+                after,
+                format!("Segment {} was added", flight(after)),
+                None,
+                Some(schedule(after)),
+            ));
+        }
+    }
+
+    let all_cancelled = !live_segments.is_empty()
+        && live_segments
+            .values()
+            .all(|segment| segment.booking_status == Some(SegmentBookingStatus::Cancelled));
+    if all_cancelled {
+        changes.push(OrderChange::cancelled("All segments were cancelled"));
+    }
+}
+
+fn diff_travelers(
+    stored: &FlightOrderData,
+    live: &FlightOrderData,
+    changes: &mut Vec<OrderChange>,
+) {
+    let stored_travelers = travelers(stored);
+    let live_travelers = travelers(live);
+
+    for (id, before) in &stored_travelers {
+        let Some(after) = live_travelers.get(id) else {
+            changes.push(OrderChange::traveler(
+                id,
+                format!("Traveler {} was removed", traveler_name(before)),
+                Some(traveler_name(before)),
+                None,
+            ));
+            continue;
+        };
+        // Birth dates and document numbers are PII: the change is recorded
+        // without the values, as changes are stored and sent unencrypted
+        let fields = [
+            ("Name", traveler_name(before), traveler_name(after), true),
+            (
+                "Date of birth",
+                before.date_of_birth.clone(),
+                after.date_of_birth.clone(),
+                false,
+            ),
+            (
+                "Documents",
+                traveler_documents(before),
+                traveler_documents(after),
+                false,
+            ),
+        ];
+        for (field, previous, current, with_values) in fields {
+            if previous != current {
+                changes.push(OrderChange::traveler(
+                    id,
+                    format!("{} of traveler {} changed", field, traveler_name(before)),
+                    Some(previous).filter(|_| with_values),
+                    Some(current).filter(|_| with_values),
+                ));
+            }
+        }
+    }
+    for (id, after) in &live_travelers {
+        if !stored_travelers.contains_key(id) {
+            changes.push(OrderChange::traveler(
+                id,
+                format!("Traveler {} was added", traveler_name(after)),
+                None,
+                Some(traveler_name(after)),
+            ));
+        }
+    }
+}
+
+/// Differences between the stored order and the live one
+pub fn diff_orders(stored: &FlightOrderData, live: &FlightOrderData) -> Vec<OrderChange> {
+    let mut changes = Vec::new();
+    diff_segments(stored, live, &mut changes);
+    diff_travelers(stored, live, &mut changes);
+    changes
+}
+
+/// Compare one booking with its live order and record what changed
+async fn reconcile_booking(
+    store: &BookingStore,
+    client: &reqwest::Client,
+    token: &str,
+    notifier: &Notifier,
+    booking: &BookingRecord,
+) -> anyhow::Result<()> {
+    let order_id = booking
+        .order_id
+        .as_deref()
+        .context("Booking has no order")?;
+    let mut revealed = booking.clone();
+    store.reveal(&mut revealed)?;
+    let stored: Option<FlightOrderData> = revealed
+        .booking_data
+        .and_then(|data| serde_json::from_value(data).ok());
+
+    let Some(live) = amadeus::find_flight_order(client, token, order_id).await? else {
+        let changes = vec![OrderChange::cancelled("Order no longer exists at Amadeus")];
+        store.record_changes(booking.id, &changes, None).await?;
+        store
+            .transition(
+                booking.id,
+                BookingStatus::Cancelled,
+                BookingUpdate::reason("Order no longer exists at Amadeus"),
+            )
+            .await?;
+        notifier
+            .send(&Notification::new(booking, "Order was cancelled", changes))
+            .await;
+        return Ok(());
+    };
+
+    if booking.status == BookingStatus::Ordered && !live.data.tickets.is_empty() {
+        store
+            .transition(
+                booking.id,
+                BookingStatus::Ticketed,
+                BookingUpdate::from_order(&live),
+            )
+            .await?;
+    }
+
+    let live_data = serde_json::to_value(&live.data)?;
+    let Some(stored) = stored else {
+        // Nothing to compare with yet; the live order is the baseline from now on
+        store
+            .record_changes(booking.id, &[], Some(&live_data))
+            .await?;
+        return Ok(());
+    };
+    let changes = diff_orders(&stored, &live.data);
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    store
+        .record_changes(booking.id, &changes, Some(&live_data))
+        .await?;
+    let cancelled = changes
+        .iter()
+        .find(|change| change.kind == OrderChangeKind::Cancelled);
+    if let Some(cancelled) = cancelled {
+        store
+            .transition(
+                booking.id,
+                BookingStatus::Cancelled,
+                BookingUpdate::reason(cancelled.description.clone()),
+            )
+            .await?;
+    }
+    let subject = match cancelled {
+        Some(_) => "Order was cancelled".to_string(),
+        None => format!("Order changed ({} changes)", changes.len()),
+    };
+    notifier
+        .send(&Notification::new(booking, subject, changes))
+        .await;
+    Ok(())
+}
+
+/// Reconcile all active bookings with Amadeus
+pub async fn reconcile(store: &BookingStore, client: &reqwest::Client, notifier: &Notifier) {
+    let bookings = match store.active_orders().await {
+        Ok(bookings) => bookings,
+        Err(e) => {
+            tracing::error!("Failed to load active bookings: {}", e);
+            return;
+        }
+    };
+    if bookings.is_empty() {
+        return;
+    }
+    let token = match amadeus::get_token(client).await {
+        Ok(token) => token,
+        Err(e) => {
+            tracing::error!("Amadeus token error: {:?}", e);
+            return;
+        }
+    };
+
+    for booking in &bookings {
+        if let Err(e) = reconcile_booking(store, client, &token, notifier, booking).await {
+            tracing::error!("Failed to reconcile booking {}: {:#}", booking.id, e);
+        }
+    }
+}
+
+/// Reconcile active bookings in the background
+pub fn spawn(
+    store: BookingStore,
+    client: reqwest::Client,
+    notifier: Notifier,
+    interval: std::time::Duration,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            reconcile(&store, &client, &notifier).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(segments: serde_json::Value, travelers: serde_json::Value) -> FlightOrderData {
+        serde_json::from_value(serde_json::json!({
+            "type": "flight-order",
+            "id": "eJzTd9f3NjIJdzUGAAp%2fAiY=",
+            "associatedRecords": [],
+            "travelers": travelers,
+            "flightOffers": [{
+                "id": "1",
+                "type": "flight-offer",
+                "source": "GDS",
+                "itineraries": [{ "segments": segments }],
+                "price": { "currency": "EUR", "total": "300.00", "base": "240.00" },
+                "validatingAirlineCodes": ["LH"],
+                "travelerPricings": []
+            }]
+        }))
+        .unwrap()
+    }
+
+    fn segment(id: &str, number: &str, departure: &str, status: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "departure": { "iataCode": "FRA", "at": departure },
+            "arrival": { "iataCode": "JFK", "at": "2025-06-01T13:00:00" },
+            "carrierCode": "LH",
+            "number": number,
+            "aircraft": { "code": "388" },
+            "bookingStatus": status
+        })
+    }
+
+    fn traveler(id: &str, last_name: &str, passport: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "dateOfBirth": "1980-01-01",
+            "name": { "firstName": "JANE", "lastName": last_name },
+            "documents": [{
+                "documentType": "PASSPORT",
+                "number": passport,
+                "expiryDate": "2030-01-01",
+                "issuanceCountry": "DE",
+                "nationality": "DE"
+            }]
+        })
+    }
+
+    #[test]
+    fn test_diff_orders() {
+        let stored = order(
+            serde_json::json!([
+                segment("1", "400", "2025-06-01T10:15:00", "CONFIRMED"),
+                segment("2", "402", "2025-06-01T18:00:00", "CONFIRMED")
+            ]),
+            serde_json::json!([
+                traveler("1", "DOE", "C01X00T47"),
+                traveler("2", "ROE", "C01X00T48")
+            ]),
+        );
+        assert!(diff_orders(&stored, &stored).is_empty());
+
+        let live = order(
+            serde_json::json!([
+                segment("1", "400", "2025-06-01T11:00:00", "WAITLISTED"),
+                segment("3", "404", "2025-06-01T20:00:00", "CONFIRMED")
+            ]),
+            serde_json::json!([
+                traveler("1", "SMITH", "C01X00T47"),
+                traveler("3", "POE", "C01X00T49")
+            ]),
+        );
+        let changes = diff_orders(&stored, &live);
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|change| {
+                (
+                    change.kind,
+                    change
+                        .segment_id
+                        .as_deref()
+                        .or(change.traveler_id.as_deref()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (OrderChangeKind::ScheduleChange, Some("1")),
+                (OrderChangeKind::SegmentStatus, Some("1")),
+                (OrderChangeKind::SegmentRemoved, Some("2")),
+                (OrderChangeKind::SegmentAdded, Some("3")),
+                (OrderChangeKind::TravelerChange, Some("1")),
+                (OrderChangeKind::TravelerChange, Some("2")),
+                (OrderChangeKind::TravelerChange, Some("3")),
+            ]
+        );
+        assert_eq!(
+            changes[0].current.as_deref(),
+            Some("LH400 FRA 2025-06-01T11:00:00 JFK 2025-06-01T13:00:00")
+        );
+        assert_eq!(changes[1].description, "LH400 FRA-JFK is now WAITLISTED");
+        assert_eq!(changes[4].description, "Name of traveler JANE DOE changed");
+    }
+
+    #[test]
+    fn test_all_segments_cancelled() {
+        let travelers = serde_json::json!([traveler("1", "DOE", "C01X00T47")]);
+        let stored = order(
+            serde_json::json!([segment("1", "400", "2025-06-01T10:15:00", "CONFIRMED")]),
+            travelers.clone(),
+        );
+        let live = order(
+            serde_json::json!([segment("1", "400", "2025-06-01T10:15:00", "CANCELLED")]),
+            travelers,
+        );
+        let kinds: Vec<_> = diff_orders(&stored, &live)
+            .iter()
+            .map(|change| change.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            [OrderChangeKind::SegmentStatus, OrderChangeKind::Cancelled]
+        );
+    }
+}
@@ -0,0 +1,414 @@
+//! Redaction of card data and travel documents
+//!
+//! Card numbers and security codes are wrapped so `Debug` and `Serialize`
+//! never print them in full; the Amadeus client reads them with `expose`
+//! where the form of payment is sent. Text that can still carry them, such as
+//! Amadeus error payloads, goes through `scrub`, which masks card numbers and
+//! the values of document number and security code fields. `Scrubbing` applies
+//! `scrub` to everything the log subscriber writes.
+
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Printed in place of secret values
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Fields whose string values are always scrubbed
+const SECRET_FIELDS: [&str; 3] = ["securityCode", "security_code", "cvv"];
+
+/// Fields whose string values are scrubbed unless they are short enough to be
+/// a flight number ("number" is the passport number of a traveler document)
+const DOCUMENT_FIELDS: [&str; 1] = ["number"];
+const MIN_DOCUMENT_NUMBER_LENGTH: usize = 5;
+
+/// A value that is never printed or serialized
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Secret(value)
+    }
+
+    /// The value itself, for the one place that has to send it
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Secret(value)
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+/// A card number (PAN), printed and serialized as "411111******1111"
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct CardNumber(String);
+
+impl CardNumber {
+    pub fn new(number: impl Into<String>) -> Self {
+        CardNumber(number.into())
+    }
+
+    /// The full number, for the one place that has to send it
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// First six and last four digits, the rest masked
+    pub fn masked(&self) -> String {
+        mask_card_number(&self.0)
+    }
+
+    pub fn last4(&self) -> &str {
+        let start = self.0.len().saturating_sub(4);
+        self.0.get(start..).unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for CardNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.masked())
+    }
+}
+
+impl Serialize for CardNumber {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.masked())
+    }
+}
+
+/// Keep the first six and last four digits of card numbers with at least 13
+/// digits, mask everything of shorter ones
+fn mask_card_number(number: &str) -> String {
+    let digits: Vec<char> = number.chars().filter(char::is_ascii_digit).collect();
+    if digits.len() < 13 {
+        return "*".repeat(digits.len());
+    }
+    digits
+        .iter()
+        .enumerate()
+        .map(|(i, digit)| {
+            if i < 6 || i >= digits.len() - 4 {
+                *digit
+            } else {
+                '*'
+            }
+        })
+        .collect()
+}
+
+/// Luhn check digit validation of a card number
+pub fn luhn_valid(number: &str) -> bool {
+    let digits: Option<Vec<u32>> = number.chars().rev().map(|c| c.to_digit(10)).collect();
+    let Some(digits) = digits else {
+        return false;
+    };
+    let sum: u32 = digits
+        .iter()
+        .enumerate()
+        .map(|(i, &digit)| match (i % 2, digit * 2) {
+            (1, doubled) if doubled > 9 => doubled - 9,
+            (1, doubled) => doubled,
+            _ => digit,
+        })
+        .sum();
+    digits.len() >= 12 && sum.is_multiple_of(10)
+}
+
+/// Whether digit groups look like a card number as typed: one run of 13 to 19
+/// digits, groups of four, or the 4-6-5 grouping of AMEX
+fn card_grouping(groups: &[usize]) -> bool {
+    let digits: usize = groups.iter().sum();
+    if !(13..=19).contains(&digits) {
+        return false;
+    }
+    match groups {
+        [_] | [4, 6, 5] => true,
+        [init @ .., last] => init.iter().all(|&group| group == 4) && *last <= 4,
+        [] => false,
+    }
+}
+
+/// Mask Luhn-valid card numbers in `text`
+fn scrub_card_numbers(text: &str) -> Cow<'_, str> {
+    let bytes = text.as_bytes();
+    let mut out = String::new();
+    let mut copied = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_digit() || (i > 0 && bytes[i - 1].is_ascii_digit()) {
+            i += 1;
+            continue;
+        }
+
+        // Digits, optionally grouped by single spaces or dashes
+        let start = i;
+        let mut end = i;
+        let mut groups = vec![0];
+        let mut separator = None;
+        while end < bytes.len() {
+            match bytes[end] {
+                b if b.is_ascii_digit() => {
+                    *groups.last_mut().unwrap() += 1;
+                    end += 1;
+                }
+                b @ (b' ' | b'-')
+                    if separator.is_none_or(|separator| separator == b)
+                        && bytes.get(end + 1).is_some_and(u8::is_ascii_digit) =>
+                {
+                    separator = Some(b);
+                    groups.push(0);
+                    end += 1;
+                }
+                _ => break,
+            }
+        }
+
+        let candidate = &text[start..end];
+        let digits: String = candidate.chars().filter(char::is_ascii_digit).collect();
+        if card_grouping(&groups) && luhn_valid(&digits) {
+            out.push_str(&text[copied..start]);
+            out.push_str(&mask_card_number(&digits));
+            copied = end;
+        }
+        i = end;
+    }
+
+    if copied == 0 {
+        Cow::Borrowed(text)
+    } else {
+        out.push_str(&text[copied..]);
+        Cow::Owned(out)
+    }
+}
+
+/// Range of the string value of `field` starting at `at`, in JSON
+/// (`"field": "value"`, also escaped inside strings) or `Debug` output
+/// (`field: "value"`, `field: Some("value")`)
+fn field_value(text: &str, at: usize, field: &str) -> Option<(usize, usize)> {
+    let rest = &text[at + field.len()..];
+    let after_key = rest
+        .strip_prefix("\\\"")
+        .or_else(|| rest.strip_prefix('"'))
+        .unwrap_or(rest);
+    let after_colon = after_key.trim_start().strip_prefix(':')?.trim_start();
+    let value = after_colon.strip_prefix("Some(").unwrap_or(after_colon);
+    let (quote, value) = match value.strip_prefix("\\\"") {
+        Some(value) => ("\\\"", value),
+        None => ("\"", value.strip_prefix('"')?),
+    };
+    let start = text.len() - value.len();
+    let len = value.find(quote)?;
+    Some((start, start + len))
+}
+
+/// Replace the string values of `fields` in `text`, keeping values shorter than `min_len`
+fn scrub_fields<'a>(text: &'a str, fields: &[&str], min_len: usize) -> Cow<'a, str> {
+    let mut ranges = Vec::new();
+    for field in fields {
+        for (at, _) in text.match_indices(field) {
+            let before = text[..at].chars().next_back();
+            if before.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+                continue;
+            }
+            if let Some((start, end)) = field_value(text, at, field)
+                && end - start >= min_len
+            {
+                ranges.push((start, end));
+            }
+        }
+    }
+    if ranges.is_empty() {
+        return Cow::Borrowed(text);
+    }
+
+    ranges.sort_unstable();
+    let mut out = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (start, end) in ranges {
+        if start < copied {
+            continue;
+        }
+        out.push_str(&text[copied..start]);
+        out.push_str(REDACTED);
+        copied = end;
+    }
+    out.push_str(&text[copied..]);
+    Cow::Owned(out)
+}
+
+/// Mask card numbers, security codes and document numbers in log or error text
+pub fn scrub(text: &str) -> Cow<'_, str> {
+    let mut scrubbed = scrub_card_numbers(text);
+    for (fields, min_len) in [
+        (&SECRET_FIELDS[..], 1),
+        (&DOCUMENT_FIELDS[..], MIN_DOCUMENT_NUMBER_LENGTH),
+    ] {
+        if let Cow::Owned(owned) = scrub_fields(&scrubbed, fields, min_len) {
+            scrubbed = Cow::Owned(owned);
+        }
+    }
+    scrubbed
+}
+
+/// Log writer factory scrubbing every formatted event before it is written
+/// (see `scrub`)
+#[derive(Debug, Clone)]
+pub struct Scrubbing<M>(M);
+
+impl<M> Scrubbing<M> {
+    pub fn new(make_writer: M) -> Self {
+        Scrubbing(make_writer)
+    }
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Scrubbing<M> {
+    type Writer = ScrubbingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        ScrubbingWriter {
+            inner: self.0.make_writer(),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+/// Collects one formatted event and writes it scrubbed when dropped
+pub struct ScrubbingWriter<W: io::Write> {
+    inner: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: io::Write> ScrubbingWriter<W> {
+    fn write_scrubbed(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let text = String::from_utf8_lossy(&self.buffer);
+        self.inner.write_all(scrub(&text).as_bytes())?;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+impl<W: io::Write> io::Write for ScrubbingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_scrubbed()?;
+        self.inner.flush()
+    }
+}
+
+impl<W: io::Write> Drop for ScrubbingWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.write_scrubbed();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrappers_redact() {
+        #[derive(Debug, Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Card {
+            number: CardNumber,
+            security_code: Secret<String>,
+        }
+
+        let card = Card {
+            number: CardNumber::new("4111111111111111"),
+            security_code: Secret::new("737".to_string()),
+        };
+        assert_eq!(
+            format!("{:?}", card),
+            r#"Card { number: "411111******1111", security_code: [REDACTED] }"#
+        );
+        assert_eq!(
+            serde_json::to_string(&card).unwrap(),
+            r#"{"number":"411111******1111","securityCode":"[REDACTED]"}"#
+        );
+        assert_eq!(card.number.expose(), "4111111111111111");
+        assert_eq!(card.number.last4(), "1111");
+        assert_eq!(card.security_code.expose(), "737");
+    }
+
+    #[test]
+    fn test_scrub_card_numbers() {
+        assert_eq!(
+            scrub("card 4111111111111111 declined"),
+            "card 411111******1111 declined"
+        );
+        assert_eq!(scrub("card 4111 1111 1111 1111."), "card 411111******1111.");
+        assert_eq!(scrub("amex 3782-822463-10005"), "amex 378282*****0005");
+
+        // Not Luhn-valid, or not grouped like a card
+        let untouched = [
+            "card 4111111111111112",
+            "ticket 220-1234567897",
+            "at 2025-06-01 10:15:00",
+            "order 41111111111111110",
+        ];
+        for text in untouched {
+            assert!(matches!(scrub(text), Cow::Borrowed(_)), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_scrub_fields() {
+        assert_eq!(
+            scrub(r#"{"documentType":"PASSPORT","number":"00000000","securityCode": "737"}"#),
+            r#"{"documentType":"PASSPORT","number":"[REDACTED]","securityCode": "[REDACTED]"}"#
+        );
+        assert_eq!(
+            scrub(r#"TravelerDocument { number: "X1234567", security_code: Some("737") }"#),
+            r#"TravelerDocument { number: "[REDACTED]", security_code: Some("[REDACTED]") }"#
+        );
+        assert_eq!(
+            scrub(r#"failed: "{\"number\":\"X1234567\"}""#),
+            r#"failed: "{\"number\":\"[REDACTED]\"}""#
+        );
+
+        // Flight numbers and other fields stay
+        let segment = r#"{"carrierCode":"LH","number":"400","flightNumber":"X1234567"}"#;
+        assert_eq!(scrub(segment), segment);
+    }
+
+    #[test]
+    fn test_scrubbing_writer() {
+        let mut output = Vec::new();
+        {
+            let mut writer = ScrubbingWriter {
+                inner: &mut output,
+                buffer: Vec::new(),
+            };
+            io::Write::write_all(&mut writer, b"paid with 4111111111111111\n").unwrap();
+        }
+        assert_eq!(output, b"paid with 411111******1111\n");
+    }
+}
@@ -0,0 +1,753 @@
+//! Schedule change monitor
+//!
+//! Airlines retime and cancel flights long after they were booked, and the
+//! order itself does not always tell. The monitor looks up every booked
+//! segment departing within the next days in the Flight Status API and
+//! compares the scheduled times with the booked ones. Retimed flights are
+//! reported, and connections that now fall below the minimum connection time
+//! are flagged. If an itinerary can no longer be flown as booked (a flight was
+//! cancelled or a connection is missed), alternative offers are searched for
+//! the rest of the itinerary and stored with the disruption, so an agent or
+//! the customer can accept one of them.
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+
+use crate::amadeus;
+use crate::bookings::{BookingRecord, BookingStore};
+use crate::models::{
+    FlightOffer, FlightOrderData, FlightSearchRequest, FlightStatusResponse, FlightTiming,
+    Itinerary, Segment,
+};
+use crate::notifications::{Notification, Notifier};
+use crate::reconciliation::{OrderChange, OrderChangeKind, flight};
+
+/// How often booked segments are checked (30 minutes)
+pub const DEFAULT_INTERVAL_SECS: u64 = 1800;
+
+/// How many days ahead segments are checked
+pub const DEFAULT_WINDOW_DAYS: i64 = 7;
+
+/// Minimum connection time at airports without an own value
+pub const DEFAULT_MIN_CONNECTION_MINUTES: i64 = 60;
+
+/// Alternative offers stored per disruption
+pub const MAX_ALTERNATIVES: usize = 5;
+
+/// Minimum connection times
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinConnectionTimes {
+    pub default: Duration,
+    /// By IATA airport code
+    pub airports: HashMap<String, Duration>,
+}
+
+impl Default for MinConnectionTimes {
+    fn default() -> Self {
+        Self {
+            default: Duration::minutes(DEFAULT_MIN_CONNECTION_MINUTES),
+            airports: HashMap::new(),
+        }
+    }
+}
+
+impl MinConnectionTimes {
+    /// Read MIN_CONNECTION_MINUTES (default 60) and
+    /// MIN_CONNECTION_MINUTES_BY_AIRPORT ("FRA=45,LHR=90")
+    pub fn from_env() -> Self {
+        let default = std::env::var("MIN_CONNECTION_MINUTES")
+            .ok()
+            .and_then(|minutes| minutes.trim().parse().ok())
+            .map(Duration::minutes)
+            .unwrap_or(Self::default().default);
+        let airports = std::env::var("MIN_CONNECTION_MINUTES_BY_AIRPORT")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|entry| {
+                let (airport, minutes) = entry.split_once('=')?;
+                let minutes = minutes.trim().parse().ok()?;
+                Some((airport.trim().to_uppercase(), Duration::minutes(minutes)))
+            })
+            .collect();
+        Self { default, airports }
+    }
+
+    pub fn at(&self, airport: &str) -> Duration {
+        self.airports.get(airport).copied().unwrap_or(self.default)
+    }
+}
+
+/// Settings of the schedule monitor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonitorConfig {
+    pub interval: std::time::Duration,
+    /// How far ahead segments are checked
+    pub window: Duration,
+    pub min_connection: MinConnectionTimes,
+}
+
+impl MonitorConfig {
+    /// Read SCHEDULE_MONITOR_INTERVAL_SECS (default 1800),
+    /// SCHEDULE_MONITOR_WINDOW_DAYS (default 7) and the minimum connection times
+    pub fn from_env() -> Self {
+        let interval = std::env::var("SCHEDULE_MONITOR_INTERVAL_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(DEFAULT_INTERVAL_SECS);
+        let window_days = std::env::var("SCHEDULE_MONITOR_WINDOW_DAYS")
+            .ok()
+            .and_then(|days| days.parse().ok())
+            .filter(|days| *days > 0)
+            .unwrap_or(DEFAULT_WINDOW_DAYS);
+        Self {
+            interval: std::time::Duration::from_secs(interval),
+            window: Duration::days(window_days),
+            min_connection: MinConnectionTimes::from_env(),
+        }
+    }
+}
+
+/// Current schedule of a booked segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlightSchedule {
+    /// Not checked (outside the window, or the lookup failed)
+    Unknown,
+    /// The flight no longer operates between the booked airports on that day
+    NotOperating,
+    /// Scheduled local times
+    Operating {
+        departure: NaiveDateTime,
+        arrival: NaiveDateTime,
+    },
+}
+
+/// Local time of an Amadeus timestamp, with ("2025-06-01T10:15+02:00") or
+/// without offset ("2025-06-01T10:15:00")
+fn local_time(value: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M%:z"))
+        .map(|at| at.naive_local())
+        .ok()
+        .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S").ok())
+        .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M").ok())
+}
+
+/// Schedule of the booked segment according to the Flight Status API
+pub fn schedule_from_status(status: &FlightStatusResponse, segment: &Segment) -> FlightSchedule {
+    let point = |iata_code: &str| {
+        status
+            .data
+            .iter()
+            .flat_map(|flight| flight.flight_points.iter().flatten())
+            .find(|point| point.iata_code.as_deref() == Some(iata_code))
+    };
+    let scheduled = |timing: Option<&FlightTiming>, qualifier: &str| {
+        timing?
+            .timings
+            .iter()
+            .flatten()
+            .find(|timing| timing.qualifier.as_deref() == Some(qualifier))?
+            .value
+            .as_deref()
+            .and_then(local_time)
+    };
+
+    let (Some(from), Some(to)) = (
+        point(&segment.departure.iata_code),
+        point(&segment.arrival.iata_code),
+    ) else {
+        return FlightSchedule::NotOperating;
+    };
+    match (
+        scheduled(from.departure.as_ref(), "STD"),
+        scheduled(to.arrival.as_ref(), "STA"),
+    ) {
+        (Some(departure), Some(arrival)) => FlightSchedule::Operating { departure, arrival },
+        _ => FlightSchedule::Unknown,
+    }
+}
+
+fn minutes(duration: Duration) -> String {
+    format!("{} minutes", duration.num_minutes())
+}
+
+/// "FRA 2025-06-01T10:15 JFK 2025-06-01T13:00"
+fn times(segment: &Segment, departure: NaiveDateTime, arrival: NaiveDateTime) -> String {
+    format!(
+        "{} {} {} {}",
+        segment.departure.iata_code,
+        departure.format("%Y-%m-%dT%H:%M"),
+        segment.arrival.iata_code,
+        arrival.format("%Y-%m-%dT%H:%M")
+    )
+}
+
+fn segment_change(
+    kind: OrderChangeKind,
+    segment: &Segment,
+    description: String,
+    previous: Option<String>,
+    current: Option<String>,
+) -> OrderChange {
+    OrderChange {
+        kind,
+        segment_id: Some(segment.id.clone()),
+        traveler_id: None,
+        description,
+        previous,
+        current,
+    }
+}
+
+/// Schedule changes of an itinerary. `schedules` holds the current schedule of
+/// each segment.
+pub fn disruptions(
+    segments: &[Segment],
+    schedules: &[FlightSchedule],
+    min_connection: &MinConnectionTimes,
+) -> Vec<OrderChange> {
+    let mut changes = Vec::new();
+    // Booked and current local times of each segment that still operates
+    let mut timings = Vec::new();
+
+    for (segment, schedule) in segments.iter().zip(schedules) {
+        let (Some(booked_departure), Some(booked_arrival)) = (
+            local_time(&segment.departure.at),
+            local_time(&segment.arrival.at),
+        ) else {
+            timings.push(None);
+            continue;
+        };
+        let booked = (booked_departure, booked_arrival);
+        match *schedule {
+            FlightSchedule::Unknown => timings.push(Some((booked, booked))),
+            FlightSchedule::NotOperating => {
+                changes.push(segment_change(
+                    OrderChangeKind::SegmentStatus,
+                    segment,
+                    format!(
+                        "{} no longer operates on {}",
+                        flight(segment),
+                        booked_departure.date()
+                    ),
+                    segment.booking_status.as_ref().map(ToString::to_string),
+                    Some("CANCELLED".to_string()),
+                ));
+                timings.push(None);
+            }
+            FlightSchedule::Operating { departure, arrival } => {
+                if (departure, arrival) != booked {
+                    changes.push(segment_change(
+                        OrderChangeKind::ScheduleChange,
+                        segment,
+                        format!("{} was retimed", flight(segment)),
+                        Some(times(segment, booked_departure, booked_arrival)),
+                        Some(times(segment, departure, arrival)),
+                    ));
+                }
+                timings.push(Some((booked, (departure, arrival))));
+            }
+        }
+    }
+
+    for (index, pair) in timings.windows(2).enumerate() {
+        let (Some((booked_in, current_in)), Some((booked_out, current_out))) = (pair[0], pair[1])
+        else {
+            continue;
+        };
+        let booked = booked_out.0 - booked_in.1;
+        let current = current_out.0 - current_in.1;
+        let airport = &segments[index].arrival.iata_code;
+        let minimum = min_connection.at(airport);
+        // Connections sold below the minimum are the airline's business
+        if current < minimum && current != booked {
+            let next = &segments[index + 1];
+            changes.push(segment_change(
+                OrderChangeKind::MissedConnection,
+                next,
+                format!(
+                    "Connection at {} to {}{} is {}, minimum is {}",
+                    airport,
+                    next.carrier_code,
+                    next.number,
+                    minutes(current),
+                    minutes(minimum)
+                ),
+                Some(minutes(booked)),
+                Some(minutes(current)),
+            ));
+        }
+    }
+    changes
+}
+
+/// Whether the itinerary can no longer be flown as booked
+fn needs_reaccommodation(changes: &[OrderChange]) -> bool {
+    changes.iter().any(|change| {
+        matches!(
+            change.kind,
+            OrderChangeKind::SegmentStatus | OrderChangeKind::MissedConnection
+        )
+    })
+}
+
+/// Identifies a disruption, so it is stored and reported once
+fn fingerprint(itinerary_index: usize, changes: &[OrderChange]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(itinerary_index.to_string());
+    hasher.update(serde_json::to_vec(changes).unwrap_or_default());
+    hex::encode(hasher.finalize())
+}
+
+/// Search for the part of the itinerary that has not been flown yet
+fn alternative_search(
+    booking: &BookingRecord,
+    offer: &FlightOffer,
+    remaining: &[&Segment],
+) -> Option<FlightSearchRequest> {
+    let first = remaining.first()?;
+    let last = remaining.last()?;
+    let travel_class = offer
+        .traveler_pricings
+        .first()
+        .and_then(|pricing| {
+            pricing
+                .fare_details_by_segment
+                .iter()
+                .find(|details| details.segment_id == first.id)
+        })
+        .map(|details| details.cabin.clone());
+    Some(FlightSearchRequest {
+        origin: first.departure.iata_code.clone(),
+        destination: last.arrival.iata_code.clone(),
+        departure_date: first.departure.at.get(..10)?.to_string(),
+        return_date: None,
+        adults: booking.adults.max(1) as u32,
+        children: booking.children.max(0) as u32,
+        infants: booking.infants.max(0) as u32,
+        seated_infants: 0,
+        seniors: 0,
+        youths: 0,
+        students: 0,
+        child_ages: None,
+        currency: booking.currency_code.clone(),
+        travel_class,
+        cabin_coverage: None,
+        non_stop: None,
+        max_connections: None,
+        return_travel_class: None,
+        return_cabin_coverage: None,
+        return_max_connections: None,
+        max_price: None,
+        max_results: Some(50),
+        included_airline_codes: None,
+        excluded_airline_codes: None,
+        additional_legs: None,
+    })
+}
+
+/// Flights of the itinerary that no longer operate, as (carrier, number, date)
+fn cancelled_flights(segments: &[Segment], schedules: &[FlightSchedule]) -> HashSet<String> {
+    segments
+        .iter()
+        .zip(schedules)
+        .filter(|(_, schedule)| **schedule == FlightSchedule::NotOperating)
+        .map(|(segment, _)| flight_key(segment))
+        .collect()
+}
+
+/// "LH400 2025-06-01"
+fn flight_key(segment: &Segment) -> String {
+    format!(
+        "{}{} {}",
+        segment.carrier_code,
+        segment.number,
+        segment.departure.at.get(..10).unwrap_or_default()
+    )
+}
+
+/// Checks the schedules of the booked segments during one run
+struct ScheduleMonitor<'a> {
+    store: &'a BookingStore,
+    client: &'a reqwest::Client,
+    notifier: &'a Notifier,
+    config: &'a MonitorConfig,
+    token: String,
+    /// Flight status lookups of this run by flight key
+    statuses: HashMap<String, Option<FlightStatusResponse>>,
+}
+
+impl ScheduleMonitor<'_> {
+    async fn schedule(&mut self, segment: &Segment, now: DateTime<Utc>) -> FlightSchedule {
+        let Some(departure) = local_time(&segment.departure.at) else {
+            return FlightSchedule::Unknown;
+        };
+        // Local times compared with UTC; being a few hours off does not matter here
+        if departure <= now.naive_utc() || departure > (now + self.config.window).naive_utc() {
+            return FlightSchedule::Unknown;
+        }
+
+        let key = flight_key(segment);
+        if !self.statuses.contains_key(&key) {
+            let status = amadeus::get_flight_status(
+                self.client,
+                &self.token,
+                &segment.carrier_code,
+                &segment.number,
+                &departure.date().to_string(),
+            )
+            .await;
+            if let Err(e) = &status {
+                tracing::warn!("Failed to get flight status of {}: {:#}", key, e);
+            }
+            self.statuses.insert(key.clone(), status.ok());
+        }
+        match &self.statuses[&key] {
+            Some(status) => schedule_from_status(status, segment),
+            None => FlightSchedule::Unknown,
+        }
+    }
+
+    async fn alternatives(
+        &self,
+        booking: &BookingRecord,
+        offer: &FlightOffer,
+        itinerary: &Itinerary,
+        schedules: &[FlightSchedule],
+        now: DateTime<Utc>,
+    ) -> Vec<FlightOffer> {
+        let remaining: Vec<&Segment> = itinerary
+            .segments
+            .iter()
+            .filter(|segment| {
+                local_time(&segment.departure.at).is_some_and(|at| at > now.naive_utc())
+            })
+            .collect();
+        let Some(request) = alternative_search(booking, offer, &remaining) else {
+            return Vec::new();
+        };
+        let cancelled = cancelled_flights(&itinerary.segments, schedules);
+        match amadeus::search_flights(self.client, &self.token, &request).await {
+            Ok(response) => response
+                .data
+                .into_iter()
+                .filter(|offer| {
+                    !offer
+                        .itineraries
+                        .iter()
+                        .flat_map(|itinerary| &itinerary.segments)
+                        .any(|segment| cancelled.contains(&flight_key(segment)))
+                })
+                .take(MAX_ALTERNATIVES)
+                .collect(),
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to search alternatives for booking {}: {:#}",
+                    booking.id,
+                    e
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    async fn check_booking(
+        &mut self,
+        booking: &BookingRecord,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        let Some(order) = booking
+            .booking_data
+            .clone()
+            .and_then(|data| serde_json::from_value::<FlightOrderData>(data).ok())
+        else {
+            return Ok(());
+        };
+
+        // Collected, as the iterator would be held across the lookups below
+        let itineraries: Vec<_> = order
+            .flight_offers
+            .iter()
+            .flat_map(|offer| {
+                offer
+                    .itineraries
+                    .iter()
+                    .map(move |itinerary| (offer, itinerary))
+            })
+            .collect();
+        for (index, (offer, itinerary)) in itineraries.into_iter().enumerate() {
+            let mut schedules = Vec::with_capacity(itinerary.segments.len());
+            for segment in &itinerary.segments {
+                schedules.push(self.schedule(segment, now).await);
+            }
+            let changes = disruptions(&itinerary.segments, &schedules, &self.config.min_connection);
+            if changes.is_empty() {
+                continue;
+            }
+
+            let fingerprint = fingerprint(index, &changes);
+            let alternatives = if needs_reaccommodation(&changes) {
+                self.alternatives(booking, offer, itinerary, &schedules, now)
+                    .await
+            } else {
+                Vec::new()
+            };
+            let Some(disruption) = self
+                .store
+                .create_disruption(
+                    booking.id,
+                    index as i32,
+                    &fingerprint,
+                    &changes,
+                    &alternatives,
+                )
+                .await?
+            else {
+                // Reported before
+                continue;
+            };
+
+            self.store
+                .record_changes(booking.id, &changes, None)
+                .await?;
+            let subject = if needs_reaccommodation(&changes) {
+                format!(
+                    "Schedule change needs re-accommodation ({} alternatives, disruption {})",
+                    alternatives.len(),
+                    disruption.id
+                )
+            } else {
+                format!("Schedule change ({} changes)", changes.len())
+            };
+            self.notifier
+                .send(&Notification::new(booking, subject, changes))
+                .await;
+        }
+        Ok(())
+    }
+}
+
+/// Check the segments of all active bookings
+pub async fn check_schedules(
+    store: &BookingStore,
+    client: &reqwest::Client,
+    notifier: &Notifier,
+    config: &MonitorConfig,
+    now: DateTime<Utc>,
+) {
+    let bookings = match store.active_orders().await {
+        Ok(bookings) => bookings,
+        Err(e) => {
+            tracing::error!("Failed to load active bookings: {}", e);
+            return;
+        }
+    };
+    if bookings.is_empty() {
+        return;
+    }
+    let token = match amadeus::get_token(client).await {
+        Ok(token) => token,
+        Err(e) => {
+            tracing::error!("Amadeus token error: {:?}", e);
+            return;
+        }
+    };
+
+    let mut monitor = ScheduleMonitor {
+        store,
+        client,
+        notifier,
+        config,
+        token,
+        statuses: HashMap::new(),
+    };
+    for booking in &bookings {
+        if let Err(e) = monitor.check_booking(booking, now).await {
+            tracing::error!(
+                "Failed to check schedule of booking {}: {:#}",
+                booking.id,
+                e
+            );
+        }
+    }
+}
+
+/// Check booked segments for schedule changes in the background
+pub fn spawn(
+    store: BookingStore,
+    client: reqwest::Client,
+    notifier: Notifier,
+    config: MonitorConfig,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval);
+        loop {
+            interval.tick().await;
+            check_schedules(&store, &client, &notifier, &config, Utc::now()).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(id: &str, from: &str, departure: &str, to: &str, arrival: &str) -> Segment {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "departure": { "iataCode": from, "at": departure },
+            "arrival": { "iataCode": to, "at": arrival },
+            "carrierCode": "LH",
+            "number": format!("4{}", id),
+            "aircraft": { "code": "320" }
+        }))
+        .unwrap()
+    }
+
+    fn at(value: &str) -> NaiveDateTime {
+        local_time(value).unwrap()
+    }
+
+    #[test]
+    fn test_schedule_from_status() {
+        let status: FlightStatusResponse = serde_json::from_value(serde_json::json!({
+            "data": [{
+                "type": "DatedFlight",
+                "scheduledDepartureDate": "2025-06-01",
+                "flightPoints": [
+                    {
+                        "iataCode": "MUC",
+                        "departure": { "timings": [
+                            { "qualifier": "STD", "value": "2025-06-01T07:30+02:00" }
+                        ] }
+                    },
+                    {
+                        "iataCode": "FRA",
+                        "arrival": { "timings": [
+                            { "qualifier": "STA", "value": "2025-06-01T08:40+02:00" }
+                        ] }
+                    }
+                ]
+            }]
+        }))
+        .unwrap();
+
+        let booked = segment(
+            "1",
+            "MUC",
+            "2025-06-01T07:00:00",
+            "FRA",
+            "2025-06-01T08:10:00",
+        );
+        assert_eq!(
+            schedule_from_status(&status, &booked),
+            FlightSchedule::Operating {
+                departure: at("2025-06-01T07:30:00"),
+                arrival: at("2025-06-01T08:40:00"),
+            }
+        );
+        let other_route = segment(
+            "1",
+            "MUC",
+            "2025-06-01T07:00:00",
+            "HAM",
+            "2025-06-01T08:10:00",
+        );
+        assert_eq!(
+            schedule_from_status(&status, &other_route),
+            FlightSchedule::NotOperating
+        );
+        let no_flight = FlightStatusResponse { data: vec![] };
+        assert_eq!(
+            schedule_from_status(&no_flight, &booked),
+            FlightSchedule::NotOperating
+        );
+    }
+
+    #[test]
+    fn test_disruptions() {
+        let segments = [
+            segment(
+                "1",
+                "MUC",
+                "2025-06-01T07:00:00",
+                "FRA",
+                "2025-06-01T08:10:00",
+            ),
+            segment(
+                "2",
+                "FRA",
+                "2025-06-01T09:30:00",
+                "JFK",
+                "2025-06-01T12:00:00",
+            ),
+        ];
+        let min_connection = MinConnectionTimes {
+            airports: HashMap::from([("FRA".to_string(), Duration::minutes(45))]),
+            ..Default::default()
+        };
+        let operating = |departure: &str, arrival: &str| FlightSchedule::Operating {
+            departure: at(departure),
+            arrival: at(arrival),
+        };
+
+        // Unchanged, or not checked
+        let schedules = [
+            operating("2025-06-01T07:00:00", "2025-06-01T08:10:00"),
+            FlightSchedule::Unknown,
+        ];
+        assert!(disruptions(&segments, &schedules, &min_connection).is_empty());
+
+        // Retimed, connection still long enough
+        let schedules = [
+            operating("2025-06-01T07:30:00", "2025-06-01T08:40:00"),
+            FlightSchedule::Unknown,
+        ];
+        let changes = disruptions(&segments, &schedules, &min_connection);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, OrderChangeKind::ScheduleChange);
+        assert_eq!(
+            changes[0].current.as_deref(),
+            Some("MUC 2025-06-01T07:30 FRA 2025-06-01T08:40")
+        );
+        assert!(!needs_reaccommodation(&changes));
+
+        // Retimed below the minimum connection time at FRA
+        let schedules = [
+            operating("2025-06-01T08:00:00", "2025-06-01T09:00:00"),
+            FlightSchedule::Unknown,
+        ];
+        let changes = disruptions(&segments, &schedules, &min_connection);
+        let kinds: Vec<_> = changes.iter().map(|change| change.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                OrderChangeKind::ScheduleChange,
+                OrderChangeKind::MissedConnection
+            ]
+        );
+        assert_eq!(
+            changes[1].description,
+            "Connection at FRA to LH42 is 30 minutes, minimum is 45 minutes"
+        );
+        assert_eq!(changes[1].previous.as_deref(), Some("80 minutes"));
+        assert!(needs_reaccommodation(&changes));
+
+        // Cancelled
+        let schedules = [FlightSchedule::NotOperating, FlightSchedule::Unknown];
+        let changes = disruptions(&segments, &schedules, &min_connection);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, OrderChangeKind::SegmentStatus);
+        assert_eq!(
+            changes[0].description,
+            "LH41 MUC-FRA no longer operates on 2025-06-01"
+        );
+        assert_eq!(
+            cancelled_flights(&segments, &schedules),
+            HashSet::from(["LH41 2025-06-01".to_string()])
+        );
+        assert_ne!(fingerprint(0, &changes), fingerprint(1, &changes));
+    }
+}
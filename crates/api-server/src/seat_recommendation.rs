@@ -0,0 +1,559 @@
+//! Seat suggestions for families and groups
+//!
+//! For every segment we look for a block of adjacent free seats in one row
+//! that fits all travelers, and pick the block and seating order with the
+//! fewest unmet window/aisle preferences, then the lowest seat price. Children
+//! and seated infants sit next to an adult, and adults holding an infant get a
+//! seat suitable for infants. If no row fits the group, travelers are seated
+//! one by one with the same rules: adults first, then children and seated
+//! infants next to one of them, or not at all.
+
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+use crate::ancillaries::{
+    has_characteristic, is_available_for, seat_pricing, seat_restrictions, travels_with_infant,
+};
+use crate::models::{
+    FlightOffer, Seat, SeatPosition, SeatPreference, SeatSelection, SeatmapResponse,
+    SegmentSeatRecommendation, TravelerPricing, TravelerType,
+};
+use crate::money::Money;
+
+/// Seat characteristic: window
+const WINDOW: &str = "W";
+
+/// Seat characteristic: aisle
+const AISLE: &str = "A";
+
+/// Seat characteristic: suitable for an adult with an infant
+const INFANT_SEAT: &str = "I";
+
+/// Largest group whose seating order is searched exhaustively within a row
+const MAX_EXACT_GROUP: usize = 6;
+
+struct Traveler<'a> {
+    pricing: &'a TravelerPricing,
+    preference: Option<SeatPosition>,
+    /// Holds an infant on the lap
+    holds_infant: bool,
+    /// Child or seated infant, sits next to an adult
+    needs_adult: bool,
+    is_adult: bool,
+}
+
+/// Unmet preferences first, then price
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Score {
+    unmet: usize,
+    price: Decimal,
+}
+
+struct Assignment {
+    /// Seat index per traveler
+    seats: Vec<usize>,
+    score: Score,
+}
+
+fn seat_price(seat: &Seat, traveler_id: &str) -> Option<Money> {
+    let price = seat_pricing(seat, traveler_id)?.price.as_ref()?;
+    Some(Money::new(price.total?, price.currency.clone()?))
+}
+
+fn preference_met(seat: &Seat, preference: Option<SeatPosition>) -> bool {
+    match preference {
+        Some(SeatPosition::Window) => has_characteristic(seat, WINDOW),
+        Some(SeatPosition::Aisle) => has_characteristic(seat, AISLE),
+        None => true,
+    }
+}
+
+struct Planner<'a> {
+    offer: &'a FlightOffer,
+    /// Whether the aircraft marks seats suitable for infants at all
+    infant_seats_marked: bool,
+}
+
+impl Planner<'_> {
+    fn allowed(&self, seat: &Seat, traveler: &Traveler<'_>) -> bool {
+        is_available_for(seat, &traveler.pricing.traveler_id)
+            && seat_restrictions(seat, self.offer, traveler.pricing).is_empty()
+            && (!traveler.holds_infant
+                || !self.infant_seats_marked
+                || has_characteristic(seat, INFANT_SEAT))
+    }
+
+    fn score(&self, seat: &Seat, traveler: &Traveler<'_>) -> Score {
+        Score {
+            unmet: usize::from(!preference_met(seat, traveler.preference)),
+            price: seat_price(seat, &traveler.pricing.traveler_id)
+                .map(|price| price.amount)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Best seating order for a row block of adjacent seats, one seat per traveler
+    fn assign_block(&self, travelers: &[Traveler<'_>], block: &[&Seat]) -> Option<Assignment> {
+        if travelers.len() > MAX_EXACT_GROUP {
+            return self.assign_greedy(travelers, block);
+        }
+
+        let mut best: Option<Assignment> = None;
+        let mut seats = Vec::with_capacity(travelers.len());
+        self.search(travelers, block, &mut seats, &mut best);
+        best
+    }
+
+    fn search(
+        &self,
+        travelers: &[Traveler<'_>],
+        block: &[&Seat],
+        seats: &mut Vec<usize>,
+        best: &mut Option<Assignment>,
+    ) {
+        let Some(traveler) = travelers.get(seats.len()) else {
+            // Children and seated infants need an adult next to them
+            let seated_with_adult = travelers.iter().zip(seats.iter()).all(|(traveler, &seat)| {
+                !traveler.needs_adult
+                    || travelers
+                        .iter()
+                        .zip(seats.iter())
+                        .any(|(other, &other_seat)| {
+                            other.is_adult && seat.abs_diff(other_seat) == 1
+                        })
+            });
+            if !seated_with_adult {
+                return;
+            }
+            let score = travelers
+                .iter()
+                .zip(seats.iter())
+                .map(|(traveler, &seat)| self.score(block[seat], traveler))
+                .fold(
+                    Score {
+                        unmet: 0,
+                        price: Decimal::ZERO,
+                    },
+                    |total, score| Score {
+                        unmet: total.unmet + score.unmet,
+                        price: total.price + score.price,
+                    },
+                );
+            if best.as_ref().is_none_or(|best| score < best.score) {
+                *best = Some(Assignment {
+                    seats: seats.clone(),
+                    score,
+                });
+            }
+            return;
+        };
+
+        for (index, seat) in block.iter().enumerate() {
+            if seats.contains(&index) || !self.allowed(seat, traveler) {
+                continue;
+            }
+            seats.push(index);
+            self.search(travelers, block, seats, best);
+            seats.pop();
+        }
+    }
+
+    /// Seat travelers of a row block one by one, each in the best remaining
+    /// seat; children and seated infants last, next to a seated adult
+    fn assign_greedy(&self, travelers: &[Traveler<'_>], seats: &[&Seat]) -> Option<Assignment> {
+        let mut taken: Vec<Option<usize>> = vec![None; travelers.len()];
+        let mut total = Score {
+            unmet: 0,
+            price: Decimal::ZERO,
+        };
+        for i in seating_order(travelers) {
+            let traveler = &travelers[i];
+            let next_to_adult = |index: usize| {
+                travelers.iter().zip(&taken).any(|(other, seat)| {
+                    other.is_adult && seat.is_some_and(|seat| seat.abs_diff(index) == 1)
+                })
+            };
+            let (index, score) = seats
+                .iter()
+                .enumerate()
+                .filter(|(index, seat)| {
+                    !taken.contains(&Some(*index))
+                        && self.allowed(seat, traveler)
+                        && (!traveler.needs_adult || next_to_adult(*index))
+                })
+                .map(|(index, seat)| (index, self.score(seat, traveler)))
+                .min_by_key(|(_, score)| *score)?;
+            taken[i] = Some(index);
+            total.unmet += score.unmet;
+            total.price += score.price;
+        }
+        Some(Assignment {
+            seats: taken.into_iter().flatten().collect(),
+            score: total,
+        })
+    }
+}
+
+/// Indexes of the travelers in the order they are seated one by one: children
+/// and seated infants after everyone else, so they can sit next to an adult
+fn seating_order(travelers: &[Traveler<'_>]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..travelers.len()).collect();
+    order.sort_by_key(|&i| travelers[i].needs_adult);
+    order
+}
+
+/// Whether two seats are next to each other (same deck and row, neighbouring columns)
+fn adjacent((deck, seat): (usize, &Seat), (other_deck, other): (usize, &Seat)) -> bool {
+    let position = |seat: &Seat| {
+        seat.coordinates
+            .as_ref()
+            .and_then(|coordinates| Some((coordinates.x?, coordinates.y?)))
+    };
+    match (position(seat), position(other)) {
+        (Some((x, y)), Some((other_x, other_y))) => {
+            deck == other_deck && x == other_x && y.abs_diff(other_y) == 1
+        }
+        _ => false,
+    }
+}
+
+/// Runs of adjacent seats (same deck and row, neighbouring columns)
+fn row_runs<'a>(seats: &[(usize, &'a Seat)]) -> Vec<Vec<&'a Seat>> {
+    let mut rows: BTreeMap<(usize, i32), Vec<(i32, &'a Seat)>> = BTreeMap::new();
+    for &(deck, seat) in seats {
+        if let Some((x, y)) = seat
+            .coordinates
+            .as_ref()
+            .and_then(|coordinates| Some((coordinates.x?, coordinates.y?)))
+        {
+            rows.entry((deck, x)).or_default().push((y, seat));
+        }
+    }
+
+    let mut runs = Vec::new();
+    for mut row in rows.into_values() {
+        row.sort_by_key(|(y, _)| *y);
+        let mut run: Vec<&'a Seat> = Vec::new();
+        let mut last_y = None;
+        for (y, seat) in row {
+            if last_y.is_some_and(|last| last + 1 != y) && !run.is_empty() {
+                runs.push(std::mem::take(&mut run));
+            }
+            run.push(seat);
+            last_y = Some(y);
+        }
+        if !run.is_empty() {
+            runs.push(run);
+        }
+    }
+    runs
+}
+
+/// Suggest seats for every segment of the offer
+pub fn recommend_seats(
+    offer: &FlightOffer,
+    seatmaps: &SeatmapResponse,
+    preferences: &[SeatPreference],
+) -> Vec<SegmentSeatRecommendation> {
+    let mut segment_ids: Vec<&str> = Vec::new();
+    for details in offer
+        .traveler_pricings
+        .iter()
+        .flat_map(|pricing| &pricing.fare_details_by_segment)
+    {
+        if !segment_ids.contains(&details.segment_id.as_str()) {
+            segment_ids.push(&details.segment_id);
+        }
+    }
+
+    segment_ids
+        .into_iter()
+        .map(|segment_id| recommend_segment(offer, seatmaps, preferences, segment_id))
+        .collect()
+}
+
+fn recommend_segment(
+    offer: &FlightOffer,
+    seatmaps: &SeatmapResponse,
+    preferences: &[SeatPreference],
+    segment_id: &str,
+) -> SegmentSeatRecommendation {
+    let mut travelers: Vec<Traveler<'_>> = offer
+        .traveler_pricings
+        .iter()
+        .filter(|pricing| pricing.traveler_type.is_seated())
+        .filter(|pricing| {
+            pricing
+                .fare_details_by_segment
+                .iter()
+                .any(|details| details.segment_id == segment_id)
+        })
+        .map(|pricing| Traveler {
+            pricing,
+            preference: preferences
+                .iter()
+                .find(|preference| preference.traveler_id == pricing.traveler_id)
+                .map(|preference| preference.position),
+            holds_infant: pricing.traveler_type != TravelerType::SeatedInfant
+                && travels_with_infant(offer, pricing),
+            needs_adult: matches!(
+                pricing.traveler_type,
+                TravelerType::Child | TravelerType::SeatedInfant
+            ),
+            is_adult: pricing.traveler_type.is_accompanying_adult(),
+        })
+        .collect();
+    // Most constrained travelers choose first when seated one by one
+    travelers.sort_by_key(|traveler| (!traveler.holds_infant, traveler.preference.is_none()));
+
+    let seats: Vec<(usize, &Seat)> = seatmaps
+        .data
+        .iter()
+        .filter(|seatmap| seatmap.segment_id.as_deref() == Some(segment_id))
+        .flat_map(|seatmap| seatmap.decks.iter().enumerate())
+        .flat_map(|(deck_index, deck)| deck.seats.iter().map(move |seat| (deck_index, seat)))
+        .collect();
+    let planner = Planner {
+        offer,
+        infant_seats_marked: seats
+            .iter()
+            .any(|(_, seat)| has_characteristic(seat, INFANT_SEAT)),
+    };
+
+    // Side by side in one row
+    let group_size = travelers.len();
+    let runs = row_runs(&seats);
+    let together = runs
+        .iter()
+        .flat_map(|run| run.windows(group_size.max(1)))
+        .filter_map(|block| {
+            planner
+                .assign_block(&travelers, block)
+                .map(|assignment| (block, assignment))
+        })
+        .min_by_key(|(_, assignment)| assignment.score);
+
+    let (chosen, seated_together, unassigned): (Vec<(&Traveler<'_>, &Seat)>, bool, Vec<String>) =
+        match together {
+            Some((block, assignment)) if group_size > 0 => (
+                travelers
+                    .iter()
+                    .zip(assignment.seats.iter().map(|&index| block[index]))
+                    .collect(),
+                true,
+                Vec::new(),
+            ),
+            _ => {
+                // One by one, skipping travelers without a suitable seat
+                let mut taken: Vec<(&Traveler<'_>, usize)> = Vec::new();
+                let mut unassigned = Vec::new();
+                for traveler in seating_order(&travelers).into_iter().map(|i| &travelers[i]) {
+                    let next_to_adult = |index: usize| {
+                        taken.iter().any(|(other, other_index)| {
+                            other.is_adult && adjacent(seats[*other_index], seats[index])
+                        })
+                    };
+                    let best = seats
+                        .iter()
+                        .enumerate()
+                        .filter(|(index, (_, seat))| {
+                            !taken.iter().any(|(_, taken)| taken == index)
+                                && planner.allowed(seat, traveler)
+                                && (!traveler.needs_adult || next_to_adult(*index))
+                        })
+                        .min_by_key(|(_, (_, seat))| planner.score(seat, traveler));
+                    match best {
+                        Some((index, _)) => taken.push((traveler, index)),
+                        None => unassigned.push(traveler.pricing.traveler_id.clone()),
+                    }
+                }
+                let chosen = taken
+                    .into_iter()
+                    .map(|(traveler, index)| (traveler, seats[index].1))
+                    .collect();
+                (chosen, false, unassigned)
+            }
+        };
+
+    let prices: Vec<Money> = chosen
+        .iter()
+        .filter_map(|(traveler, seat)| seat_price(seat, &traveler.pricing.traveler_id))
+        .filter(|price| !price.is_zero())
+        .collect();
+    let total = prices
+        .first()
+        .and_then(|first| Money::sum(&first.currency, prices.iter()).ok());
+
+    let mut selections: Vec<SeatSelection> = chosen
+        .iter()
+        .map(|(traveler, seat)| SeatSelection {
+            traveler_id: traveler.pricing.traveler_id.clone(),
+            segment_id: segment_id.to_string(),
+            seat_number: seat.number.clone(),
+        })
+        .collect();
+    selections.sort_by(|a, b| a.traveler_id.cmp(&b.traveler_id));
+
+    SegmentSeatRecommendation {
+        segment_id: segment_id.to_string(),
+        unmet_preferences: chosen
+            .iter()
+            .filter(|(traveler, seat)| !preference_met(seat, traveler.preference))
+            .count(),
+        seats: selections,
+        seated_together: seated_together && group_size > 1,
+        total,
+        unassigned_traveler_ids: unassigned,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer(travelers: &[(&str, &str, Option<&str>)]) -> FlightOffer {
+        let pricings: Vec<_> = travelers
+            .iter()
+            .map(|(id, traveler_type, adult)| {
+                serde_json::json!({
+                    "travelerId": id,
+                    "fareOption": "STANDARD",
+                    "travelerType": traveler_type,
+                    "associatedAdultId": adult,
+                    "price": { "currency": "EUR", "total": "100.00", "base": "80.00" },
+                    "fareDetailsBySegment": [
+                        { "segmentId": "1", "cabin": "ECONOMY", "fareBasis": "YOWEU", "class": "Y" }
+                    ]
+                })
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "type": "flight-offer",
+            "source": "GDS",
+            "itineraries": [],
+            "price": { "currency": "EUR", "total": "300.00", "base": "240.00" },
+            "validatingAirlineCodes": ["LH"],
+            "travelerPricings": pricings
+        }))
+        .unwrap()
+    }
+
+    /// Rows 10 to 12, columns A B C | D E F, all free except 11B and 11C.
+    /// Row 10 costs 20 EUR per seat, 11 and 12 are free, row 12 is an exit row.
+    fn seatmaps() -> SeatmapResponse {
+        let mut seats = Vec::new();
+        for (x, row) in [(0, 10), (1, 11), (2, 12)] {
+            for (y, letter) in [(0, "A"), (1, "B"), (2, "C"), (4, "D"), (5, "E"), (6, "F")] {
+                let mut codes = vec![match letter {
+                    "A" | "F" => "W",
+                    "C" | "D" => "A",
+                    _ => "9",
+                }];
+                if row == 12 {
+                    codes.push("E");
+                }
+                if row == 11 && letter == "F" {
+                    codes.push("I");
+                }
+                let occupied = row == 11 && (letter == "B" || letter == "C");
+                let pricing: Vec<_> = ["1", "2", "3", "4"]
+                    .iter()
+                    .map(|id| {
+                        serde_json::json!({
+                            "travelerId": id,
+                            "seatAvailabilityStatus": if occupied { "OCCUPIED" } else { "AVAILABLE" },
+                            "price": {
+                                "currency": "EUR",
+                                "total": if row == 10 { "20.00" } else { "0.00" }
+                            }
+                        })
+                    })
+                    .collect();
+                seats.push(serde_json::json!({
+                    "number": format!("{}{}", row, letter),
+                    "characteristicsCodes": codes,
+                    "coordinates": { "x": x, "y": y },
+                    "travelerPricing": pricing
+                }));
+            }
+        }
+        serde_json::from_value(serde_json::json!({
+            "data": [{ "type": "seatmap", "segmentId": "1", "decks": [{ "seats": seats }] }]
+        }))
+        .unwrap()
+    }
+
+    fn seat_numbers(recommendation: &SegmentSeatRecommendation) -> Vec<&str> {
+        recommendation
+            .seats
+            .iter()
+            .map(|seat| seat.seat_number.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_family_sits_together_in_free_seats() {
+        let offer = offer(&[
+            ("1", "ADULT", None),
+            ("2", "CHILD", None),
+            ("3", "CHILD", None),
+        ]);
+        let preferences = vec![SeatPreference {
+            traveler_id: "2".to_string(),
+            position: SeatPosition::Window,
+        }];
+
+        let recommendations = recommend_seats(&offer, &seatmaps(), &preferences);
+        assert_eq!(recommendations.len(), 1);
+        let segment = &recommendations[0];
+
+        // Row 10 costs extra and children may not sit in the exit row 12
+        assert!(segment.seated_together);
+        assert_eq!(seat_numbers(segment), ["11E", "11F", "11D"]);
+        assert_eq!(segment.unmet_preferences, 0);
+        assert_eq!(segment.total, None);
+    }
+
+    #[test]
+    fn test_infant_holder_gets_infant_seat() {
+        let offer = offer(&[("1", "ADULT", None), ("2", "HELD_INFANT", Some("1"))]);
+
+        let segment = &recommend_seats(&offer, &seatmaps(), &[])[0];
+        assert_eq!(seat_numbers(segment), ["11F"]);
+        assert!(!segment.seated_together);
+    }
+
+    #[test]
+    fn test_group_split_when_no_row_fits() {
+        let offer = offer(&[
+            ("1", "ADULT", None),
+            ("2", "ADULT", None),
+            ("3", "ADULT", None),
+            ("4", "ADULT", None),
+        ]);
+
+        let segment = &recommend_seats(&offer, &seatmaps(), &[])[0];
+        assert!(!segment.seated_together);
+        assert_eq!(segment.seats.len(), 4);
+        assert!(segment.unassigned_traveler_ids.is_empty());
+        assert_eq!(segment.total, None);
+    }
+
+    #[test]
+    fn test_children_next_to_an_adult_when_split() {
+        let offer = offer(&[
+            ("1", "ADULT", None),
+            ("2", "CHILD", None),
+            ("3", "ADULT", None),
+            ("4", "CHILD", None),
+        ]);
+
+        // Adults take the best seats first: 11A and 11D; of the seats next
+        // to them only 11E is free, so the second child gets no seat
+        let segment = &recommend_seats(&offer, &seatmaps(), &[])[0];
+        assert!(!segment.seated_together);
+        assert_eq!(seat_numbers(segment), ["11A", "11E", "11D"]);
+        assert_eq!(segment.unassigned_traveler_ids, ["4"]);
+    }
+}
@@ -0,0 +1,251 @@
+//! Seatmap grid view
+//!
+//! Amadeus describes a deck as a list of seats and facilities with x/y
+//! coordinates (x along the aircraft, y across it) plus the wing and exit row
+//! positions. Clients want rows and columns, so we lay the deck out once here:
+//! columns without seats are aisles, and every row gets one cell per column.
+
+use serde::Deserialize;
+use std::collections::BTreeSet;
+
+use crate::models::{
+    Deck, DeckGrid, GridCell, GridColumn, GridRow, Seat, SeatAvailabilityStatus, SeatmapResponse,
+};
+
+/// Query parameters of the seatmap endpoints
+#[derive(Debug, Default, Deserialize)]
+pub struct SeatmapViewQuery {
+    /// Add the grid view to every deck (`?grid=true`)
+    #[serde(default)]
+    pub grid: bool,
+}
+
+/// Readable name of a facility code
+fn facility_name(code: &str) -> Option<&'static str> {
+    match code {
+        "LA" => Some("LAVATORY"),
+        "G" | "GA" => Some("GALLEY"),
+        "CL" => Some("CLOSET"),
+        "SO" => Some("STORAGE"),
+        "ST" => Some("STAIRS"),
+        "BA" => Some("BAR"),
+        _ => None,
+    }
+}
+
+/// "12A" -> ("12", "A")
+fn split_seat_number(number: &str) -> (&str, &str) {
+    let letters = number.trim_start_matches(|c: char| c.is_ascii_digit());
+    (&number[..number.len() - letters.len()], letters)
+}
+
+fn seat_position(seat: &Seat) -> Option<(i32, i32)> {
+    let coordinates = seat.coordinates.as_ref()?;
+    Some((coordinates.x?, coordinates.y?))
+}
+
+/// Availability for the traveler best off: available if anyone may take it
+fn seat_status(seat: &Seat) -> Option<SeatAvailabilityStatus> {
+    let mut statuses = seat
+        .traveler_pricing
+        .iter()
+        .flatten()
+        .filter_map(|pricing| pricing.seat_availability_status.clone());
+    let first = statuses.next()?;
+    if first == SeatAvailabilityStatus::Available {
+        return Some(first);
+    }
+    Some(
+        statuses
+            .find(|status| *status == SeatAvailabilityStatus::Available)
+            .unwrap_or(first),
+    )
+}
+
+/// Lay out a deck as rows and columns
+pub fn build_grid(deck: &Deck) -> DeckGrid {
+    let config = deck.deck_configuration.as_ref();
+    let seats: Vec<((i32, i32), &Seat)> = deck
+        .seats
+        .iter()
+        .filter_map(|seat| seat_position(seat).map(|position| (position, seat)))
+        .collect();
+    let facilities: Vec<((i32, i32), &str)> = deck
+        .facilities
+        .iter()
+        .flatten()
+        .filter_map(|facility| {
+            let coordinates = facility.coordinates.as_ref()?;
+            Some(((coordinates.x?, coordinates.y?), facility.code.as_deref()?))
+        })
+        .collect();
+
+    let max_y = seats
+        .iter()
+        .map(|((_, y), _)| *y)
+        .chain(facilities.iter().map(|((_, y), _)| *y))
+        .max();
+    let width = config
+        .and_then(|config| config.width)
+        .into_iter()
+        .chain(max_y.map(|y| y + 1))
+        .max()
+        .unwrap_or(0);
+
+    let columns: Vec<GridColumn> = (0..width)
+        .map(|y| {
+            let letter = seats
+                .iter()
+                .filter(|((_, seat_y), _)| *seat_y == y)
+                .map(|(_, seat)| split_seat_number(&seat.number).1)
+                .find(|letter| !letter.is_empty())
+                .map(str::to_string);
+            GridColumn {
+                is_aisle: letter.is_none(),
+                letter,
+            }
+        })
+        .collect();
+
+    let row_positions: BTreeSet<i32> = seats
+        .iter()
+        .map(|((x, _), _)| *x)
+        .chain(facilities.iter().map(|((x, _), _)| *x))
+        .collect();
+    let exit_rows = config
+        .and_then(|config| config.exit_rows_x.as_deref())
+        .unwrap_or_default();
+    let wings = config.and_then(|config| Some(config.start_wings_x?..=config.end_wings_x?));
+
+    let rows = row_positions
+        .into_iter()
+        .map(|x| {
+            let cells = columns
+                .iter()
+                .zip(0..)
+                .map(|(column, y)| {
+                    if let Some((_, seat)) = seats.iter().find(|(position, _)| *position == (x, y))
+                    {
+                        GridCell::Seat {
+                            number: seat.number.clone(),
+                            status: seat_status(seat),
+                            characteristics_codes: seat
+                                .characteristics_codes
+                                .clone()
+                                .unwrap_or_default(),
+                        }
+                    } else if let Some((_, code)) =
+                        facilities.iter().find(|(position, _)| *position == (x, y))
+                    {
+                        GridCell::Facility {
+                            code: code.to_string(),
+                            name: facility_name(code).map(str::to_string),
+                        }
+                    } else if column.is_aisle {
+                        GridCell::Aisle
+                    } else {
+                        GridCell::Empty
+                    }
+                })
+                .collect();
+            GridRow {
+                number: seats
+                    .iter()
+                    .filter(|((seat_x, _), _)| *seat_x == x)
+                    .map(|(_, seat)| split_seat_number(&seat.number).0)
+                    .find(|number| !number.is_empty())
+                    .map(str::to_string),
+                is_exit_row: exit_rows.contains(&x),
+                is_over_wing: wings.as_ref().is_some_and(|wings| wings.contains(&x)),
+                cells,
+            }
+        })
+        .collect();
+
+    DeckGrid { columns, rows }
+}
+
+/// Add the grid view to every deck of the seatmaps
+pub fn add_grids(resp: &mut SeatmapResponse) {
+    for deck in resp.data.iter_mut().flat_map(|seatmap| &mut seatmap.decks) {
+        deck.grid = Some(build_grid(deck));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deck() -> Deck {
+        let seat = |number: &str, x: i32, y: i32, status: &str| {
+            serde_json::json!({
+                "number": number,
+                "characteristicsCodes": ["CH"],
+                "coordinates": { "x": x, "y": y },
+                "travelerPricing": [{ "travelerId": "1", "seatAvailabilityStatus": status }]
+            })
+        };
+        serde_json::from_value(serde_json::json!({
+            "deckType": "MAIN",
+            "deckConfiguration": {
+                "width": 5,
+                "length": 3,
+                "startWingsX": 2,
+                "endWingsX": 3,
+                "exitRowsX": [2]
+            },
+            "seats": [
+                seat("10A", 1, 0, "AVAILABLE"),
+                seat("10B", 1, 1, "OCCUPIED"),
+                seat("10D", 1, 3, "AVAILABLE"),
+                seat("10E", 1, 4, "BLOCKED"),
+                seat("11A", 2, 0, "AVAILABLE"),
+                seat("11E", 2, 4, "AVAILABLE")
+            ],
+            "facilities": [
+                { "code": "LA", "position": "FRONT", "coordinates": { "x": 0, "y": 0 } },
+                { "code": "XX", "position": "FRONT", "coordinates": { "x": 0, "y": 4 } }
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_grid_layout() {
+        let grid = build_grid(&deck());
+
+        let letters: Vec<Option<&str>> = grid
+            .columns
+            .iter()
+            .map(|column| column.letter.as_deref())
+            .collect();
+        assert_eq!(letters, [Some("A"), Some("B"), None, Some("D"), Some("E")]);
+        assert!(grid.columns[2].is_aisle);
+
+        assert_eq!(grid.rows.len(), 3);
+        let facilities = &grid.rows[0];
+        assert_eq!(facilities.number, None);
+        assert_eq!(
+            facilities.cells[0],
+            GridCell::Facility {
+                code: "LA".to_string(),
+                name: Some("LAVATORY".to_string())
+            }
+        );
+        assert_eq!(facilities.cells[1], GridCell::Empty);
+        assert_eq!(facilities.cells[2], GridCell::Aisle);
+
+        let row_10 = &grid.rows[1];
+        assert_eq!(row_10.number.as_deref(), Some("10"));
+        assert!(!row_10.is_exit_row && !row_10.is_over_wing);
+        let GridCell::Seat { ref status, .. } = row_10.cells[1] else {
+            panic!("expected a seat");
+        };
+        assert_eq!(status, &Some(SeatAvailabilityStatus::Occupied));
+
+        let row_11 = &grid.rows[2];
+        assert!(row_11.is_exit_row && row_11.is_over_wing);
+        assert_eq!(row_11.cells[1], GridCell::Empty);
+        assert_eq!(row_11.cells[2], GridCell::Aisle);
+    }
+}
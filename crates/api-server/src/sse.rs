@@ -5,22 +5,35 @@
 
 use axum::{
     Json,
-    extract::State,
+    extract::{Query, State},
     http::StatusCode,
-    response::sse::{Event, Sse},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, Sse},
+    },
 };
+use chrono::NaiveDate;
 use futures::stream::{self, Stream, StreamExt};
 use redis::AsyncCommands;
+use rust_decimal::Decimal;
 use std::convert::Infallible;
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, error, info, warn};
 
+use crate::currency::DisplayCurrencyQuery;
+use crate::markup::{ApplyMarkup, SalesContext};
 use crate::rate_limiter::RateLimiter;
+use crate::validation::{self, Validate, ValidationErrors};
 use crate::{
-    AppState, amadeus,
-    models::{FlightOffer, FlightPriceResponse, FlightSearchRequest, PriceMatrixRequest},
+    AppState, amadeus, brand_comparison,
+    models::{
+        BrandComparison, DisplayAmount, FlightOffer, FlightPriceResponse, FlightSearchRequest,
+        PriceMatrixRequest, PricingInclude, pricing_includes,
+    },
+    money::Money,
 };
+use crate::{fare_rules, price_change};
 
 /// Request payload for pricing stream
 #[derive(Debug, serde::Deserialize)]
@@ -28,6 +41,8 @@ use crate::{
 pub struct PricingStreamRequest {
     pub flight_offers: Vec<FlightOffer>,
     pub include_bags: bool,
+    #[serde(default)]
+    pub include: Vec<PricingInclude>,
 }
 
 /// Request payload for upsell stream
@@ -37,10 +52,27 @@ pub struct UpsellStreamRequest {
     pub flight_offers: Vec<FlightOffer>,
 }
 
+impl Validate for PricingStreamRequest {
+    fn validate_on(&self, _today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        validation::check_flight_offers(&mut errors, "flightOffers", &self.flight_offers);
+        validation::check_pricing_includes(&mut errors, "include", &self.include);
+        errors.into_result()
+    }
+}
+
+impl Validate for UpsellStreamRequest {
+    fn validate_on(&self, _today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        validation::check_flight_offers(&mut errors, "flightOffers", &self.flight_offers);
+        errors.into_result()
+    }
+}
+
 /// SSE event for pricing result
 #[derive(Debug, serde::Serialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
-#[allow(dead_code)]
+#[allow(dead_code, clippy::large_enum_variant)]
 pub enum PricingEvent {
     /// Pricing succeeded for an offer
     Success {
@@ -68,8 +100,10 @@ pub enum PriceMatrixEvent {
     Price {
         outbound_date: String,
         inbound_date: String,
-        price: Option<String>,
+        price: Option<Decimal>,
         currency: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        display_price: Option<DisplayAmount>,
     },
     /// Progress update
     Progress { current: usize, total: usize },
@@ -84,19 +118,25 @@ pub enum PriceMatrixEvent {
 /// Stream flight pricing results with rate limiting
 pub async fn flight_price_stream(
     State(state): State<Arc<AppState>>,
+    sales: SalesContext,
+    Query(display): Query<DisplayCurrencyQuery>,
     Json(payload): Json<PricingStreamRequest>,
-) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Response> {
     info!(
         "Pricing stream started for {} offers",
         payload.flight_offers.len()
     );
 
+    // Reject invalid requests before calling Amadeus
+    payload.validate().map_err(IntoResponse::into_response)?;
+    display.validate().map_err(IntoResponse::into_response)?;
+
     // Get token (cached)
     let token = match amadeus::get_token(&state.amadeus_client).await {
         Ok(t) => t,
         Err(e) => {
             error!("Amadeus token error: {:?}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
         }
     };
 
@@ -107,14 +147,23 @@ pub async fn flight_price_stream(
 
     // Clone data for the stream
     let client = state.amadeus_client.clone();
+    let currency = state.currency.clone();
+    let markup = state.markup.clone();
+    let display_currency = display.display_currency;
     let offers = payload.flight_offers;
-    let include_bags = payload.include_bags;
+    let include = pricing_includes(payload.include_bags, &payload.include);
+    let tolerance = state.price_tolerance;
 
     let stream = stream::iter(offers.into_iter().enumerate())
         .then(move |(index, offer)| {
             let client = client.clone();
             let token = token.clone();
             let limiter = rate_limiter.clone();
+            let currency = currency.clone();
+            let markup = markup.clone();
+            let sales = sales.clone();
+            let display_currency = display_currency.clone();
+            let include = include.clone();
 
             async move {
                 // Wait for rate limiter
@@ -132,8 +181,24 @@ pub async fn flight_price_stream(
                 let progress = Event::default().data(progress_json);
 
                 // Price the offer
-                match amadeus::price_flight_offers(&client, &token, &[offer], include_bags).await {
-                    Ok(result) => {
+                match amadeus::price_flight_offers(
+                    &client,
+                    &token,
+                    std::slice::from_ref(&offer),
+                    &include,
+                    None,
+                )
+                .await
+                {
+                    Ok(mut result) => {
+                        result.apply_markup(&markup, &sales, &currency.rates());
+                        fare_rules::attach_penalty_summaries(&mut result);
+                        price_change::attach_reports(
+                            &mut result,
+                            std::slice::from_ref(&offer),
+                            &tolerance,
+                        );
+                        currency.apply(display_currency.as_deref(), &mut result);
                         let event = PricingEvent::Success { offer_id, result };
                         let json = serde_json::to_string(&event).unwrap_or_default();
                         vec![Ok(progress), Ok(Event::default().data(json))]
@@ -167,6 +232,7 @@ pub enum UpsellEvent {
     Success {
         offer_id: String,
         upsells: Vec<FlightOffer>,
+        comparison: BrandComparison,
     },
     /// Upsell failed for an offer
     Error { offer_id: String, error: String },
@@ -177,19 +243,25 @@ pub enum UpsellEvent {
 /// Stream upsell options with rate limiting
 pub async fn upsell_stream(
     State(state): State<Arc<AppState>>,
+    sales: SalesContext,
+    Query(display): Query<DisplayCurrencyQuery>,
     Json(payload): Json<UpsellStreamRequest>,
-) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Response> {
     info!(
         "Upsell stream started for {} offers",
         payload.flight_offers.len()
     );
 
+    // Reject invalid requests before calling Amadeus
+    payload.validate().map_err(IntoResponse::into_response)?;
+    display.validate().map_err(IntoResponse::into_response)?;
+
     // Get token (cached)
     let token = match amadeus::get_token(&state.amadeus_client).await {
         Ok(t) => t,
         Err(e) => {
             error!("Amadeus token error: {:?}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
         }
     };
 
@@ -200,6 +272,9 @@ pub async fn upsell_stream(
 
     // Clone data for the stream
     let client = state.amadeus_client.clone();
+    let currency = state.currency.clone();
+    let markup = state.markup.clone();
+    let display_currency = display.display_currency;
     let offers = payload.flight_offers;
 
     let stream = stream::iter(offers.into_iter().enumerate())
@@ -207,6 +282,10 @@ pub async fn upsell_stream(
             let client = client.clone();
             let token = token.clone();
             let limiter = rate_limiter.clone();
+            let currency = currency.clone();
+            let markup = markup.clone();
+            let sales = sales.clone();
+            let display_currency = display_currency.clone();
 
             async move {
                 // Wait for rate limiter
@@ -225,9 +304,12 @@ pub async fn upsell_stream(
 
                 // Get upsell options
                 match amadeus::get_upsell_offers(&client, &token, &[offer]).await {
-                    Ok(result) => {
+                    Ok(mut result) => {
+                        result.apply_markup(&markup, &sales, &currency.rates());
+                        currency.apply(display_currency.as_deref(), &mut result);
                         let event = UpsellEvent::Success {
                             offer_id,
+                            comparison: brand_comparison::compare(&result.data),
                             upsells: result.data,
                         };
                         let json = serde_json::to_string(&event).unwrap_or_default();
@@ -256,8 +338,10 @@ pub async fn upsell_stream(
 /// Stream price matrix results with rate limiting
 pub async fn price_matrix_stream(
     State(state): State<Arc<AppState>>,
+    sales: SalesContext,
+    Query(display): Query<DisplayCurrencyQuery>,
     Json(payload): Json<PriceMatrixRequest>,
-) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Response> {
     info!(
         "Price matrix stream started: {} -> {}, {} outbound x {} inbound dates",
         payload.origin,
@@ -266,12 +350,16 @@ pub async fn price_matrix_stream(
         payload.inbound_dates.len()
     );
 
+    // Reject invalid requests before calling Amadeus
+    payload.validate().map_err(IntoResponse::into_response)?;
+    display.validate().map_err(IntoResponse::into_response)?;
+
     // Get token (cached)
     let token = match amadeus::get_token(&state.amadeus_client).await {
         Ok(t) => t,
         Err(e) => {
             error!("Amadeus token error: {:?}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
         }
     };
 
@@ -294,13 +382,20 @@ pub async fn price_matrix_stream(
     // Clone data for the stream
     let client = state.amadeus_client.clone();
     let redis_client = state.redis_client.clone();
+    let currency_service = state.currency.clone();
+    let markup = state.markup.clone();
+    let display_currency = display.display_currency;
     let currency = payload.currency.unwrap_or_else(|| "EUR".to_string());
     let origin = payload.origin;
     let destination = payload.destination;
     let adults = payload.adults;
     let children = payload.children;
     let infants = payload.infants;
-
+    let seated_infants = payload.seated_infants;
+    let seniors = payload.seniors;
+    let youths = payload.youths;
+    let students = payload.students;
+    let child_ages = payload.child_ages;
     let stream = stream::iter(combinations.into_iter().enumerate())
         .map(move |(index, (outbound, inbound))| {
             let client = client.clone();
@@ -308,8 +403,13 @@ pub async fn price_matrix_stream(
             let token = token.clone();
             let limiter = rate_limiter.clone();
             let currency = currency.clone();
+            let currency_service = currency_service.clone();
+            let markup = markup.clone();
+            let sales = sales.clone();
+            let display_currency = display_currency.clone();
             let origin = origin.clone();
             let destination = destination.clone();
+            let child_ages = child_ages.clone();
 
             async move {
                 // Wait for rate limiter (only if we need to call API)
@@ -325,9 +425,19 @@ pub async fn price_matrix_stream(
                     adults,
                     children,
                     infants,
+                    seated_infants,
+                    seniors,
+                    youths,
+                    students,
+                    child_ages,
                     currency: Some(currency.clone()),
                     travel_class: None,
+                    cabin_coverage: None,
                     non_stop: None,
+                    max_connections: None,
+                    return_travel_class: None,
+                    return_cabin_coverage: None,
+                    return_max_connections: None,
                     max_price: None,
                     max_results: Some(250),
                     included_airline_codes: None,
@@ -336,31 +446,15 @@ pub async fn price_matrix_stream(
                 };
 
                 // Generate cache key
-                let cache_key = format!(
-                    "flight_search:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}",
-                    req.origin,
-                    req.destination,
-                    req.departure_date,
-                    req.return_date.as_deref().unwrap_or(""),
-                    req.adults,
-                    req.children,
-                    req.infants,
-                    req.travel_class.as_deref().unwrap_or("ECONOMY"),
-                    req.non_stop.unwrap_or(false),
-                    req.max_results.unwrap_or(50)
-                );
+                let cache_key = req.cache_key();
 
                 // Try to get from cache first
                 let cached_result = if let Some(ref r_client) = redis_client {
                     match r_client.get_multiplexed_async_connection().await {
                         Ok(mut conn) => match conn.get::<_, String>(&cache_key).await {
                             Ok(cached) => {
-                                match serde_json::from_str::<crate::models::FlightOffersResponse>(
-                                    &cached,
-                                ) {
-                                    Ok(resp) => Some(resp),
-                                    Err(_) => None,
-                                }
+                                serde_json::from_str::<crate::models::FlightOffersResponse>(&cached)
+                                    .ok()
                             }
                             Err(_) => None,
                         },
@@ -370,27 +464,26 @@ pub async fn price_matrix_stream(
                     None
                 };
 
-                let price = if let Some(resp) = cached_result {
+                let price = if let Some(mut resp) = cached_result {
                     debug!("Cache hit for {} -> {}", outbound, inbound);
-                    resp.data.first().map(|offer| offer.price.total.clone())
+                    resp.apply_markup(&markup, &sales, &currency_service.rates());
+                    resp.data.iter().map(|offer| offer.price.total).min()
                 } else {
                     // Not in cache, proceed with API call
                     limiter.wait().await;
 
                     match amadeus::search_flights(&client, &token, &req).await {
-                        Ok(resp) => {
+                        Ok(mut resp) => {
                             // Cache success response
-                            if let Some(ref r_client) = redis_client {
-                                if let Ok(mut conn) =
+                            if let Some(ref r_client) = redis_client
+                                && let Ok(mut conn) =
                                     r_client.get_multiplexed_async_connection().await
-                                {
-                                    if let Ok(json) = serde_json::to_string(&resp) {
-                                        // 300 seconds TTL (5 mins)
-                                        let _: Result<(), _> =
-                                            conn.set_ex(&cache_key, json, 300).await;
-                                    }
-                                }
+                                && let Ok(json) = serde_json::to_string(&resp)
+                            {
+                                // 300 seconds TTL (5 mins)
+                                let _: Result<(), _> = conn.set_ex(&cache_key, json, 300).await;
                             }
+                            resp.apply_markup(&markup, &sales, &currency_service.rates());
 
                             if resp.data.is_empty() {
                                 warn!("⚠️ No flights found for {} -> {}", outbound, inbound);
@@ -403,10 +496,10 @@ pub async fn price_matrix_stream(
                                     inbound,
                                     resp.data
                                         .first()
-                                        .map(|o| o.price.total.as_str())
-                                        .unwrap_or("N/A")
+                                        .map(|o| o.price.total_money().to_string())
+                                        .unwrap_or_else(|| "N/A".to_string())
                                 );
-                                resp.data.first().map(|offer| offer.price.total.clone())
+                                resp.data.iter().map(|offer| offer.price.total).min()
                             }
                         }
                         Err(e) => {
@@ -420,11 +513,20 @@ pub async fn price_matrix_stream(
                 };
 
                 // Send price event
+                let display_price =
+                    price
+                        .zip(display_currency.as_deref())
+                        .and_then(|(price, display_currency)| {
+                            currency_service
+                                .rates()
+                                .convert(&Money::new(price, currency.clone()), display_currency)
+                        });
                 let price_event = PriceMatrixEvent::Price {
                     outbound_date: outbound.clone(),
                     inbound_date: inbound.clone(),
                     price,
                     currency: currency.clone(),
+                    display_price,
                 };
 
                 let mut events = vec![
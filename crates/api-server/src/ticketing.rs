@@ -0,0 +1,508 @@
+//! Ticketing deadlines
+//!
+//! An Amadeus order must be ticketed before the offers' last ticketing date
+//! and, with a DELAY_TO_QUEUE or DELAY_TO_CANCEL ticketing agreement, before
+//! the agreement's date. The earliest of them is stored on the booking when the
+//! order is created. A background task checks the ordered bookings
+//! periodically, sends a reminder notification as each reminder offset is
+//! reached and, once the deadline has passed, cancels the order at Amadeus if
+//! the policy asks for it, releasing or refunding the payment taken for it (see
+//! `payments`). Every outcome is recorded in `booking_events` and sent through
+//! `notifications`.
+//!
+//! Amadeus gives last ticketing times in the local time of the office the
+//! order was created in, usually without an offset. They are read in
+//! AMADEUS_OFFICE_UTC_OFFSET (e.g. "+05:30", default UTC); with the default,
+//! deadlines of offices east of UTC come out too late by their offset.
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+use std::sync::{Arc, OnceLock};
+
+use crate::amadeus;
+use crate::bookings::{BookingRecord, BookingStatus, BookingStore, BookingUpdate};
+use crate::models::{FlightOrderData, TicketingOption};
+use crate::notifications::{Notification, Notifier};
+use crate::payments::{PaymentProvider, Refund, settle_cancellation};
+
+/// Hours before the deadline at which reminders are recorded
+pub const DEFAULT_REMINDER_HOURS: &[i64] = &[24, 2];
+
+/// How often stored bookings are checked (5 minutes)
+pub const DEFAULT_CHECK_INTERVAL_SECS: u64 = 300;
+
+/// Event recorded when the deadline passed and the order was left alone
+pub const DEADLINE_PASSED_EVENT: &str = "TICKETING_DEADLINE_PASSED";
+
+/// Event recorded when the order was cancelled at Amadeus
+pub const AUTO_CANCELLED_EVENT: &str = "TICKETING_AUTO_CANCELLED";
+
+/// Event recorded when cancelling the order failed (retried on the next check)
+pub const AUTO_CANCEL_FAILED_EVENT: &str = "TICKETING_AUTO_CANCEL_FAILED";
+
+static OFFICE_OFFSET: OnceLock<FixedOffset> = OnceLock::new();
+
+/// UTC offset of the Amadeus office (AMADEUS_OFFICE_UTC_OFFSET, default UTC)
+fn office_offset() -> FixedOffset {
+    *OFFICE_OFFSET.get_or_init(|| {
+        let utc = FixedOffset::east_opt(0).expect("valid offset");
+        match std::env::var("AMADEUS_OFFICE_UTC_OFFSET") {
+            Ok(value) if !value.trim().is_empty() => value.trim().parse().unwrap_or_else(|_| {
+                tracing::warn!("Invalid AMADEUS_OFFICE_UTC_OFFSET {:?}, using UTC", value);
+                utc
+            }),
+            _ => utc,
+        }
+    })
+}
+
+/// Amadeus date ("2025-06-14") or local date-time ("2025-06-14T18:00:00").
+/// Dates stand for the end of the day; dates and times without an offset are
+/// in the office's local time.
+fn parse_deadline(value: &str, office: FixedOffset) -> Option<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Some(at.with_timezone(&Utc));
+    }
+    let local = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(23, 59, 59))
+        })?;
+    local
+        .and_local_timezone(office)
+        .single()
+        .map(|at| at.with_timezone(&Utc))
+}
+
+/// Latest time an order can be ticketed: the earliest last ticketing date of its
+/// offers and, for delayed ticketing, the date of the ticketing agreement
+pub fn deadline(order: &FlightOrderData) -> Option<DateTime<Utc>> {
+    deadline_in(order, office_offset())
+}
+
+/// [`deadline`] for an office at the given UTC offset
+fn deadline_in(order: &FlightOrderData, office: FixedOffset) -> Option<DateTime<Utc>> {
+    let parse = |value: &str| parse_deadline(value, office);
+    let offers = order.flight_offers.iter().filter_map(|offer| {
+        offer
+            .last_ticketing_date_time
+            .as_deref()
+            .or(offer.last_ticketing_date.as_deref())
+            .and_then(parse)
+    });
+    let agreement = order
+        .ticketing_agreement
+        .as_ref()
+        .filter(|agreement| {
+            matches!(
+                agreement.option,
+                TicketingOption::DelayToQueue | TicketingOption::DelayToCancel
+            )
+        })
+        .and_then(|agreement| agreement.date_time.as_deref())
+        .and_then(parse);
+    offers.chain(agreement).min()
+}
+
+/// What the scheduler does about unticketed orders
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TicketingPolicy {
+    /// Reminder offsets before the deadline, in hours
+    pub reminder_hours: Vec<i64>,
+    /// Cancel every order whose deadline passed. Orders with a DELAY_TO_CANCEL
+    /// agreement are always cancelled.
+    pub auto_cancel: bool,
+    pub check_interval: std::time::Duration,
+}
+
+impl Default for TicketingPolicy {
+    fn default() -> Self {
+        Self {
+            reminder_hours: DEFAULT_REMINDER_HOURS.to_vec(),
+            auto_cancel: false,
+            check_interval: std::time::Duration::from_secs(DEFAULT_CHECK_INTERVAL_SECS),
+        }
+    }
+}
+
+impl TicketingPolicy {
+    /// Read TICKETING_REMINDER_HOURS (comma-separated, default "24,2"),
+    /// TICKETING_AUTO_CANCEL (default false) and TICKETING_CHECK_INTERVAL_SECS
+    /// (default 300)
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let reminder_hours = std::env::var("TICKETING_REMINDER_HOURS")
+            .ok()
+            .map(|hours| {
+                hours
+                    .split(',')
+                    .filter_map(|hours| hours.trim().parse().ok())
+                    .filter(|hours| *hours > 0)
+                    .collect()
+            })
+            .unwrap_or(default.reminder_hours);
+        let auto_cancel = std::env::var("TICKETING_AUTO_CANCEL")
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(default.auto_cancel);
+        let check_interval = std::env::var("TICKETING_CHECK_INTERVAL_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .filter(|secs| *secs > 0)
+            .map(std::time::Duration::from_secs)
+            .unwrap_or(default.check_interval);
+        Self {
+            reminder_hours,
+            auto_cancel,
+            check_interval,
+        }
+    }
+
+    /// How far ahead of a deadline bookings need to be looked at
+    fn horizon(&self) -> Duration {
+        Duration::hours(self.reminder_hours.iter().copied().max().unwrap_or(0))
+    }
+
+    fn cancels(&self, option: Option<&TicketingOption>) -> bool {
+        self.auto_cancel || option == Some(&TicketingOption::DelayToCancel)
+    }
+}
+
+/// What is due for an unticketed order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TicketingAction {
+    /// The deadline is at most `hours` away
+    Remind { hours: i64 },
+    /// The deadline passed and the order is cancelled
+    Cancel,
+    /// The deadline passed and the order is left alone
+    Expired,
+}
+
+impl TicketingAction {
+    /// Kind of the booking event recording the action
+    pub fn event_kind(&self) -> String {
+        match self {
+            TicketingAction::Remind { hours } => format!("TICKETING_REMINDER_{}H", hours),
+            TicketingAction::Cancel => AUTO_CANCELLED_EVENT.to_string(),
+            TicketingAction::Expired => DEADLINE_PASSED_EVENT.to_string(),
+        }
+    }
+
+    /// Subject of the notification about the action
+    pub fn subject(&self, deadline: DateTime<Utc>) -> String {
+        match self {
+            TicketingAction::Remind { hours } => format!(
+                "Ticketing deadline {} is less than {} hours away",
+                deadline.to_rfc3339(),
+                hours
+            ),
+            TicketingAction::Cancel => format!(
+                "Ticketing deadline {} passed, cancelling the order",
+                deadline.to_rfc3339()
+            ),
+            TicketingAction::Expired => format!(
+                "Ticketing deadline {} passed without ticketing",
+                deadline.to_rfc3339()
+            ),
+        }
+    }
+}
+
+/// The action due at `now`. Only the closest reminder is returned, so a
+/// scheduler that was down does not send every missed reminder at once.
+pub fn due_action(
+    deadline: DateTime<Utc>,
+    now: DateTime<Utc>,
+    policy: &TicketingPolicy,
+    option: Option<&TicketingOption>,
+) -> Option<TicketingAction> {
+    if now >= deadline {
+        return Some(if policy.cancels(option) {
+            TicketingAction::Cancel
+        } else {
+            TicketingAction::Expired
+        });
+    }
+    policy
+        .reminder_hours
+        .iter()
+        .copied()
+        .filter(|hours| deadline - now <= Duration::hours(*hours))
+        .min()
+        .map(|hours| TicketingAction::Remind { hours })
+}
+
+/// Cancel the order of a booking whose deadline passed, record the outcome and
+/// notify about it
+async fn cancel(
+    store: &BookingStore,
+    client: &reqwest::Client,
+    payments: Option<&dyn PaymentProvider>,
+    notifier: &Notifier,
+    booking: &BookingRecord,
+) {
+    let Some(order_id) = booking.order_id.as_deref() else {
+        return;
+    };
+    let result = match amadeus::get_token(client).await {
+        Ok(token) => amadeus::delete_flight_order(client, &token, order_id).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => {
+            tracing::warn!(
+                "Cancelled order {} of booking {}: ticketing deadline passed",
+                order_id,
+                booking.id
+            );
+            let update = BookingUpdate::reason("Ticketing deadline passed");
+            if let Err(e) = store
+                .transition(booking.id, BookingStatus::Cancelled, update)
+                .await
+            {
+                tracing::error!("Failed to update booking {}: {}", booking.id, e);
+            }
+            if record(store, booking, AUTO_CANCELLED_EVENT, Some(order_id)).await {
+                let subject = format!("Order {} cancelled: ticketing deadline passed", order_id);
+                notifier
+                    .send(&Notification::new(booking, subject, Vec::new()))
+                    .await;
+            }
+            // Nothing was ticketed, so everything goes back
+            if let Some(provider) = payments {
+                settle_cancellation(provider, store, booking.id, Refund::Full).await;
+            }
+        }
+        Err(e) => {
+            tracing::error!(
+                "Failed to cancel order {} of booking {}: {:#}",
+                order_id,
+                booking.id,
+                e
+            );
+            // Failures are retried on every check; only the first one is sent
+            if record(
+                store,
+                booking,
+                AUTO_CANCEL_FAILED_EVENT,
+                Some(&format!("{:#}", e)),
+            )
+            .await
+            {
+                let subject = format!("Cancelling order {} failed: {:#}", order_id, e);
+                notifier
+                    .send(&Notification::new(booking, subject, Vec::new()))
+                    .await;
+            }
+        }
+    }
+}
+
+/// Record an event, returning whether it is new
+async fn record(
+    store: &BookingStore,
+    booking: &BookingRecord,
+    kind: &str,
+    detail: Option<&str>,
+) -> bool {
+    match store.record_event(booking.id, kind, detail).await {
+        Ok(new) => new,
+        Err(e) => {
+            tracing::error!("Failed to record {} of booking {}: {}", kind, booking.id, e);
+            false
+        }
+    }
+}
+
+/// Check all ordered bookings with a deadline within the reminder horizon
+pub async fn check_deadlines(
+    store: &BookingStore,
+    client: &reqwest::Client,
+    payments: Option<&dyn PaymentProvider>,
+    notifier: &Notifier,
+    policy: &TicketingPolicy,
+    now: DateTime<Utc>,
+) {
+    let bookings = match store.ticketing_due(now + policy.horizon()).await {
+        Ok(bookings) => bookings,
+        Err(e) => {
+            tracing::error!("Failed to load bookings due for ticketing: {}", e);
+            return;
+        }
+    };
+
+    for booking in bookings {
+        let Some(deadline) = booking.ticketing_deadline else {
+            continue;
+        };
+        let option = booking
+            .ticketing_option
+            .as_deref()
+            .map(TicketingOption::from);
+        match due_action(deadline, now, policy, option.as_ref()) {
+            Some(TicketingAction::Cancel) => {
+                cancel(store, client, payments, notifier, &booking).await
+            }
+            Some(action) => {
+                let detail = format!("Ticketing deadline {}", deadline.to_rfc3339());
+                if record(store, &booking, &action.event_kind(), Some(&detail)).await {
+                    let subject = action.subject(deadline);
+                    notifier
+                        .send(&Notification::new(&booking, subject, Vec::new()))
+                        .await;
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+/// Check the ticketing deadlines of stored bookings in the background
+pub fn spawn(
+    store: BookingStore,
+    client: reqwest::Client,
+    payments: Option<Arc<dyn PaymentProvider>>,
+    notifier: Notifier,
+    policy: TicketingPolicy,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(policy.check_interval);
+        loop {
+            interval.tick().await;
+            check_deadlines(
+                &store,
+                &client,
+                payments.as_deref(),
+                &notifier,
+                &policy,
+                Utc::now(),
+            )
+            .await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc() -> FixedOffset {
+        FixedOffset::east_opt(0).unwrap()
+    }
+
+    fn at(value: &str) -> DateTime<Utc> {
+        parse_deadline(value, utc()).unwrap()
+    }
+
+    #[test]
+    fn test_deadline() {
+        let offer = |last_date: &str, last_date_time: Option<&str>| {
+            serde_json::json!({
+                "id": "1",
+                "type": "flight-offer",
+                "source": "GDS",
+                "lastTicketingDate": last_date,
+                "lastTicketingDateTime": last_date_time,
+                "itineraries": [],
+                "price": { "currency": "EUR", "total": "300.00", "base": "240.00" },
+                "validatingAirlineCodes": ["LH"],
+                "travelerPricings": []
+            })
+        };
+        let order = |offers: Vec<serde_json::Value>, agreement: serde_json::Value| {
+            serde_json::from_value::<FlightOrderData>(serde_json::json!({
+                "type": "flight-order",
+                "id": "eJzTd9f3NjIJdzUGAAp%2fAiY=",
+                "associatedRecords": [],
+                "travelers": [],
+                "flightOffers": offers,
+                "ticketingAgreement": agreement
+            }))
+            .unwrap()
+        };
+
+        // The end of the last ticketing date, or its date-time when given
+        let data = order(
+            vec![offer("2025-06-14", None), offer("2025-06-12", None)],
+            serde_json::Value::Null,
+        );
+        assert_eq!(deadline_in(&data, utc()), Some(at("2025-06-12T23:59:59")));
+        let data = order(
+            vec![offer("2025-06-14", Some("2025-06-14T12:00:00"))],
+            serde_json::json!({ "option": "CONFIRM" }),
+        );
+        assert_eq!(deadline_in(&data, utc()), Some(at("2025-06-14T12:00:00")));
+
+        // Delayed ticketing must happen before the agreement's date
+        let data = order(
+            vec![offer("2025-06-14", None)],
+            serde_json::json!({ "option": "DELAY_TO_CANCEL", "dateTime": "2025-06-10T09:30:00" }),
+        );
+        assert_eq!(deadline_in(&data, utc()), Some(at("2025-06-10T09:30:00")));
+        let data = order(
+            vec![offer("2025-06-14", None)],
+            serde_json::json!({ "option": "DELAY_TO_QUEUE", "dateTime": "2025-06-20T09:30:00" }),
+        );
+        assert_eq!(deadline_in(&data, utc()), Some(at("2025-06-14T23:59:59")));
+
+        // Local times of an office east of UTC are earlier in UTC
+        let india = FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap();
+        let data = order(
+            vec![offer("2025-06-14", Some("2025-06-14T12:00:00"))],
+            serde_json::Value::Null,
+        );
+        assert_eq!(deadline_in(&data, india), Some(at("2025-06-14T06:30:00")));
+        let data = order(vec![offer("2025-06-14", None)], serde_json::Value::Null);
+        assert_eq!(deadline_in(&data, india), Some(at("2025-06-14T18:29:59")));
+        assert_eq!(
+            parse_deadline("2025-06-14T12:00:00+02:00", india),
+            Some(at("2025-06-14T10:00:00"))
+        );
+
+        let data = order(vec![], serde_json::Value::Null);
+        assert_eq!(deadline_in(&data, utc()), None);
+    }
+
+    #[test]
+    fn test_due_action() {
+        let policy = TicketingPolicy::default();
+        let deadline = at("2025-06-14T12:00:00");
+        let due = |now: &str, option: Option<TicketingOption>| {
+            due_action(deadline, at(now), &policy, option.as_ref())
+        };
+
+        assert_eq!(due("2025-06-12T12:00:00", None), None);
+        assert_eq!(
+            due("2025-06-13T12:00:00", None),
+            Some(TicketingAction::Remind { hours: 24 })
+        );
+        assert_eq!(
+            due("2025-06-14T11:00:00", None),
+            Some(TicketingAction::Remind { hours: 2 })
+        );
+        assert_eq!(
+            due("2025-06-14T12:00:00", None),
+            Some(TicketingAction::Expired)
+        );
+        assert_eq!(
+            due("2025-06-14T12:00:00", Some(TicketingOption::DelayToCancel)),
+            Some(TicketingAction::Cancel)
+        );
+
+        let policy = TicketingPolicy {
+            auto_cancel: true,
+            ..policy
+        };
+        assert_eq!(
+            due_action(deadline, at("2025-06-15T00:00:00"), &policy, None),
+            Some(TicketingAction::Cancel)
+        );
+        assert_eq!(policy.horizon(), Duration::hours(24));
+        assert_eq!(
+            TicketingAction::Remind { hours: 24 }.event_kind(),
+            "TICKETING_REMINDER_24H"
+        );
+    }
+}
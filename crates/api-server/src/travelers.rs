@@ -0,0 +1,542 @@
+//! Traveler checks for flight orders
+//!
+//! Amadeus rejects orders with traveler data the airline cannot use, usually
+//! with a single error for the whole request. We check the travelers, their
+//! documents and the contacts up front and report every problem per field:
+//! names, phone numbers and email addresses must have a format airlines
+//! accept, ages must match the traveler types of the offer, and documents
+//! must stay valid until the last flight. On top, pricing returns booking
+//! requirements saying which data the airline needs for this offer.
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::models::{
+    BookingRequirements, DeviceType, FlightOffer, FlightOrderRequest, Phone, Traveler, TravelerType,
+};
+use crate::passengers::CHILD_AGES;
+use crate::validation::{ValidationErrors, check_date};
+
+/// Longest first or last name airlines accept
+pub const MAX_NAME_LENGTH: usize = 56;
+
+/// E.164 allows at most 15 digits including the country calling code
+const MAX_PHONE_DIGITS: usize = 15;
+
+/// Gender values accepted by Flight Create Orders
+const GENDERS: &[&str] = &["MALE", "FEMALE", "UNSPECIFIED", "UNDISCLOSED"];
+
+/// Completed years of a person born on `birth_date` at `date`
+pub fn age_on(birth_date: NaiveDate, date: NaiveDate) -> i32 {
+    let mut age = date.year() - birth_date.year();
+    if (date.month(), date.day()) < (birth_date.month(), birth_date.day()) {
+        age -= 1;
+    }
+    age
+}
+
+fn segment_dates(offers: &[FlightOffer], arrival: bool) -> Vec<NaiveDate> {
+    offers
+        .iter()
+        .flat_map(|offer| &offer.itineraries)
+        .flat_map(|itinerary| &itinerary.segments)
+        .filter_map(|segment| {
+            let at = if arrival {
+                &segment.arrival.at
+            } else {
+                &segment.departure.at
+            };
+            NaiveDate::parse_from_str(at.get(..10)?, "%Y-%m-%d").ok()
+        })
+        .collect()
+}
+
+/// Letters, spaces, hyphens and apostrophes, starting with a letter
+fn check_name(errors: &mut ValidationErrors, field: &str, name: &str) {
+    if name.trim().is_empty() {
+        errors.add(field, "Name is required");
+    } else if !name.starts_with(|c: char| c.is_ascii_alphabetic())
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphabetic() || matches!(c, ' ' | '-' | '\''))
+    {
+        errors.add(
+            field,
+            format!(
+                "Must contain only letters A-Z, spaces, hyphens and apostrophes, got '{}'",
+                name
+            ),
+        );
+    } else if name.len() > MAX_NAME_LENGTH {
+        errors.add(
+            field,
+            format!("Must be at most {} characters", MAX_NAME_LENGTH),
+        );
+    }
+}
+
+/// Country calling code and number as digits, together at most 15 digits
+pub fn check_phone(errors: &mut ValidationErrors, field: &str, phone: &Phone) {
+    let calling_code = &phone.country_calling_code;
+    if !(1..=3).contains(&calling_code.len()) || !calling_code.bytes().all(|b| b.is_ascii_digit()) {
+        errors.add(
+            format!("{}.countryCallingCode", field),
+            format!("Must be 1 to 3 digits without '+', got '{}'", calling_code),
+        );
+    }
+    if phone.number.len() < 4 || !phone.number.bytes().all(|b| b.is_ascii_digit()) {
+        errors.add(
+            format!("{}.number", field),
+            format!("Must be at least 4 digits, got '{}'", phone.number),
+        );
+    } else if calling_code.len() + phone.number.len() > MAX_PHONE_DIGITS {
+        errors.add(
+            format!("{}.number", field),
+            format!(
+                "Must have at most {} digits including the country calling code",
+                MAX_PHONE_DIGITS
+            ),
+        );
+    }
+}
+
+pub fn check_email(errors: &mut ValidationErrors, field: &str, email: &str) {
+    let valid = match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !email.contains(char::is_whitespace)
+        }
+        None => false,
+    };
+    if !valid {
+        errors.add(field, format!("Must be an email address, got '{}'", email));
+    }
+}
+
+/// Whether a traveler of `traveler_type` may be `age` years old
+fn check_age(
+    errors: &mut ValidationErrors,
+    field: &str,
+    traveler_type: &TravelerType,
+    age_at_departure: i32,
+    age_at_return: i32,
+) {
+    let message = match traveler_type {
+        TravelerType::HeldInfant | TravelerType::SeatedInfant if age_at_return >= 2 => {
+            Some("Infants must be under 2 years old for the whole trip")
+        }
+        TravelerType::Child if !CHILD_AGES.contains(&(age_at_departure.max(0) as u32)) => {
+            Some("Children must be 2 to 11 years old at departure")
+        }
+        TravelerType::Adult | TravelerType::Senior if age_at_departure < 12 => {
+            Some("Adults must be at least 12 years old at departure")
+        }
+        _ => None,
+    };
+    if let Some(message) = message {
+        errors.add(
+            field,
+            format!("{} ({} is {})", message, traveler_type, age_at_departure),
+        );
+    }
+}
+
+fn check_traveler(
+    errors: &mut ValidationErrors,
+    field: &str,
+    traveler: &Traveler,
+    offers: &[FlightOffer],
+    today: NaiveDate,
+) {
+    check_name(
+        errors,
+        &format!("{}.name.firstName", field),
+        &traveler.name.first_name,
+    );
+    check_name(
+        errors,
+        &format!("{}.name.lastName", field),
+        &traveler.name.last_name,
+    );
+    if let Some(ref middle_name) = traveler.name.middle_name {
+        check_name(errors, &format!("{}.name.middleName", field), middle_name);
+    }
+    if let Some(ref gender) = traveler.gender
+        && !GENDERS.contains(&gender.as_str())
+    {
+        errors.add(
+            format!("{}.gender", field),
+            format!("Must be one of {}, got '{}'", GENDERS.join(", "), gender),
+        );
+    }
+
+    if let Some(ref contact) = traveler.contact {
+        if let Some(ref email) = contact.email_address {
+            check_email(errors, &format!("{}.contact.emailAddress", field), email);
+        }
+        for (i, phone) in contact.phones.iter().flatten().enumerate() {
+            check_phone(errors, &format!("{}.contact.phones[{}]", field, i), phone);
+        }
+    }
+
+    // Documents must stay valid until the last flight
+    let last_flight = segment_dates(offers, true).into_iter().max();
+    for (i, document) in traveler.documents.iter().flatten().enumerate() {
+        let document_field = format!("{}.documents[{}]", field, i);
+        if document.number.trim().is_empty() {
+            errors.add(
+                format!("{}.number", document_field),
+                "Document number is required",
+            );
+        }
+        let expiry_field = format!("{}.expiryDate", document_field);
+        if let Some(expiry) = check_date(errors, &expiry_field, &document.expiry_date) {
+            match last_flight {
+                Some(last_flight) if expiry <= last_flight => errors.add(
+                    expiry_field,
+                    format!("Document expires before the last flight on {}", last_flight),
+                ),
+                _ if expiry <= today => errors.add(expiry_field, "Document has expired"),
+                _ => {}
+            }
+        }
+        for (name, code) in [
+            ("issuanceCountry", &document.issuance_country),
+            ("nationality", &document.nationality),
+        ] {
+            if code.len() != 2 || !code.bytes().all(|b| b.is_ascii_uppercase()) {
+                errors.add(
+                    format!("{}.{}", document_field, name),
+                    format!("Must be a 2-letter ISO country code, got '{}'", code),
+                );
+            }
+        }
+    }
+
+    // Age must match the traveler type priced in the offer
+    let Ok(birth_date) = NaiveDate::parse_from_str(&traveler.date_of_birth, "%Y-%m-%d") else {
+        return;
+    };
+    let departures = segment_dates(offers, false);
+    let (Some(first), Some(last)) = (departures.iter().min(), departures.iter().max()) else {
+        return;
+    };
+    let pricing = offers.first().and_then(|offer| {
+        offer
+            .traveler_pricings
+            .iter()
+            .find(|pricing| pricing.traveler_id == traveler.id)
+    });
+    match pricing {
+        Some(pricing) => check_age(
+            errors,
+            &format!("{}.dateOfBirth", field),
+            &pricing.traveler_type,
+            age_on(birth_date, *first),
+            age_on(birth_date, *last),
+        ),
+        None => errors.add(
+            format!("{}.id", field),
+            format!("No traveler {} in the flight offer", traveler.id),
+        ),
+    }
+}
+
+/// Check names, contacts, documents and ages of all travelers
+pub fn check_travelers(
+    errors: &mut ValidationErrors,
+    request: &FlightOrderRequest,
+    today: NaiveDate,
+) {
+    for (i, traveler) in request.travelers.iter().enumerate() {
+        check_traveler(
+            errors,
+            &format!("travelers[{}]", i),
+            traveler,
+            &request.flight_offers,
+            today,
+        );
+    }
+    for (i, contact) in request.contacts.iter().flatten().enumerate() {
+        if let Some(ref email) = contact.email_address {
+            check_email(errors, &format!("contacts[{}].emailAddress", i), email);
+        }
+        for (j, phone) in contact.phones.iter().flatten().enumerate() {
+            check_phone(errors, &format!("contacts[{}].phones[{}]", i, j), phone);
+        }
+    }
+}
+
+/// Check the order against the booking requirements returned by pricing
+pub fn check_booking_requirements(
+    errors: &mut ValidationErrors,
+    request: &FlightOrderRequest,
+    requirements: &BookingRequirements,
+) {
+    let contacts = request.contacts.iter().flatten();
+    let traveler_contacts = request
+        .travelers
+        .iter()
+        .filter_map(|traveler| traveler.contact.as_ref());
+    let has_email = contacts
+        .clone()
+        .any(|contact| contact.email_address.is_some())
+        || traveler_contacts
+            .clone()
+            .any(|contact| contact.email_address.is_some());
+    let has_mobile = contacts
+        .clone()
+        .flat_map(|contact| contact.phones.iter().flatten())
+        .chain(traveler_contacts.flat_map(|contact| contact.phones.iter().flatten()))
+        .any(|phone| phone.device_type == DeviceType::Mobile);
+
+    if requirements.email_address_required && !has_email {
+        errors.add("contacts", "The airline requires an email address");
+    }
+    if requirements.mobile_phone_number_required && !has_mobile {
+        errors.add("contacts", "The airline requires a mobile phone number");
+    }
+
+    for requirement in &requirements.traveler_requirements {
+        let Some((i, traveler)) = request
+            .travelers
+            .iter()
+            .enumerate()
+            .find(|(_, traveler)| traveler.id == requirement.traveler_id)
+        else {
+            errors.add(
+                "travelers",
+                format!("Traveler {} is missing", requirement.traveler_id),
+            );
+            continue;
+        };
+        let field = format!("travelers[{}]", i);
+        let documents = traveler.documents.as_deref().unwrap_or_default();
+
+        if requirement.date_of_birth_required && traveler.date_of_birth.trim().is_empty() {
+            errors.add(
+                format!("{}.dateOfBirth", field),
+                "The airline requires the date of birth",
+            );
+        }
+        if requirement.gender_required && traveler.gender.is_none() {
+            errors.add(
+                format!("{}.gender", field),
+                "The airline requires the gender",
+            );
+        }
+        if requirement.document_required && documents.is_empty() {
+            errors.add(
+                format!("{}.documents", field),
+                "The airline requires a travel document",
+            );
+        }
+        if requirement.document_issuance_city_required {
+            for (j, document) in documents.iter().enumerate() {
+                if document.issuance_location.is_none() {
+                    errors.add(
+                        format!("{}.documents[{}].issuanceLocation", field, j),
+                        "The airline requires the city the document was issued in",
+                    );
+                }
+            }
+        }
+        // Amadeus takes the residence from the address of the booking contacts
+        if requirement.residence_required
+            && !request
+                .contacts
+                .iter()
+                .flatten()
+                .any(|contact| contact.address.is_some())
+        {
+            errors.add(
+                "contacts",
+                format!(
+                    "The airline requires a residence address for traveler {}",
+                    traveler.id
+                ),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, 10).unwrap()
+    }
+
+    fn fields(errors: &ValidationErrors) -> Vec<&str> {
+        errors.errors.iter().map(|e| e.field.as_str()).collect()
+    }
+
+    fn order(travelers: serde_json::Value, contacts: serde_json::Value) -> FlightOrderRequest {
+        let pricing = |id: &str, traveler_type: &str| {
+            serde_json::json!({
+                "travelerId": id,
+                "fareOption": "STANDARD",
+                "travelerType": traveler_type,
+                "price": { "currency": "EUR", "total": "100.00", "base": "80.00" },
+                "fareDetailsBySegment": []
+            })
+        };
+        let segment = |id: &str, departure: &str, arrival: &str| {
+            serde_json::json!({
+                "id": id,
+                "departure": { "iataCode": "FRA", "at": departure },
+                "arrival": { "iataCode": "JFK", "at": arrival },
+                "carrierCode": "LH",
+                "number": "400",
+                "aircraft": { "code": "388" }
+            })
+        };
+        serde_json::from_value(serde_json::json!({
+            "flightOffers": [{
+                "id": "1",
+                "type": "flight-offer",
+                "source": "GDS",
+                "itineraries": [
+                    { "segments": [segment("1", "2025-03-01T10:00:00", "2025-03-01T13:00:00")] },
+                    { "segments": [segment("2", "2025-03-14T18:00:00", "2025-03-15T08:00:00")] }
+                ],
+                "price": { "currency": "EUR", "total": "300.00", "base": "240.00" },
+                "validatingAirlineCodes": ["LH"],
+                "travelerPricings": [
+                    pricing("1", "ADULT"),
+                    pricing("2", "CHILD"),
+                    pricing("3", "HELD_INFANT")
+                ]
+            }],
+            "travelers": travelers,
+            "contacts": contacts
+        }))
+        .unwrap()
+    }
+
+    fn traveler(id: &str, first_name: &str, date_of_birth: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "dateOfBirth": date_of_birth,
+            "gender": "FEMALE",
+            "name": { "firstName": first_name, "lastName": "DOE" }
+        })
+    }
+
+    fn family() -> serde_json::Value {
+        serde_json::json!([
+            traveler("1", "JANE", "1980-01-01"),
+            traveler("2", "JOHN", "2015-06-01"),
+            traveler("3", "JIM", "2024-01-01")
+        ])
+    }
+
+    #[test]
+    fn test_age_on() {
+        let birth = NaiveDate::from_ymd_opt(2023, 3, 15).unwrap();
+        assert_eq!(
+            age_on(birth, NaiveDate::from_ymd_opt(2025, 3, 14).unwrap()),
+            1
+        );
+        assert_eq!(
+            age_on(birth, NaiveDate::from_ymd_opt(2025, 3, 15).unwrap()),
+            2
+        );
+    }
+
+    #[test]
+    fn test_valid_travelers() {
+        let request = order(
+            family(),
+            serde_json::json!([{
+                "purpose": "STANDARD",
+                "emailAddress": "jane.doe@example.com",
+                "phones": [{ "deviceType": "MOBILE", "countryCallingCode": "49", "number": "1701234567" }]
+            }]),
+        );
+        let mut errors = ValidationErrors::new();
+        check_travelers(&mut errors, &request, today());
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_traveler_field_errors() {
+        let mut travelers = family();
+        travelers[0]["name"]["firstName"] = "J4NE".into();
+        travelers[0]["gender"] = "F".into();
+        travelers[0]["documents"] = serde_json::json!([{
+            "documentType": "PASSPORT",
+            "number": "C01X00T47",
+            "expiryDate": "2025-03-10",
+            "issuanceCountry": "DE",
+            "nationality": "de"
+        }]);
+        // Turns 12 before departure, and the infant turns 2 before the return flight
+        travelers[1]["dateOfBirth"] = "2013-02-01".into();
+        travelers[2]["dateOfBirth"] = "2023-03-10".into();
+        let request = order(
+            travelers,
+            serde_json::json!([{
+                "purpose": "STANDARD",
+                "emailAddress": "jane.doe@example",
+                "phones": [{ "deviceType": "MOBILE", "countryCallingCode": "+49", "number": "0170 123" }]
+            }]),
+        );
+
+        let mut errors = ValidationErrors::new();
+        check_travelers(&mut errors, &request, today());
+        assert_eq!(
+            fields(&errors),
+            [
+                "travelers[0].name.firstName",
+                "travelers[0].gender",
+                "travelers[0].documents[0].expiryDate",
+                "travelers[0].documents[0].nationality",
+                "travelers[1].dateOfBirth",
+                "travelers[2].dateOfBirth",
+                "contacts[0].emailAddress",
+                "contacts[0].phones[0].countryCallingCode",
+                "contacts[0].phones[0].number",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_booking_requirements() {
+        let requirements: BookingRequirements = serde_json::from_value(serde_json::json!({
+            "emailAddressRequired": true,
+            "mobilePhoneNumberRequired": true,
+            "travelerRequirements": [
+                { "travelerId": "1", "genderRequired": true, "documentRequired": true },
+                { "travelerId": "4", "dateOfBirthRequired": true }
+            ]
+        }))
+        .unwrap();
+        let mut travelers = family();
+        travelers[0]["gender"] = serde_json::Value::Null;
+        let request = order(
+            travelers,
+            serde_json::json!([{
+                "purpose": "STANDARD",
+                "phones": [{ "deviceType": "LANDLINE", "countryCallingCode": "49", "number": "301234567" }]
+            }]),
+        );
+
+        let mut errors = ValidationErrors::new();
+        check_booking_requirements(&mut errors, &request, &requirements);
+        assert_eq!(
+            fields(&errors),
+            [
+                "contacts",
+                "contacts",
+                "travelers[0].gender",
+                "travelers[0].documents",
+                "travelers"
+            ]
+        );
+    }
+}
@@ -0,0 +1,876 @@
+//! Request validation
+//!
+//! Validation runs before any call to Amadeus so that bad input is reported
+//! as a 400 with field-level details instead of a generic upstream error.
+
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::models::{
+    AcceptAlternativeRequest, BagSelectionRequest, Cabin, CabinCoverage,
+    CancellationConfirmRequest, CardTokenRequest, FlightAvailabilityRequest,
+    FlightChoicePredictionRequest, FlightOffer, FlightOrderRequest, FlightPriceRequest,
+    FlightSearchRequest, OrderDeletionQuery, PaymentCard, PriceMatrixRequest, PricingInclude,
+    SeatRecommendationRequest, SeatSelectionRequest, SeatmapRequest, UpsellRequest,
+};
+use crate::passengers::PassengerMix;
+use crate::redaction::luhn_valid;
+
+/// Amadeus only sells flights departing within the next 361 days
+pub const MAX_DAYS_AHEAD: i64 = 361;
+
+/// Amadeus allows at most 2 connections per origin-destination
+pub const MAX_CONNECTIONS: u32 = 2;
+
+/// Offset of the westernmost time zone (UTC−12), whose date is the last to change
+const EARLIEST_UTC_OFFSET_HOURS: i64 = -12;
+
+/// The date that is still current somewhere on earth. Travel dates are local
+/// dates, so a departure later today in a time zone behind UTC must not be
+/// rejected as past once the UTC date has moved on.
+pub fn earliest_local_date() -> NaiveDate {
+    (Utc::now() + Duration::hours(EARLIEST_UTC_OFFSET_HOURS)).date_naive()
+}
+
+/// Amadeus returns at most 250 offers per search
+pub const MAX_RESULTS: i32 = 250;
+
+/// Longest cancellation reason stored with a booking
+pub const MAX_REASON_LENGTH: usize = 500;
+
+/// A single invalid field in a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    /// Request field the error refers to (camelCase, e.g. "infants" or "additionalLegs[0].origin")
+    pub field: String,
+    /// Human readable description of the problem
+    pub message: String,
+}
+
+/// All field errors found while validating a request
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an error for a field
+    pub fn add(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    /// Take over all errors of another validation
+    pub fn merge(&mut self, other: ValidationErrors) {
+        self.errors.extend(other.errors);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Ok if no errors were recorded
+    pub fn into_result(self) -> Result<(), ValidationErrors> {
+        if self.is_empty() { Ok(()) } else { Err(self) }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let details: Vec<_> = self
+            .errors
+            .iter()
+            .map(|e| format!("{}: {}", e.field, e.message))
+            .collect();
+        write!(f, "Invalid request: {}", details.join("; "))
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+impl ValidationErrors {
+    /// Error body in the same shape as Amadeus error responses so clients can
+    /// handle both with one code path
+    pub fn to_error_body(&self) -> serde_json::Value {
+        let errors: Vec<_> = self
+            .errors
+            .iter()
+            .map(|e| {
+                serde_json::json!({
+                    "status": 400,
+                    "code": crate::models::error_codes::INVALID_FORMAT,
+                    "title": "INVALID_FIELD",
+                    "detail": e.message,
+                    "source": { "parameter": e.field }
+                })
+            })
+            .collect();
+
+        serde_json::json!({ "errors": errors })
+    }
+}
+
+impl From<ValidationErrors> for (StatusCode, Json<serde_json::Value>) {
+    fn from(errors: ValidationErrors) -> Self {
+        (StatusCode::BAD_REQUEST, Json(errors.to_error_body()))
+    }
+}
+
+impl IntoResponse for ValidationErrors {
+    fn into_response(self) -> Response {
+        <(StatusCode, Json<serde_json::Value>)>::from(self).into_response()
+    }
+}
+
+/// Request models that can be checked before they are sent to Amadeus
+pub trait Validate {
+    /// Validate relative to `today`, which anchors the travel date window
+    fn validate_on(&self, today: NaiveDate) -> Result<(), ValidationErrors>;
+
+    /// Validate relative to the earliest local date
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        self.validate_on(earliest_local_date())
+    }
+}
+
+/// Require a 3-letter IATA airport or city code
+pub fn check_iata_code(errors: &mut ValidationErrors, field: &str, code: &str) {
+    if code.len() != 3 || !code.bytes().all(|b| b.is_ascii_uppercase()) {
+        errors.add(
+            field,
+            format!("Must be a 3-letter IATA location code, got '{}'", code),
+        );
+    }
+}
+
+/// Require a 2-character IATA airline code
+pub fn check_airline_code(errors: &mut ValidationErrors, field: &str, code: &str) {
+    if code.len() != 2
+        || !code
+            .bytes()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+    {
+        errors.add(
+            field,
+            format!("Must be a 2-character IATA airline code, got '{}'", code),
+        );
+    }
+}
+
+/// Require a 3-letter ISO 4217 currency code
+pub fn check_currency(errors: &mut ValidationErrors, field: &str, code: &str) {
+    if code.len() != 3 || !code.bytes().all(|b| b.is_ascii_uppercase()) {
+        errors.add(
+            field,
+            format!("Must be a 3-letter ISO currency code, got '{}'", code),
+        );
+    }
+}
+
+/// Require an ISO date (YYYY-MM-DD)
+pub fn check_date(errors: &mut ValidationErrors, field: &str, value: &str) -> Option<NaiveDate> {
+    match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(date) => Some(date),
+        Err(_) => {
+            errors.add(
+                field,
+                format!("Must be an ISO date (YYYY-MM-DD), got '{}'", value),
+            );
+            None
+        }
+    }
+}
+
+/// Require an ISO travel date between `today` and the Amadeus booking horizon
+pub fn check_travel_date(
+    errors: &mut ValidationErrors,
+    field: &str,
+    value: &str,
+    today: NaiveDate,
+) -> Option<NaiveDate> {
+    let date = check_date(errors, field, value)?;
+    if date < today {
+        errors.add(field, format!("Date {} is in the past", value));
+    } else if date > today + Duration::days(MAX_DAYS_AHEAD) {
+        errors.add(
+            field,
+            format!("Date {} is more than {} days ahead", value, MAX_DAYS_AHEAD),
+        );
+    }
+    Some(date)
+}
+
+/// Reject codes that are not known Amadeus values
+pub fn check_known<T>(errors: &mut ValidationErrors, field: &str, value: &T, known: &[&str])
+where
+    T: fmt::Display,
+{
+    if !known.contains(&value.to_string().as_str()) {
+        errors.add(
+            field,
+            format!("Must be one of {}, got '{}'", known.join(", "), value),
+        );
+    }
+}
+
+/// Includes supported by Flight Offers Price
+pub fn check_pricing_includes(
+    errors: &mut ValidationErrors,
+    field: &str,
+    include: &[PricingInclude],
+) {
+    for (i, include) in include.iter().enumerate() {
+        check_known(
+            errors,
+            &format!("{}[{}]", field, i),
+            include,
+            PricingInclude::VALUES,
+        );
+    }
+}
+
+/// Card brand as an Amadeus code and an optional 6 to 8 digit BIN
+pub fn check_payment_card(errors: &mut ValidationErrors, field: &str, card: &PaymentCard) {
+    if card.brand.is_empty()
+        || !card
+            .brand
+            .bytes()
+            .all(|b| b.is_ascii_uppercase() || b == b'_')
+    {
+        errors.add(
+            format!("{}.brand", field),
+            format!("Must be an uppercase card brand code, got '{}'", card.brand),
+        );
+    }
+    if let Some(ref bin) = card.bin_number
+        && (!(6..=8).contains(&bin.len()) || !bin.bytes().all(|b| b.is_ascii_digit()))
+    {
+        errors.add(
+            format!("{}.binNumber", field),
+            format!("Must be 6 to 8 digits, got '{}'", bin),
+        );
+    }
+}
+
+/// Whether an Amadeus "2028-08" card expiry date is before `today`'s month;
+/// `None` if it is not a valid YYYY-MM date
+pub fn card_expired(expiry_date: &str, today: NaiveDate) -> Option<bool> {
+    let first = NaiveDate::parse_from_str(&format!("{}-01", expiry_date), "%Y-%m-%d").ok()?;
+    let end = first.checked_add_months(chrono::Months::new(1))?;
+    Some(end <= today)
+}
+
+fn check_max_connections(errors: &mut ValidationErrors, field: &str, max: u32) {
+    if max > MAX_CONNECTIONS {
+        errors.add(
+            field,
+            format!("Must be at most {}, got {}", MAX_CONNECTIONS, max),
+        );
+    }
+}
+
+/// Require a non-empty list of flight offers with itineraries
+pub fn check_flight_offers(errors: &mut ValidationErrors, field: &str, offers: &[FlightOffer]) {
+    if offers.is_empty() {
+        errors.add(field, "At least one flight offer is required");
+    }
+    for (i, offer) in offers.iter().enumerate() {
+        if offer.itineraries.is_empty() {
+            errors.add(
+                format!("{}[{}].itineraries", field, i),
+                "A flight offer needs at least one itinerary",
+            );
+        }
+    }
+}
+
+impl Validate for FlightSearchRequest {
+    fn validate_on(&self, today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        check_iata_code(&mut errors, "origin", &self.origin);
+        check_iata_code(&mut errors, "destination", &self.destination);
+        if self.origin == self.destination {
+            errors.add("destination", "Destination must differ from origin");
+        }
+
+        let departure =
+            check_travel_date(&mut errors, "departureDate", &self.departure_date, today);
+        if let Some(ref return_date) = self.return_date
+            && let Some(return_date) =
+                check_travel_date(&mut errors, "returnDate", return_date, today)
+            && departure.is_some_and(|departure| return_date < departure)
+        {
+            errors.add(
+                "returnDate",
+                "Return date must not be before the departure date",
+            );
+        }
+
+        if let Err(passenger_errors) = PassengerMix::from(self).validate() {
+            errors.merge(passenger_errors);
+        }
+
+        if let Some(ref currency) = self.currency {
+            check_currency(&mut errors, "currency", currency);
+        }
+        if let Some(ref cabin) = self.travel_class {
+            check_known(&mut errors, "travelClass", cabin, Cabin::VALUES);
+        }
+        if let Some(ref cabin) = self.return_travel_class {
+            check_known(&mut errors, "returnTravelClass", cabin, Cabin::VALUES);
+        }
+        if let Some(ref coverage) = self.cabin_coverage {
+            check_known(
+                &mut errors,
+                "cabinCoverage",
+                coverage,
+                CabinCoverage::VALUES,
+            );
+        }
+        if let Some(ref coverage) = self.return_cabin_coverage {
+            check_known(
+                &mut errors,
+                "returnCabinCoverage",
+                coverage,
+                CabinCoverage::VALUES,
+            );
+        }
+        if let Some(max) = self.max_connections {
+            check_max_connections(&mut errors, "maxConnections", max);
+        }
+        if let Some(max) = self.return_max_connections {
+            check_max_connections(&mut errors, "returnMaxConnections", max);
+        }
+
+        if let Some(max_price) = self.max_price
+            && max_price <= 0
+        {
+            errors.add("maxPrice", "Must be greater than 0");
+        }
+        if let Some(max_results) = self.max_results
+            && !(1..=MAX_RESULTS).contains(&max_results)
+        {
+            errors.add(
+                "maxResults",
+                format!("Must be between 1 and {}", MAX_RESULTS),
+            );
+        }
+
+        let included = self.included_airline_codes.as_deref().unwrap_or_default();
+        let excluded = self.excluded_airline_codes.as_deref().unwrap_or_default();
+        for (i, code) in included.iter().enumerate() {
+            check_airline_code(&mut errors, &format!("includedAirlineCodes[{}]", i), code);
+        }
+        for (i, code) in excluded.iter().enumerate() {
+            check_airline_code(&mut errors, &format!("excludedAirlineCodes[{}]", i), code);
+        }
+        let included_set: HashSet<_> = included.iter().collect();
+        let overlap: Vec<_> = excluded
+            .iter()
+            .filter(|code| included_set.contains(code))
+            .map(String::as_str)
+            .collect();
+        if !overlap.is_empty() {
+            errors.add(
+                "excludedAirlineCodes",
+                format!(
+                    "Airlines cannot be both included and excluded: {}",
+                    overlap.join(", ")
+                ),
+            );
+        }
+
+        // Multi-city searches have no return flight, only further legs
+        if self.return_date.is_some()
+            && self
+                .additional_legs
+                .as_ref()
+                .is_some_and(|legs| !legs.is_empty())
+        {
+            errors.add(
+                "returnDate",
+                "Multi-city searches take no return date; add the return flight as a leg",
+            );
+        }
+
+        // Multi-city legs must be valid and in chronological order
+        let mut previous_date = departure;
+        for (i, leg) in self.additional_legs.iter().flatten().enumerate() {
+            let field = |name: &str| format!("additionalLegs[{}].{}", i, name);
+
+            check_iata_code(&mut errors, &field("origin"), &leg.origin);
+            check_iata_code(&mut errors, &field("destination"), &leg.destination);
+            let date = check_travel_date(
+                &mut errors,
+                &field("departureDate"),
+                &leg.departure_date,
+                today,
+            );
+            if let (Some(date), Some(previous)) = (date, previous_date)
+                && date < previous
+            {
+                errors.add(
+                    field("departureDate"),
+                    "Legs must be in chronological order",
+                );
+            }
+            previous_date = date.or(previous_date);
+
+            if let Some(ref cabin) = leg.travel_class {
+                check_known(&mut errors, &field("travelClass"), cabin, Cabin::VALUES);
+            }
+            if let Some(ref coverage) = leg.cabin_coverage {
+                check_known(
+                    &mut errors,
+                    &field("cabinCoverage"),
+                    coverage,
+                    CabinCoverage::VALUES,
+                );
+            }
+            if let Some(max) = leg.max_connections {
+                check_max_connections(&mut errors, &field("maxConnections"), max);
+            }
+        }
+
+        errors.into_result()
+    }
+}
+
+impl Validate for PriceMatrixRequest {
+    fn validate_on(&self, today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        check_iata_code(&mut errors, "origin", &self.origin);
+        check_iata_code(&mut errors, "destination", &self.destination);
+        if self.origin == self.destination {
+            errors.add("destination", "Destination must differ from origin");
+        }
+
+        if self.outbound_dates.is_empty() {
+            errors.add("outboundDates", "At least one outbound date is required");
+        }
+        if self.inbound_dates.is_empty() {
+            errors.add("inboundDates", "At least one inbound date is required");
+        }
+        for (i, date) in self.outbound_dates.iter().enumerate() {
+            check_travel_date(&mut errors, &format!("outboundDates[{}]", i), date, today);
+        }
+        for (i, date) in self.inbound_dates.iter().enumerate() {
+            check_travel_date(&mut errors, &format!("inboundDates[{}]", i), date, today);
+        }
+
+        if let Err(passenger_errors) = PassengerMix::from(self).validate() {
+            errors.merge(passenger_errors);
+        }
+        if let Some(ref currency) = self.currency {
+            check_currency(&mut errors, "currency", currency);
+        }
+
+        errors.into_result()
+    }
+}
+
+impl Validate for FlightPriceRequest {
+    fn validate_on(&self, _today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        check_flight_offers(
+            &mut errors,
+            "flightOffer",
+            std::slice::from_ref(&self.flight_offer),
+        );
+        check_pricing_includes(&mut errors, "include", &self.include);
+        if let Some(ref card) = self.payment_card {
+            check_payment_card(&mut errors, "paymentCard", card);
+        }
+        errors.into_result()
+    }
+}
+
+impl Validate for BagSelectionRequest {
+    fn validate_on(&self, _today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        check_flight_offers(
+            &mut errors,
+            "flightOffer",
+            std::slice::from_ref(&self.flight_offer),
+        );
+        if self.selections.is_empty() {
+            errors.add("selections", "At least one bag selection is required");
+        }
+        errors.into_result()
+    }
+}
+
+impl Validate for SeatSelectionRequest {
+    fn validate_on(&self, _today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        check_flight_offers(
+            &mut errors,
+            "flightOffer",
+            std::slice::from_ref(&self.flight_offer),
+        );
+        if self.selections.is_empty() {
+            errors.add("selections", "At least one seat selection is required");
+        }
+        errors.into_result()
+    }
+}
+
+impl Validate for SeatRecommendationRequest {
+    fn validate_on(&self, _today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        check_flight_offers(
+            &mut errors,
+            "flightOffer",
+            std::slice::from_ref(&self.flight_offer),
+        );
+        for (i, preference) in self.preferences.iter().enumerate() {
+            let seated = self.flight_offer.traveler_pricings.iter().any(|pricing| {
+                pricing.traveler_id == preference.traveler_id && pricing.traveler_type.is_seated()
+            });
+            if !seated {
+                errors.add(
+                    format!("preferences[{i}].travelerId"),
+                    format!("No seated traveler {} in the offer", preference.traveler_id),
+                );
+            }
+        }
+        errors.into_result()
+    }
+}
+
+impl Validate for FlightOrderRequest {
+    fn validate_on(&self, today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        check_flight_offers(&mut errors, "flightOffers", &self.flight_offers);
+
+        if self.travelers.is_empty() {
+            errors.add("travelers", "At least one traveler is required");
+        }
+        let mut ids = HashSet::new();
+        for (i, traveler) in self.travelers.iter().enumerate() {
+            if !ids.insert(traveler.id.as_str()) {
+                errors.add(
+                    format!("travelers[{}].id", i),
+                    format!("Duplicate traveler id '{}'", traveler.id),
+                );
+            }
+            let field = format!("travelers[{}].dateOfBirth", i);
+            if let Some(birth_date) = check_date(&mut errors, &field, &traveler.date_of_birth)
+                && birth_date > today
+            {
+                errors.add(field, "Date of birth is in the future");
+            }
+        }
+        crate::travelers::check_travelers(&mut errors, self, today);
+
+        if let Some(card) = self
+            .form_of_payment
+            .as_ref()
+            .and_then(|payment| payment.credit_card.as_ref())
+            && card.token.is_some()
+            && (card.number.is_some() || card.security_code.is_some())
+        {
+            errors.add(
+                "formOfPayment.creditCard.token",
+                "Send either a card token or the card number and security code",
+            );
+        }
+
+        errors.into_result()
+    }
+}
+
+impl Validate for CardTokenRequest {
+    fn validate_on(&self, today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        // Card data is never echoed back in errors
+        let number = self.number.expose();
+        if !(13..=19).contains(&number.len()) || !luhn_valid(number) {
+            errors.add("number", "Not a valid card number");
+        }
+        match card_expired(&self.expiry_date, today) {
+            None => errors.add(
+                "expiryDate",
+                format!("Must be YYYY-MM, got '{}'", self.expiry_date),
+            ),
+            Some(true) => errors.add("expiryDate", "Card has expired"),
+            Some(false) => {}
+        }
+        if let Some(ref code) = self.security_code {
+            let code = code.expose();
+            if !(3..=4).contains(&code.len()) || !code.bytes().all(|b| b.is_ascii_digit()) {
+                errors.add("securityCode", "Must be 3 or 4 digits");
+            }
+        }
+
+        errors.into_result()
+    }
+}
+
+/// Who cancels and why, as recorded with the cancellation
+fn check_cancellation(errors: &mut ValidationErrors, cancelled_by: &str, reason: &str) {
+    let cancelled_by = cancelled_by.trim();
+    if cancelled_by.is_empty() || cancelled_by.len() > 255 {
+        errors.add("cancelledBy", "Must be between 1 and 255 characters");
+    }
+    let reason = reason.trim();
+    if reason.is_empty() || reason.len() > MAX_REASON_LENGTH {
+        errors.add(
+            "reason",
+            format!("Must be between 1 and {} characters", MAX_REASON_LENGTH),
+        );
+    }
+}
+
+impl Validate for CancellationConfirmRequest {
+    fn validate_on(&self, _today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        check_cancellation(&mut errors, &self.cancelled_by, &self.reason);
+        errors.into_result()
+    }
+}
+
+impl Validate for OrderDeletionQuery {
+    fn validate_on(&self, _today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        check_cancellation(&mut errors, self.cancelled_by(), self.reason());
+        errors.into_result()
+    }
+}
+
+impl Validate for AcceptAlternativeRequest {
+    fn validate_on(&self, _today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        if self.offer_id.trim().is_empty() {
+            errors.add("offerId", "Must not be empty");
+        }
+        errors.into_result()
+    }
+}
+
+impl Validate for SeatmapRequest {
+    fn validate_on(&self, _today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        check_flight_offers(&mut errors, "flightOffers", &self.flight_offers);
+        errors.into_result()
+    }
+}
+
+impl Validate for UpsellRequest {
+    fn validate_on(&self, _today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        check_flight_offers(&mut errors, "flightOffers", &self.flight_offers);
+        errors.into_result()
+    }
+}
+
+impl Validate for FlightChoicePredictionRequest {
+    fn validate_on(&self, _today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        check_flight_offers(&mut errors, "data", &self.data);
+        errors.into_result()
+    }
+}
+
+impl Validate for FlightAvailabilityRequest {
+    fn validate_on(&self, today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if self.origin_destinations.is_empty() {
+            errors.add(
+                "originDestinations",
+                "At least one origin-destination is required",
+            );
+        }
+        for (i, od) in self.origin_destinations.iter().enumerate() {
+            let field = |name: &str| format!("originDestinations[{}].{}", i, name);
+            check_iata_code(
+                &mut errors,
+                &field("originLocationCode"),
+                &od.origin_location_code,
+            );
+            check_iata_code(
+                &mut errors,
+                &field("destinationLocationCode"),
+                &od.destination_location_code,
+            );
+            check_travel_date(
+                &mut errors,
+                &field("departureDateTimeRange.date"),
+                &od.departure_date_time_range.date,
+                today,
+            );
+        }
+
+        if self.travelers.is_empty() {
+            errors.add("travelers", "At least one traveler is required");
+        }
+        if self.sources.is_empty() {
+            errors.add("sources", "At least one source is required");
+        }
+
+        errors.into_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, 10).unwrap()
+    }
+
+    fn search_request(json: serde_json::Value) -> FlightSearchRequest {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn fields(errors: &ValidationErrors) -> Vec<&str> {
+        errors.errors.iter().map(|e| e.field.as_str()).collect()
+    }
+
+    #[test]
+    fn test_valid_search_request() {
+        let req = search_request(serde_json::json!({
+            "origin": "FRA",
+            "destination": "JFK",
+            "departureDate": "2025-03-01",
+            "returnDate": "2025-03-01",
+            "adults": 2,
+            "currency": "USD",
+            "travelClass": "BUSINESS",
+            "includedAirlineCodes": ["LH", "UA"],
+            "maxPrice": 1500
+        }));
+        assert!(req.validate_on(today()).is_ok());
+    }
+
+    #[test]
+    fn test_search_request_field_errors() {
+        let req = search_request(serde_json::json!({
+            "origin": "fra",
+            "destination": "JFKX",
+            "departureDate": "2025-01-09",
+            "returnDate": "2025/03/01",
+            "adults": 1,
+            "travelClass": "BUSINES",
+            "maxPrice": 0,
+            "includedAirlineCodes": ["LH"],
+            "excludedAirlineCodes": ["LH"]
+        }));
+
+        let errors = req.validate_on(today()).unwrap_err();
+        assert_eq!(
+            fields(&errors),
+            [
+                "origin",
+                "destination",
+                "departureDate",
+                "returnDate",
+                "travelClass",
+                "maxPrice",
+                "excludedAirlineCodes"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_travel_date_window() {
+        let mut errors = ValidationErrors::new();
+        assert!(check_travel_date(&mut errors, "d", "2025-01-10", today()).is_some());
+        assert!(check_travel_date(&mut errors, "d", "2026-01-06", today()).is_some());
+        assert!(errors.is_empty());
+
+        check_travel_date(&mut errors, "d", "2026-01-07", today());
+        assert_eq!(errors.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_return_before_departure_and_leg_order() {
+        let req = search_request(serde_json::json!({
+            "origin": "FRA",
+            "destination": "JFK",
+            "departureDate": "2025-03-10",
+            "returnDate": "2025-03-01",
+            "adults": 1
+        }));
+        let errors = req.validate_on(today()).unwrap_err();
+        assert_eq!(fields(&errors), ["returnDate"]);
+
+        let req = search_request(serde_json::json!({
+            "origin": "FRA",
+            "destination": "JFK",
+            "departureDate": "2025-03-10",
+            "adults": 1,
+            "additionalLegs": [
+                { "origin": "JFK", "destination": "LAX", "departureDate": "2025-03-05", "maxConnections": 3 }
+            ]
+        }));
+        let errors = req.validate_on(today()).unwrap_err();
+        assert_eq!(
+            fields(&errors),
+            [
+                "additionalLegs[0].departureDate",
+                "additionalLegs[0].maxConnections"
+            ]
+        );
+
+        let req = search_request(serde_json::json!({
+            "origin": "FRA",
+            "destination": "JFK",
+            "departureDate": "2025-03-10",
+            "returnDate": "2025-03-20",
+            "adults": 1,
+            "additionalLegs": [
+                { "origin": "JFK", "destination": "LAX", "departureDate": "2025-03-15" }
+            ]
+        }));
+        let errors = req.validate_on(today()).unwrap_err();
+        assert_eq!(fields(&errors), ["returnDate"]);
+    }
+
+    #[test]
+    fn test_earliest_local_date() {
+        // Never ahead of UTC and at most one day behind it
+        let utc = Utc::now().date_naive();
+        let earliest = earliest_local_date();
+        assert!(earliest <= utc);
+        assert!(earliest >= utc - Duration::days(1));
+    }
+
+    #[test]
+    fn test_error_body_matches_amadeus_shape() {
+        let mut errors = ValidationErrors::new();
+        errors.add("origin", "Must be a 3-letter IATA location code, got 'X'");
+
+        let body = errors.to_error_body();
+        let parsed: crate::models::AmadeusErrorResponse = serde_json::from_value(body).unwrap();
+        assert_eq!(parsed.errors[0].status, Some(400));
+        assert_eq!(
+            parsed.errors[0]
+                .source
+                .as_ref()
+                .unwrap()
+                .parameter
+                .as_deref(),
+            Some("origin")
+        );
+    }
+}